@@ -0,0 +1,59 @@
+//! CLI integration test for `--format json` on sync commands.
+//!
+//! Verifies the JSON report is the only thing on stdout and carries the
+//! schema version, per-artifact outcomes, and skip guidance.
+
+use std::fs;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+#[test]
+fn sync_commands_json_reports_skips_with_guidance() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    fs::create_dir_all(home.path().join(".claude/commands"))?;
+    fs::write(home.path().join(".claude/commands/review.md"), "# Review")?;
+    fs::write(home.path().join(".claude/commands/plan.md"), "# Plan")?;
+    fs::create_dir_all(home.path().join(".codex/prompts"))?;
+    fs::write(home.path().join(".codex/prompts/review.md"), "# Keep me")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_skrills"))
+        .env("HOME", home.path())
+        .args([
+            "sync-commands",
+            "--from",
+            "claude",
+            "--skip-existing-commands",
+            "--format",
+            "json",
+        ])
+        .output()
+        .context("Failed to execute sync-commands --format json")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "sync-commands should succeed\nSTDOUT:\n{stdout}\nSTDERR:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let doc: serde_json::Value =
+        serde_json::from_str(&stdout).context("stdout should be a single JSON document")?;
+    assert_eq!(doc["schema_version"], 1);
+    assert_eq!(doc["command"], "sync-commands");
+    assert_eq!(doc["source"], "claude");
+    assert_eq!(doc["target"], "codex");
+    assert_eq!(doc["dry_run"], false);
+    assert_eq!(doc["artifacts"]["commands"]["written"], 1);
+    let skipped = &doc["artifacts"]["commands"]["skipped"][0];
+    assert_eq!(skipped["type"], "WouldOverwrite");
+    assert_eq!(skipped["item"], "review");
+    assert!(skipped["guidance"].is_string());
+    assert_eq!(doc["totals"]["skipped"], 1);
+    assert_eq!(
+        fs::read_to_string(home.path().join(".codex/prompts/review.md"))?,
+        "# Keep me"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! CLI integration test for `skrills sync-all --project`.
+//!
+//! Verifies repository-local Claude config lands in the repository's Codex
+//! equivalents without touching the home directory.
+
+use std::fs;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+#[test]
+fn sync_all_project_syncs_repo_local_config() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let repo = tempfile::tempdir()?;
+    let root = repo.path();
+
+    fs::create_dir_all(root.join(".git"))?;
+    fs::create_dir_all(root.join(".claude/commands"))?;
+    fs::write(root.join(".claude/commands/review.md"), "# Review")?;
+    fs::create_dir_all(root.join(".claude/skills/lint"))?;
+    fs::write(root.join(".claude/skills/lint/SKILL.md"), "# Lint")?;
+    fs::write(
+        root.join(".mcp.json"),
+        r#"{"mcpServers": {"repo-server": {"command": "repo-srv"}}}"#,
+    )?;
+    fs::write(root.join("CLAUDE.md"), "# Team conventions")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_skrills"))
+        .env("HOME", home.path())
+        .current_dir(root)
+        .args(["sync-all", "--from", "claude", "--project"])
+        .output()
+        .context("Failed to execute sync-all --project")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "sync-all --project should succeed\nSTDOUT:\n{stdout}\nSTDERR:\n{stderr}"
+    );
+
+    assert_eq!(
+        fs::read_to_string(root.join(".codex/prompts/review.md"))?,
+        "# Review"
+    );
+    assert!(root.join(".codex/skills/lint/SKILL.md").exists());
+    let config: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join(".codex/config.json"))?)?;
+    assert_eq!(config["mcpServers"]["repo-server"]["command"], "repo-srv");
+    let agents = fs::read_to_string(root.join("AGENTS.md"))?;
+    assert!(agents.starts_with("<!-- skrills:claude-memory:start -->"));
+    assert!(agents.contains("# Team conventions"));
+    assert!(!home.path().join(".codex/prompts").exists());
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! `---` delimited frontmatter of skill and agent markdown.
+//!
+//! Only the flat subset these files use is understood: `key: value`
+//! scalars, `key:` followed by `- item` lines, and folded (`>`) or literal
+//! (`|`) blocks, which are joined into one line. Nested mappings are not
+//! interpreted.
+
+use std::collections::BTreeMap;
+
+/// Scalar or list value from frontmatter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontValue {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+impl FrontValue {
+    pub fn as_scalar(&self) -> Option<&str> {
+        match self {
+            FrontValue::Scalar(s) => Some(s),
+            FrontValue::List(_) => None,
+        }
+    }
+
+    /// Lists, inline `[a, b]` lists and comma-separated scalars.
+    pub fn to_list(&self) -> Vec<String> {
+        let items: Vec<&str> = match self {
+            FrontValue::List(items) => items.iter().map(String::as_str).collect(),
+            FrontValue::Scalar(s) => s
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .collect(),
+        };
+        items
+            .into_iter()
+            .map(unquote)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(s)
+}
+
+/// Splits frontmatter from the markdown body.
+///
+/// The header opens with a `---` first line and ends at the next line that
+/// is exactly `---`; without one the whole text is body.
+pub fn split_frontmatter(text: &str) -> (Option<&str>, &str) {
+    let text = text.trim_start_matches('\u{feff}');
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// Parses the frontmatter fields and returns them with the body.
+pub fn parse_frontmatter(text: &str) -> (BTreeMap<String, FrontValue>, &str) {
+    let (header, body) = split_frontmatter(text);
+    let fields = header.map(parse_fields).unwrap_or_default();
+    (fields, body)
+}
+
+fn parse_fields(header: &str) -> BTreeMap<String, FrontValue> {
+    let mut fields = BTreeMap::new();
+    let mut lines = header.lines().peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        // Indented lines and `- item` lines belong to this key.
+        let mut block = Vec::new();
+        while let Some(next) = lines.peek() {
+            let next_trimmed = next.trim();
+            if !(next.starts_with(char::is_whitespace)
+                || next_trimmed.is_empty()
+                || next_trimmed.starts_with("- "))
+            {
+                break;
+            }
+            if !next_trimmed.is_empty() {
+                block.push(next_trimmed);
+            }
+            lines.next();
+        }
+        let parsed = if value.starts_with(['>', '|']) {
+            FrontValue::Scalar(block.join(" "))
+        } else if !value.is_empty() {
+            FrontValue::Scalar(unquote(value).to_string())
+        } else if block.iter().all(|l| l.starts_with("- ")) {
+            FrontValue::List(block.iter().map(|l| unquote(&l[2..]).to_string()).collect())
+        } else {
+            FrontValue::Scalar(block.join(" "))
+        };
+        fields.insert(key.trim().to_string(), parsed);
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_ends_only_at_an_exact_delimiter_line() {
+        let text = "---\nname: a\n----\nnote: x\n---foo\n---\r\nbody\n";
+        let (header, body) = split_frontmatter(text);
+        assert_eq!(header, Some("name: a\n----\nnote: x\n---foo\n"));
+        assert_eq!(body, "body\n");
+
+        let unclosed = "---\nname: a\n";
+        assert_eq!(split_frontmatter(unclosed), (None, unclosed));
+        assert_eq!(
+            split_frontmatter("\u{feff}---\r\nname: a\r\n---\r\n"),
+            (Some("name: a\r\n"), "")
+        );
+        assert_eq!(split_frontmatter("# Title\n"), (None, "# Title\n"));
+    }
+
+    #[test]
+    fn fields_cover_scalars_lists_and_blocks() {
+        let text = "---\n# comment\nname: \"reviewer\"\ntools:\n  - search\n  - 'fetch'\nempty:\ndescription: >\n  Formats Rust\n  with rustfmt\nmodel: sonnet\n---\nBody";
+        let (fields, body) = parse_frontmatter(text);
+        assert_eq!(body, "Body");
+        let scalar = |key: &str| fields.get(key).and_then(FrontValue::as_scalar);
+        assert_eq!(scalar("name"), Some("reviewer"));
+        assert_eq!(scalar("description"), Some("Formats Rust with rustfmt"));
+        assert_eq!(scalar("model"), Some("sonnet"));
+        assert_eq!(fields["tools"].to_list(), vec!["search", "fetch"]);
+        assert_eq!(fields["empty"], FrontValue::List(Vec::new()));
+        assert_eq!(
+            FrontValue::Scalar("[Read, 'Grep']".into()).to_list(),
+            vec!["Read", "Grep"]
+        );
+    }
+}
@@ -3,12 +3,18 @@
 //! This crate provides mechanisms for:
 //! - Scanning directories for skill files.
 //! - Extracting metadata from skills.
+//! - Parsing skill and agent frontmatter.
 //! - Managing skill sources and priority.
+//! - Reading Claude's installed plugin registry.
 //! - Calculating prompt similarity using trigrams.
 
+pub mod frontmatter;
+pub mod plugins;
 pub mod scanner;
 pub mod types;
 
+pub use frontmatter::{parse_frontmatter, split_frontmatter, FrontValue};
+pub use plugins::{InstalledPlugin, PluginRegistry, INSTALLED_PLUGINS_FILE};
 pub use scanner::{
     default_priority, default_roots, discover_agents, discover_skills, extra_skill_roots,
     extract_refs_from_agents, hash_file, load_priority_override, priority_labels,
@@ -0,0 +1,245 @@
+//! Claude Code plugin registry awareness.
+//!
+//! Claude records installed plugins in `~/.claude/plugins/installed_plugins.json`
+//! and whether each one is enabled in the `enabledPlugins` map of
+//! `~/.claude/settings.json`. This module reads both so discovery and sync can
+//! restrict plugin content to enabled plugins and tag it with the plugin name.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of Claude's installed plugin registry (under `plugins/`).
+pub const INSTALLED_PLUGINS_FILE: &str = "installed_plugins.json";
+
+/// A plugin recorded in Claude's installed plugin registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledPlugin {
+    /// Registry key, usually `name@marketplace`.
+    pub id: String,
+    /// Plugin name without the marketplace suffix.
+    pub name: String,
+    /// Marketplace the plugin was installed from, when known.
+    pub marketplace: Option<String>,
+    /// Installed version, when recorded.
+    pub version: Option<String>,
+    /// Directory holding the installed plugin contents.
+    pub install_path: PathBuf,
+    /// Whether the plugin is enabled in `settings.json`.
+    pub enabled: bool,
+}
+
+impl InstalledPlugin {
+    /// Returns `name` or `name@version` for display.
+    pub fn label(&self) -> String {
+        match &self.version {
+            Some(v) => format!("{}@{}", self.name, v),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Returns the directory this plugin occupies inside the marketplace catalog.
+    fn marketplace_dir(&self, plugins_dir: &Path) -> Option<PathBuf> {
+        self.marketplace.as_ref().map(|m| {
+            plugins_dir
+                .join("marketplaces")
+                .join(m)
+                .join("plugins")
+                .join(&self.name)
+        })
+    }
+}
+
+/// Installed plugins parsed from a Claude configuration root.
+#[derive(Debug, Clone, Default)]
+pub struct PluginRegistry {
+    plugins_dir: PathBuf,
+    plugins: Vec<InstalledPlugin>,
+}
+
+impl PluginRegistry {
+    /// Loads the registry for a Claude root (e.g. `~/.claude`).
+    ///
+    /// Returns `Ok(None)` when no registry file exists so callers can fall back
+    /// to plain path-based plugin handling.
+    pub fn load(claude_root: &Path) -> Result<Option<Self>> {
+        let plugins_dir = claude_root.join("plugins");
+        let registry_path = plugins_dir.join(INSTALLED_PLUGINS_FILE);
+        if !registry_path.exists() {
+            return Ok(None);
+        }
+        let registry: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&registry_path)?)?;
+        let enabled = read_enabled_plugins(&claude_root.join("settings.json"))?;
+
+        let mut plugins = Vec::new();
+        if let Some(entries) = registry.get("plugins").and_then(|v| v.as_object()) {
+            for (id, entry) in entries {
+                // v1 stores one object per plugin; v2 stores a list of scoped installs.
+                let installs: Vec<&serde_json::Value> = match entry {
+                    serde_json::Value::Array(list) => list.iter().collect(),
+                    other => vec![other],
+                };
+                let Some(install) = installs
+                    .into_iter()
+                    .find(|i| i.get("installPath").is_some())
+                else {
+                    continue;
+                };
+                let Some(raw_path) = install.get("installPath").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let install_path = PathBuf::from(raw_path);
+                let install_path = if install_path.is_absolute() {
+                    install_path
+                } else {
+                    plugins_dir.join(install_path)
+                };
+                let (name, marketplace) = match id.split_once('@') {
+                    Some((n, m)) => (n.to_string(), Some(m.to_string())),
+                    None => (id.clone(), None),
+                };
+                plugins.push(InstalledPlugin {
+                    id: id.clone(),
+                    name,
+                    marketplace,
+                    version: install
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(String::from),
+                    install_path,
+                    // Claude enables plugins on install; only an explicit `false` disables.
+                    enabled: enabled
+                        .as_ref()
+                        .and_then(|m| m.get(id))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(true),
+                });
+            }
+        }
+        plugins.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(Some(Self {
+            plugins_dir,
+            plugins,
+        }))
+    }
+
+    /// Returns every installed plugin, enabled or not.
+    pub fn plugins(&self) -> &[InstalledPlugin] {
+        &self.plugins
+    }
+
+    /// Returns only enabled plugins.
+    pub fn enabled(&self) -> impl Iterator<Item = &InstalledPlugin> {
+        self.plugins.iter().filter(|p| p.enabled)
+    }
+
+    /// Finds the plugin owning `path`, matching install paths and marketplace
+    /// catalog directories. The most specific match wins.
+    pub fn plugin_for_path(&self, path: &Path) -> Option<&InstalledPlugin> {
+        self.plugins
+            .iter()
+            .filter_map(|p| {
+                std::iter::once(p.install_path.clone())
+                    .chain(p.marketplace_dir(&self.plugins_dir))
+                    .filter(|dir| path.starts_with(dir))
+                    .map(|dir| dir.components().count())
+                    .max()
+                    .map(|depth| (depth, p))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, p)| p)
+    }
+
+    /// Returns the enabled plugin owning `path`, if any.
+    pub fn enabled_plugin_for_path(&self, path: &Path) -> Option<&InstalledPlugin> {
+        self.plugin_for_path(path).filter(|p| p.enabled)
+    }
+}
+
+fn read_enabled_plugins(
+    settings_path: &Path,
+) -> Result<Option<serde_json::Map<String, serde_json::Value>>> {
+    if !settings_path.exists() {
+        return Ok(None);
+    }
+    let settings: serde_json::Value = serde_json::from_str(&fs::read_to_string(settings_path)?)?;
+    Ok(settings
+        .get("enabledPlugins")
+        .and_then(|v| v.as_object())
+        .cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_registry(root: &Path, registry: serde_json::Value, settings: serde_json::Value) {
+        fs::create_dir_all(root.join("plugins")).unwrap();
+        fs::write(
+            root.join("plugins").join(INSTALLED_PLUGINS_FILE),
+            registry.to_string(),
+        )
+        .unwrap();
+        fs::write(root.join("settings.json"), settings.to_string()).unwrap();
+    }
+
+    #[test]
+    fn load_returns_none_without_registry() {
+        let tmp = tempdir().unwrap();
+        assert!(PluginRegistry::load(tmp.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn load_parses_v1_and_v2_entries_with_enabled_state() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let alpha = root.join("plugins/cache/mkt/alpha/1.0.0");
+        let beta = root.join("plugins/cache/mkt/beta/2.0.0");
+        write_registry(
+            root,
+            serde_json::json!({
+                "version": 2,
+                "plugins": {
+                    "alpha@mkt": { "version": "1.0.0", "installPath": alpha },
+                    "beta@mkt": [{ "scope": "user", "version": "2.0.0", "installPath": beta }]
+                }
+            }),
+            serde_json::json!({ "enabledPlugins": { "beta@mkt": false } }),
+        );
+
+        let registry = PluginRegistry::load(root).unwrap().unwrap();
+        assert_eq!(registry.plugins().len(), 2);
+        let a = &registry.plugins()[0];
+        assert_eq!(a.name, "alpha");
+        assert_eq!(a.marketplace.as_deref(), Some("mkt"));
+        assert_eq!(a.label(), "alpha@1.0.0");
+        assert!(a.enabled);
+        assert!(!registry.plugins()[1].enabled);
+        assert_eq!(registry.enabled().count(), 1);
+    }
+
+    #[test]
+    fn plugin_for_path_matches_install_and_marketplace_dirs() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let install = root.join("plugins/cache/mkt/alpha/1.0.0");
+        write_registry(
+            root,
+            serde_json::json!({
+                "plugins": { "alpha@mkt": { "version": "1.0.0", "installPath": install } }
+            }),
+            serde_json::json!({}),
+        );
+        let registry = PluginRegistry::load(root).unwrap().unwrap();
+
+        let cached = install.join("skills/x/SKILL.md");
+        assert_eq!(registry.plugin_for_path(&cached).unwrap().name, "alpha");
+        let catalog = root.join("plugins/marketplaces/mkt/plugins/alpha/commands/c.md");
+        assert_eq!(registry.plugin_for_path(&catalog).unwrap().name, "alpha");
+        let other = root.join("plugins/cache/mkt/beta/skills/y/SKILL.md");
+        assert!(registry.plugin_for_path(&other).is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::plugins::PluginRegistry;
 use crate::types::{parse_source_key, DuplicateInfo, SkillMeta, SkillRoot, SkillSource};
 use anyhow::Result;
 use pathdiff::diff_paths;
@@ -149,6 +150,46 @@ fn file_hash(path: &Path) -> Result<String> {
     Ok(format!("{:x}", digest))
 }
 
+/// Loads the plugin registry governing a Claude plugin root
+/// (`<claude>/plugins/cache` or `<claude>/plugins/marketplaces`).
+///
+/// A missing or unreadable registry yields `None`, keeping the plain
+/// path-based behavior.
+fn registry_for_root(root_cfg: &SkillRoot) -> Option<PluginRegistry> {
+    if !matches!(
+        root_cfg.source,
+        SkillSource::Cache | SkillSource::Marketplace
+    ) {
+        return None;
+    }
+    let plugins_dir = root_cfg.root.parent()?;
+    if plugins_dir.file_name()? != "plugins" {
+        return None;
+    }
+    PluginRegistry::load(plugins_dir.parent()?).ok().flatten()
+}
+
+/// Decides whether a file under a plugin root is visible and which plugin owns it.
+///
+/// Returns `None` when the file must be skipped: installed plugin content
+/// (`Cache`) is limited to enabled plugins. Marketplace content is an explicit
+/// opt-in for uninstalled plugins, so it is only tagged, never filtered.
+fn plugin_visibility(
+    registry: Option<&PluginRegistry>,
+    source: &SkillSource,
+    path: &Path,
+) -> Option<Option<String>> {
+    let Some(registry) = registry else {
+        return Some(None);
+    };
+    match source {
+        SkillSource::Cache => registry
+            .enabled_plugin_for_path(path)
+            .map(|p| Some(p.name.clone())),
+        _ => Some(registry.plugin_for_path(path).map(|p| p.name.clone())),
+    }
+}
+
 /// Collects skill metadata from the provided roots.
 fn collect_skills_from(
     roots: &[SkillRoot],
@@ -164,6 +205,7 @@ fn collect_skills_from(
         if !root.exists() {
             continue;
         }
+        let registry = registry_for_root(root_cfg);
         let entries: Vec<_> = WalkDir::new(root)
             .min_depth(1)
             .max_depth(MAX_SKILL_DEPTH)
@@ -177,21 +219,25 @@ fn collect_skills_from(
             })
             .filter_map(|e| e.ok())
             .filter(is_skill_file)
+            .filter_map(|e| {
+                plugin_visibility(registry.as_ref(), &root_cfg.source, e.path())
+                    .map(|plugin| (e, plugin))
+            })
             .collect();
 
         let metas: Vec<_> = entries
             .par_iter()
-            .map(|entry| {
+            .map(|(entry, plugin)| {
                 let path = entry.path().to_path_buf();
                 let name = diff_paths(&path, root)
                     .and_then(|p| p.to_str().map(|s| s.to_owned()))
                     .unwrap_or_else(|| path.to_string_lossy().into_owned());
                 let hash = file_hash(&path)?;
-                Ok((name, path, hash))
+                Ok((name, path, hash, plugin.clone()))
             })
             .collect::<Result<Vec<_>>>()?;
 
-        for (name, path, hash) in metas {
+        for (name, path, hash, plugin) in metas {
             if let Some((seen_src, seen_root)) = seen.get(&name) {
                 if let Some(dup_log) = dup_log.as_mut() {
                     dup_log.push(DuplicateInfo {
@@ -211,6 +257,7 @@ fn collect_skills_from(
                 source: root_cfg.source.clone(),
                 root: root.clone(),
                 hash,
+                plugin,
             });
             seen.insert(name, (root_cfg.source.label(), root.display().to_string()));
         }
@@ -238,6 +285,7 @@ pub fn discover_agents(roots: &[SkillRoot]) -> Result<Vec<crate::types::AgentMet
         if !root.exists() {
             continue;
         }
+        let registry = registry_for_root(root_cfg);
         for entry in WalkDir::new(root)
             .min_depth(1)
             .max_depth(20)
@@ -254,6 +302,10 @@ pub fn discover_agents(roots: &[SkillRoot]) -> Result<Vec<crate::types::AgentMet
             if !is_agent_file(&entry) {
                 continue;
             }
+            let Some(plugin) = plugin_visibility(registry.as_ref(), &root_cfg.source, entry.path())
+            else {
+                continue;
+            };
             let path = entry.into_path();
             let name = diff_paths(&path, root)
                 .and_then(|p| p.to_str().map(|s| s.to_owned()))
@@ -265,6 +317,7 @@ pub fn discover_agents(roots: &[SkillRoot]) -> Result<Vec<crate::types::AgentMet
                 source: root_cfg.source.clone(),
                 root: root.clone(),
                 hash,
+                plugin,
             });
         }
     }
@@ -359,6 +412,13 @@ mod tests {
         assert!(!refs.contains("rules"));
     }
 
+    #[test]
+    fn agent_meta_without_plugin_deserializes() {
+        let json = r#"{"name":"a","path":"/a.md","source":"Codex","root":"/","hash":"h"}"#;
+        let agent: crate::types::AgentMeta = serde_json::from_str(json).unwrap();
+        assert_eq!(agent.plugin, None);
+    }
+
     #[test]
     fn default_roots_use_priority_order() {
         let tmp = tempdir().unwrap();
@@ -684,6 +744,45 @@ mod tests {
         assert_eq!(rank_map.get("agent").unwrap(), 6);
     }
 
+    #[test]
+    fn discover_skills_limits_cache_to_enabled_plugins() {
+        let tmp = tempdir().unwrap();
+        let claude = tmp.path().join(".claude");
+        let cache = claude.join("plugins/cache");
+        let alpha = cache.join("mkt/alpha/1.0.0");
+        let beta = cache.join("mkt/beta/1.0.0");
+        for dir in [&alpha, &beta] {
+            fs::create_dir_all(dir.join("skills/demo")).unwrap();
+            fs::write(dir.join("skills/demo/SKILL.md"), "demo").unwrap();
+        }
+        fs::write(
+            claude.join("plugins/installed_plugins.json"),
+            serde_json::json!({
+                "version": 1,
+                "plugins": {
+                    "alpha@mkt": { "version": "1.0.0", "installPath": alpha },
+                    "beta@mkt": { "version": "1.0.0", "installPath": beta }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            claude.join("settings.json"),
+            r#"{"enabledPlugins": {"beta@mkt": false}}"#,
+        )
+        .unwrap();
+
+        let roots = vec![SkillRoot {
+            root: cache,
+            source: SkillSource::Cache,
+        }];
+        let skills = discover_skills(&roots, None).unwrap();
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills[0].name, "mkt/alpha/1.0.0/skills/demo/SKILL.md");
+        assert_eq!(skills[0].plugin.as_deref(), Some("alpha"));
+    }
+
     #[test]
     fn test_discover_skills_empty_root() {
         let tmp = tempdir().unwrap();
@@ -75,16 +75,22 @@ pub struct SkillMeta {
     pub source: SkillSource,
     pub root: PathBuf,
     pub hash: String,
+    /// Claude plugin providing this skill, when known from the plugin registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
 }
 
 /// Metadata for a discovered agent definition.
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AgentMeta {
     pub name: String,
     pub path: PathBuf,
     pub source: SkillSource,
     pub root: PathBuf,
     pub hash: String,
+    /// Claude plugin providing this agent, when known from the plugin registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
 }
 
 /// Information about a duplicate skill that was skipped due to priority.
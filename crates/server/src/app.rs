@@ -17,7 +17,7 @@ use crate::autoload::{
     env_embed_threshold, render_autoload_with_reader, render_preview_stats, AutoloadOptions,
     RenderMode,
 };
-use crate::cli::{Cli, Commands};
+use crate::cli::{Cli, Commands, OutputFormat, SubagentsAction, SyncAction};
 use crate::discovery::{
     agents_manifest, collect_agents, collect_skills, merge_extra_dirs, priority_labels,
     priority_labels_and_rank_map, read_skill, resolve_agent, resolve_skill, skill_roots,
@@ -31,6 +31,8 @@ use crate::runtime::{
 };
 use crate::signals::ignore_sigchld;
 use crate::sync::{mirror_source_root, sync_agents, sync_from_claude};
+#[cfg(feature = "watch")]
+use crate::sync_watch::SyncWatch;
 use crate::trace::stdio_with_optional_trace;
 use crate::tui::tui_flow;
 use anyhow::{anyhow, Result};
@@ -58,6 +60,12 @@ use skrills_state::{
 };
 #[cfg(feature = "subagents")]
 use skrills_subagents::SubagentService;
+#[cfg(feature = "watch")]
+use skrills_sync::parse_direction;
+use skrills_sync::{
+    AgentAdapter, ClaudeAdapter, CodexAdapter, ProjectScope, SyncOrchestrator, SyncParams,
+    SyncReport, SyncReportDocument,
+};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
@@ -120,6 +128,34 @@ fn peer_accepts_gzip(peer_info: Option<&ClientInfo>) -> bool {
     false
 }
 
+/// Relays subagent run progress to the client as `notifications/progress`.
+///
+/// Returns `None` when the request carries no progress token. The relay
+/// ends once the service drops the sender or the client goes away.
+#[cfg(feature = "subagents")]
+fn subagent_progress_relay(
+    context: &rmcp::service::RequestContext<rmcp::RoleServer>,
+) -> Option<skrills_subagents::ProgressSender> {
+    let token = context.meta.get_progress_token()?;
+    let peer = context.peer.clone();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<skrills_subagents::RunProgress>();
+    tokio::spawn(async move {
+        while let Some(update) = rx.recv().await {
+            let param = rmcp::model::ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress: update.progress,
+                total: update.total,
+                message: Some(update.message),
+            };
+            if let Err(e) = peer.notify_progress(param).await {
+                tracing::debug!(error = %e, "client stopped accepting progress");
+                return;
+            }
+        }
+    });
+    Some(tx)
+}
+
 /// Checks if the client is included in the manifest allowlist.
 ///
 /// The allowlist is an optional JSON file specified by the `SKRILLS_MANIFEST_ALLOWLIST`
@@ -524,6 +560,67 @@ impl ContentCache {
     }
 }
 
+/// Selects skills for subagent runs started with `autoload: true`, using the
+/// server's caches and the same pipeline as `autoload-snippet`.
+#[cfg(feature = "subagents")]
+struct SubagentSkills {
+    cache: Arc<Mutex<SkillCache>>,
+    content_cache: Arc<Mutex<ContentCache>>,
+}
+
+#[cfg(feature = "subagents")]
+impl skrills_subagents::SkillAutoloader for SubagentSkills {
+    fn autoload(
+        &self,
+        prompt: &str,
+        max_bytes: Option<usize>,
+    ) -> Result<skrills_subagents::SkillContext> {
+        let (skills, _) = self
+            .cache
+            .lock()
+            .map_err(|e| anyhow!("skill cache poisoned: {e}"))?
+            .skills_with_dups()?;
+        let content_cache = || {
+            self.content_cache
+                .lock()
+                .map_err(|e| anyhow!("content cache poisoned: {e}"))
+        };
+        let pinned = load_pinned_with_defaults().unwrap_or_default();
+        let mut matched = HashSet::new();
+        let mut diag = Diagnostics::default();
+        let content = render_autoload_with_reader(
+            &skills,
+            AutoloadOptions {
+                include_claude: env_include_claude_default(),
+                max_bytes: max_bytes.or(env_max_bytes()),
+                prompt: Some(prompt),
+                pinned: Some(&pinned),
+                matched: Some(&mut matched),
+                diagnostics: Some(&mut diag),
+                // The manifest stays small and is what remains when the
+                // content exceeds `max_bytes`.
+                render_mode: RenderMode::Dual,
+                minimal_manifest: true,
+                ..AutoloadOptions::default()
+            },
+            |meta| content_cache()?.read_full(meta),
+            |meta, max| content_cache()?.read_prefix(meta, max),
+        )?;
+        Ok(skrills_subagents::SkillContext {
+            content,
+            skills: skills
+                .iter()
+                .filter(|s| matched.contains(&s.name))
+                .map(|s| skrills_subagents::LoadedSkill {
+                    name: s.name.clone(),
+                    hash: s.hash.clone(),
+                })
+                .collect(),
+            truncated: diag.truncated,
+        })
+    }
+}
+
 /// Manages and serves skills via the Remote Method Call Protocol (RMCP).
 ///
 /// This service discovers, caches, and facilitates interaction with skills.
@@ -606,13 +703,22 @@ impl SkillService {
             skills = "deferred", // Skill discovery is deferred until after initialize to keep initial response fast.
             "SkillService constructed"
         );
+        let cache = Arc::new(Mutex::new(SkillCache::new_with_ttl(roots, ttl)));
+        let content_cache = Arc::new(Mutex::new(ContentCache::default()));
+        #[cfg(feature = "subagents")]
+        let subagents = SubagentService::new()?
+            .with_agent_roots(crate::discovery::agent_roots(&extra_dirs)?)
+            .with_skills(Arc::new(SubagentSkills {
+                cache: cache.clone(),
+                content_cache: content_cache.clone(),
+            }));
         Ok(Self {
-            cache: Arc::new(Mutex::new(SkillCache::new_with_ttl(roots, ttl))),
-            content_cache: Arc::new(Mutex::new(ContentCache::default())),
+            cache,
+            content_cache,
             warmup_started: AtomicBool::new(false),
             runtime: Arc::new(Mutex::new(RuntimeOverrides::load()?)),
             #[cfg(feature = "subagents")]
-            subagents: Some(SubagentService::new()?),
+            subagents: Some(subagents),
         })
     }
 
@@ -1010,6 +1116,10 @@ impl ServerHandler for SkillService {
                 "force": {
                     "type": "boolean",
                     "description": "Skip confirmation prompts"
+                },
+                "skip_memory": {
+                    "type": "boolean",
+                    "description": "Leave CLAUDE.md and AGENTS.md untouched (sync-all, sync-status)"
                 }
             }),
         );
@@ -1171,7 +1281,7 @@ impl ServerHandler for SkillService {
                 name: "sync-all".into(),
                 title: Some("Sync all configurations".into()),
                 description: Some(
-                    "Sync skills, commands, MCP servers, and preferences in one operation.".into(),
+                    "Sync skills, commands, MCP servers, preferences, and memory files in one operation.".into(),
                 ),
                 input_schema: sync_schema.clone(),
                 output_schema: None,
@@ -1225,21 +1335,28 @@ impl ServerHandler for SkillService {
                     "list_subagents"
                         | "run_subagent"
                         | "run_subagent_async"
+                        | "continue_run"
+                        | "run_subagent_batch"
                         | "get_run_status"
                         | "get_async_status"
                         | "stop_run"
                         | "get_run_history"
+                        | "get_usage_summary"
                         | "download_transcript_secure"
                 ) {
                     if let Some(service) = &self.subagents {
                         let args = request.arguments.as_ref();
-                        let res = service.handle_call(&name, args).await.map_err(|e| {
-                            rmcp::model::ErrorData::new(
-                                rmcp::model::ErrorCode::INTERNAL_ERROR,
-                                format!("subagent error: {e}"),
-                                None,
-                            )
-                        })?;
+                        let progress = subagent_progress_relay(&context);
+                        let res = service
+                            .handle_call_with_progress(&name, args, progress)
+                            .await
+                            .map_err(|e| {
+                                rmcp::model::ErrorData::new(
+                                    rmcp::model::ErrorCode::INTERNAL_ERROR,
+                                    format!("subagent error: {e}"),
+                                    None,
+                                )
+                            })?;
                         return Ok(res);
                     }
                 }
@@ -1641,6 +1758,7 @@ impl ServerHandler for SkillService {
                         sync_mcp_servers: false,
                         sync_preferences: false,
                         sync_skills: false,
+                        sync_memory: false,
                         include_marketplace,
                         ..Default::default()
                     };
@@ -1685,6 +1803,7 @@ impl ServerHandler for SkillService {
                         sync_mcp_servers: true,
                         sync_preferences: false,
                         sync_skills: false,
+                        sync_memory: false,
                         ..Default::default()
                     };
 
@@ -1727,6 +1846,7 @@ impl ServerHandler for SkillService {
                         sync_mcp_servers: false,
                         sync_preferences: true,
                         sync_skills: false,
+                        sync_memory: false,
                         ..Default::default()
                     };
 
@@ -1767,6 +1887,11 @@ impl ServerHandler for SkillService {
                         .and_then(|v| v.as_bool())
                         .unwrap_or(false);
 
+                    let skip_memory = request.arguments.as_ref()
+                        .and_then(|obj| obj.get("skip_memory"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+
                     // Sync skills first (using existing mechanism)
                     let skill_report = if from == "claude" && !dry_run {
                         let home = home_dir()?;
@@ -1790,6 +1915,7 @@ impl ServerHandler for SkillService {
                         sync_mcp_servers: true,
                         sync_preferences: true,
                         sync_skills: false, // Handled above
+                        sync_memory: !skip_memory,
                         include_marketplace,
                         ..Default::default()
                     };
@@ -1829,6 +1955,10 @@ impl ServerHandler for SkillService {
                         .and_then(|obj| obj.get("from"))
                         .and_then(|v| v.as_str())
                         .unwrap_or("claude");
+                    let skip_memory = request.arguments.as_ref()
+                        .and_then(|obj| obj.get("skip_memory"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
 
                     let params = SyncParams {
                         from: Some(from.to_string()),
@@ -1837,6 +1967,7 @@ impl ServerHandler for SkillService {
                         sync_mcp_servers: true,
                         sync_preferences: true,
                         sync_skills: true,
+                        sync_memory: !skip_memory,
                         ..Default::default()
                     };
 
@@ -1977,9 +2108,26 @@ impl ServerHandler for SkillService {
 }
 
 /// Print a JSON list of discovered skills.
-fn list_skills(extra_dirs: &[PathBuf]) -> Result<()> {
+///
+/// With `by_plugin`, prints an object keyed by plugin name instead; skills that
+/// do not come from a Claude plugin are keyed by their source label.
+fn list_skills(extra_dirs: &[PathBuf], by_plugin: bool) -> Result<()> {
     let skills = collect_skills(extra_dirs)?;
-    println!("{}", serde_json::to_string_pretty(&skills)?);
+    if by_plugin {
+        let mut grouped: std::collections::BTreeMap<String, Vec<SkillMeta>> =
+            std::collections::BTreeMap::new();
+        for skill in skills {
+            let key = skill
+                .plugin
+                .as_ref()
+                .map(|p| format!("plugin:{p}"))
+                .unwrap_or_else(|| skill.source.label());
+            grouped.entry(key).or_default().push(skill);
+        }
+        println!("{}", serde_json::to_string_pretty(&grouped)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&skills)?);
+    }
     Ok(())
 }
 
@@ -2005,6 +2153,12 @@ fn handle_serve_command(
     let transport = stdio_with_optional_trace(trace_wire);
     let rt = Runtime::new()?;
     let running = rt.block_on(async {
+        // Requeue or fail runs a previous server left unfinished before
+        // clients can query them.
+        #[cfg(feature = "subagents")]
+        if let Some(subagents) = &service.subagents {
+            subagents.start().await;
+        }
         serve_server(service, transport)
             .await
             .map_err(|e| anyhow!("failed to start server: {e}"))
@@ -2086,15 +2240,20 @@ fn handle_sync_agents_command(path: Option<PathBuf>, skill_dirs: Vec<PathBuf>) -
 }
 
 /// Handle the `sync` command.
-fn handle_sync_command(include_marketplace: bool) -> Result<()> {
+fn handle_sync_command(include_marketplace: bool, format: OutputFormat) -> Result<()> {
     let home = home_dir()?;
-    let report = sync_from_claude(
+    let mirrored = sync_from_claude(
         &mirror_source_root(&home),
         &home.join(".codex/skills-mirror"),
         include_marketplace,
     )?;
-    println!("copied: {}, skipped: {}", report.copied, report.skipped);
-    Ok(())
+    let mut report = SyncReport::new();
+    report.skills = mirrored.to_write_report();
+    report.summary = report.format_summary("claude", "codex");
+
+    let text = format!("copied: {}, skipped: {}", mirrored.copied, mirrored.skipped);
+    let doc = sync_document("sync", "claude", None, &report, false);
+    print_sync_output(format, &text, &doc)
 }
 
 fn handle_mirror_command(
@@ -2126,6 +2285,7 @@ fn handle_mirror_command(
         skip_existing_commands,
         sync_mcp_servers: true,
         sync_preferences: true,
+        sync_memory: false,
         include_marketplace,
         ..Default::default()
     };
@@ -2157,6 +2317,418 @@ fn handle_mirror_command(
     Ok(())
 }
 
+/// Resolves the `--project` flag: `None` targets the user's home configuration,
+/// `Some(None)` the git root of the current directory.
+fn resolve_project(project: Option<Option<PathBuf>>) -> Result<Option<ProjectScope>> {
+    project
+        .map(|dir| ProjectScope::resolve(dir.as_deref()))
+        .transpose()
+}
+
+/// Runs the sync orchestrator from `from` to the other agent, either on the
+/// home configuration or within a project.
+fn run_sync(from: &str, project: Option<&ProjectScope>, params: &SyncParams) -> Result<SyncReport> {
+    let (claude, codex) = match project {
+        Some(scope) => (scope.claude_adapter(), scope.codex_adapter()),
+        None => (ClaudeAdapter::new()?, CodexAdapter::new()?),
+    };
+    if from == "claude" {
+        SyncOrchestrator::new(claude, codex).sync(params)
+    } else {
+        SyncOrchestrator::new(codex, claude).sync(params)
+    }
+}
+
+/// The agent synced into when syncing from `from`.
+fn target_agent(from: &str) -> &'static str {
+    if from == "claude" {
+        "codex"
+    } else {
+        "claude"
+    }
+}
+
+/// Trailing note for dry-run text output.
+fn dry_run_note(dry_run: bool) -> &'static str {
+    if dry_run {
+        "\n(dry run - no changes made)"
+    } else {
+        ""
+    }
+}
+
+/// Builds the versioned JSON report for a sync command.
+fn sync_document(
+    command: &str,
+    from: &str,
+    project: Option<&ProjectScope>,
+    report: &SyncReport,
+    dry_run: bool,
+) -> SyncReportDocument {
+    SyncReportDocument::new(command, from, target_agent(from), report)
+        .dry_run(dry_run)
+        .project(project.map(|scope| scope.root().to_path_buf()))
+}
+
+/// Prints `text`, or the JSON report when `--format json` is selected.
+fn print_sync_output(format: OutputFormat, text: &str, doc: &SyncReportDocument) -> Result<()> {
+    match format {
+        OutputFormat::Text => println!("{text}"),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(doc)?),
+    }
+    Ok(())
+}
+
+/// Formats the list of commands kept on the target because of `--skip-existing-commands`.
+fn skipped_commands_note(report: &SyncReport, skip_existing_commands: bool) -> String {
+    if skip_existing_commands && !report.commands.skipped.is_empty() {
+        format!(
+            "\nSkipped existing commands (kept target copy): {}",
+            report
+                .commands
+                .skipped
+                .iter()
+                .map(|r| r.description())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// Handle the `sync-commands` command.
+fn handle_sync_commands_command(
+    from: String,
+    dry_run: bool,
+    skip_existing_commands: bool,
+    include_marketplace: bool,
+    project: Option<Option<PathBuf>>,
+    format: OutputFormat,
+) -> Result<()> {
+    let project = resolve_project(project)?;
+    // A project sync writes under the project, never to ~/.codex/prompts.
+    let overwrite_warning = (!skip_existing_commands && project.is_none()).then_some(
+        "syncing commands will overwrite existing files under ~/.codex/prompts when names match. Use --skip-existing-commands to keep existing copies.",
+    );
+    if let (Some(warning), OutputFormat::Text) = (overwrite_warning, format) {
+        eprintln!("Warning: {warning}");
+    }
+
+    let params = SyncParams {
+        from: Some(from.clone()),
+        dry_run,
+        sync_commands: true,
+        skip_existing_commands,
+        sync_mcp_servers: false,
+        sync_preferences: false,
+        sync_skills: false,
+        sync_memory: false,
+        include_marketplace,
+        ..Default::default()
+    };
+    let report = run_sync(&from, project.as_ref(), &params)?;
+
+    let text = format!(
+        "{}{}{}",
+        report.summary,
+        skipped_commands_note(&report, skip_existing_commands),
+        dry_run_note(dry_run)
+    );
+    let mut doc = sync_document("sync-commands", &from, project.as_ref(), &report, dry_run);
+    if let Some(warning) = overwrite_warning {
+        doc = doc.warning(warning);
+    }
+    print_sync_output(format, &text, &doc)
+}
+
+/// Handle the `sync-mcp-servers` command.
+fn handle_sync_mcp_servers_command(
+    from: String,
+    dry_run: bool,
+    project: Option<Option<PathBuf>>,
+    format: OutputFormat,
+) -> Result<()> {
+    let project = resolve_project(project)?;
+    let params = SyncParams {
+        from: Some(from.clone()),
+        dry_run,
+        sync_commands: false,
+        sync_mcp_servers: true,
+        sync_preferences: false,
+        sync_skills: false,
+        sync_memory: false,
+        ..Default::default()
+    };
+    let report = run_sync(&from, project.as_ref(), &params)?;
+
+    let text = format!("{}{}", report.summary, dry_run_note(dry_run));
+    let doc = sync_document(
+        "sync-mcp-servers",
+        &from,
+        project.as_ref(),
+        &report,
+        dry_run,
+    );
+    print_sync_output(format, &text, &doc)
+}
+
+/// Handle the `sync-preferences` command.
+fn handle_sync_preferences_command(
+    from: String,
+    dry_run: bool,
+    project: Option<Option<PathBuf>>,
+    format: OutputFormat,
+) -> Result<()> {
+    let project = resolve_project(project)?;
+    let params = SyncParams {
+        from: Some(from.clone()),
+        dry_run,
+        sync_commands: false,
+        sync_mcp_servers: false,
+        sync_preferences: true,
+        sync_skills: false,
+        sync_memory: false,
+        ..Default::default()
+    };
+    let report = run_sync(&from, project.as_ref(), &params)?;
+
+    let text = format!("{}{}", report.summary, dry_run_note(dry_run));
+    let doc = sync_document(
+        "sync-preferences",
+        &from,
+        project.as_ref(),
+        &report,
+        dry_run,
+    );
+    print_sync_output(format, &text, &doc)
+}
+
+/// Handle the `sync-all` command.
+fn handle_sync_all_command(
+    from: String,
+    dry_run: bool,
+    skip_existing_commands: bool,
+    include_marketplace: bool,
+    skip_memory: bool,
+    project: Option<Option<PathBuf>>,
+    format: OutputFormat,
+) -> Result<()> {
+    let project = resolve_project(project)?;
+
+    // First sync skills using existing mechanism
+    let skill_report = if from == "claude" && !dry_run {
+        let (claude_root, mirror_root) = match &project {
+            Some(scope) => (
+                scope.root().join(".claude/skills"),
+                scope.root().join(".codex/skills"),
+            ),
+            None => {
+                let home = home_dir()?;
+                (mirror_source_root(&home), home.join(".codex/skills-mirror"))
+            }
+        };
+        Some(sync_from_claude(
+            &claude_root,
+            &mirror_root,
+            include_marketplace,
+        )?)
+    } else {
+        None
+    };
+
+    // Then sync commands, MCP servers, preferences, memory, and skills (Codex source)
+    let sync_skills = from != "claude";
+    let params = SyncParams {
+        from: Some(from.clone()),
+        dry_run,
+        sync_commands: true,
+        skip_existing_commands,
+        sync_mcp_servers: true,
+        sync_preferences: true,
+        sync_skills, // Claude source handled above; enable for Codex→Claude
+        sync_memory: !skip_memory,
+        include_marketplace,
+        ..Default::default()
+    };
+    let mut report = run_sync(&from, project.as_ref(), &params)?;
+    if let Some(skill_report) = &skill_report {
+        report.skills = skill_report.to_write_report();
+        report.summary = report.format_summary(&from, target_agent(&from));
+    }
+
+    let text = format!(
+        "{}{}{}",
+        report.summary,
+        skipped_commands_note(&report, skip_existing_commands),
+        dry_run_note(dry_run)
+    );
+    let doc = sync_document("sync-all", &from, project.as_ref(), &report, dry_run);
+    print_sync_output(format, &text, &doc)
+}
+
+/// Handle the `sync-status` command.
+fn handle_sync_status_command(
+    from: String,
+    skip_memory: bool,
+    project: Option<Option<PathBuf>>,
+    format: OutputFormat,
+) -> Result<()> {
+    let project = resolve_project(project)?;
+    let sync_skills = from != "claude";
+    let params = SyncParams {
+        from: Some(from.clone()),
+        dry_run: true,
+        sync_commands: true,
+        sync_mcp_servers: true,
+        sync_preferences: true,
+        sync_skills,
+        sync_memory: !skip_memory,
+        ..Default::default()
+    };
+    let report = run_sync(&from, project.as_ref(), &params)?;
+
+    let mut text = format!("Sync direction: {} → {}", from, target_agent(&from));
+    if let Some(scope) = &project {
+        text.push_str(&format!("\nProject: {}", scope.root().display()));
+    }
+    text.push_str("\n\nPending changes:");
+    text.push_str(&format!(
+        "\n  Commands: {} would sync",
+        report.commands.written
+    ));
+    text.push_str(&format!(
+        "\n  MCP Servers: {} would sync",
+        report.mcp_servers.written
+    ));
+    text.push_str(&format!(
+        "\n  Preferences: {} would sync",
+        report.preferences.written
+    ));
+    text.push_str(&format!("\n  Memory: {} would sync", report.memory.written));
+
+    // Count skills
+    let source_root = match &project {
+        Some(scope) if from == "claude" => scope.root().join(".claude/skills"),
+        Some(scope) => scope.root().join(".codex/skills"),
+        None => {
+            let home = home_dir()?;
+            if from == "claude" {
+                mirror_source_root(&home)
+            } else {
+                home.join(".codex/skills")
+            }
+        }
+    };
+    let skill_count = walkdir::WalkDir::new(&source_root)
+        .min_depth(1)
+        .max_depth(6)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(crate::discovery::is_skill_file)
+        .count();
+    text.push_str(&format!("\n  Skills: {} found in source", skill_count));
+
+    // Group Claude plugin content by plugin when the registry is available.
+    let claude = match &project {
+        Some(scope) => scope.claude_adapter(),
+        None => ClaudeAdapter::new()?,
+    };
+    let plugins = claude.installed_plugins();
+    let mut plugin_details = Vec::new();
+    if !plugins.is_empty() {
+        let commands = claude.read_commands(false)?;
+        let servers = claude.read_mcp_servers()?;
+        text.push_str("\n\nClaude plugins:");
+        for plugin in &plugins {
+            let owned_commands = commands
+                .iter()
+                .filter(|c| c.plugin.as_deref() == Some(plugin.name.as_str()))
+                .count();
+            let owned_servers = servers
+                .values()
+                .filter(|s| s.plugin.as_deref() == Some(plugin.name.as_str()))
+                .count();
+            text.push_str(&format!(
+                "\n  {} ({}): {} commands, {} MCP servers",
+                plugin.label(),
+                if plugin.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                },
+                owned_commands,
+                owned_servers
+            ));
+            plugin_details.push(json!({
+                "id": plugin.id,
+                "name": plugin.name,
+                "version": plugin.version,
+                "enabled": plugin.enabled,
+                "commands": owned_commands,
+                "mcp_servers": owned_servers,
+            }));
+        }
+    }
+
+    let doc = sync_document("sync-status", &from, project.as_ref(), &report, true).details(json!({
+        "skills_found": skill_count,
+        "plugins": plugin_details,
+    }));
+    print_sync_output(format, &text, &doc)
+}
+
+/// Handle the `sync watch` command.
+#[cfg(feature = "watch")]
+fn handle_sync_watch_command(
+    from: Option<String>,
+    debounce_ms: u64,
+    include_marketplace: bool,
+    skip_memory: bool,
+    project: Option<Option<PathBuf>>,
+) -> Result<()> {
+    let watch = match resolve_project(project)? {
+        Some(scope) => SyncWatch::for_project(scope),
+        None => SyncWatch::for_home(&home_dir()?),
+    };
+    let watch = match from {
+        Some(from) => watch.one_way(parse_direction(&from)?),
+        None => watch,
+    };
+    watch
+        .include_marketplace(include_marketplace)
+        .skip_memory(skip_memory)
+        .run(Duration::from_millis(debounce_ms))
+}
+
+/// Placeholder for `sync watch` when the 'watch' feature is disabled.
+#[cfg(not(feature = "watch"))]
+fn handle_sync_watch_command(
+    _from: Option<String>,
+    _debounce_ms: u64,
+    _include_marketplace: bool,
+    _skip_memory: bool,
+    _project: Option<Option<PathBuf>>,
+) -> Result<()> {
+    Err(anyhow!(
+        "watch feature is disabled; rebuild with --features watch"
+    ))
+}
+
+/// Handle the `subagents` command.
+#[cfg(feature = "subagents")]
+fn handle_subagents_command(action: SubagentsAction) -> Result<()> {
+    crate::subagents::run(action)
+}
+
+/// Placeholder for `subagents` when the 'subagents' feature is disabled.
+#[cfg(not(feature = "subagents"))]
+fn handle_subagents_command(_action: SubagentsAction) -> Result<()> {
+    Err(anyhow!(
+        "subagents feature is disabled; rebuild with --features subagents"
+    ))
+}
+
 fn handle_agent_command(agent_spec: String, skill_dirs: Vec<PathBuf>, dry_run: bool) -> Result<()> {
     let agents = collect_agents(&merge_extra_dirs(&skill_dirs))?;
     let agent = resolve_agent(&agent_spec, &agents)?;
@@ -2221,8 +2793,66 @@ pub fn run() -> Result<()> {
     let is_serve = matches!(command_ref, Some(Commands::Serve { .. }) | None);
     let is_setup = matches!(command_ref, Some(Commands::Setup { .. }));
     let is_batch = matches!(command_ref, Some(Commands::SyncAll { .. }));
+    // JSON output must stay parseable, so never interleave the setup prompt.
+    let is_json = matches!(
+        command_ref,
+        Some(
+            Commands::Sync {
+                format: OutputFormat::Json,
+                ..
+            } | Commands::SyncCommands {
+                format: OutputFormat::Json,
+                ..
+            } | Commands::SyncMcpServers {
+                format: OutputFormat::Json,
+                ..
+            } | Commands::SyncPreferences {
+                format: OutputFormat::Json,
+                ..
+            } | Commands::SyncStatus {
+                format: OutputFormat::Json,
+                ..
+            } | Commands::Subagents {
+                action: SubagentsAction::Run {
+                    format: OutputFormat::Json,
+                    ..
+                } | SubagentsAction::Run { json: true, .. }
+                    | SubagentsAction::Eval {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::Status {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::Status { json: true, .. }
+                    | SubagentsAction::Logs {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::Logs { json: true, .. }
+                    | SubagentsAction::Stop {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::History {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::History { json: true, .. }
+                    | SubagentsAction::Usage {
+                        format: OutputFormat::Json,
+                        ..
+                    }
+                    | SubagentsAction::VerifyTranscript {
+                        format: OutputFormat::Json,
+                        ..
+                    },
+            }
+        )
+    );
 
-    if !is_serve && !is_setup && !is_batch {
+    if !is_serve && !is_setup && !is_batch && !is_json {
         if let Ok(true) = crate::setup::is_first_run() {
             if let Ok(true) = crate::setup::prompt_first_run_setup() {
                 // Run interactive setup
@@ -2260,7 +2890,7 @@ pub fn run() -> Result<()> {
             #[cfg(feature = "watch")]
             watch,
         ),
-        Commands::List => list_skills(&merge_extra_dirs(&[])),
+        Commands::List { by_plugin } => list_skills(&merge_extra_dirs(&[]), by_plugin),
         Commands::ListPinned => handle_list_pinned_command(),
         Commands::Pin { skills } => handle_pin_command(skills),
         Commands::Unpin { skills, all } => handle_unpin_command(skills, all),
@@ -2298,252 +2928,74 @@ pub fn run() -> Result<()> {
             diagnose,
         ),
         Commands::Sync {
+            action: None,
             include_marketplace,
-        } => handle_sync_command(include_marketplace),
+            format,
+        } => handle_sync_command(include_marketplace, format),
+        Commands::Sync {
+            action:
+                Some(SyncAction::Watch {
+                    from,
+                    debounce_ms,
+                    include_marketplace,
+                    skip_memory,
+                    project,
+                }),
+            ..
+        } => {
+            handle_sync_watch_command(from, debounce_ms, include_marketplace, skip_memory, project)
+        }
         Commands::SyncCommands {
             from,
             dry_run,
             skip_existing_commands,
             include_marketplace,
-        } => {
-            use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
-
-            if !skip_existing_commands {
-                eprintln!(
-            "Warning: syncing commands will overwrite existing files under ~/.codex/prompts when names match. Use --skip-existing-commands to keep existing copies."
-        );
-            }
-
-            let params = SyncParams {
-                from: Some(from.clone()),
-                dry_run,
-                sync_commands: true,
-                skip_existing_commands,
-                sync_mcp_servers: false,
-                sync_preferences: false,
-                sync_skills: false,
-                include_marketplace,
-                ..Default::default()
-            };
-
-            let report = if from == "claude" {
-                let source = ClaudeAdapter::new()?;
-                let target = CodexAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            } else {
-                let source = CodexAdapter::new()?;
-                let target = ClaudeAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            };
-
-            println!(
-                "{}{}",
-                report.summary,
-                if skip_existing_commands && !report.commands.skipped.is_empty() {
-                    format!(
-                        "\nSkipped existing commands (kept target copy): {}",
-                        report
-                            .commands
-                            .skipped
-                            .iter()
-                            .map(|r| r.description())
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    )
-                } else {
-                    String::new()
-                }
-            );
-            if dry_run {
-                println!("(dry run - no changes made)");
-            }
-            Ok(())
-        }
-        Commands::SyncMcpServers { from, dry_run } => {
-            use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
-
-            let params = SyncParams {
-                from: Some(from.clone()),
-                dry_run,
-                sync_commands: false,
-                sync_mcp_servers: true,
-                sync_preferences: false,
-                sync_skills: false,
-                ..Default::default()
-            };
-
-            let report = if from == "claude" {
-                let source = ClaudeAdapter::new()?;
-                let target = CodexAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            } else {
-                let source = CodexAdapter::new()?;
-                let target = ClaudeAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            };
-
-            println!("{}", report.summary);
-            if dry_run {
-                println!("(dry run - no changes made)");
-            }
-            Ok(())
-        }
-        Commands::SyncPreferences { from, dry_run } => {
-            use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
-
-            let params = SyncParams {
-                from: Some(from.clone()),
-                dry_run,
-                sync_commands: false,
-                sync_mcp_servers: false,
-                sync_preferences: true,
-                sync_skills: false,
-                ..Default::default()
-            };
-
-            let report = if from == "claude" {
-                let source = ClaudeAdapter::new()?;
-                let target = CodexAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            } else {
-                let source = CodexAdapter::new()?;
-                let target = ClaudeAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            };
-
-            println!("{}", report.summary);
-            if dry_run {
-                println!("(dry run - no changes made)");
-            }
-            Ok(())
-        }
+            project,
+            format,
+        } => handle_sync_commands_command(
+            from,
+            dry_run,
+            skip_existing_commands,
+            include_marketplace,
+            project,
+            format,
+        ),
+        Commands::SyncMcpServers {
+            from,
+            dry_run,
+            project,
+            format,
+        } => handle_sync_mcp_servers_command(from, dry_run, project, format),
+        Commands::SyncPreferences {
+            from,
+            dry_run,
+            project,
+            format,
+        } => handle_sync_preferences_command(from, dry_run, project, format),
         Commands::SyncAll {
             from,
             dry_run,
             skip_existing_commands,
             include_marketplace,
-        } => {
-            use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
-
-            // First sync skills using existing mechanism
-            if from == "claude" && !dry_run {
-                let home = home_dir()?;
-                let claude_root = mirror_source_root(&home);
-                let mirror_root = home.join(".codex/skills-mirror");
-                let skill_report =
-                    sync_from_claude(&claude_root, &mirror_root, include_marketplace)?;
-                println!(
-                    "Skills: {} synced, {} unchanged",
-                    skill_report.copied, skill_report.skipped
-                );
-            }
-
-            // Then sync commands, MCP servers, preferences, and skills (Codex source)
-            let sync_skills = from != "claude";
-            let params = SyncParams {
-                from: Some(from.clone()),
-                dry_run,
-                sync_commands: true,
-                skip_existing_commands,
-                sync_mcp_servers: true,
-                sync_preferences: true,
-                sync_skills, // Claude source handled above; enable for Codex→Claude
-                include_marketplace,
-                ..Default::default()
-            };
-
-            let report = if from == "claude" {
-                let source = ClaudeAdapter::new()?;
-                let target = CodexAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            } else {
-                let source = CodexAdapter::new()?;
-                let target = ClaudeAdapter::new()?;
-                SyncOrchestrator::new(source, target).sync(&params)?
-            };
-
-            println!(
-                "{}{}",
-                report.summary,
-                if skip_existing_commands && !report.commands.skipped.is_empty() {
-                    format!(
-                        "\nSkipped existing commands (kept target copy): {}",
-                        report
-                            .commands
-                            .skipped
-                            .iter()
-                            .map(|r| r.description())
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    )
-                } else {
-                    String::new()
-                }
-            );
-            if dry_run {
-                println!("(dry run - no changes made)");
-            }
-            Ok(())
-        }
-        Commands::SyncStatus { from } => {
-            use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
-
-            let sync_skills = from != "claude";
-            let params = SyncParams {
-                from: Some(from.clone()),
-                dry_run: true,
-                sync_commands: true,
-                sync_mcp_servers: true,
-                sync_preferences: true,
-                sync_skills,
-                ..Default::default()
-            };
-
-            let report = if from == "claude" {
-                let source = ClaudeAdapter::new()?;
-                let target = CodexAdapter::new()?;
-                let orch = SyncOrchestrator::new(source, target);
-                println!(
-                    "Sync direction: {} → {}",
-                    orch.source_name(),
-                    orch.target_name()
-                );
-                orch.sync(&params)?
-            } else {
-                let source = CodexAdapter::new()?;
-                let target = ClaudeAdapter::new()?;
-                let orch = SyncOrchestrator::new(source, target);
-                println!(
-                    "Sync direction: {} → {}",
-                    orch.source_name(),
-                    orch.target_name()
-                );
-                orch.sync(&params)?
-            };
-
-            println!("\nPending changes:");
-            println!("  Commands: {} would sync", report.commands.written);
-            println!("  MCP Servers: {} would sync", report.mcp_servers.written);
-            println!("  Preferences: {} would sync", report.preferences.written);
-
-            // Count skills
-            let home = home_dir()?;
-            let source_root = if from == "claude" {
-                mirror_source_root(&home)
-            } else {
-                home.join(".codex/skills")
-            };
-            let skill_count = walkdir::WalkDir::new(&source_root)
-                .min_depth(1)
-                .max_depth(6)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(crate::discovery::is_skill_file)
-                .count();
-            println!("  Skills: {} found in source", skill_count);
-
-            Ok(())
-        }
+            skip_memory,
+            project,
+            format,
+        } => handle_sync_all_command(
+            from,
+            dry_run,
+            skip_existing_commands,
+            include_marketplace,
+            skip_memory,
+            project,
+            format,
+        ),
+        Commands::SyncStatus {
+            from,
+            skip_memory,
+            project,
+            format,
+        } => handle_sync_status_command(from, skip_memory, project, format),
+        Commands::Subagents { action } => handle_subagents_command(action),
         Commands::Doctor => doctor_report(),
         Commands::Tui { skill_dirs } => tui_flow(&merge_extra_dirs(&skill_dirs)),
         Commands::Setup {
@@ -2625,6 +3077,7 @@ mod tests {
                 source,
                 root: root.to_path_buf(),
                 hash: hash_file(&path)?,
+                plugin: None,
             }];
             Ok(SkillFixture { skills })
         }
@@ -2850,6 +3303,62 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "subagents")]
+    #[test]
+    fn subagent_autoload_reports_matched_skills_and_hashes() -> Result<()> {
+        use skrills_subagents::SkillAutoloader;
+
+        let _guard = env_guard();
+        let tmp = tempdir()?;
+        let original_home = std::env::var("HOME").ok();
+        std::env::set_var("HOME", tmp.path());
+        let codex_root = tmp.path().join("codex/skills");
+        for (name, body) in [
+            (
+                "borrowck",
+                "How the Rust borrow checker reasons about lifetimes.",
+            ),
+            ("pandas", "Cleaning dataframes."),
+        ] {
+            fs::create_dir_all(codex_root.join(name))?;
+            fs::write(codex_root.join(name).join("SKILL.md"), body)?;
+        }
+        let skills = SubagentSkills {
+            cache: Arc::new(Mutex::new(SkillCache::new_with_ttl(
+                vec![SkillRoot {
+                    root: codex_root.clone(),
+                    source: SkillSource::Codex,
+                }],
+                Duration::from_secs(3600),
+            ))),
+            content_cache: Arc::new(Mutex::new(ContentCache::default())),
+        };
+
+        let context = skills.autoload("fix this borrowck error", None)?;
+        let names: Vec<_> = context.skills.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["borrowck/SKILL.md"]);
+        assert_eq!(
+            context.skills[0].hash,
+            hash_file(&codex_root.join("borrowck/SKILL.md"))?
+        );
+        assert!(context
+            .content
+            .contains("borrow checker reasons about lifetimes"));
+        assert!(!context.truncated);
+
+        // Too small for the content: only the manifest is left.
+        let context = skills.autoload("fix this borrowck error", Some(200))?;
+        assert!(context.truncated);
+        assert!(!context.content.contains("lifetimes"));
+
+        if let Some(home) = original_home {
+            std::env::set_var("HOME", home);
+        } else {
+            std::env::remove_var("HOME");
+        }
+        Ok(())
+    }
+
     #[test]
     fn runtime_overrides_cached_memoizes() -> Result<()> {
         let _guard = env_guard();
@@ -3319,6 +3828,7 @@ mod tests {
                 source: SkillSource::Codex,
                 root: codex_dir.clone(),
                 hash: hash_file(&codex_skill)?,
+                plugin: None,
             },
             SkillMeta {
                 name: "mirror/SKILL.md".into(),
@@ -3326,6 +3836,7 @@ mod tests {
                 source: SkillSource::Mirror,
                 root: mirror_dir.clone(),
                 hash: hash_file(&mirror_skill)?,
+                plugin: None,
             },
         ];
 
@@ -3424,6 +3935,7 @@ mod tests {
             source: SkillSource::Codex,
             root: codex_dir.clone(),
             hash: hash_file(&skill_path)?,
+            plugin: None,
         }];
 
         std::env::set_var("SKRILLS_EMBED_THRESHOLD", "0.9");
@@ -3460,6 +3972,7 @@ mod tests {
             source: SkillSource::Codex,
             root: codex_dir.clone(),
             hash: hash_file(&skill_path)?,
+            plugin: None,
         }];
 
         // WHEN embed threshold is high but keyword hits
@@ -3498,6 +4011,7 @@ mod tests {
             source: SkillSource::Codex,
             root: codex_dir.clone(),
             hash: hash_file(&skill_path)?,
+            plugin: None,
         }];
 
         let stats = render_preview_stats(&skills, false)?;
@@ -3522,6 +4036,7 @@ mod tests {
             source: SkillSource::Codex,
             root: codex_dir.clone(),
             hash: hash_file(&codex_skill)?,
+            plugin: None,
         }];
 
         let manifest_only_full = render_autoload(
@@ -3571,6 +4086,7 @@ mod tests {
                 source: SkillSource::Codex,
                 root: codex_dir.clone(),
                 hash: hash_file(&skill_path)?,
+                plugin: None,
             });
         }
 
@@ -3677,6 +4193,7 @@ mod tests {
             source: SkillSource::Codex,
             root: codex_dir.clone(),
             hash: hash_file(&codex_skill)?,
+            plugin: None,
         }];
 
         let full = render_autoload(
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// Command-line interface for the `skrills` application.
@@ -33,7 +33,11 @@ pub enum Commands {
     },
     /// Lists discovered skills (debug).
     #[command(alias = "list-skills")]
-    List,
+    List {
+        /// Groups skills by the Claude plugin that provides them.
+        #[arg(long, default_value_t = false)]
+        by_plugin: bool,
+    },
     /// Lists pinned skills.
     ListPinned,
     /// Mirrors Claude assets (skills, agents, commands, MCP prefs) into Codex defaults and refreshes AGENTS.md.
@@ -121,9 +125,14 @@ pub enum Commands {
     },
     /// Copies skills from `~/.claude` into `~/.codex/skills-mirror`.
     Sync {
+        #[command(subcommand)]
+        action: Option<SyncAction>,
         /// Include marketplace content (uninstalled plugins).
         #[arg(long, env = "SKRILLS_INCLUDE_MARKETPLACE", default_value_t = false)]
         include_marketplace: bool,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// Syncs slash commands between Claude Code and Codex.
     SyncCommands {
@@ -139,6 +148,13 @@ pub enum Commands {
         /// Include marketplace content (uninstalled plugins).
         #[arg(long, env = "SKRILLS_INCLUDE_MARKETPLACE", default_value_t = false)]
         include_marketplace: bool,
+        /// Syncs a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// Syncs MCP server configurations between Claude Code and Codex.
     SyncMcpServers {
@@ -148,6 +164,13 @@ pub enum Commands {
         /// Preview changes without writing.
         #[arg(long)]
         dry_run: bool,
+        /// Syncs a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// Syncs preferences between Claude Code and Codex.
     SyncPreferences {
@@ -157,8 +180,15 @@ pub enum Commands {
         /// Preview changes without writing.
         #[arg(long)]
         dry_run: bool,
+        /// Syncs a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
-    /// Syncs all configurations (commands, MCP servers, preferences, skills).
+    /// Syncs all configurations (commands, MCP servers, preferences, skills, memory).
     SyncAll {
         /// Source agent: "claude" or "codex".
         #[arg(long, default_value = "claude")]
@@ -169,15 +199,40 @@ pub enum Commands {
         /// Do not overwrite existing commands on target side.
         #[arg(long, default_value_t = false)]
         skip_existing_commands: bool,
+        /// Leave CLAUDE.md and AGENTS.md untouched.
+        #[arg(long, default_value_t = false)]
+        skip_memory: bool,
         /// Include marketplace content (uninstalled plugins).
         #[arg(long, env = "SKRILLS_INCLUDE_MARKETPLACE", default_value_t = false)]
         include_marketplace: bool,
+        /// Syncs a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// Shows sync status and configuration differences.
     SyncStatus {
         /// Source agent: "claude" or "codex".
         #[arg(long, default_value = "claude")]
         from: String,
+        /// Leave CLAUDE.md and AGENTS.md untouched.
+        #[arg(long, default_value_t = false)]
+        skip_memory: bool,
+        /// Syncs a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+        /// Output format: human-readable text or a versioned JSON report.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Inspects subagent runs recorded by the MCP server (requires `subagents` feature).
+    Subagents {
+        #[command(subcommand)]
+        action: SubagentsAction,
     },
     /// Diagnoses Codex MCP configuration for this server.
     Doctor,
@@ -215,3 +270,156 @@ pub enum Commands {
         mirror_source: Option<PathBuf>,
     },
 }
+
+/// Subcommands of `skrills subagents`.
+#[derive(Debug, Subcommand)]
+pub enum SubagentsAction {
+    /// Runs a subagent and waits for it to finish, printing its events.
+    Run {
+        /// Prompt sent to the subagent.
+        prompt: String,
+        /// Backend to run on: codex, claude, codex-cli, claude-cli or a declared backend.
+        #[arg(long)]
+        backend: Option<String>,
+        /// Template id, as listed by the `list_subagents` tool.
+        #[arg(long, value_name = "ID")]
+        template: Option<String>,
+        /// Output format: events and output as text, or the finished run as JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Shorthand for `--format json`.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Runs an eval suite and reports which cases pass their assertions.
+    Eval {
+        /// Suite file listing the cases and their assertions.
+        suite: PathBuf,
+        /// Answers cases with the outputs recorded in this directory instead of running them.
+        #[arg(long, value_name = "DIR", conflicts_with = "record")]
+        replay: Option<PathBuf>,
+        /// Saves each successful output in this directory for later `--replay` runs.
+        #[arg(long, value_name = "DIR")]
+        record: Option<PathBuf>,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Shows a run's state, output and token usage.
+    Status {
+        run_id: String,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Shorthand for `--format json`.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Prints the events recorded for a run.
+    Logs {
+        run_id: String,
+        /// Keeps printing new events until the run finishes.
+        #[arg(long, short)]
+        follow: bool,
+        /// Output format: text, or one JSON event per line.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Shorthand for `--format json`.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Cancels a run that has not finished; a server executing it aborts it
+    /// within a second.
+    Stop {
+        run_id: String,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Lists recorded runs, most recent first.
+    History {
+        /// Maximum number of runs to list.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only runs in this state (pending, running, succeeded, failed, canceled, schema_failed).
+        #[arg(long)]
+        state: Option<String>,
+        /// Only runs on this backend.
+        #[arg(long)]
+        backend: Option<String>,
+        /// Only runs from this `run_subagent_batch` batch.
+        #[arg(long, value_name = "ID")]
+        batch: Option<String>,
+        /// Only runs from the last N days.
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Shorthand for `--format json`.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Summarizes token usage and cost of recorded runs by backend, template and day.
+    Usage {
+        /// Only counts runs from the last N days.
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Checks the signature and manifest of a transcript bundle offline.
+    VerifyTranscript {
+        /// Bundle written by `download_transcript_secure`.
+        bundle: PathBuf,
+        /// Keyfile the bundle was signed with (default: ~/.codex/subagents/transcript.key).
+        #[arg(long, value_name = "FILE", env = "SKRILLS_TRANSCRIPT_KEY_FILE")]
+        key: Option<PathBuf>,
+        /// Output format: human-readable text or JSON.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+/// Subcommands of `skrills sync`.
+#[derive(Debug, Subcommand)]
+pub enum SyncAction {
+    /// Watches Claude and Codex config and syncs changes continuously (requires `watch` feature).
+    Watch {
+        /// Only sync changes made on this side ("claude" or "codex"); both by default.
+        #[arg(long)]
+        from: Option<String>,
+        /// Quiet period before a batch of changes is synced, in milliseconds.
+        #[arg(long, value_name = "MILLIS", default_value_t = 500)]
+        debounce_ms: u64,
+        /// Include marketplace content (uninstalled plugins).
+        #[arg(long, env = "SKRILLS_INCLUDE_MARKETPLACE", default_value_t = false)]
+        include_marketplace: bool,
+        /// Leave CLAUDE.md and AGENTS.md untouched.
+        #[arg(long, default_value_t = false)]
+        skip_memory: bool,
+        /// Watches a repository's `.claude/`, `.mcp.json`, and CLAUDE.md with its
+        /// `.codex/` and AGENTS.md instead of home config (defaults to the git root).
+        #[arg(long, value_name = "DIR", num_args = 0..=1)]
+        project: Option<Option<PathBuf>>,
+    },
+}
+
+/// Output format for sync and report commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    /// [`OutputFormat::Json`] when a `--json` flag was given, `self` otherwise.
+    pub fn or_json(self, json: bool) -> Self {
+        if json {
+            Self::Json
+        } else {
+            self
+        }
+    }
+}
@@ -560,6 +560,7 @@ mod tests {
                 source: SkillSource::Codex,
                 root: PathBuf::from("/root"),
                 hash: "a".into(),
+                plugin: None,
             },
             AgentMeta {
                 name: "beta/agent.md".into(),
@@ -567,6 +568,7 @@ mod tests {
                 source: SkillSource::Claude,
                 root: PathBuf::from("/root"),
                 hash: "b".into(),
+                plugin: None,
             },
         ];
 
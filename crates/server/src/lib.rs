@@ -7,7 +7,9 @@
 //! - `autoload`: Handles skill autoloading and rendering.
 //! - `doctor`: Provides configuration diagnostics.
 //! - `emit`: Manages hook emission for shell integration.
+//! - `subagents`: `skrills subagents` reports over recorded runs (`subagents` feature).
 //! - `sync`: Manages skill synchronization.
+//! - `sync_watch`: Keeps Claude and Codex in sync as files change (`watch` feature).
 //! - `tui`: Implements the interactive terminal UI.
 
 mod app;
@@ -15,7 +17,11 @@ mod autoload;
 mod doctor;
 mod emit;
 mod setup;
+#[cfg(feature = "subagents")]
+mod subagents;
 mod sync;
+#[cfg(feature = "watch")]
+mod sync_watch;
 mod tui;
 
 /// Command-line interface for the server.
@@ -0,0 +1,477 @@
+//! `skrills subagents`: runs subagents and eval suites from the shell,
+//! reports over the runs recorded by the subagent MCP tools, and checks the
+//! transcripts they export offline.
+//!
+//! Runs are read from the same SQLite database the server writes, so the
+//! reports work while the server is running or not. `run` goes through the
+//! same [`SubagentService`] as the MCP tools; `stop` cancels the run in the
+//! store, as the TUI does.
+
+use crate::cli::{OutputFormat, SubagentsAction};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::json;
+use skrills_subagents::backend::cancel_run;
+use skrills_subagents::eval::{self, EvalSuite, Responses};
+use skrills_subagents::sqlite;
+use skrills_subagents::templates::{backend_from_str, default_config_path};
+use skrills_subagents::transcript::{self, default_key_path, TranscriptKey};
+use skrills_subagents::usage::{days_ago, summarize};
+use skrills_subagents::{
+    RunEvent, RunFilter, RunId, RunRecord, RunState, RunStore, SubagentService, SubagentsConfig,
+};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// How often `logs --follow` and `run` poll the store for new events.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+pub(crate) fn run(action: SubagentsAction) -> Result<()> {
+    match action {
+        SubagentsAction::Run {
+            prompt,
+            backend,
+            template,
+            format,
+            json,
+        } => run_subagent(prompt, backend, template, format.or_json(json)),
+        SubagentsAction::Eval {
+            suite,
+            replay,
+            record,
+            format,
+        } => eval(&suite, replay, record, format),
+        SubagentsAction::Status {
+            run_id,
+            format,
+            json,
+        } => status(&run_id, format.or_json(json)),
+        SubagentsAction::Logs {
+            run_id,
+            follow,
+            format,
+            json,
+        } => logs(&run_id, follow, format.or_json(json)),
+        SubagentsAction::Stop { run_id, format } => stop(&run_id, format),
+        SubagentsAction::History {
+            limit,
+            state,
+            backend,
+            batch,
+            days,
+            format,
+            json,
+        } => history(limit, state, backend, batch, days, format.or_json(json)),
+        SubagentsAction::Usage { days, format } => usage(days, format),
+        SubagentsAction::VerifyTranscript {
+            bundle,
+            key,
+            format,
+        } => verify_transcript(&bundle, key, format),
+    }
+}
+
+pub(crate) fn open_store() -> Result<Arc<dyn RunStore>> {
+    let config = SubagentsConfig::load(&default_config_path()?)?;
+    Ok(Arc::new(sqlite::open_default(&config)?))
+}
+
+async fn get_run(store: &Arc<dyn RunStore>, run_id: RunId) -> Result<RunRecord> {
+    store
+        .get_run(run_id)
+        .await?
+        .ok_or_else(|| anyhow!("run not found: {run_id}"))
+}
+
+/// Passes each event of `run_id` to `on_event` as it is recorded, returning
+/// the run once it finishes.
+async fn follow(
+    store: &Arc<dyn RunStore>,
+    run_id: RunId,
+    mut on_event: impl FnMut(&RunEvent) -> Result<()>,
+) -> Result<RunRecord> {
+    let mut seen = 0;
+    loop {
+        let record = get_run(store, run_id).await?;
+        for event in record.events.iter().skip(seen) {
+            on_event(event)?;
+        }
+        seen = record.events.len();
+        if record.status.state.is_terminal() {
+            return Ok(record);
+        }
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+    }
+}
+
+/// Writes events as text, one per line, with streamed tokens joined into a
+/// single line.
+#[derive(Default)]
+pub(crate) struct EventPrinter {
+    streaming: bool,
+}
+
+impl EventPrinter {
+    pub(crate) fn print(&mut self, out: &mut impl Write, event: &RunEvent) -> io::Result<()> {
+        let ts = event.ts;
+        let clock = format!("{:02}:{:02}:{:02}", ts.hour(), ts.minute(), ts.second());
+        let token = (event.kind == "stream")
+            .then(|| event.data.as_ref()?.get("token")?.as_str())
+            .flatten();
+        if let Some(token) = token {
+            if !self.streaming {
+                write!(out, "{clock} stream ")?;
+                self.streaming = true;
+            }
+            write!(out, "{token}")?;
+            return out.flush();
+        }
+        self.finish(out)?;
+        match &event.data {
+            Some(data) => writeln!(out, "{clock} {} {data}", event.kind),
+            None => writeln!(out, "{clock} {}", event.kind),
+        }
+    }
+
+    /// Ends a line of streamed tokens.
+    pub(crate) fn finish(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if std::mem::take(&mut self.streaming) {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+fn status_line(record: &RunRecord) -> String {
+    let mut line = format!(
+        "{}  {:<13} {:<10}",
+        record.id,
+        record.status.state.as_str(),
+        record.request.backend.name()
+    );
+    if let Some(message) = &record.status.message {
+        line.push_str(&format!(" {message}"));
+    }
+    line
+}
+
+/// Runs one subagent, printing its events to stderr while it runs and its
+/// output to stdout once it finishes.
+fn run_subagent(
+    prompt: String,
+    backend: Option<String>,
+    template: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut args = json!({"prompt": prompt, "stream": true});
+    if let Some(backend) = backend {
+        args["backend"] = json!(backend);
+    }
+    if let Some(template) = template {
+        args["template_id"] = json!(template);
+    }
+    let service = SubagentService::new()?.with_agent_roots(crate::discovery::agent_roots(&[])?);
+    Runtime::new()?.block_on(async {
+        let started = service
+            .handle_call("run_subagent", args.as_object())
+            .await?;
+        let run_id: RunId = started
+            .structured_content
+            .as_ref()
+            .and_then(|v| v.get("run_id")?.as_str())
+            .ok_or_else(|| anyhow!("run_subagent returned no run_id"))?
+            .parse()?;
+        let store = service.store();
+        let mut printer = EventPrinter::default();
+        let mut stderr = io::stderr();
+        let record = follow(&store, run_id, |event| {
+            if format == OutputFormat::Text {
+                printer.print(&mut stderr, event)?;
+            }
+            Ok(())
+        })
+        .await?;
+        printer.finish(&mut stderr)?;
+        match format {
+            OutputFormat::Text => {
+                if let Some(result) = &record.result {
+                    println!("{}", serde_json::to_string_pretty(result)?);
+                } else if let Some(text) = record.completion_text() {
+                    println!("{text}");
+                }
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&record)?),
+        }
+        if record.status.state != RunState::Succeeded {
+            bail!("{}", status_line(&record));
+        }
+        Ok(())
+    })
+}
+
+fn eval(
+    suite: &Path,
+    replay: Option<PathBuf>,
+    record: Option<PathBuf>,
+    format: OutputFormat,
+) -> Result<()> {
+    let suite = EvalSuite::load(suite)?;
+    let responses = match replay {
+        Some(dir) => Responses::Replay(dir),
+        None => Responses::Live { record },
+    };
+    let service = SubagentService::new()?.with_agent_roots(crate::discovery::agent_roots(&[])?);
+    let report = Runtime::new()?.block_on(eval::run_suite(&service, &suite, &responses))?;
+    match format {
+        OutputFormat::Text => print!("{}", report.render_text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    if report.failed > 0 {
+        bail!("{} of {} cases failed", report.failed, report.cases.len());
+    }
+    Ok(())
+}
+
+fn status(run_id: &str, format: OutputFormat) -> Result<()> {
+    let run_id: RunId = run_id.parse()?;
+    let store = open_store()?;
+    let record = Runtime::new()?.block_on(get_run(&store, run_id))?;
+    match format {
+        OutputFormat::Text => {
+            let request = &record.request;
+            println!("run:      {}", record.id);
+            println!("state:    {}", record.status.state.as_str());
+            if let Some(message) = &record.status.message {
+                println!("message:  {message}");
+            }
+            println!("backend:  {}", request.backend.name());
+            if let Some(template) = &request.template_id {
+                println!("template: {template}");
+            }
+            if let Some(batch) = &request.batch_id {
+                println!("batch:    {batch}");
+            }
+            println!("created:  {}", record.created_at);
+            println!("updated:  {}", record.updated_at);
+            if let Some(usage) = &record.usage {
+                println!(
+                    "tokens:   {} in, {} out, {} cached, {} cache-write",
+                    usage.input_tokens,
+                    usage.output_tokens,
+                    usage.cached_tokens,
+                    usage.cache_write_tokens
+                );
+            }
+            if let Some(result) = &record.result {
+                println!("\n{}", serde_json::to_string_pretty(result)?);
+            } else if let Some(text) = record.completion_text() {
+                println!("\n{text}");
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&record)?),
+    }
+    Ok(())
+}
+
+fn logs(run_id: &str, follow_run: bool, format: OutputFormat) -> Result<()> {
+    let run_id: RunId = run_id.parse()?;
+    let store = open_store()?;
+    let mut printer = EventPrinter::default();
+    let mut stdout = io::stdout();
+    let mut print = |event: &RunEvent| -> Result<()> {
+        match format {
+            OutputFormat::Text => printer.print(&mut stdout, event)?,
+            OutputFormat::Json => {
+                writeln!(stdout, "{}", serde_json::to_string(event)?)?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    };
+    let rt = Runtime::new()?;
+    if follow_run {
+        rt.block_on(follow(&store, run_id, &mut print))?;
+    } else {
+        for event in &rt.block_on(get_run(&store, run_id))?.events {
+            print(event)?;
+        }
+    }
+    printer.finish(&mut stdout)?;
+    Ok(())
+}
+
+fn stop(run_id: &str, format: OutputFormat) -> Result<()> {
+    let run_id: RunId = run_id.parse()?;
+    let store = open_store()?;
+    println!(
+        "{}",
+        Runtime::new()?.block_on(stop_run(&store, run_id, format))?
+    );
+    Ok(())
+}
+
+/// Cancels `run_id` and describes the outcome in `format`.
+async fn stop_run(
+    store: &Arc<dyn RunStore>,
+    run_id: RunId,
+    format: OutputFormat,
+) -> Result<String> {
+    let stopped = cancel_run(run_id, store).await?;
+    Ok(match format {
+        OutputFormat::Text if stopped => format!("stopped {run_id}"),
+        OutputFormat::Text => format!("{run_id} had already finished"),
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(&json!({"run_id": run_id, "stopped": stopped}))?
+        }
+    })
+}
+
+fn history(
+    limit: usize,
+    state: Option<String>,
+    backend: Option<String>,
+    batch: Option<String>,
+    days: Option<u32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let filter = RunFilter {
+        state: state.as_deref().map(str::parse).transpose()?,
+        backend: backend.as_deref().map(backend_from_str),
+        batch_id: batch,
+        since: days.map(days_ago),
+        limit: Some(limit),
+        // The text listing only prints status lines.
+        without_events: matches!(format, OutputFormat::Text),
+        ..RunFilter::default()
+    };
+    let store = open_store()?;
+    let runs = Runtime::new()?.block_on(store.query(&filter))?;
+    match format {
+        OutputFormat::Text => {
+            if runs.is_empty() {
+                println!("no runs recorded");
+            }
+            for record in &runs {
+                println!("{}", status_line(record));
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&runs)?),
+    }
+    Ok(())
+}
+
+fn usage(days: Option<u32>, format: OutputFormat) -> Result<()> {
+    let config = SubagentsConfig::load(&default_config_path()?)?;
+    let store = sqlite::open_default(&config)?;
+    let since = days.map(days_ago);
+    let filter = RunFilter {
+        since,
+        without_events: true,
+        ..RunFilter::default()
+    };
+    let runs = Runtime::new()?.block_on(store.query(&filter))?;
+    let summary = summarize(&runs, &config.prices, since);
+    match format {
+        OutputFormat::Text => print!("{}", summary.render_text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+    }
+    Ok(())
+}
+
+fn verify_transcript(bundle: &Path, key: Option<PathBuf>, format: OutputFormat) -> Result<()> {
+    let key_path = match key {
+        Some(path) => path,
+        None => default_key_path()?,
+    };
+    let key = TranscriptKey::load(&key_path)?;
+    let text =
+        std::fs::read_to_string(bundle).with_context(|| format!("reading {}", bundle.display()))?;
+    let verified = transcript::verify(&text, &key)?;
+    match format {
+        OutputFormat::Text => {
+            println!(
+                "ok: run {} ({}, {}) signed with key {}{}",
+                verified.run_id,
+                verified.backend,
+                verified.state.as_str(),
+                verified.key_id,
+                if verified.encrypted {
+                    ", encrypted"
+                } else {
+                    ""
+                }
+            );
+            for entry in &verified.manifest {
+                println!("  {}  {:>8}  {}", entry.sha256, entry.bytes, entry.name);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&verified)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use time::OffsetDateTime;
+
+    fn event(kind: &str, data: Option<Value>) -> RunEvent {
+        RunEvent {
+            ts: OffsetDateTime::UNIX_EPOCH,
+            kind: kind.into(),
+            data,
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_cancels_the_run_once() {
+        let store: Arc<dyn RunStore> = Arc::new(skrills_subagents::store::MemRunStore::new());
+        let run_id = store
+            .create_run(skrills_subagents::RunRequest {
+                prompt: "hi".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let out = stop_run(&store, run_id, OutputFormat::Text).await.unwrap();
+        assert_eq!(out, format!("stopped {run_id}"));
+        let record = store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Canceled);
+        assert_eq!(record.events.last().unwrap().kind, "canceled");
+
+        let out = stop_run(&store, run_id, OutputFormat::Json).await.unwrap();
+        let out: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(out["stopped"], false);
+        assert!(stop_run(
+            &store,
+            "00000000-0000-0000-0000-000000000000".parse().unwrap(),
+            OutputFormat::Text
+        )
+        .await
+        .is_err());
+    }
+
+    #[test]
+    fn printer_joins_streamed_tokens_into_one_line() {
+        let mut out = Vec::new();
+        let mut printer = EventPrinter::default();
+        for e in [
+            event("start", Some(json!({"pid": 7}))),
+            event("stream", Some(json!({"token": "Hel"}))),
+            event("stream", Some(json!({"token": "lo"}))),
+            event("completion", None),
+            event("stream", Some(json!({"token": "!"}))),
+        ] {
+            printer.print(&mut out, &e).unwrap();
+        }
+        printer.finish(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00:00:00 start {\"pid\":7}\n00:00:00 stream Hello\n00:00:00 completion\n00:00:00 stream !\n"
+        );
+    }
+}
@@ -5,7 +5,7 @@
 //! - Generating and updating `AGENTS.md` with available skills.
 
 use anyhow::Result;
-use skrills_discovery::{hash_file, AgentMeta, SkillMeta};
+use skrills_discovery::{hash_file, AgentMeta, PluginRegistry, SkillMeta};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -23,6 +23,26 @@ pub(crate) struct SyncReport {
     pub(crate) skipped: usize,
     /// Relative paths of skills that were copied (new or updated).
     pub(crate) copied_names: Vec<String>,
+    /// Relative paths of skills left alone because they were unchanged.
+    pub(crate) skipped_names: Vec<String>,
+    /// Every file written to the mirror, including agents and supporting files.
+    pub(crate) written_paths: Vec<PathBuf>,
+}
+
+impl SyncReport {
+    /// Converts to the sync crate's per-artifact report for the skills row.
+    pub(crate) fn to_write_report(&self) -> skrills_sync::WriteReport {
+        skrills_sync::WriteReport {
+            written: self.copied,
+            skipped: self
+                .skipped_names
+                .iter()
+                .map(|name| skrills_sync::SkipReason::Unchanged { item: name.clone() })
+                .collect(),
+            warnings: Vec::new(),
+            paths: self.written_paths.clone(),
+        }
+    }
 }
 
 /// Resolves the mirror source root, honoring `SKRILLS_MIRROR_SOURCE` when set.
@@ -36,6 +56,8 @@ pub(crate) fn mirror_source_root(home: &Path) -> PathBuf {
 ///
 /// Walks through the source directory and copies `SKILL.md` files to the destination,
 /// only copying if the file is new or has changed (based on hash comparison).
+/// When Claude's plugin registry is present, installed plugin content under
+/// `plugins/cache` is limited to enabled plugins.
 pub(crate) fn sync_from_claude(
     claude_root: &Path,
     mirror_root: &Path,
@@ -50,6 +72,10 @@ pub(crate) fn sync_from_claude(
         .parent()
         .map(|p| p.join("agents"))
         .unwrap_or_else(|| mirror_root.join("../agents"));
+    let registry = PluginRegistry::load(claude_root).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "ignoring unreadable Claude plugin registry");
+        None
+    });
     // Track directories we've already mirrored to avoid repeated work when multiple SKILLs exist.
     let mut mirrored_dirs: std::collections::HashSet<PathBuf> = std::collections::HashSet::new();
     for entry in WalkDir::new(claude_root)
@@ -67,6 +93,15 @@ pub(crate) fn sync_from_claude(
                 }
             }
         }
+        if let Some(registry) = &registry {
+            let path = entry.path();
+            let in_cache = path
+                .strip_prefix(claude_root)
+                .is_ok_and(|rel| rel.starts_with("plugins/cache"));
+            if in_cache && registry.enabled_plugin_for_path(path).is_none() {
+                continue;
+            }
+        }
 
         let is_skill = is_skill_file(&entry);
         let is_agent = entry.file_type().is_file()
@@ -99,19 +134,16 @@ pub(crate) fn sync_from_claude(
         };
         if should_copy {
             fs::copy(&src, &dest)?;
+            report.written_paths.push(dest.clone());
             if is_agent {
                 fs::copy(&src, &agent_dest)?;
+                report.written_paths.push(agent_dest.clone());
             }
             if is_skill {
                 report.copied += 1;
                 // Store the relative path (directory name) for display
-                if let Some(rel_path) = relative_path(claude_root, &src) {
-                    // Extract parent directory name as the skill name (e.g., "nested" from "nested/SKILL.md")
-                    let skill_name = rel_path
-                        .parent()
-                        .and_then(|p| p.to_str())
-                        .unwrap_or_else(|| rel_path.to_str().unwrap_or("unknown"));
-                    report.copied_names.push(skill_name.to_string());
+                if let Some(name) = skill_display_name(claude_root, &src) {
+                    report.copied_names.push(name);
                 }
             }
             // Mirror additional supporting files that live alongside the SKILL.md
@@ -147,6 +179,7 @@ pub(crate) fn sync_from_claude(
                             };
                             if copy_support {
                                 fs::copy(file_src, &file_dest)?;
+                                report.written_paths.push(file_dest);
                             }
                         }
                     }
@@ -154,11 +187,27 @@ pub(crate) fn sync_from_claude(
             }
         } else if is_skill {
             report.skipped += 1;
+            if let Some(name) = skill_display_name(claude_root, &src) {
+                report.skipped_names.push(name);
+            }
         }
     }
     Ok(report)
 }
 
+/// Returns the skill's directory relative to `root` (e.g. "nested" for
+/// "nested/SKILL.md"), used to name skills in reports.
+fn skill_display_name(root: &Path, skill_file: &Path) -> Option<String> {
+    let rel_path = relative_path(root, skill_file)?;
+    Some(
+        rel_path
+            .parent()
+            .and_then(|p| p.to_str())
+            .unwrap_or_else(|| rel_path.to_str().unwrap_or("unknown"))
+            .to_string(),
+    )
+}
+
 /// Renders a lightweight skills reference for AGENTS.md.
 ///
 /// Instead of embedding a massive XML list (which can exceed 60K tokens),
@@ -296,6 +345,7 @@ mod tests {
             source: SkillSource::Codex,
             root: path.clone(),
             hash: hash_file(&skill_path).unwrap(),
+            plugin: None,
         }];
         let reference = render_skills_reference(&skills);
         assert!(reference.contains("Total: 1 skills"));
@@ -314,6 +364,7 @@ mod tests {
             source: SkillSource::Codex,
             root: tmp.path().join("codex/skills"),
             hash: "abc".into(),
+            plugin: None,
         }];
         sync_agents_with_assets(&agents, &skills, &[])?;
         let text = fs::read_to_string(&agents)?;
@@ -336,6 +387,7 @@ mod tests {
             source: SkillSource::Codex,
             root: tmp.path().join("codex/skills"),
             hash: "abc".into(),
+            plugin: None,
         }];
         let reference = render_skills_reference(&skills);
         assert!(reference.contains("skrills list"));
@@ -356,6 +408,7 @@ mod tests {
             source: SkillSource::Cache,
             root: tmp.path().join("plugins/cache"),
             hash: "123".into(),
+            plugin: None,
         }];
         sync_agents_with_assets(&agents_path, &skills, &agents)?;
         let text = fs::read_to_string(&agents_path)?;
@@ -465,6 +518,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sync_from_claude_skips_disabled_plugins() -> Result<()> {
+        let tmp = tempdir()?;
+        let claude_root = tmp.path().join("claude");
+        let mirror_root = tmp.path().join("mirror");
+
+        let enabled = claude_root.join("plugins/cache/mkt/on/1.0.0");
+        let disabled = claude_root.join("plugins/cache/mkt/off/1.0.0");
+        for dir in [&enabled, &disabled] {
+            fs::create_dir_all(dir.join("skills/demo"))?;
+            fs::write(dir.join("skills/demo/SKILL.md"), "demo")?;
+        }
+        fs::write(
+            claude_root.join("plugins/installed_plugins.json"),
+            serde_json::json!({
+                "plugins": {
+                    "on@mkt": { "installPath": enabled },
+                    "off@mkt": { "installPath": disabled }
+                }
+            })
+            .to_string(),
+        )?;
+        fs::write(
+            claude_root.join("settings.json"),
+            r#"{"enabledPlugins": {"off@mkt": false}}"#,
+        )?;
+
+        let report = sync_from_claude(&claude_root, &mirror_root, false)?;
+        assert_eq!(report.copied, 1);
+        assert!(mirror_root
+            .join("plugins/cache/mkt/on/1.0.0/skills/demo/SKILL.md")
+            .exists());
+        assert!(!mirror_root.join("plugins/cache/mkt/off").exists());
+        Ok(())
+    }
+
     #[test]
     fn sync_from_claude_copies_supporting_files() -> Result<()> {
         let tmp = tempdir()?;
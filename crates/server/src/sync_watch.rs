@@ -0,0 +1,642 @@
+//! Continuous Claude ⇄ Codex sync driven by filesystem events.
+//!
+//! `skrills sync watch` watches both agents' config roots, batches events until
+//! the filesystem has been quiet for the debounce window, and then syncs only
+//! the artifacts that changed, in the direction of the side that changed.
+//! Events for files that still hold what a sync wrote are dropped so the two
+//! sides do not ping-pong; other edits under the targets are synced as usual.
+
+use crate::sync::{mirror_source_root, sync_from_claude};
+use anyhow::{anyhow, Result};
+use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
+use skrills_discovery::hash_file;
+use skrills_sync::{
+    ClaudeAdapter, CodexAdapter, ProjectScope, SyncDirection, SyncOrchestrator, SyncParams,
+    SyncReport,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Artifact kinds that can be synced independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Artifact {
+    Commands,
+    McpServers,
+    Preferences,
+    Skills,
+    Memory,
+}
+
+impl Artifact {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Commands => "commands",
+            Self::McpServers => "mcp-servers",
+            Self::Preferences => "preferences",
+            Self::Skills => "skills",
+            Self::Memory => "memory",
+        }
+    }
+}
+
+/// Pending work: the artifacts to sync per direction.
+pub(crate) type SyncPlan = BTreeMap<SyncDirection, BTreeSet<Artifact>>;
+
+/// Agent whose changes drive `direction`.
+fn source_agent(direction: SyncDirection) -> &'static str {
+    match direction {
+        SyncDirection::ClaudeToCodex => "claude",
+        SyncDirection::CodexToClaude => "codex",
+    }
+}
+
+/// Watches Claude and Codex config and keeps them in sync.
+pub(crate) struct SyncWatch {
+    claude_root: PathBuf,
+    codex_root: PathBuf,
+    /// Source directory for Claude skills (`~/.claude` or `<repo>/.claude/skills`).
+    skills_source: PathBuf,
+    /// Destination for Claude skills (`~/.codex/skills-mirror` or `<repo>/.codex/skills`).
+    skills_mirror: PathBuf,
+    project: Option<ProjectScope>,
+    directions: Vec<SyncDirection>,
+    include_marketplace: bool,
+    skip_memory: bool,
+}
+
+impl SyncWatch {
+    /// Watches `~/.claude` and `~/.codex`.
+    pub(crate) fn for_home(home: &Path) -> Self {
+        Self {
+            claude_root: home.join(".claude"),
+            codex_root: home.join(".codex"),
+            skills_source: mirror_source_root(home),
+            skills_mirror: home.join(".codex/skills-mirror"),
+            project: None,
+            directions: vec![SyncDirection::ClaudeToCodex, SyncDirection::CodexToClaude],
+            include_marketplace: false,
+            skip_memory: false,
+        }
+    }
+
+    /// Watches a repository's `.claude/`, `.codex/`, and root memory files.
+    pub(crate) fn for_project(scope: ProjectScope) -> Self {
+        let root = scope.root().to_path_buf();
+        Self {
+            claude_root: root.join(".claude"),
+            codex_root: root.join(".codex"),
+            skills_source: root.join(".claude/skills"),
+            skills_mirror: root.join(".codex/skills"),
+            project: Some(scope),
+            directions: vec![SyncDirection::ClaudeToCodex, SyncDirection::CodexToClaude],
+            include_marketplace: false,
+            skip_memory: false,
+        }
+    }
+
+    /// Restricts syncing to a single direction.
+    pub(crate) fn one_way(mut self, direction: SyncDirection) -> Self {
+        self.directions = vec![direction];
+        self
+    }
+
+    /// Includes marketplace content when syncing Claude commands and skills.
+    pub(crate) fn include_marketplace(mut self, include: bool) -> Self {
+        self.include_marketplace = include;
+        self
+    }
+
+    /// Leaves CLAUDE.md and AGENTS.md alone.
+    pub(crate) fn skip_memory(mut self, skip: bool) -> Self {
+        self.skip_memory = skip;
+        self
+    }
+
+    fn claude_adapter(&self) -> ClaudeAdapter {
+        match &self.project {
+            Some(scope) => scope.claude_adapter(),
+            None => ClaudeAdapter::with_root(self.claude_root.clone()),
+        }
+    }
+
+    fn codex_adapter(&self) -> CodexAdapter {
+        match &self.project {
+            Some(scope) => scope.codex_adapter(),
+            None => CodexAdapter::with_root(self.codex_root.clone()),
+        }
+    }
+
+    /// Maps a changed path to the direction and artifacts it affects.
+    pub(crate) fn classify(&self, path: &Path) -> Option<(SyncDirection, Vec<Artifact>)> {
+        use Artifact::*;
+
+        if let Some(scope) = &self.project {
+            if let Ok(rel) = path.strip_prefix(scope.root()) {
+                let direction_artifacts = match rel.to_str() {
+                    Some("CLAUDE.md") => Some((SyncDirection::ClaudeToCodex, vec![Memory])),
+                    Some(".mcp.json") => Some((SyncDirection::ClaudeToCodex, vec![McpServers])),
+                    Some("AGENTS.md") => Some((SyncDirection::CodexToClaude, vec![Memory])),
+                    _ => None,
+                };
+                if direction_artifacts.is_some() {
+                    return direction_artifacts;
+                }
+            }
+        }
+
+        // A custom mirror source (`SKRILLS_MIRROR_SOURCE`) only feeds skills.
+        if self.skills_source != self.claude_root && path.starts_with(&self.skills_source) {
+            return Some((SyncDirection::ClaudeToCodex, vec![Skills]));
+        }
+
+        if let Ok(rel) = path.strip_prefix(&self.claude_root) {
+            let first = rel.components().next()?.as_os_str().to_str()?;
+            let artifacts = match first {
+                "commands" => vec![Commands],
+                "skills" => vec![Skills],
+                // Plugin installs and toggles change commands and plugin MCP servers.
+                "plugins" => vec![Commands, McpServers],
+                "settings.json" if self.project.is_some() => vec![Preferences],
+                "settings.json" => vec![Commands, McpServers, Preferences],
+                "CLAUDE.md" => vec![Memory],
+                _ => return None,
+            };
+            return Some((SyncDirection::ClaudeToCodex, artifacts));
+        }
+
+        if let Ok(rel) = path.strip_prefix(&self.codex_root) {
+            let first = rel.components().next()?.as_os_str().to_str()?;
+            let artifacts = match first {
+                "prompts" => vec![Commands],
+                "skills" => vec![Skills],
+                "config.json" => vec![McpServers, Preferences],
+                "AGENTS.md" if self.project.is_none() => vec![Memory],
+                _ => return None,
+            };
+            return Some((SyncDirection::CodexToClaude, artifacts));
+        }
+
+        None
+    }
+
+    /// Builds a sync plan from changed paths, ignoring disabled directions
+    /// and, with [`SyncWatch::skip_memory`], memory files.
+    pub(crate) fn plan<'a>(&self, paths: impl IntoIterator<Item = &'a PathBuf>) -> SyncPlan {
+        let mut plan = SyncPlan::new();
+        for path in paths {
+            if let Some((direction, artifacts)) = self.classify(path) {
+                if !self.directions.contains(&direction) {
+                    continue;
+                }
+                let artifacts = artifacts
+                    .into_iter()
+                    .filter(|a| !(self.skip_memory && *a == Artifact::Memory));
+                plan.entry(direction).or_default().extend(artifacts);
+            }
+        }
+        plan.retain(|_, artifacts| !artifacts.is_empty());
+        plan
+    }
+
+    /// Runs one sync cycle for `plan`, returning a report or error per
+    /// direction. A failed direction does not stop the other.
+    pub(crate) fn run_cycle(&self, plan: &SyncPlan) -> Vec<(SyncDirection, Result<SyncReport>)> {
+        plan.iter()
+            .map(|(&direction, artifacts)| (direction, self.sync_direction(direction, artifacts)))
+            .collect()
+    }
+
+    /// Syncs `artifacts` in `direction`.
+    fn sync_direction(
+        &self,
+        direction: SyncDirection,
+        artifacts: &BTreeSet<Artifact>,
+    ) -> Result<SyncReport> {
+        let claude_skills =
+            direction == SyncDirection::ClaudeToCodex && artifacts.contains(&Artifact::Skills);
+        let params = SyncParams {
+            from: Some(source_agent(direction).to_string()),
+            sync_commands: artifacts.contains(&Artifact::Commands),
+            sync_mcp_servers: artifacts.contains(&Artifact::McpServers),
+            sync_preferences: artifacts.contains(&Artifact::Preferences),
+            // Claude skills are mirrored like `sync-all` does, below.
+            sync_skills: artifacts.contains(&Artifact::Skills) && !claude_skills,
+            sync_memory: artifacts.contains(&Artifact::Memory),
+            include_marketplace: self.include_marketplace,
+            ..Default::default()
+        };
+        let mut report = match direction {
+            SyncDirection::ClaudeToCodex => {
+                SyncOrchestrator::new(self.claude_adapter(), self.codex_adapter()).sync(&params)?
+            }
+            SyncDirection::CodexToClaude => {
+                SyncOrchestrator::new(self.codex_adapter(), self.claude_adapter()).sync(&params)?
+            }
+        };
+        if claude_skills {
+            let mirrored = sync_from_claude(
+                &self.skills_source,
+                &self.skills_mirror,
+                self.include_marketplace,
+            )?;
+            report.skills = mirrored.to_write_report();
+            report.summary = report.format_summary("claude", "codex");
+        }
+        Ok(report)
+    }
+
+    /// Roots written by syncing in `direction`.
+    fn target_roots(&self, direction: SyncDirection) -> Vec<PathBuf> {
+        let mut roots = match direction {
+            SyncDirection::ClaudeToCodex => {
+                vec![self.codex_root.clone(), self.skills_mirror.clone()]
+            }
+            SyncDirection::CodexToClaude => vec![self.claude_root.clone()],
+        };
+        if let Some(scope) = &self.project {
+            roots.push(scope.root().join(match direction {
+                SyncDirection::ClaudeToCodex => "AGENTS.md",
+                SyncDirection::CodexToClaude => "CLAUDE.md",
+            }));
+        }
+        roots
+    }
+
+    /// Directories to register with the watcher.
+    fn watch_targets(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut targets = vec![
+            (self.claude_root.clone(), RecursiveMode::Recursive),
+            (self.codex_root.clone(), RecursiveMode::Recursive),
+        ];
+        if self.skills_source != self.claude_root && self.project.is_none() {
+            targets.push((self.skills_source.clone(), RecursiveMode::Recursive));
+        }
+        if let Some(scope) = &self.project {
+            targets.push((scope.root().to_path_buf(), RecursiveMode::NonRecursive));
+        }
+        targets
+    }
+
+    /// Starts watching and syncs until the process is interrupted.
+    pub(crate) fn run(&self, debounce: Duration) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = RecommendedWatcher::new(
+            move |event: notify::Result<notify::Event>| {
+                // Reading config during a sync emits access events; only writes matter.
+                if let Some(event) = event.ok().filter(|e| !e.kind.is_access()) {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+            },
+            NotifyConfig::default(),
+        )?;
+        let mut watched = 0;
+        for (path, mode) in self.watch_targets() {
+            if path.exists() {
+                watcher.watch(&path, mode)?;
+                println!("Watching {}", path.display());
+                watched += 1;
+            }
+        }
+        if watched == 0 {
+            return Err(anyhow!("Nothing to watch: no Claude or Codex config found"));
+        }
+        println!(
+            "Syncing changes after {}ms of quiet (Ctrl-C to stop)",
+            debounce.as_millis()
+        );
+        self.run_loop(&rx, debounce, |cycle, direction, artifacts, result| {
+            println!(
+                "\n[cycle {cycle}] {} changed: {}",
+                source_agent(direction),
+                artifacts
+                    .iter()
+                    .map(|a| a.label())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            match result {
+                Ok(report) => {
+                    print!("{}", report.summary);
+                    tracing::info!(
+                        target: "skrills::sync_watch",
+                        cycle,
+                        from = source_agent(direction),
+                        synced = report.total_synced(),
+                        skipped = report.total_skipped(),
+                        "sync cycle complete"
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Sync failed: {e:#}; still watching");
+                    tracing::warn!(
+                        target: "skrills::sync_watch",
+                        cycle,
+                        from = source_agent(direction),
+                        error = %e,
+                        "sync cycle failed"
+                    );
+                }
+            }
+        })
+    }
+
+    /// Whether a change to `path` can trigger a sync.
+    fn is_relevant(&self, path: &Path) -> bool {
+        self.classify(path)
+            .is_some_and(|(direction, _)| self.directions.contains(&direction))
+    }
+
+    /// Debounces paths from `rx` into sync cycles until the channel closes.
+    ///
+    /// `on_report` is called once per direction synced in each cycle, with
+    /// the error when that direction failed; a failed sync does not stop the
+    /// loop.
+    pub(crate) fn run_loop<F>(
+        &self,
+        rx: &Receiver<PathBuf>,
+        debounce: Duration,
+        mut on_report: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, SyncDirection, &BTreeSet<Artifact>, &Result<SyncReport>),
+    {
+        let mut pending: Vec<PathBuf> = Vec::new();
+        let mut cycle = 0;
+        loop {
+            while pending.is_empty() {
+                match rx.recv() {
+                    Ok(path) if self.is_relevant(&path) => pending.push(path),
+                    Ok(_) => {}
+                    Err(_) => return Ok(()),
+                }
+            }
+            let mut closed = self.collect_until_quiet(rx, debounce, &mut pending);
+
+            let plan = self.plan(&pending);
+            pending.clear();
+            if !plan.is_empty() {
+                cycle += 1;
+                let roots: Vec<PathBuf> = plan
+                    .keys()
+                    .flat_map(|direction| self.target_roots(*direction))
+                    .collect();
+                let mut written = HashMap::new();
+                for (direction, result) in self.run_cycle(&plan) {
+                    if let Ok(report) = &result {
+                        written.extend(
+                            report
+                                .written_paths()
+                                .filter_map(|path| Some((path.clone(), hash_file(path).ok()?))),
+                        );
+                    }
+                    on_report(cycle, direction, &plan[&direction], &result);
+                }
+                // Events from our own writes arrive after the cycle.
+                if !closed {
+                    closed = self.collect_until_quiet(rx, debounce, &mut pending);
+                    pending.retain(|p| !is_echo(p, &written, &roots));
+                }
+            }
+            if closed && pending.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Appends relevant paths to `pending` until no relevant event arrives for
+    /// `debounce`; other events do not extend the wait. Returns `true` when
+    /// the channel has closed.
+    fn collect_until_quiet(
+        &self,
+        rx: &Receiver<PathBuf>,
+        debounce: Duration,
+        pending: &mut Vec<PathBuf>,
+    ) -> bool {
+        let mut deadline = Instant::now() + debounce;
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(wait) {
+                Ok(path) if self.is_relevant(&path) => {
+                    pending.push(path);
+                    deadline = Instant::now() + debounce;
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => return false,
+                Err(RecvTimeoutError::Disconnected) => return true,
+            }
+        }
+    }
+}
+
+/// Whether the event for `path` comes from the sync that produced `written`
+/// (paths mapped to the hash of what was written): the file still holds
+/// what was written, or it is a directory under the target `roots`. Other
+/// events are edits made in the meantime.
+fn is_echo(path: &Path, written: &HashMap<PathBuf, String>, roots: &[PathBuf]) -> bool {
+    match written.get(path) {
+        Some(hash) => hash_file(path).ok().as_ref() == Some(hash),
+        None => path.is_dir() && roots.iter().any(|root| path.starts_with(root)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn classify_maps_paths_to_artifacts() {
+        let tmp = tempdir().unwrap();
+        let watch = SyncWatch::for_home(tmp.path());
+        let claude = tmp.path().join(".claude");
+        let codex = tmp.path().join(".codex");
+
+        assert_eq!(
+            watch.classify(&claude.join("commands/review.md")),
+            Some((SyncDirection::ClaudeToCodex, vec![Artifact::Commands]))
+        );
+        assert_eq!(
+            watch.classify(&codex.join("config.json")),
+            Some((
+                SyncDirection::CodexToClaude,
+                vec![Artifact::McpServers, Artifact::Preferences]
+            ))
+        );
+        assert_eq!(
+            watch.classify(&codex.join("AGENTS.md")),
+            Some((SyncDirection::CodexToClaude, vec![Artifact::Memory]))
+        );
+        assert_eq!(
+            watch.classify(&codex.join("skills-mirror/x/SKILL.md")),
+            None
+        );
+        assert_eq!(watch.classify(&tmp.path().join("elsewhere.md")), None);
+    }
+
+    #[test]
+    fn project_classify_uses_repo_memory_files() {
+        let tmp = tempdir().unwrap();
+        let watch = SyncWatch::for_project(ProjectScope::new(tmp.path().to_path_buf()));
+        assert_eq!(
+            watch.classify(&tmp.path().join("AGENTS.md")),
+            Some((SyncDirection::CodexToClaude, vec![Artifact::Memory]))
+        );
+        assert_eq!(
+            watch.classify(&tmp.path().join(".mcp.json")),
+            Some((SyncDirection::ClaudeToCodex, vec![Artifact::McpServers]))
+        );
+        assert_eq!(watch.classify(&tmp.path().join("README.md")), None);
+    }
+
+    #[test]
+    fn run_loop_debounces_and_syncs_only_changed_artifacts() {
+        let tmp = tempdir().unwrap();
+        let claude = tmp.path().join(".claude");
+        fs::create_dir_all(claude.join("commands")).unwrap();
+        fs::write(claude.join("commands/a.md"), "# A").unwrap();
+        fs::write(claude.join("commands/b.md"), "# B").unwrap();
+        fs::write(claude.join("CLAUDE.md"), "# Memory").unwrap();
+        let watch = SyncWatch::for_home(tmp.path());
+
+        let (tx, rx) = mpsc::channel();
+        tx.send(claude.join("commands/a.md")).unwrap();
+        tx.send(claude.join("commands/b.md")).unwrap();
+        drop(tx);
+
+        let mut cycles = Vec::new();
+        watch
+            .run_loop(
+                &rx,
+                Duration::from_millis(10),
+                |cycle, direction, artifacts, report| {
+                    let written = report.as_ref().unwrap().commands.written;
+                    cycles.push((cycle, direction, artifacts.clone(), written));
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            cycles,
+            vec![(
+                1,
+                SyncDirection::ClaudeToCodex,
+                BTreeSet::from([Artifact::Commands]),
+                2
+            )]
+        );
+        assert!(tmp.path().join(".codex/prompts/a.md").exists());
+        // Memory was not touched, so it was not synced.
+        assert!(!tmp.path().join(".codex/AGENTS.md").exists());
+    }
+
+    #[test]
+    fn only_files_still_holding_synced_content_are_echoes() {
+        let tmp = tempdir().unwrap();
+        let roots = vec![tmp.path().to_path_buf()];
+        let (synced, edited) = (tmp.path().join("synced.md"), tmp.path().join("edited.md"));
+        fs::write(&synced, "# Synced").unwrap();
+        fs::write(&edited, "old").unwrap();
+        let written = HashMap::from([(synced.clone(), hash_file(&synced).unwrap())]);
+        assert!(is_echo(&synced, &written, &roots));
+        assert!(is_echo(tmp.path(), &written, &roots));
+
+        // Edits made after the sync are not echoes, even on written files.
+        fs::write(&edited, "new").unwrap();
+        assert!(!is_echo(&edited, &written, &roots));
+        fs::write(&synced, "# Changed by hand").unwrap();
+        assert!(!is_echo(&synced, &written, &roots));
+    }
+
+    #[test]
+    fn run_loop_keeps_watching_after_a_failed_sync() {
+        let tmp = tempdir().unwrap();
+        let (claude, codex) = (tmp.path().join(".claude"), tmp.path().join(".codex"));
+        fs::create_dir_all(claude.join("commands")).unwrap();
+        fs::create_dir_all(&codex).unwrap();
+        fs::write(claude.join("commands/a.md"), "# A").unwrap();
+        fs::write(codex.join("config.json"), "{ not json").unwrap();
+        let watch = SyncWatch::for_home(tmp.path());
+
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            tx.send(codex.join("config.json")).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            tx.send(claude.join("commands/a.md")).unwrap();
+        });
+
+        let mut results = Vec::new();
+        watch
+            .run_loop(&rx, Duration::from_millis(20), |_, direction, _, result| {
+                results.push((direction, result.is_ok()));
+            })
+            .unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                (SyncDirection::CodexToClaude, false),
+                (SyncDirection::ClaudeToCodex, true)
+            ]
+        );
+        assert!(tmp.path().join(".codex/prompts/a.md").exists());
+    }
+
+    #[test]
+    fn unrelated_events_do_not_extend_the_debounce() {
+        let tmp = tempdir().unwrap();
+        let watch = SyncWatch::for_home(tmp.path());
+        let (tx, rx) = mpsc::channel();
+        tx.send(tmp.path().join(".claude/commands/a.md")).unwrap();
+        let log = tmp.path().join(".codex/log/session.log");
+        let noise = std::thread::spawn(move || {
+            for _ in 0..50 {
+                if tx.send(log.clone()).is_err() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let mut pending = Vec::new();
+        let started = Instant::now();
+        let closed = watch.collect_until_quiet(&rx, Duration::from_millis(50), &mut pending);
+        let elapsed = started.elapsed();
+        drop(rx);
+        noise.join().unwrap();
+
+        assert!(!closed);
+        assert!(elapsed < Duration::from_millis(300), "waited {elapsed:?}");
+        assert_eq!(pending, vec![tmp.path().join(".claude/commands/a.md")]);
+    }
+
+    #[test]
+    fn one_way_ignores_other_side() {
+        let tmp = tempdir().unwrap();
+        let watch = SyncWatch::for_home(tmp.path()).one_way(SyncDirection::ClaudeToCodex);
+        let plan = watch.plan(&[tmp.path().join(".codex/prompts/x.md")]);
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn skip_memory_drops_memory_files_from_the_plan() {
+        let tmp = tempdir().unwrap();
+        let watch = SyncWatch::for_home(tmp.path()).skip_memory(true);
+        assert!(watch
+            .plan(&[tmp.path().join(".claude/CLAUDE.md")])
+            .is_empty());
+        let plan = watch.plan(&[
+            tmp.path().join(".codex/AGENTS.md"),
+            tmp.path().join(".codex/prompts/x.md"),
+        ]);
+        assert_eq!(
+            plan[&SyncDirection::CodexToClaude],
+            BTreeSet::from([Artifact::Commands])
+        );
+    }
+}
@@ -1,136 +1,316 @@
 //! Interactive terminal UI for skill management.
 //!
-//! It provides a user-friendly interface to:
-//! - Synchronizing skills from `~/.claude`.
-//! - Selecting and pinning skills.
-//! - Managing skill visibility.
+//! A full-screen interface with one pane per task:
+//! - Skills: searching, previewing and pinning discovered skills, with the
+//!   copies each one shadows and how often it was autoloaded.
+//! - Sync: the pending Claude → Codex command and MCP server changes, with
+//!   diffs, and the sync that writes them.
+//! - Autoload: what would be autoloaded for a typed prompt.
+//! - History: recent autoloads.
+//! - Runs: subagent runs and their events, refreshed while the pane is open.
+
+mod app;
+mod catalog;
+mod markdown;
+mod view;
 
 use anyhow::{anyhow, Result};
-use inquire::{Confirm, MultiSelect};
-use skrills_state::{home_dir, load_pinned, save_pinned};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+use skrills_discovery::{discover_skills, Diagnostics, SkillMeta};
+use skrills_state::{env_max_bytes, load_history, load_pinned, save_pinned};
 use std::collections::HashSet;
 use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use crate::discovery::collect_skills;
-use crate::sync::{mirror_source_root, sync_from_claude};
+use crate::autoload::{render_autoload, AutoloadOptions, RenderMode};
+use crate::discovery::{read_skill, skill_roots};
+use crate::runtime::{env_include_claude_default, runtime_overrides_cached};
+use app::{App, Effect, Pane};
+use catalog::SkillDoc;
 use skrills_sync::{ClaudeAdapter, CodexAdapter, SyncOrchestrator, SyncParams};
 
-/// Runs an interactive TUI for sync and pin management.
-///
-/// Users can:
-/// 1. Optionally sync skills from `~/.claude` to `~/.codex/skills-mirror`.
-/// 2. Select which skills to pin for autoload.
+/// How long to wait for a key before redrawing.
+const TICK: Duration = Duration::from_millis(250);
+/// How often the runs pane reloads while it is shown.
+const RUNS_REFRESH: Duration = Duration::from_secs(1);
+/// Number of runs listed in the runs pane.
+#[cfg(feature = "subagents")]
+const RUNS_LIMIT: usize = 50;
+
+/// Runs the interactive TUI until the user quits.
 pub(crate) fn tui_flow(extra_dirs: &[PathBuf]) -> Result<()> {
     if !std::io::stdout().is_terminal() {
         return Err(anyhow!("TUI requires a TTY"));
     }
-    if Confirm::new("Run claude → codex mirror sync first? (skills, agents, commands, prefs)")
-        .with_default(false)
-        .prompt()?
-    {
-        let skip_existing_commands =
-            Confirm::new("Keep existing prompts under ~/.codex/prompts (skip overwrites)?")
-                .with_default(true)
-                .prompt()?;
-
-        let include_marketplace =
-            Confirm::new("Include marketplace content (uninstalled plugins)?")
-                .with_default(false)
-                .prompt()?;
-
-        let home = home_dir()?;
-        let mirror_root = home.join(".codex/skills-mirror");
-        let report = sync_from_claude(
-            &mirror_source_root(&home),
-            &mirror_root,
-            include_marketplace,
-        )?;
+    let pinned = load_pinned().unwrap_or_default();
+    let mut app = App::new(load_skills(extra_dirs)?, pinned);
+    app.set_history(load_history().unwrap_or_default());
+    let mut sources = Sources::new();
+    sources.apply(Effect::RefreshSync, &mut app);
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app, &mut sources);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App, sources: &mut Sources) -> Result<()> {
+    let mut runs_loaded = Instant::now();
+    loop {
+        terminal.draw(|frame| view::draw(frame, app))?;
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match app.handle_key(key) {
+                        Some(Effect::Quit) => return Ok(()),
+                        Some(effect) => {
+                            if effect == Effect::RefreshRuns {
+                                runs_loaded = Instant::now();
+                            }
+                            sources.apply(effect, app);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+        if app.pane == Pane::Runs && runs_loaded.elapsed() >= RUNS_REFRESH {
+            runs_loaded = Instant::now();
+            sources.apply(Effect::RefreshRuns, app);
+        }
+    }
+}
+
+/// Performs the effects of key presses against skills, sync targets and
+/// the run store.
+struct Sources {
+    #[cfg(feature = "subagents")]
+    runs: Result<RunSource, String>,
+}
 
-        // Mirror commands/prefs/MCP
-        let source = ClaudeAdapter::new()?;
-        let target = CodexAdapter::new()?;
-        let orch = SyncOrchestrator::new(source, target);
-        let params = SyncParams {
-            sync_skills: false,
-            sync_commands: true,
-            skip_existing_commands,
-            sync_mcp_servers: true,
-            sync_preferences: true,
-            include_marketplace,
-            ..Default::default()
+impl Sources {
+    fn new() -> Self {
+        Self {
+            #[cfg(feature = "subagents")]
+            runs: RunSource::open().map_err(|e| format!("run store unavailable: {e}")),
+        }
+    }
+
+    /// Performs `effect`, reporting failures in the affected pane or the
+    /// status line instead of leaving the UI.
+    fn apply(&mut self, effect: Effect, app: &mut App) {
+        let result = match effect {
+            Effect::Quit => Ok(()),
+            Effect::SavePins => save_pinned(&app.pinned)
+                .map(|()| app.status = format!("Pinned {} skills.", app.pinned.len())),
+            Effect::RefreshSync => {
+                let changes = orchestrator().and_then(|o| o.pending_changes(&sync_params(app)));
+                match changes {
+                    Ok(changes) => {
+                        app.sync.changes = changes;
+                        app.sync.error = None;
+                    }
+                    Err(e) => app.sync.error = Some(format!("could not diff sync targets: {e}")),
+                }
+                Ok(())
+            }
+            Effect::Sync => self.sync(app),
+            Effect::Autoload => {
+                if let Err(e) = self.autoload(app) {
+                    app.autoload.error = Some(format!("autoload failed: {e}"));
+                }
+                Ok(())
+            }
+            Effect::RefreshRuns => {
+                self.refresh_runs(app);
+                Ok(())
+            }
+            Effect::StopRun(id) => self.stop_run(&id, app),
         };
-        let sync_report = orch.sync(&params)?;
-
-        println!(
-            "Mirror sync complete:\n  Skills: copied {}, skipped {}\n  Commands: written {}, skipped {}{}\n  Prefs: {}  MCP: {}",
-            report.copied,
-            report.skipped,
-            sync_report.commands.written,
-            sync_report.commands.skipped.len(),
-            if skip_existing_commands && !sync_report.commands.skipped.is_empty() {
-                format!(
-                    " (kept existing: {})",
-                    sync_report
-                        .commands
-                        .skipped
-                        .iter()
-                        .map(|r| r.description())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            } else {
-                String::new()
-            },
-            sync_report.preferences.written,
-            sync_report.mcp_servers.written,
+        if let Err(e) = result {
+            app.status = format!("error: {e}");
+        }
+        app.clamp_selections();
+    }
+
+    /// Writes the previewed commands and MCP servers into Codex.
+    fn sync(&mut self, app: &mut App) -> Result<()> {
+        let report = orchestrator()?.sync(&sync_params(app))?;
+        app.status = format!(
+            "Sync complete: commands written {}, skipped {}; MCP {}",
+            report.commands.written,
+            report.commands.skipped.len(),
+            report.mcp_servers.written,
         );
+        self.apply(Effect::RefreshSync, app);
+        Ok(())
     }
 
-    let skills = collect_skills(extra_dirs)?;
-    if skills.is_empty() {
-        println!("No skills found.");
-        return Ok(());
+    /// Renders the autoload output for the prompt without recording it in
+    /// the history.
+    fn autoload(&self, app: &mut App) -> Result<()> {
+        let skills: Vec<SkillMeta> = app.skills().iter().map(|d| d.meta.clone()).collect();
+        let view = &mut app.autoload;
+        let pinned = skrills_state::load_pinned_with_defaults().unwrap_or_default();
+        let mut matched = HashSet::new();
+        let mut diag = Diagnostics::default();
+        let output = render_autoload(
+            &skills,
+            AutoloadOptions {
+                include_claude: env_include_claude_default(),
+                max_bytes: env_max_bytes(),
+                prompt: Some(&view.prompt),
+                pinned: Some(&pinned),
+                matched: Some(&mut matched),
+                diagnostics: Some(&mut diag),
+                render_mode: RenderMode::Dual,
+                minimal_manifest: runtime_overrides_cached().manifest_minimal(),
+                ..AutoloadOptions::default()
+            },
+        )?;
+        view.included = diag
+            .included
+            .into_iter()
+            .map(|(name, source, _, _)| (name, source))
+            .collect();
+        view.skipped = diag.skipped.len();
+        view.truncated = diag.truncated;
+        view.output = output;
+        view.error = None;
+        app.scroll = 0;
+        Ok(())
     }
 
-    let pinned = load_pinned().unwrap_or_default();
-    let mut items = Vec::new();
-    let mut default_indices = Vec::new();
-    for (idx, s) in skills.iter().enumerate() {
-        let display = format!(
-            "[{} | {}] {}",
-            s.source.label(),
-            s.source.location(),
-            s.name
-        );
-        items.push(display);
-        if pinned.contains(&s.name) {
-            default_indices.push(idx);
+    #[cfg(feature = "subagents")]
+    fn refresh_runs(&mut self, app: &mut App) {
+        let runs = match &self.runs {
+            Ok(source) => source
+                .rows()
+                .map_err(|e| format!("could not load runs: {e}")),
+            Err(e) => Err(e.clone()),
+        };
+        match runs {
+            Ok(runs) => {
+                app.runs.runs = runs;
+                app.runs.error = None;
+            }
+            Err(e) => app.runs.error = Some(e),
         }
     }
 
-    let selected = MultiSelect::new(
-        "Select skills to pin (space to toggle, enter to save)",
-        items.clone(),
-    )
-    .with_default(&default_indices)
-    .prompt()?;
-
-    let mut new_pins = HashSet::new();
-    for item in &selected {
-        if let Some(skill) = skills.iter().find(|s| {
-            let display = format!(
-                "[{} | {}] {}",
-                s.source.label(),
-                s.source.location(),
-                s.name
-            );
-            &display == item
-        }) {
-            new_pins.insert(skill.name.clone());
-        }
+    #[cfg(not(feature = "subagents"))]
+    fn refresh_runs(&mut self, app: &mut App) {
+        app.runs.error = Some("built without the `subagents` feature".into());
+    }
+
+    #[cfg(feature = "subagents")]
+    fn stop_run(&mut self, id: &str, app: &mut App) -> Result<()> {
+        let source = self.runs.as_ref().map_err(|e| anyhow!("{e}"))?;
+        let stopped = source.stop(id)?;
+        app.status = if stopped {
+            format!("Canceled run {id}.")
+        } else {
+            format!("Run {id} had already finished.")
+        };
+        self.refresh_runs(app);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "subagents"))]
+    fn stop_run(&mut self, _id: &str, _app: &mut App) -> Result<()> {
+        Err(anyhow!("built without the `subagents` feature"))
+    }
+}
+
+/// Discovers skills, noting the duplicates each one shadows, and reads
+/// their `SKILL.md` for search and preview.
+fn load_skills(extra_dirs: &[PathBuf]) -> Result<Vec<SkillDoc>> {
+    let mut duplicates = Vec::new();
+    let skills = discover_skills(&skill_roots(extra_dirs)?, Some(&mut duplicates))?;
+    Ok(catalog::skill_docs(skills, &duplicates, read_skill))
+}
+
+fn orchestrator() -> Result<SyncOrchestrator<ClaudeAdapter, CodexAdapter>> {
+    Ok(SyncOrchestrator::new(
+        ClaudeAdapter::new()?,
+        CodexAdapter::new()?,
+    ))
+}
+
+/// Claude → Codex parameters for the options chosen in the sync pane, used
+/// both for the preview and the sync itself. Skills, preferences and memory
+/// are left to `skrills sync` and `skrills mirror`.
+fn sync_params(app: &App) -> SyncParams {
+    SyncParams {
+        sync_skills: false,
+        sync_commands: true,
+        skip_existing_commands: app.sync.keep_existing,
+        sync_mcp_servers: true,
+        sync_preferences: false,
+        sync_memory: false,
+        include_marketplace: app.sync.include_marketplace,
+        ..Default::default()
+    }
+}
+
+/// The subagent run store, read from blocking UI code.
+#[cfg(feature = "subagents")]
+struct RunSource {
+    runtime: tokio::runtime::Runtime,
+    store: std::sync::Arc<dyn skrills_subagents::RunStore>,
+}
+
+#[cfg(feature = "subagents")]
+impl RunSource {
+    fn open() -> Result<Self> {
+        Ok(Self {
+            runtime: tokio::runtime::Runtime::new()?,
+            store: crate::subagents::open_store()?,
+        })
+    }
+
+    fn rows(&self) -> Result<Vec<app::RunRow>> {
+        let runs = self.runtime.block_on(self.store.history(RUNS_LIMIT))?;
+        Ok(runs.iter().map(run_row).collect())
+    }
+
+    fn stop(&self, id: &str) -> Result<bool> {
+        let run_id = id.parse().map_err(|_| anyhow!("invalid run id: {id}"))?;
+        self.runtime
+            .block_on(skrills_subagents::backend::cancel_run(run_id, &self.store))
+    }
+}
+
+#[cfg(feature = "subagents")]
+fn run_row(record: &skrills_subagents::RunRecord) -> app::RunRow {
+    let mut out = Vec::new();
+    let mut printer = crate::subagents::EventPrinter::default();
+    for event in &record.events {
+        let _ = printer.print(&mut out, event);
+    }
+    let _ = printer.finish(&mut out);
+    let created = record.created_at;
+    let mut events: Vec<String> = String::from_utf8_lossy(&out)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if let Some(message) = &record.status.message {
+        events.push(format!("status: {message}"));
+    }
+    app::RunRow {
+        id: record.id.to_string(),
+        state: record.status.state.as_str().to_string(),
+        backend: record.request.backend.name().to_string(),
+        template: record.request.template_id.clone(),
+        created: format!(
+            "{} {:02}:{:02}",
+            created.date(),
+            created.hour(),
+            created.minute()
+        ),
+        finished: record.status.state.is_terminal(),
+        events,
     }
-    save_pinned(&new_pins)?;
-    println!("Pinned {} skills.", new_pins.len());
-    Ok(())
 }
@@ -0,0 +1,474 @@
+//! State of the terminal UI and its key bindings.
+//!
+//! Key handling only updates this state; anything that reads or writes
+//! files, syncs or talks to the run store is returned as an [`Effect`] for
+//! the event loop to perform.
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use skrills_state::HistoryEntry;
+use skrills_sync::PendingChange;
+use std::collections::{HashMap, HashSet};
+
+use super::catalog::{autoload_counts, search, Autoloads, Field, Hit, SkillDoc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pane {
+    Skills,
+    Sync,
+    Autoload,
+    History,
+    Runs,
+}
+
+impl Pane {
+    pub(crate) const ALL: [Pane; 5] = [
+        Pane::Skills,
+        Pane::Sync,
+        Pane::Autoload,
+        Pane::History,
+        Pane::Runs,
+    ];
+
+    pub(crate) fn title(self) -> &'static str {
+        match self {
+            Pane::Skills => "Skills",
+            Pane::Sync => "Sync",
+            Pane::Autoload => "Autoload",
+            Pane::History => "History",
+            Pane::Runs => "Runs",
+        }
+    }
+
+    fn index(self) -> usize {
+        Pane::ALL.iter().position(|p| *p == self).unwrap_or(0)
+    }
+}
+
+/// Where typed characters go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Normal,
+    /// Editing the skills search.
+    Filter,
+    /// Editing the autoload prompt.
+    Prompt,
+}
+
+/// Work the event loop performs after a key press.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Effect {
+    Quit,
+    /// Persist `App::pinned`.
+    SavePins,
+    /// Recompute the pending sync changes.
+    RefreshSync,
+    /// Write the pending Claude → Codex changes.
+    Sync,
+    /// Render the autoload preview for `AutoloadView::prompt`.
+    Autoload,
+    /// Reload the runs and their events.
+    RefreshRuns,
+    /// Cancel the run with this id.
+    StopRun(String),
+}
+
+/// Pending Claude → Codex changes and the options of the next sync.
+#[derive(Debug, Default)]
+pub(crate) struct SyncView {
+    pub(crate) changes: Vec<PendingChange>,
+    pub(crate) selected: usize,
+    /// Keep existing prompts under `~/.codex/prompts`.
+    pub(crate) keep_existing: bool,
+    pub(crate) include_marketplace: bool,
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AutoloadView {
+    pub(crate) prompt: String,
+    /// Included skills as (name, source label).
+    pub(crate) included: Vec<(String, String)>,
+    pub(crate) skipped: usize,
+    pub(crate) truncated: bool,
+    pub(crate) output: String,
+    pub(crate) error: Option<String>,
+}
+
+/// A subagent run as listed in the runs pane.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct RunRow {
+    pub(crate) id: String,
+    pub(crate) state: String,
+    pub(crate) backend: String,
+    pub(crate) template: Option<String>,
+    pub(crate) created: String,
+    pub(crate) finished: bool,
+    /// Events as `HH:MM:SS kind data` lines.
+    pub(crate) events: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RunsView {
+    pub(crate) runs: Vec<RunRow>,
+    pub(crate) selected: usize,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) struct App {
+    pub(crate) pane: Pane,
+    pub(crate) mode: Mode,
+    skills: Vec<SkillDoc>,
+    pub(crate) pinned: HashSet<String>,
+    /// Autoload counts by skill name, from the history.
+    pub(crate) autoloads: HashMap<String, Autoloads>,
+    pub(crate) filter: String,
+    /// Skills matching `filter`, best first.
+    matches: Vec<Hit>,
+    /// Index into `matches`.
+    pub(crate) selected: usize,
+    pub(crate) sync: SyncView,
+    pub(crate) autoload: AutoloadView,
+    /// Autoload history, most recent first.
+    history: Vec<HistoryEntry>,
+    pub(crate) history_selected: usize,
+    pub(crate) runs: RunsView,
+    /// Scroll offset of the right-hand pane.
+    pub(crate) scroll: u16,
+    /// Message shown in the status line.
+    pub(crate) status: String,
+}
+
+fn step(index: usize, len: usize, down: bool) -> usize {
+    match (len, down) {
+        (0, _) => 0,
+        (_, true) => (index + 1).min(len - 1),
+        (_, false) => index.saturating_sub(1),
+    }
+}
+
+impl App {
+    pub(crate) fn new(skills: Vec<SkillDoc>, pinned: HashSet<String>) -> Self {
+        let matches = search(&skills, "");
+        Self {
+            pane: Pane::Skills,
+            mode: Mode::Normal,
+            skills,
+            pinned,
+            autoloads: HashMap::new(),
+            filter: String::new(),
+            matches,
+            selected: 0,
+            sync: SyncView {
+                keep_existing: true,
+                ..SyncView::default()
+            },
+            autoload: AutoloadView::default(),
+            history: Vec::new(),
+            history_selected: 0,
+            runs: RunsView::default(),
+            scroll: 0,
+            status: String::new(),
+        }
+    }
+
+    pub(crate) fn skills(&self) -> &[SkillDoc] {
+        &self.skills
+    }
+
+    /// Sets the history, oldest first as stored, and recounts autoloads.
+    pub(crate) fn set_history(&mut self, mut history: Vec<HistoryEntry>) {
+        self.autoloads = autoload_counts(&history);
+        history.reverse();
+        self.history = history;
+    }
+
+    pub(crate) fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    fn refilter(&mut self) {
+        self.matches = search(&self.skills, &self.filter);
+        self.clamp_selections();
+    }
+
+    /// Skills matching the filter, best first, with where they matched.
+    pub(crate) fn visible_skills(&self) -> impl Iterator<Item = (&SkillDoc, Field)> {
+        self.matches
+            .iter()
+            .map(|hit| (&self.skills[hit.index], hit.field))
+    }
+
+    pub(crate) fn selected_skill(&self) -> Option<&SkillDoc> {
+        let hit = self.matches.get(self.selected)?;
+        Some(&self.skills[hit.index])
+    }
+
+    pub(crate) fn selected_run(&self) -> Option<&RunRow> {
+        self.runs.runs.get(self.runs.selected)
+    }
+
+    pub(crate) fn selected_change(&self) -> Option<&PendingChange> {
+        self.sync.changes.get(self.sync.selected)
+    }
+
+    /// Keeps every selection within its list after the lists were reloaded.
+    pub(crate) fn clamp_selections(&mut self) {
+        let clamp = |index: usize, len: usize| index.min(len.saturating_sub(1));
+        self.selected = clamp(self.selected, self.matches.len());
+        self.sync.selected = clamp(self.sync.selected, self.sync.changes.len());
+        self.history_selected = clamp(self.history_selected, self.history.len());
+        self.runs.selected = clamp(self.runs.selected, self.runs.runs.len());
+    }
+
+    fn switch(&mut self, pane: Pane) -> Option<Effect> {
+        self.pane = pane;
+        self.scroll = 0;
+        (pane == Pane::Runs).then_some(Effect::RefreshRuns)
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        match self.pane {
+            Pane::Skills => {
+                self.selected = step(self.selected, self.matches.len(), down);
+            }
+            Pane::Sync => {
+                self.sync.selected = step(self.sync.selected, self.sync.changes.len(), down);
+            }
+            Pane::Autoload => return,
+            Pane::History => {
+                self.history_selected = step(self.history_selected, self.history.len(), down);
+            }
+            Pane::Runs => {
+                self.runs.selected = step(self.runs.selected, self.runs.runs.len(), down);
+            }
+        }
+        self.scroll = 0;
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Effect::Quit);
+        }
+        match self.mode {
+            Mode::Filter => self.edit_filter(key),
+            Mode::Prompt => self.edit_prompt(key),
+            Mode::Normal => self.normal_key(key),
+        }
+    }
+
+    fn edit_filter(&mut self, key: KeyEvent) -> Option<Effect> {
+        match key.code {
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                return None;
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            KeyCode::Down => {
+                self.move_selection(true);
+                return None;
+            }
+            KeyCode::Up => {
+                self.move_selection(false);
+                return None;
+            }
+            _ => return None,
+        }
+        // The best match moves to the top as the search changes.
+        self.selected = 0;
+        self.scroll = 0;
+        self.refilter();
+        None
+    }
+
+    fn edit_prompt(&mut self, key: KeyEvent) -> Option<Effect> {
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                return Some(Effect::Autoload);
+            }
+            KeyCode::Backspace => {
+                self.autoload.prompt.pop();
+            }
+            KeyCode::Char(c) => self.autoload.prompt.push(c),
+            _ => {}
+        }
+        None
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Effect::Quit),
+            KeyCode::Tab => return self.switch(Pane::ALL[(self.pane.index() + 1) % 5]),
+            KeyCode::BackTab => return self.switch(Pane::ALL[(self.pane.index() + 4) % 5]),
+            KeyCode::Char(c @ '1'..='5') => {
+                return self.switch(Pane::ALL[c as usize - '1' as usize]);
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            _ => return self.pane_key(key),
+        }
+        None
+    }
+
+    fn pane_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        match (self.pane, key.code) {
+            (Pane::Skills, KeyCode::Char('/')) => self.mode = Mode::Filter,
+            (Pane::Skills, KeyCode::Char(' ')) => {
+                let name = self.selected_skill()?.meta.name.clone();
+                if !self.pinned.remove(&name) {
+                    self.pinned.insert(name);
+                }
+                return Some(Effect::SavePins);
+            }
+            (Pane::Sync, KeyCode::Char('r')) => return Some(Effect::RefreshSync),
+            (Pane::Sync, KeyCode::Char('s')) => return Some(Effect::Sync),
+            (Pane::Sync, KeyCode::Char('e')) => {
+                self.sync.keep_existing = !self.sync.keep_existing;
+                return Some(Effect::RefreshSync);
+            }
+            (Pane::Sync, KeyCode::Char('m')) => {
+                self.sync.include_marketplace = !self.sync.include_marketplace;
+                return Some(Effect::RefreshSync);
+            }
+            (Pane::Autoload, KeyCode::Char('/') | KeyCode::Enter) => self.mode = Mode::Prompt,
+            (Pane::Runs, KeyCode::Char('r')) => return Some(Effect::RefreshRuns),
+            (Pane::Runs, KeyCode::Char('x')) => {
+                let run = self.selected_run().filter(|r| !r.finished)?;
+                return Some(Effect::StopRun(run.id.clone()));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Key hints for the status line.
+    pub(crate) fn hints(&self) -> &'static str {
+        match (self.mode, self.pane) {
+            (Mode::Filter, _) => {
+                "type to search names, descriptions and bodies  enter keep  esc clear"
+            }
+            (Mode::Prompt, _) => "type a prompt  enter preview  esc cancel",
+            (_, Pane::Skills) => "/ search  space pin  pgup/pgdn scroll  tab pane  q quit",
+            (_, Pane::Sync) => "s sync  r refresh  e keep existing  m marketplace  q quit",
+            (_, Pane::Autoload) => "enter edit prompt  pgup/pgdn scroll  q quit",
+            (_, Pane::History) => "j/k move  tab pane  q quit",
+            (_, Pane::Runs) => "r refresh  x stop  pgup/pgdn scroll  q quit",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skrills_discovery::{SkillMeta, SkillSource};
+    use std::path::PathBuf;
+
+    fn skill(name: &str) -> SkillDoc {
+        let meta = SkillMeta {
+            name: name.into(),
+            path: PathBuf::from(format!("/nonexistent/{name}/SKILL.md")),
+            source: SkillSource::Codex,
+            root: PathBuf::from("/nonexistent"),
+            hash: "h".into(),
+            plugin: None,
+        };
+        SkillDoc::new(meta, format!("# {name}\n"), Vec::new())
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Effect> {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn filter_narrows_skills_and_space_toggles_pins() {
+        let mut app = App::new(
+            vec![skill("rust-review"), skill("python"), skill("rustfmt")],
+            HashSet::new(),
+        );
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "rust");
+        assert_eq!(app.mode, Mode::Filter);
+        let mut names: Vec<_> = app
+            .visible_skills()
+            .map(|(s, _)| s.meta.name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["rust-review", "rustfmt"]);
+
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('j'));
+        let second = app.selected_skill().unwrap().meta.name.clone();
+        assert_eq!(press(&mut app, KeyCode::Char(' ')), Some(Effect::SavePins));
+        assert_eq!(app.pinned, HashSet::from([second]));
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.pinned.is_empty());
+
+        // Narrowing the search selects the best match.
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "fmt");
+        assert_eq!(app.selected_skill().unwrap().meta.name, "rustfmt");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.visible_skills().count(), 3);
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Some(Effect::Quit));
+    }
+
+    #[test]
+    fn panes_route_keys_to_their_effects() {
+        let mut app = App::new(vec![skill("a")], HashSet::new());
+        assert_eq!(
+            press(&mut app, KeyCode::Char('5')),
+            Some(Effect::RefreshRuns)
+        );
+        assert_eq!(app.pane, Pane::Runs);
+        app.runs.runs = vec![
+            RunRow {
+                id: "done".into(),
+                finished: true,
+                ..RunRow::default()
+            },
+            RunRow {
+                id: "live".into(),
+                ..RunRow::default()
+            },
+        ];
+        assert_eq!(press(&mut app, KeyCode::Char('x')), None);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            press(&mut app, KeyCode::Char('x')),
+            Some(Effect::StopRun("live".into()))
+        );
+
+        press(&mut app, KeyCode::BackTab);
+        press(&mut app, KeyCode::BackTab);
+        assert_eq!(app.pane, Pane::Autoload);
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "q1");
+        assert_eq!(app.autoload.prompt, "q1");
+        assert_eq!(press(&mut app, KeyCode::Enter), Some(Effect::Autoload));
+
+        press(&mut app, KeyCode::Char('2'));
+        assert!(app.sync.keep_existing);
+        assert_eq!(
+            press(&mut app, KeyCode::Char('e')),
+            Some(Effect::RefreshSync)
+        );
+        assert!(!app.sync.keep_existing);
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Some(Effect::Sync));
+    }
+}
@@ -0,0 +1,231 @@
+//! Skill documents shown in the skills pane: their search, the copies they
+//! shadow, and how often they were autoloaded.
+
+use anyhow::Result;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use skrills_discovery::{
+    parse_frontmatter, split_frontmatter, DuplicateInfo, FrontValue, SkillMeta,
+};
+use skrills_state::HistoryEntry;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Search scores are multiplied by these so a match in the name outranks an
+/// equally good one in the description, which outranks one in the body.
+const NAME_WEIGHT: i64 = 3;
+const DESCRIPTION_WEIGHT: i64 = 2;
+
+/// A discovered skill and its `SKILL.md`.
+pub(crate) struct SkillDoc {
+    pub(crate) meta: SkillMeta,
+    pub(crate) text: String,
+    /// The frontmatter `description`, if any.
+    pub(crate) description: Option<String>,
+    /// Copies of the skill from lower-priority roots that it hides.
+    pub(crate) shadows: Vec<DuplicateInfo>,
+}
+
+impl SkillDoc {
+    pub(crate) fn new(meta: SkillMeta, text: String, shadows: Vec<DuplicateInfo>) -> Self {
+        let description = parse_frontmatter(&text)
+            .0
+            .get("description")
+            .and_then(FrontValue::as_scalar)
+            .filter(|d| !d.is_empty())
+            .map(String::from);
+        Self {
+            meta,
+            text,
+            description,
+            shadows,
+        }
+    }
+
+    /// The text after the frontmatter.
+    pub(crate) fn body(&self) -> &str {
+        split_frontmatter(&self.text).1
+    }
+}
+
+/// Reads the `SKILL.md` of each skill with `read` and attaches the
+/// duplicates each one shadows.
+pub(crate) fn skill_docs(
+    skills: Vec<SkillMeta>,
+    duplicates: &[DuplicateInfo],
+    read: impl Fn(&Path) -> Result<String>,
+) -> Vec<SkillDoc> {
+    skills
+        .into_iter()
+        .map(|meta| {
+            let text = read(&meta.path).unwrap_or_else(|e| format!("could not read skill: {e}"));
+            let shadows = duplicates
+                .iter()
+                .filter(|d| d.name == meta.name)
+                .cloned()
+                .collect();
+            SkillDoc::new(meta, text, shadows)
+        })
+        .collect()
+}
+
+/// Where a search matched a skill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    Name,
+    Description,
+    Body,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Hit {
+    /// Index into the searched skills.
+    pub(crate) index: usize,
+    pub(crate) field: Field,
+    score: i64,
+}
+
+/// Fuzzy matches `query` against the name, description and body lines of
+/// each skill, best first. An empty query lists every skill in order.
+pub(crate) fn search(docs: &[SkillDoc], query: &str) -> Vec<Hit> {
+    let query = query.trim();
+    if query.is_empty() {
+        return (0..docs.len())
+            .map(|index| Hit {
+                index,
+                field: Field::Name,
+                score: 0,
+            })
+            .collect();
+    }
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut hits: Vec<Hit> = docs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, doc)| {
+            let body = doc
+                .body()
+                .lines()
+                .filter_map(|line| matcher.fuzzy_match(line, query))
+                .max()
+                .map(|score| (score, Field::Body));
+            let description = doc
+                .description
+                .as_deref()
+                .and_then(|d| matcher.fuzzy_match(d, query))
+                .map(|score| (score * DESCRIPTION_WEIGHT, Field::Description));
+            let name = matcher
+                .fuzzy_match(&doc.meta.name, query)
+                .map(|score| (score * NAME_WEIGHT, Field::Name));
+            // On equal scores `max_by_key` keeps the last, so the name wins.
+            [body, description, name]
+                .into_iter()
+                .flatten()
+                .max_by_key(|(score, _)| *score)
+                .map(|(score, field)| Hit {
+                    index,
+                    field,
+                    score,
+                })
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+    hits
+}
+
+/// How often a skill was autoloaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Autoloads {
+    pub(crate) count: usize,
+    /// Seconds since the epoch of the latest autoload.
+    pub(crate) last: u64,
+}
+
+pub(crate) fn autoload_counts(history: &[HistoryEntry]) -> HashMap<String, Autoloads> {
+    let mut counts: HashMap<String, Autoloads> = HashMap::new();
+    for entry in history {
+        for skill in &entry.skills {
+            let stats = counts.entry(skill.clone()).or_default();
+            stats.count += 1;
+            stats.last = stats.last.max(entry.ts);
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skrills_discovery::SkillSource;
+    use std::path::PathBuf;
+
+    fn doc(name: &str, text: &str) -> SkillDoc {
+        let meta = SkillMeta {
+            name: name.into(),
+            path: PathBuf::from(format!("/skills/{name}/SKILL.md")),
+            source: SkillSource::Codex,
+            root: PathBuf::from("/skills"),
+            hash: "h".into(),
+            plugin: None,
+        };
+        SkillDoc::new(meta, text.into(), Vec::new())
+    }
+
+    #[test]
+    fn reads_inline_and_folded_descriptions() {
+        let inline = doc(
+            "a",
+            "---\nname: a\ndescription: \"Reviews diffs\"\n---\n# A\n",
+        );
+        assert_eq!(inline.description.as_deref(), Some("Reviews diffs"));
+        assert_eq!(inline.body(), "# A\n");
+
+        let folded = doc(
+            "b",
+            "---\ndescription: >\n  Formats Rust\n  with rustfmt\nname: b\n---\nbody\n",
+        );
+        assert_eq!(
+            folded.description.as_deref(),
+            Some("Formats Rust with rustfmt")
+        );
+
+        let plain = doc("c", "# No frontmatter\n");
+        assert_eq!(plain.description, None);
+        assert_eq!(plain.body(), "# No frontmatter\n");
+    }
+
+    #[test]
+    fn search_ranks_names_over_descriptions_over_bodies() {
+        let docs = [
+            doc("notes", "Mentions the deploy checklist in passing.\n"),
+            doc("ship", "---\ndescription: Deploy services\n---\nSteps.\n"),
+            doc("deploy", "Runs the release.\n"),
+            doc("python", "Type hints.\n"),
+        ];
+        let hits = search(&docs, "deploy");
+        let ranked: Vec<_> = hits.iter().map(|h| (h.index, h.field)).collect();
+        assert_eq!(
+            ranked,
+            [(2, Field::Name), (1, Field::Description), (0, Field::Body)]
+        );
+        assert_eq!(search(&docs, "").len(), 4);
+        assert!(search(&docs, "zzz").is_empty());
+    }
+
+    #[test]
+    fn counts_autoloads_per_skill() {
+        let history = [
+            HistoryEntry {
+                ts: 10,
+                skills: vec!["a".into(), "b".into()],
+            },
+            HistoryEntry {
+                ts: 20,
+                skills: vec!["a".into()],
+            },
+        ];
+        let counts = autoload_counts(&history);
+        assert_eq!(counts["a"], Autoloads { count: 2, last: 20 });
+        assert_eq!(counts["b"], Autoloads { count: 1, last: 10 });
+    }
+}
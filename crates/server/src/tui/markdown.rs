@@ -0,0 +1,132 @@
+//! Line-based styling of `SKILL.md` files for the preview pane.
+//!
+//! Covers what skills commonly use: frontmatter, headings, lists, quotes,
+//! fenced code, and inline `code` and `**bold**` spans.
+
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+
+use skrills_discovery::split_frontmatter;
+
+const CODE: Style = Style::new().fg(ratatui::style::Color::Yellow);
+
+pub(crate) fn render(text: &str) -> Vec<Line<'static>> {
+    let (frontmatter, body) = split_frontmatter(text);
+    let mut lines = Vec::new();
+    if let Some(frontmatter) = frontmatter {
+        lines.push(Line::from("---").dark_gray());
+        lines.extend(
+            frontmatter
+                .lines()
+                .map(|l| Line::from(l.to_string()).dark_gray()),
+        );
+        lines.push(Line::from("---").dark_gray());
+    }
+    let mut in_code = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            lines.push(Line::from(line.to_string()).dark_gray());
+        } else if in_code {
+            lines.push(Line::styled(line.to_string(), CODE));
+        } else {
+            lines.push(render_line(line));
+        }
+    }
+    lines
+}
+
+fn render_line(line: &str) -> Line<'static> {
+    let trimmed = line.trim_start();
+    let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        let style = Style::new().bold().cyan();
+        let style = if hashes == 1 {
+            style.add_modifier(Modifier::UNDERLINED)
+        } else {
+            style
+        };
+        return Line::styled(trimmed[hashes + 1..].to_string(), style);
+    }
+    if let Some(quote) = trimmed.strip_prefix("> ") {
+        return Line::from(inline(quote)).dark_gray().italic();
+    }
+    let indent = &line[..line.len() - trimmed.len()];
+    let bullet = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker));
+    match bullet {
+        Some(item) => {
+            let mut spans = vec![Span::raw(format!("{indent}• "))];
+            spans.extend(inline(item));
+            Line::from(spans)
+        }
+        None => Line::from(inline(line)),
+    }
+}
+
+/// Splits `text` into plain, `code` and `**bold**` spans.
+fn inline(text: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let code = rest.find('`');
+        let bold = rest.find("**");
+        let (start, delim, style) = match (code, bold) {
+            (Some(c), Some(b)) if b < c => (b, "**", Style::new().bold()),
+            (Some(c), _) => (c, "`", CODE),
+            (None, Some(b)) => (b, "**", Style::new().bold()),
+            (None, None) => break,
+        };
+        let inner = &rest[start + delim.len()..];
+        let Some(end) = inner.find(delim) else {
+            break;
+        };
+        if start > 0 {
+            spans.push(Span::raw(rest[..start].to_string()));
+        }
+        spans.push(Span::styled(inner[..end].to_string(), style));
+        rest = &inner[end + delim.len()..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::raw(rest.to_string()));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn styles_headings_lists_code_and_inline_spans() {
+        let lines = render(
+            "---\nname: x\n---\n# Title\n- use `cargo fmt` **first**\n```sh\n# not a heading\n```\n",
+        );
+        let text: Vec<_> = lines.iter().map(plain).collect();
+        assert_eq!(
+            text,
+            [
+                "---",
+                "name: x",
+                "---",
+                "Title",
+                "• use cargo fmt first",
+                "```sh",
+                "# not a heading",
+                "```",
+            ]
+        );
+        assert!(lines[3].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(lines[4].spans[2].style, CODE);
+        assert!(lines[4].spans[4]
+            .style
+            .add_modifier
+            .contains(Modifier::BOLD));
+        assert_eq!(lines[6].style, CODE);
+    }
+}
@@ -0,0 +1,435 @@
+//! Rendering of the terminal UI.
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Tabs, Wrap};
+use ratatui::Frame;
+use similar::{ChangeTag, TextDiff};
+use skrills_sync::ChangeKind;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::app::{App, Mode, Pane};
+use super::catalog::{Field, SkillDoc};
+use super::markdown;
+
+const HIGHLIGHT: Style = Style::new().add_modifier(Modifier::REVERSED);
+
+pub(crate) fn draw(frame: &mut Frame, app: &mut App) {
+    let [tabs, body, status] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let titles = Pane::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("{} {}", i + 1, p.title()));
+    let selected = Pane::ALL.iter().position(|p| *p == app.pane);
+    frame.render_widget(
+        Tabs::new(titles)
+            .select(selected)
+            .highlight_style(Style::new().bold().fg(Color::Cyan)),
+        tabs,
+    );
+
+    match app.pane {
+        Pane::Skills => draw_skills(frame, app, body),
+        Pane::Sync => draw_sync(frame, app, body),
+        Pane::Autoload => draw_autoload(frame, app, body),
+        Pane::History => draw_history(frame, app, body),
+        Pane::Runs => draw_runs(frame, app, body),
+    }
+
+    let line = if app.status.is_empty() {
+        Line::from(app.hints()).dark_gray()
+    } else {
+        Line::from(vec![
+            Span::raw(app.status.clone()),
+            Span::raw("  "),
+            Span::raw(app.hints()).dark_gray(),
+        ])
+    };
+    frame.render_widget(line, status);
+}
+
+fn split(area: Rect, left: u16) -> [Rect; 2] {
+    Layout::horizontal([Constraint::Percentage(left), Constraint::Min(0)]).areas(area)
+}
+
+fn list<'a>(items: Vec<ListItem<'a>>, title: String) -> List<'a> {
+    List::new(items)
+        .block(Block::bordered().title(title))
+        .highlight_style(HIGHLIGHT)
+}
+
+fn render_list(frame: &mut Frame, list: List, selected: Option<usize>, area: Rect) {
+    let mut state = ListState::default().with_selected(selected);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn text_pane<'a>(text: impl Into<Text<'a>>, title: &'a str, scroll: u16) -> Paragraph<'a> {
+    Paragraph::new(text)
+        .block(Block::bordered().title(title))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+}
+
+fn draw_skills(frame: &mut Frame, app: &mut App, area: Rect) {
+    let [left, right] = split(area, 40);
+    let searching = !app.filter.trim().is_empty();
+    let items: Vec<ListItem> = app
+        .visible_skills()
+        .map(|(doc, field)| {
+            let name = &doc.meta.name;
+            let pin = if app.pinned.contains(name) {
+                "● "
+            } else {
+                "  "
+            };
+            let mut spans = vec![Span::raw(pin).green(), Span::raw(name.clone())];
+            if let Some(stats) = app.autoloads.get(name) {
+                spans.push(Span::raw(format!(" ×{}", stats.count)).blue());
+            }
+            if !doc.shadows.is_empty() {
+                spans.push(Span::raw(format!(" ⧉{}", doc.shadows.len())).yellow());
+            }
+            let tag = match field {
+                Field::Description if searching => " (description)",
+                Field::Body if searching => " (body)",
+                _ => "",
+            };
+            spans.push(Span::raw(format!("  {}{tag}", doc.meta.source.label())).dark_gray());
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let title = match (app.mode, searching) {
+        (Mode::Filter, _) => format!("Skills /{}▏ ({})", app.filter, items.len()),
+        (_, true) => format!("Skills /{} ({})", app.filter, items.len()),
+        (_, false) => format!("Skills ({} pinned)", app.pinned.len()),
+    };
+    let selected = (!items.is_empty()).then_some(app.selected);
+    render_list(frame, list(items, title), selected, left);
+
+    let text = app
+        .selected_skill()
+        .map(|doc| skill_preview(doc, app, now()))
+        .unwrap_or_default();
+    frame.render_widget(text_pane(text, "SKILL.md", app.scroll), right);
+}
+
+/// A summary of where the skill comes from, how it was used and what it
+/// shadows, followed by its rendered `SKILL.md`.
+fn skill_preview(doc: &SkillDoc, app: &App, now: u64) -> Vec<Line<'static>> {
+    let meta = &doc.meta;
+    let mut lines = Vec::new();
+    if let Some(description) = &doc.description {
+        lines.push(Line::from(description.clone()).italic());
+    }
+    lines.push(
+        Line::from(format!(
+            "{} | {}  {}",
+            meta.source.label(),
+            meta.source.location(),
+            meta.path.display()
+        ))
+        .dark_gray(),
+    );
+    let pinned = if app.pinned.contains(&meta.name) {
+        "Pinned. "
+    } else {
+        ""
+    };
+    let used = match app.autoloads.get(&meta.name) {
+        Some(stats) => format!(
+            "Autoloaded {} times, last {}.",
+            stats.count,
+            ago(stats.last, now)
+        ),
+        None => "Not autoloaded recently.".to_string(),
+    };
+    lines.push(Line::from(format!("{pinned}{used}")).blue());
+    for dup in &doc.shadows {
+        lines.push(
+            Line::from(format!(
+                "Shadows the copy in {} ({})",
+                dup.skipped_source, dup.skipped_root
+            ))
+            .yellow(),
+        );
+    }
+    lines.push(Line::default());
+    lines.extend(markdown::render(&doc.text));
+    lines
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn diff_lines(current: &str, incoming: &str) -> Vec<Line<'static>> {
+    TextDiff::from_lines(current, incoming)
+        .iter_all_changes()
+        .map(|change| {
+            let text = change.value().trim_end_matches('\n').to_string();
+            match change.tag() {
+                ChangeTag::Delete => Line::from(format!("-{text}")).red(),
+                ChangeTag::Insert => Line::from(format!("+{text}")).green(),
+                ChangeTag::Equal => Line::from(format!(" {text}")),
+            }
+        })
+        .collect()
+}
+
+fn draw_sync(frame: &mut Frame, app: &mut App, area: Rect) {
+    let [left, right] = split(area, 40);
+    let items: Vec<ListItem> = app
+        .sync
+        .changes
+        .iter()
+        .map(|c| {
+            let (mark, color) = match c.kind {
+                ChangeKind::Added => ("+", Color::Green),
+                ChangeKind::Modified => ("~", Color::Yellow),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{mark} "), Style::new().fg(color)),
+                Span::raw(c.name.clone()),
+                Span::raw(format!("  {}", c.artifact)).dark_gray(),
+            ]))
+        })
+        .collect();
+    let on_off = |b: bool| if b { "on" } else { "off" };
+    let title = format!(
+        "Claude → Codex ({} pending, keep existing {}, marketplace {})",
+        items.len(),
+        on_off(app.sync.keep_existing),
+        on_off(app.sync.include_marketplace)
+    );
+    let selected = (!items.is_empty()).then_some(app.sync.selected);
+    render_list(frame, list(items, title), selected, left);
+
+    let text: Text = if let Some(err) = &app.sync.error {
+        Line::from(err.clone()).red().into()
+    } else if let Some(change) = app.selected_change() {
+        diff_lines(&change.current, &change.incoming).into()
+    } else {
+        "Nothing to sync.".into()
+    };
+    frame.render_widget(text_pane(text, "Diff", app.scroll), right);
+}
+
+fn draw_autoload(frame: &mut Frame, app: &mut App, area: Rect) {
+    let [input, body] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+    let cursor = if app.mode == Mode::Prompt { "▏" } else { "" };
+    frame.render_widget(
+        Paragraph::new(format!("{}{cursor}", app.autoload.prompt))
+            .block(Block::bordered().title("Prompt")),
+        input,
+    );
+
+    let [left, right] = split(body, 30);
+    let view = &app.autoload;
+    let items: Vec<ListItem> = view
+        .included
+        .iter()
+        .map(|(name, source)| {
+            ListItem::new(Line::from(vec![
+                Span::raw(name.clone()),
+                Span::raw(format!("  {source}")).dark_gray(),
+            ]))
+        })
+        .collect();
+    let mut title = format!("Included ({}, {} skipped)", items.len(), view.skipped);
+    if view.truncated {
+        title.push_str(" truncated");
+    }
+    render_list(frame, list(items, title), None, left);
+
+    let text: Text = match &view.error {
+        Some(err) => Line::from(err.clone()).red().into(),
+        None => view.output.clone().into(),
+    };
+    frame.render_widget(text_pane(text, "Output", app.scroll), right);
+}
+
+/// Formats the time since `ts` (seconds since the epoch), e.g. `5m ago`.
+fn ago(ts: u64, now: u64) -> String {
+    let secs = now.saturating_sub(ts);
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+fn draw_history(frame: &mut Frame, app: &mut App, area: Rect) {
+    let [left, right] = split(area, 40);
+    let now = now();
+    let items: Vec<ListItem> = app
+        .history()
+        .iter()
+        .map(|h| ListItem::new(format!("{:<9} {} skills", ago(h.ts, now), h.skills.len())))
+        .collect();
+    let selected = (!items.is_empty()).then_some(app.history_selected);
+    render_list(
+        frame,
+        list(items, "Autoload history".to_string()),
+        selected,
+        left,
+    );
+
+    let skills = app
+        .history()
+        .get(app.history_selected)
+        .map(|h| h.skills.join("\n"))
+        .unwrap_or_default();
+    frame.render_widget(text_pane(skills, "Skills", app.scroll), right);
+}
+
+fn draw_runs(frame: &mut Frame, app: &mut App, area: Rect) {
+    let [left, right] = split(area, 45);
+    let items: Vec<ListItem> = app
+        .runs
+        .runs
+        .iter()
+        .map(|r| {
+            let state = Span::raw(format!("{:<10}", r.state));
+            let state = if r.finished { state } else { state.yellow() };
+            let mut spans = vec![
+                Span::raw(format!("{} ", r.created)).dark_gray(),
+                state,
+                Span::raw(format!(" {}", r.backend)),
+            ];
+            if let Some(template) = &r.template {
+                spans.push(Span::raw(format!(" {template}")).dark_gray());
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let selected = (!items.is_empty()).then_some(app.runs.selected);
+    render_list(
+        frame,
+        list(items, "Subagent runs".to_string()),
+        selected,
+        left,
+    );
+
+    let text: Text = if let Some(err) = &app.runs.error {
+        Line::from(err.clone()).red().into()
+    } else if let Some(run) = app.selected_run() {
+        Text::from_iter(
+            std::iter::once(Line::from(run.id.clone()).bold())
+                .chain(run.events.iter().map(|e| Line::from(e.clone()))),
+        )
+    } else {
+        "No runs recorded.".into()
+    };
+    frame.render_widget(text_pane(text, "Events", app.scroll), right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::app::RunRow;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use skrills_discovery::{DuplicateInfo, SkillMeta, SkillSource};
+    use skrills_state::HistoryEntry;
+    use skrills_sync::PendingChange;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn screen(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 14)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn renders_skills_diffs_and_runs() {
+        let skill = SkillMeta {
+            name: "review".into(),
+            path: PathBuf::from("/skills/review/SKILL.md"),
+            source: SkillSource::Codex,
+            root: PathBuf::from("/skills"),
+            hash: "h".into(),
+            plugin: None,
+        };
+        let shadowed = DuplicateInfo {
+            name: "review".into(),
+            skipped_source: "claude".into(),
+            skipped_root: "/claude/skills".into(),
+            kept_source: "codex".into(),
+            kept_root: "/skills".into(),
+        };
+        let doc = SkillDoc::new(
+            skill,
+            "---\ndescription: Reviews pull requests\n---\n# Review\nCheck `diff`.\n".into(),
+            vec![shadowed],
+        );
+        let mut app = App::new(vec![doc], HashSet::from(["review".to_string()]));
+        app.set_history(vec![
+            HistoryEntry {
+                ts: 0,
+                skills: vec!["review".into()],
+            },
+            HistoryEntry {
+                ts: 1,
+                skills: vec!["review".into()],
+            },
+        ]);
+        let out = screen(&mut app);
+        assert!(out.contains("● review ×2 ⧉1"), "{out}");
+        assert!(out.contains("Reviews pull requests"), "{out}");
+        assert!(out.contains("Autoloaded 2 times"), "{out}");
+        assert!(
+            out.contains("Shadows the copy in claude (/claude/skills)"),
+            "{out}"
+        );
+        assert!(out.contains("Check diff."), "{out}");
+
+        app.pane = Pane::Sync;
+        app.sync.changes = vec![PendingChange {
+            artifact: "command".into(),
+            name: "deploy".into(),
+            kind: ChangeKind::Modified,
+            current: "old step\nshared\n".into(),
+            incoming: "new step\nshared\n".into(),
+        }];
+        let out = screen(&mut app);
+        assert!(out.contains("~ deploy"), "{out}");
+        assert!(out.contains("-old step"), "{out}");
+        assert!(out.contains("+new step"), "{out}");
+
+        app.pane = Pane::Runs;
+        app.runs.runs = vec![RunRow {
+            id: "run-1".into(),
+            state: "running".into(),
+            backend: "claude".into(),
+            events: vec!["12:00:00 start".into()],
+            ..RunRow::default()
+        }];
+        let out = screen(&mut app);
+        assert!(out.contains("running"), "{out}");
+        assert!(out.contains("12:00:00 start"), "{out}");
+    }
+
+    #[test]
+    fn ago_picks_the_largest_unit() {
+        assert_eq!(ago(100, 130), "30s ago");
+        assert_eq!(ago(0, 7200), "2h ago");
+        assert_eq!(ago(0, 3 * 86400 + 5), "3d ago");
+    }
+}
@@ -8,40 +8,76 @@ use serde::Serialize;
 use serde_json::{json, Value};
 use time::OffsetDateTime;
 
-use crate::backend::{config::AdapterConfig, AdapterCapabilities, BackendAdapter};
+use crate::backend::sse::{is_event_stream, read_body, stream_completion, SseEvent, SseStep};
+use crate::backend::{
+    cancel_run,
+    config::{AdapterConfig, AuthHeader, Endpoint, DEFAULT_TIMEOUT_MS},
+    execute_tool_call, finish_run, load_history, record_offered_tools, resume_execution,
+    retry::send_with_retry,
+    start_event_data, start_execution, AdapterCapabilities, BackendAdapter, Execute, RunHandle,
+};
 use crate::store::{
-    BackendKind, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus, RunStore,
-    SubagentTemplate,
+    BackendKind, ChatMessage, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus,
+    RunStore, RunUsage, SubagentTemplate,
 };
+use crate::tools::{ToolLoop, ToolSpec};
 
 const DEFAULT_BASE: &str = "https://api.anthropic.com/v1/";
-const DEFAULT_TIMEOUT_MS: u64 = 120_000;
 const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 1024;
 
 #[derive(Debug, Clone)]
 pub struct ClaudeAdapter {
     config: AdapterConfig,
+    endpoint: Endpoint,
     client: reqwest::Client,
+    tools: Option<ToolLoop>,
 }
 
 impl ClaudeAdapter {
     pub fn new(model: String) -> Self {
-        let config = AdapterConfig::from_env("CLAUDE", &model, DEFAULT_BASE, DEFAULT_TIMEOUT_MS)
+        Self::with_timeout(model, DEFAULT_TIMEOUT_MS)
+    }
+
+    /// Like [`Self::new`], but waits `timeout_ms` to connect or for the next
+    /// chunk of a response unless `SKRILLS_CLAUDE_TIMEOUT_MS` is set.
+    pub fn with_timeout(model: String, timeout_ms: u64) -> Self {
+        let config = AdapterConfig::from_env("CLAUDE", &model, DEFAULT_BASE, timeout_ms)
             .unwrap_or_else(|_| AdapterConfig {
-                api_key: String::new(),
-                base_url: Url::parse(DEFAULT_BASE).expect("valid default base"),
-                model,
-                timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+                timeout: Duration::from_millis(timeout_ms),
+                ..AdapterConfig::new(Url::parse(DEFAULT_BASE).expect("valid default base"), model)
             });
         Self::with_config(config)
     }
 
     pub fn with_config(config: AdapterConfig) -> Self {
         let client = reqwest::Client::builder()
-            .timeout(config.timeout)
+            .connect_timeout(config.timeout)
             .build()
             .expect("failed to build reqwest client");
-        Self { config, client }
+        Self {
+            config,
+            endpoint: Endpoint {
+                kind: BackendKind::Claude,
+                auth: AuthHeader::Named("x-api-key".into()),
+                key_var: Some("SKRILLS_CLAUDE_API_KEY".into()),
+            },
+            client,
+            tools: None,
+        }
+    }
+
+    /// Serves `endpoint` instead of the built-in Claude backend, for
+    /// servers declared in `subagents.toml`.
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Offers `tools` to the model and runs its tool calls.
+    pub fn with_tools(mut self, tools: ToolLoop) -> Self {
+        self.tools = Some(tools);
+        self
     }
 
     async fn execute_run(
@@ -50,29 +86,35 @@ impl ClaudeAdapter {
         request: RunRequest,
         store: Arc<dyn RunStore>,
     ) -> Result<()> {
+        let tools = match &self.tools {
+            Some(tools) => tools.tools_for(&request).await?,
+            None => Vec::new(),
+        };
+        let mut start = start_event_data(&request);
+        record_offered_tools(&mut start, &tools);
         store
             .append_event(
                 run_id,
                 RunEvent {
                     ts: OffsetDateTime::now_utc(),
                     kind: "start".into(),
-                    data: None,
+                    data: Some(start),
                 },
             )
             .await?;
 
-        if self.config.api_key.is_empty() {
+        if let Some(msg) = self.endpoint.missing_key(&self.config.api_key) {
             store
                 .update_status(
                     run_id,
                     RunStatus {
                         state: RunState::Failed,
-                        message: Some("missing SKRILLS_CLAUDE_API_KEY".into()),
+                        message: Some(msg.clone()),
                         updated_at: OffsetDateTime::now_utc(),
                     },
                 )
                 .await?;
-            return Err(anyhow!("missing SKRILLS_CLAUDE_API_KEY"));
+            return Err(anyhow!(msg));
         }
 
         let url = self
@@ -80,94 +122,148 @@ impl ClaudeAdapter {
             .base_url
             .join("messages")
             .unwrap_or_else(|_| self.config.base_url.clone());
-        let body = build_anthropic_body(&self.config.model, &request);
-
-        let resp = self
-            .client
-            .post(url)
-            .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .json(&body)
-            .send()
-            .await
-            .context("calling Claude API")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
-        let parsed: Value = serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
-
-        if !status.is_success() {
-            let msg = parsed
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or("claude call failed")
-                .to_string();
-            store
-                .append_event(
-                    run_id,
-                    RunEvent {
-                        ts: OffsetDateTime::now_utc(),
-                        kind: "error".into(),
-                        data: Some(parsed.clone()),
-                    },
-                )
-                .await?;
-            store
-                .update_status(
-                    run_id,
-                    RunStatus {
-                        state: RunState::Failed,
-                        message: Some(msg.clone()),
-                        updated_at: OffsetDateTime::now_utc(),
-                    },
-                )
-                .await?;
-            return Err(anyhow!(msg));
+        let history = load_history(run_id, &store).await?;
+        let mut body = build_anthropic_body(&self.config.model, &request, &history);
+        if !tools.is_empty() {
+            body.tools = Some(tools.iter().map(anthropic_tool).collect());
+            // Tool use blocks are read from whole responses.
+            body.stream = Some(false);
         }
 
-        let completion = extract_anthropic_text(&parsed).unwrap_or_else(|| text.clone());
-        for token in completion.split_whitespace() {
-            store
-                .append_event(
+        let mut iterations = 0;
+        loop {
+            let resp = send_with_retry(run_id, &store, &self.config, "Claude API", || {
+                self.endpoint
+                    .auth
+                    .apply(self.client.post(url.clone()), &self.config.api_key)
+                    .header("anthropic-version", ANTHROPIC_VERSION)
+                    .json(&body)
+            })
+            .await?;
+
+            let status = resp.status();
+            if status.is_success() && is_event_stream(&resp) {
+                let completion = stream_completion(
+                    resp,
                     run_id,
-                    RunEvent {
-                        ts: OffsetDateTime::now_utc(),
-                        kind: "stream".into(),
-                        data: Some(json!({ "token": token })),
-                    },
+                    &store,
+                    self.config.timeout,
+                    decode_anthropic_event,
                 )
                 .await?;
+                return finish_run(run_id, completion, &store).await;
+            }
+            let text = read_body(resp, self.config.timeout).await?;
+            let parsed: Value =
+                serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
+            if let Some(usage) = extract_anthropic_usage(&parsed) {
+                store.add_usage(run_id, usage).await?;
+            }
+
+            if !status.is_success() {
+                let msg = parsed
+                    .get("error")
+                    .and_then(|e| e.get("message"))
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("claude call failed")
+                    .to_string();
+                store
+                    .append_event(
+                        run_id,
+                        RunEvent {
+                            ts: OffsetDateTime::now_utc(),
+                            kind: "error".into(),
+                            data: Some(parsed.clone()),
+                        },
+                    )
+                    .await?;
+                store
+                    .update_status(
+                        run_id,
+                        RunStatus {
+                            state: RunState::Failed,
+                            message: Some(msg.clone()),
+                            updated_at: OffsetDateTime::now_utc(),
+                        },
+                    )
+                    .await?;
+                return Err(anyhow!(msg));
+            }
+
+            let content = parsed.get("content").and_then(|c| c.as_array());
+            let uses: Vec<&Value> = content
+                .into_iter()
+                .flatten()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                .collect();
+            let tools = match self.tools.as_ref() {
+                Some(tools) if !uses.is_empty() => tools,
+                _ => {
+                    let completion =
+                        extract_anthropic_text(&parsed).unwrap_or_else(|| text.clone());
+                    return finish_run(run_id, completion, &store).await;
+                }
+            };
+            iterations += 1;
+            if iterations > tools.max_iterations {
+                return Err(anyhow!(
+                    "tool loop exceeded {} iterations",
+                    tools.max_iterations
+                ));
+            }
+            body.messages.push(AnthropicMessage {
+                role: "assistant".into(),
+                content: Value::Array(content.cloned().unwrap_or_default()),
+            });
+            let mut results = Vec::new();
+            for block in uses {
+                let id = block.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+                let name = block
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                let output =
+                    execute_tool_call(run_id, &store, tools.provider.as_ref(), id, name, input)
+                        .await?;
+                results.push(json!({
+                    "type": "tool_result",
+                    "tool_use_id": id,
+                    "content": output.content,
+                    "is_error": output.is_error,
+                }));
+            }
+            body.messages.push(AnthropicMessage {
+                role: "user".into(),
+                content: Value::Array(results),
+            });
         }
-        store
-            .append_event(
-                run_id,
-                RunEvent {
-                    ts: OffsetDateTime::now_utc(),
-                    kind: "completion".into(),
-                    data: Some(json!({ "text": completion })),
-                },
-            )
-            .await?;
+    }
+}
 
-        store
-            .update_status(
-                run_id,
-                RunStatus {
-                    state: RunState::Succeeded,
-                    message: Some("completed".into()),
-                    updated_at: OffsetDateTime::now_utc(),
-                },
-            )
-            .await?;
-        Ok(())
+/// Describes `tool` in the messages API tool format.
+fn anthropic_tool(tool: &ToolSpec) -> Value {
+    let mut def = json!({"name": tool.name, "input_schema": tool.input_schema});
+    if let Some(description) = &tool.description {
+        def["description"] = json!(description);
     }
+    def
 }
 
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    /// Plain text, or content blocks for tool use turns.
+    content: Value,
+}
+
+impl AnthropicMessage {
+    fn text(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: Value::String(content.into()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -176,14 +272,24 @@ struct AnthropicBody {
     messages: Vec<AnthropicMessage>,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     metadata: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
 }
 
-fn build_anthropic_body(model: &str, request: &RunRequest) -> AnthropicBody {
+/// Builds a messages body; `history` is the run's conversation and falls
+/// back to the bare prompt when empty.
+fn build_anthropic_body(
+    model: &str,
+    request: &RunRequest,
+    history: &[ChatMessage],
+) -> AnthropicBody {
     let response_format = request.output_schema.as_ref().map(|schema| {
         json!({
             "type": "json_schema",
@@ -198,16 +304,28 @@ fn build_anthropic_body(model: &str, request: &RunRequest) -> AnthropicBody {
     } else {
         None
     };
+    let template = request.template.as_ref();
+    let messages = if history.is_empty() {
+        vec![AnthropicMessage::text("user", request.prompt.clone())]
+    } else {
+        history
+            .iter()
+            .map(|m| AnthropicMessage::text(m.role.as_str(), m.content.clone()))
+            .collect()
+    };
     AnthropicBody {
-        model: model.to_string(),
-        messages: vec![AnthropicMessage {
-            role: "user".into(),
-            content: request.prompt.clone(),
-        }],
-        max_tokens: 1024,
+        model: template
+            .and_then(|t| t.model.clone())
+            .unwrap_or_else(|| model.to_string()),
+        messages,
+        max_tokens: template
+            .and_then(|t| t.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS),
+        system: request.system_prompt(),
         stream: Some(request.async_mode),
         metadata,
         response_format,
+        tools: None,
     }
 }
 
@@ -234,10 +352,100 @@ fn extract_anthropic_text(val: &Value) -> Option<String> {
         })
 }
 
+/// Reads the `usage` block of a message. Cache reads and cache writes are
+/// reported separately from plain input, since both are priced differently.
+fn extract_anthropic_usage(message: &Value) -> Option<RunUsage> {
+    let usage = message.get("usage").filter(|u| u.is_object())?;
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    Some(RunUsage {
+        model: message
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(String::from),
+        input_tokens: count("input_tokens"),
+        output_tokens: count("output_tokens"),
+        cached_tokens: count("cache_read_input_tokens"),
+        cache_write_tokens: count("cache_creation_input_tokens"),
+    })
+}
+
+/// Decodes one messages stream event into its text delta.
+///
+/// Only `text_delta` content is collected. Input usage comes with
+/// `message_start` and the final output count with `message_delta`; `ping`
+/// and other bookkeeping events are skipped.
+fn decode_anthropic_event(event: &SseEvent) -> Result<SseStep> {
+    let payload: Value = serde_json::from_str(&event.data)
+        .with_context(|| format!("invalid Claude stream event: {}", event.data))?;
+    let kind = event
+        .event
+        .as_deref()
+        .or_else(|| payload.get("type").and_then(|t| t.as_str()))
+        .unwrap_or_default();
+    match kind {
+        "content_block_delta" => Ok(payload
+            .get("delta")
+            .filter(|d| d.get("type").and_then(|t| t.as_str()) == Some("text_delta"))
+            .and_then(|d| d.get("text"))
+            .and_then(|t| t.as_str())
+            .map(|s| SseStep::Delta(s.to_string()))
+            .unwrap_or(SseStep::Skip)),
+        "message_start" => Ok(payload
+            .get("message")
+            .and_then(extract_anthropic_usage)
+            .map(|usage| {
+                // The output count here is a placeholder; `message_delta`
+                // carries the real one.
+                SseStep::Usage(RunUsage {
+                    output_tokens: 0,
+                    ..usage
+                })
+            })
+            .unwrap_or(SseStep::Skip)),
+        "message_delta" => Ok(payload
+            .get("usage")
+            .and_then(|u| u.get("output_tokens"))
+            .and_then(|t| t.as_u64())
+            .map(|output_tokens| {
+                SseStep::Usage(RunUsage {
+                    output_tokens,
+                    ..RunUsage::default()
+                })
+            })
+            .unwrap_or(SseStep::Skip)),
+        "message_stop" => Ok(SseStep::Done(None)),
+        "error" => {
+            let msg = payload
+                .get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("claude stream failed");
+            Err(anyhow!(msg.to_string()))
+        }
+        _ => Ok(SseStep::Skip),
+    }
+}
+
+#[async_trait]
+impl Execute for ClaudeAdapter {
+    fn schema_repairs(&self) -> usize {
+        self.config.schema_repairs
+    }
+
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        self.execute_run(run_id, request, store).await
+    }
+}
+
 #[async_trait]
 impl BackendAdapter for ClaudeAdapter {
     fn backend(&self) -> BackendKind {
-        BackendKind::Claude
+        self.endpoint.kind.clone()
     }
 
     fn capabilities(&self) -> AdapterCapabilities {
@@ -249,57 +457,43 @@ impl BackendAdapter for ClaudeAdapter {
         }
     }
 
+    fn with_tool_loop(&self, tools: ToolLoop) -> Option<Arc<dyn BackendAdapter>> {
+        Some(Arc::new(self.clone().with_tools(tools)))
+    }
+
     async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+        let (name, description) = match &self.endpoint.kind {
+            BackendKind::Claude => (
+                "Claude Code Subagent".to_string(),
+                format!("Claude model {}", self.config.model),
+            ),
+            other => (
+                format!("Default {} Agent", other.name()),
+                format!("{} model {}", other.name(), self.config.model),
+            ),
+        };
         Ok(vec![SubagentTemplate {
             id: "default".into(),
-            name: "Claude Code Subagent".into(),
-            description: Some(format!("Claude model {}", self.config.model)),
-            backend: BackendKind::Claude,
+            name,
+            description: Some(description),
+            backend: self.endpoint.kind.clone(),
             capabilities: vec!["tools".into(), "structured_outputs".into()],
+            model: None,
+            system_prompt: None,
+            max_tokens: None,
+            tools: Vec::new(),
+            output_schema: None,
+            source: None,
         }])
     }
 
-    async fn run(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunId> {
-        request.backend = BackendKind::Claude;
-        let run_id = store.create_run(request.clone()).await?;
-        store
-            .update_status(
-                run_id,
-                RunStatus {
-                    state: RunState::Running,
-                    message: Some("dispatched".into()),
-                    updated_at: OffsetDateTime::now_utc(),
-                },
-            )
-            .await?;
-
-        let cloned = self.clone();
-        tokio::spawn(async move {
-            if let Err(err) = cloned.execute_run(run_id, request, store.clone()).await {
-                let _ = store
-                    .append_event(
-                        run_id,
-                        RunEvent {
-                            ts: OffsetDateTime::now_utc(),
-                            kind: "error".into(),
-                            data: Some(json!({"message": err.to_string()})),
-                        },
-                    )
-                    .await;
-                let _ = store
-                    .update_status(
-                        run_id,
-                        RunStatus {
-                            state: RunState::Failed,
-                            message: Some(err.to_string()),
-                            updated_at: OffsetDateTime::now_utc(),
-                        },
-                    )
-                    .await;
-            }
-        });
+    async fn start(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        request.backend = self.endpoint.kind.clone();
+        start_execution(self, request, store).await
+    }
 
-        Ok(run_id)
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        resume_execution(self, run_id, store).await
     }
 
     async fn get_status(
@@ -311,7 +505,7 @@ impl BackendAdapter for ClaudeAdapter {
     }
 
     async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
-        store.stop(run_id).await
+        cancel_run(run_id, &store).await
     }
 
     async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
@@ -339,9 +533,11 @@ mod tests {
     fn test_claude_adapter_with_config() {
         let config = AdapterConfig {
             api_key: "test-key".to_string(),
-            base_url: reqwest::Url::parse("https://test.com").unwrap(),
-            model: "test-model".to_string(),
             timeout: Duration::from_secs(30),
+            ..AdapterConfig::new(
+                reqwest::Url::parse("https://test.com").unwrap(),
+                "test-model",
+            )
         };
 
         let adapter = ClaudeAdapter::with_config(config.clone());
@@ -388,12 +584,10 @@ mod tests {
             backend: BackendKind::Claude,
             prompt: "Hello, world!".to_string(),
             template_id: Some("test".to_string()),
-            output_schema: None,
-            tracing: false,
-            async_mode: false,
+            ..Default::default()
         };
 
-        let body = build_anthropic_body("claude-3-haiku-20240307", &request);
+        let body = build_anthropic_body("claude-3-haiku-20240307", &request, &[]);
 
         assert_eq!(body.model, "claude-3-haiku-20240307");
         assert_eq!(body.messages.len(), 1);
@@ -420,9 +614,10 @@ mod tests {
             output_schema: Some(serde_json::Value::Object(schema)),
             tracing: true,
             async_mode: true,
+            ..Default::default()
         };
 
-        let body = build_anthropic_body("claude-3-haiku-20240307", &request);
+        let body = build_anthropic_body("claude-3-haiku-20240307", &request, &[]);
 
         assert_eq!(body.stream, Some(true));
         assert!(body.metadata.is_some());
@@ -490,11 +685,62 @@ mod tests {
     }
 
     #[test]
-    fn test_anthropic_message_serialization() {
-        let message = AnthropicMessage {
-            role: "user".to_string(),
-            content: "Hello, Claude!".to_string(),
+    fn test_extract_anthropic_usage_tracks_cache_writes_separately() {
+        let value = json!({
+            "model": "claude-sonnet-4-5",
+            "usage": {
+                "input_tokens": 10,
+                "cache_creation_input_tokens": 200,
+                "cache_read_input_tokens": 50,
+                "output_tokens": 40
+            }
+        });
+
+        assert_eq!(
+            extract_anthropic_usage(&value),
+            Some(RunUsage {
+                model: Some("claude-sonnet-4-5".into()),
+                input_tokens: 10,
+                output_tokens: 40,
+                cached_tokens: 50,
+                cache_write_tokens: 200,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_anthropic_event_splits_usage_across_start_and_delta() {
+        let event = |name: &str, data: Value| SseEvent {
+            event: Some(name.into()),
+            data: data.to_string(),
         };
+        let start = event(
+            "message_start",
+            json!({"type": "message_start", "message": {
+                "model": "claude-haiku-4-5",
+                "usage": {"input_tokens": 25, "output_tokens": 1}
+            }}),
+        );
+        let delta = event(
+            "message_delta",
+            json!({"type": "message_delta", "usage": {"output_tokens": 15}}),
+        );
+
+        let SseStep::Usage(start) = decode_anthropic_event(&start).unwrap() else {
+            panic!("expected usage from message_start");
+        };
+        let SseStep::Usage(delta) = decode_anthropic_event(&delta).unwrap() else {
+            panic!("expected usage from message_delta");
+        };
+        let mut total = start;
+        total.add(delta);
+        assert_eq!(total.model.as_deref(), Some("claude-haiku-4-5"));
+        assert_eq!((total.input_tokens, total.output_tokens), (25, 15));
+    }
+
+    #[test]
+    fn test_anthropic_message_serialization() {
+        let message = AnthropicMessage::text("user", "Hello, Claude!");
 
         let json = serde_json::to_string(&message).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
@@ -503,18 +749,48 @@ mod tests {
         assert_eq!(parsed["content"], "Hello, Claude!");
     }
 
+    #[test]
+    fn test_build_anthropic_body_applies_template() {
+        let request = RunRequest {
+            backend: BackendKind::Claude,
+            prompt: "Review this".to_string(),
+            template_id: Some("reviewer".to_string()),
+            async_mode: true,
+            template: Some(SubagentTemplate {
+                id: "reviewer".into(),
+                name: "reviewer".into(),
+                description: None,
+                backend: BackendKind::Claude,
+                capabilities: Vec::new(),
+                model: Some("claude-haiku-4-5".into()),
+                system_prompt: Some("You review code.".into()),
+                max_tokens: Some(4096),
+                tools: Vec::new(),
+                output_schema: None,
+                source: None,
+            }),
+            ..Default::default()
+        };
+
+        let body = build_anthropic_body("claude-code", &request, &[]);
+
+        assert_eq!(body.model, "claude-haiku-4-5");
+        assert_eq!(body.max_tokens, 4096);
+        assert_eq!(body.system.as_deref(), Some("You review code."));
+        assert_eq!(body.messages.len(), 1);
+    }
+
     #[test]
     fn test_anthropic_body_serialization() {
         let body = AnthropicBody {
             model: "claude-3-haiku-20240307".to_string(),
-            messages: vec![AnthropicMessage {
-                role: "user".to_string(),
-                content: "Test".to_string(),
-            }],
+            messages: vec![AnthropicMessage::text("user", "Test")],
             max_tokens: 100,
+            system: None,
             stream: Some(true),
             metadata: Some(json!({"trace": true})),
             response_format: None,
+            tools: None,
         };
 
         let json = serde_json::to_string(&body).unwrap();
@@ -531,14 +807,13 @@ mod tests {
     fn test_anthropic_body_serialization_skips_none() {
         let body = AnthropicBody {
             model: "claude-3-haiku-20240307".to_string(),
-            messages: vec![AnthropicMessage {
-                role: "user".to_string(),
-                content: "Test".to_string(),
-            }],
+            messages: vec![AnthropicMessage::text("user", "Test")],
             max_tokens: 100,
+            system: None,
             stream: None,
             metadata: None,
             response_format: None,
+            tools: None,
         };
 
         let json = serde_json::to_string(&body).unwrap();
@@ -561,9 +836,7 @@ mod tests {
             backend: BackendKind::Claude,
             prompt: "Test prompt".to_string(),
             template_id: Some("test".to_string()),
-            output_schema: None,
-            tracing: false,
-            async_mode: false,
+            ..Default::default()
         };
 
         // The run method would:
@@ -1,11 +1,19 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::backend::{config::AdapterConfig, AdapterCapabilities, BackendAdapter};
+use crate::backend::sse::{is_event_stream, read_body, stream_completion, SseEvent, SseStep};
+use crate::backend::{
+    cancel_run,
+    config::{AdapterConfig, AuthHeader, Endpoint, DEFAULT_TIMEOUT_MS},
+    execute_tool_call, finish_run, load_history, record_offered_tools, resume_execution,
+    retry::send_with_retry,
+    start_event_data, start_execution, AdapterCapabilities, BackendAdapter, Execute, RunHandle,
+};
 use crate::store::{
-    BackendKind, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus, RunStore,
-    SubagentTemplate,
+    BackendKind, ChatMessage, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus,
+    RunStore, RunUsage, SubagentTemplate,
 };
+use crate::tools::{ToolLoop, ToolSpec};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use reqwest::Url;
@@ -14,32 +22,59 @@ use serde_json::{json, Value};
 use time::OffsetDateTime;
 
 const DEFAULT_BASE: &str = "https://api.openai.com/v1";
-const DEFAULT_TIMEOUT_MS: u64 = 120_000;
 
 #[derive(Debug, Clone)]
 pub struct CodexAdapter {
     config: AdapterConfig,
+    endpoint: Endpoint,
     client: reqwest::Client,
+    tools: Option<ToolLoop>,
 }
 
 impl CodexAdapter {
     pub fn new(model: String) -> Self {
-        let config = AdapterConfig::from_env("CODEX", &model, DEFAULT_BASE, DEFAULT_TIMEOUT_MS)
+        Self::with_timeout(model, DEFAULT_TIMEOUT_MS)
+    }
+
+    /// Like [`Self::new`], but waits `timeout_ms` to connect or for the next
+    /// chunk of a response unless `SKRILLS_CODEX_TIMEOUT_MS` is set.
+    pub fn with_timeout(model: String, timeout_ms: u64) -> Self {
+        let config = AdapterConfig::from_env("CODEX", &model, DEFAULT_BASE, timeout_ms)
             .unwrap_or_else(|_| AdapterConfig {
-                api_key: String::new(),
-                base_url: Url::parse(DEFAULT_BASE).expect("valid default base"),
-                model,
-                timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+                timeout: Duration::from_millis(timeout_ms),
+                ..AdapterConfig::new(Url::parse(DEFAULT_BASE).expect("valid default base"), model)
             });
         Self::with_config(config)
     }
 
     pub fn with_config(config: AdapterConfig) -> Self {
         let client = reqwest::Client::builder()
-            .timeout(config.timeout)
+            .connect_timeout(config.timeout)
             .build()
             .expect("failed to build reqwest client");
-        Self { config, client }
+        Self {
+            config,
+            endpoint: Endpoint {
+                kind: BackendKind::Codex,
+                auth: AuthHeader::Bearer,
+                key_var: Some("SKRILLS_CODEX_API_KEY".into()),
+            },
+            client,
+            tools: None,
+        }
+    }
+
+    /// Serves `endpoint` instead of the built-in Codex backend, for
+    /// servers declared in `subagents.toml`.
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Offers `tools` to the model and runs its tool calls.
+    pub fn with_tools(mut self, tools: ToolLoop) -> Self {
+        self.tools = Some(tools);
+        self
     }
 
     async fn execute_run(
@@ -48,125 +83,194 @@ impl CodexAdapter {
         request: RunRequest,
         store: Arc<dyn RunStore>,
     ) -> Result<()> {
+        let tools = match &self.tools {
+            Some(tools) => tools.tools_for(&request).await?,
+            None => Vec::new(),
+        };
+        let mut start = start_event_data(&request);
+        record_offered_tools(&mut start, &tools);
         store
             .append_event(
                 run_id,
                 RunEvent {
                     ts: OffsetDateTime::now_utc(),
                     kind: "start".into(),
-                    data: None,
+                    data: Some(start),
                 },
             )
             .await?;
 
-        if self.config.api_key.is_empty() {
+        if let Some(msg) = self.endpoint.missing_key(&self.config.api_key) {
             store
                 .update_status(
                     run_id,
                     RunStatus {
                         state: RunState::Failed,
-                        message: Some("missing SKRILLS_CODEX_API_KEY".into()),
+                        message: Some(msg.clone()),
                         updated_at: OffsetDateTime::now_utc(),
                     },
                 )
                 .await?;
-            return Err(anyhow!("missing SKRILLS_CODEX_API_KEY"));
+            return Err(anyhow!(msg));
         }
 
-        let body = build_openai_body(&self.config.model, &request);
+        let history = load_history(run_id, &store).await?;
+        let mut body = build_openai_body(&self.config.model, &request, &history);
+        if !tools.is_empty() {
+            body.tools = Some(tools.iter().map(openai_tool).collect());
+            // Tool calls are read from whole responses.
+            body.stream = Some(false);
+            body.stream_options = None;
+        }
         let url = self
             .config
             .base_url
             .join("chat/completions")
             .unwrap_or_else(|_| self.config.base_url.clone());
 
-        let resp = self
-            .client
-            .post(url)
-            .bearer_auth(&self.config.api_key)
-            .json(&body)
-            .send()
-            .await
-            .context("calling Codex API")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
-        let parsed: Value = serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
-
-        if !status.is_success() {
-            let msg = parsed
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or("codex call failed")
-                .to_string();
-            store
-                .append_event(
-                    run_id,
-                    RunEvent {
-                        ts: OffsetDateTime::now_utc(),
-                        kind: "error".into(),
-                        data: Some(parsed.clone()),
-                    },
-                )
-                .await?;
-            store
-                .update_status(
-                    run_id,
-                    RunStatus {
-                        state: RunState::Failed,
-                        message: Some(msg.clone()),
-                        updated_at: OffsetDateTime::now_utc(),
-                    },
-                )
-                .await?;
-            return Err(anyhow!(msg));
-        }
+        let mut iterations = 0;
+        loop {
+            let resp = send_with_retry(run_id, &store, &self.config, "Codex API", || {
+                self.endpoint
+                    .auth
+                    .apply(self.client.post(url.clone()), &self.config.api_key)
+                    .json(&body)
+            })
+            .await?;
 
-        let completion = extract_openai_text(&parsed).unwrap_or_else(|| text.clone());
-        // Simulate streaming events by chunking the completion into words.
-        for token in completion.split_whitespace() {
-            store
-                .append_event(
+            let status = resp.status();
+            if status.is_success() && is_event_stream(&resp) {
+                let completion = stream_completion(
+                    resp,
                     run_id,
-                    RunEvent {
-                        ts: OffsetDateTime::now_utc(),
-                        kind: "stream".into(),
-                        data: Some(json!({ "token": token })),
-                    },
+                    &store,
+                    self.config.timeout,
+                    decode_openai_event,
                 )
                 .await?;
-        }
-        store
-            .append_event(
-                run_id,
-                RunEvent {
-                    ts: OffsetDateTime::now_utc(),
-                    kind: "completion".into(),
-                    data: Some(json!({ "text": completion })),
-                },
-            )
-            .await?;
+                return finish_run(run_id, completion, &store).await;
+            }
+            let text = read_body(resp, self.config.timeout).await?;
+            let parsed: Value =
+                serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
+            if let Some(usage) = extract_openai_usage(&parsed) {
+                store.add_usage(run_id, usage).await?;
+            }
 
-        store
-            .update_status(
-                run_id,
-                RunStatus {
-                    state: RunState::Succeeded,
-                    message: Some("completed".into()),
-                    updated_at: OffsetDateTime::now_utc(),
-                },
-            )
-            .await?;
+            if !status.is_success() {
+                let msg = parsed
+                    .get("error")
+                    .and_then(|e| e.get("message"))
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("codex call failed")
+                    .to_string();
+                store
+                    .append_event(
+                        run_id,
+                        RunEvent {
+                            ts: OffsetDateTime::now_utc(),
+                            kind: "error".into(),
+                            data: Some(parsed.clone()),
+                        },
+                    )
+                    .await?;
+                store
+                    .update_status(
+                        run_id,
+                        RunStatus {
+                            state: RunState::Failed,
+                            message: Some(msg.clone()),
+                            updated_at: OffsetDateTime::now_utc(),
+                        },
+                    )
+                    .await?;
+                return Err(anyhow!(msg));
+            }
+
+            let message = parsed
+                .get("choices")
+                .and_then(|c| c.get(0))
+                .and_then(|c| c.get("message"));
+            let calls = message
+                .and_then(|m| m.get("tool_calls"))
+                .and_then(|c| c.as_array())
+                .filter(|c| !c.is_empty());
+            let (Some(calls), Some(tools)) = (calls, self.tools.as_ref()) else {
+                let completion = extract_openai_text(&parsed).unwrap_or_else(|| text.clone());
+                return finish_run(run_id, completion, &store).await;
+            };
+            iterations += 1;
+            if iterations > tools.max_iterations {
+                return Err(anyhow!(
+                    "tool loop exceeded {} iterations",
+                    tools.max_iterations
+                ));
+            }
+            body.messages.push(OpenAiMessage {
+                role: "assistant".into(),
+                content: message
+                    .and_then(|m| m.get("content"))
+                    .cloned()
+                    .unwrap_or(Value::Null),
+                tool_calls: Some(Value::Array(calls.clone())),
+                tool_call_id: None,
+            });
+            for call in calls {
+                let id = call.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+                let function = call.get("function");
+                let name = function
+                    .and_then(|f| f.get("name"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                // Arguments arrive JSON-encoded; anything unparsable is passed
+                // through so the provider can report it back to the model.
+                let raw = function
+                    .and_then(|f| f.get("arguments"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("{}");
+                let arguments = serde_json::from_str(raw).unwrap_or_else(|_| json!(raw));
+                let output =
+                    execute_tool_call(run_id, &store, tools.provider.as_ref(), id, name, arguments)
+                        .await?;
+                body.messages.push(OpenAiMessage {
+                    role: "tool".into(),
+                    content: Value::String(output.content),
+                    tool_calls: None,
+                    tool_call_id: Some(id.to_string()),
+                });
+            }
+        }
+    }
+}
 
-        Ok(())
+/// Describes `tool` as a chat/completions function tool.
+fn openai_tool(tool: &ToolSpec) -> Value {
+    let mut function = json!({"name": tool.name, "parameters": tool.input_schema});
+    if let Some(description) = &tool.description {
+        function["description"] = json!(description);
     }
+    json!({"type": "function", "function": function})
 }
 
 #[derive(Debug, Serialize)]
 struct OpenAiMessage {
     role: String,
-    content: String,
+    content: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl OpenAiMessage {
+    fn text(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: Value::String(content.into()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -183,14 +287,23 @@ struct OpenAiBody {
     model: String,
     messages: Vec<OpenAiMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<JsonSchemaFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
+    /// Asks for a final chunk carrying `usage` on streamed responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
 }
 
-fn build_openai_body(model: &str, request: &RunRequest) -> OpenAiBody {
+/// Builds a chat/completions body; `history` is the run's conversation and
+/// falls back to the bare prompt when empty.
+fn build_openai_body(model: &str, request: &RunRequest, history: &[ChatMessage]) -> OpenAiBody {
     let response_format = request
         .output_schema
         .as_ref()
@@ -204,15 +317,30 @@ fn build_openai_body(model: &str, request: &RunRequest) -> OpenAiBody {
     } else {
         None
     };
+    let template = request.template.as_ref();
+    let mut messages = Vec::new();
+    if let Some(system) = request.system_prompt() {
+        messages.push(OpenAiMessage::text("system", system));
+    }
+    if history.is_empty() {
+        messages.push(OpenAiMessage::text("user", request.prompt.clone()));
+    }
+    messages.extend(
+        history
+            .iter()
+            .map(|m| OpenAiMessage::text(m.role.as_str(), m.content.clone())),
+    );
     OpenAiBody {
-        model: model.to_string(),
-        messages: vec![OpenAiMessage {
-            role: "user".into(),
-            content: request.prompt.clone(),
-        }],
+        model: template
+            .and_then(|t| t.model.clone())
+            .unwrap_or_else(|| model.to_string()),
+        messages,
+        max_tokens: template.and_then(|t| t.max_tokens),
         response_format,
         stream: Some(request.async_mode),
+        stream_options: request.async_mode.then(|| json!({ "include_usage": true })),
         metadata,
+        tools: None,
     }
 }
 
@@ -246,10 +374,72 @@ fn extract_openai_text(val: &Value) -> Option<String> {
         })
 }
 
+/// Reads the `usage` block of a response or final stream chunk. Cached
+/// prompt tokens are reported as part of `prompt_tokens` and split out here.
+fn extract_openai_usage(val: &Value) -> Option<RunUsage> {
+    let usage = val.get("usage").filter(|u| u.is_object())?;
+    let count = |v: Option<&Value>| v.and_then(|v| v.as_u64()).unwrap_or(0);
+    let prompt = count(usage.get("prompt_tokens"));
+    let cached = count(
+        usage
+            .get("prompt_tokens_details")
+            .and_then(|d| d.get("cached_tokens")),
+    );
+    Some(RunUsage {
+        model: val.get("model").and_then(|m| m.as_str()).map(String::from),
+        input_tokens: prompt.saturating_sub(cached),
+        output_tokens: count(usage.get("completion_tokens")),
+        cached_tokens: cached,
+        ..RunUsage::default()
+    })
+}
+
+/// Decodes one chat/completions stream chunk into its text delta, or the
+/// usage carried by the final chunk.
+fn decode_openai_event(event: &SseEvent) -> Result<SseStep> {
+    if event.data.trim() == "[DONE]" {
+        return Ok(SseStep::Done(None));
+    }
+    let chunk: Value = serde_json::from_str(&event.data)
+        .with_context(|| format!("invalid Codex stream chunk: {}", event.data))?;
+    if let Some(err) = chunk.get("error") {
+        let msg = err
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("codex stream failed");
+        return Err(anyhow!(msg.to_string()));
+    }
+    Ok(chunk
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("delta"))
+        .and_then(|d| d.get("content"))
+        .and_then(|c| c.as_str())
+        .map(|s| SseStep::Delta(s.to_string()))
+        .or_else(|| extract_openai_usage(&chunk).map(SseStep::Usage))
+        .unwrap_or(SseStep::Skip))
+}
+
+#[async_trait]
+impl Execute for CodexAdapter {
+    fn schema_repairs(&self) -> usize {
+        self.config.schema_repairs
+    }
+
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        self.execute_run(run_id, request, store).await
+    }
+}
+
 #[async_trait]
 impl BackendAdapter for CodexAdapter {
     fn backend(&self) -> BackendKind {
-        BackendKind::Codex
+        self.endpoint.kind.clone()
     }
 
     fn capabilities(&self) -> AdapterCapabilities {
@@ -257,61 +447,48 @@ impl BackendAdapter for CodexAdapter {
             supports_schema: true,
             supports_async: true,
             supports_tracing: true,
-            supports_secure_transcript: true,
+            // Transcript bundles stay limited to the hosted Codex backend.
+            supports_secure_transcript: self.endpoint.kind == BackendKind::Codex,
         }
     }
 
+    fn with_tool_loop(&self, tools: ToolLoop) -> Option<Arc<dyn BackendAdapter>> {
+        Some(Arc::new(self.clone().with_tools(tools)))
+    }
+
     async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+        let (name, description) = match &self.endpoint.kind {
+            BackendKind::Codex => (
+                "Default Codex Agent".to_string(),
+                format!("Codex model {}", self.config.model),
+            ),
+            other => (
+                format!("Default {} Agent", other.name()),
+                format!("{} model {}", other.name(), self.config.model),
+            ),
+        };
         Ok(vec![SubagentTemplate {
             id: "default".into(),
-            name: "Default Codex Agent".into(),
-            description: Some(format!("Codex model {}", self.config.model)),
-            backend: BackendKind::Codex,
+            name,
+            description: Some(description),
+            backend: self.endpoint.kind.clone(),
             capabilities: vec!["tools".into(), "structured_outputs".into()],
+            model: None,
+            system_prompt: None,
+            max_tokens: None,
+            tools: Vec::new(),
+            output_schema: None,
+            source: None,
         }])
     }
 
-    async fn run(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunId> {
-        request.backend = BackendKind::Codex;
-        let run_id = store.create_run(request.clone()).await?;
-        store
-            .update_status(
-                run_id,
-                RunStatus {
-                    state: RunState::Running,
-                    message: Some("dispatched".into()),
-                    updated_at: OffsetDateTime::now_utc(),
-                },
-            )
-            .await?;
-
-        let cloned = self.clone();
-        tokio::spawn(async move {
-            if let Err(err) = cloned.execute_run(run_id, request, store.clone()).await {
-                let _ = store
-                    .append_event(
-                        run_id,
-                        RunEvent {
-                            ts: OffsetDateTime::now_utc(),
-                            kind: "error".into(),
-                            data: Some(json!({"message": err.to_string()})),
-                        },
-                    )
-                    .await;
-                let _ = store
-                    .update_status(
-                        run_id,
-                        RunStatus {
-                            state: RunState::Failed,
-                            message: Some(err.to_string()),
-                            updated_at: OffsetDateTime::now_utc(),
-                        },
-                    )
-                    .await;
-            }
-        });
+    async fn start(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        request.backend = self.endpoint.kind.clone();
+        start_execution(self, request, store).await
+    }
 
-        Ok(run_id)
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        resume_execution(self, run_id, store).await
     }
 
     async fn get_status(
@@ -323,7 +500,7 @@ impl BackendAdapter for CodexAdapter {
     }
 
     async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
-        store.stop(run_id).await
+        cancel_run(run_id, &store).await
     }
 
     async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
@@ -351,9 +528,11 @@ mod tests {
     fn test_codex_adapter_with_config() {
         let config = AdapterConfig {
             api_key: "test-key".to_string(),
-            base_url: reqwest::Url::parse("https://test.com").unwrap(),
-            model: "test-model".to_string(),
             timeout: Duration::from_secs(30),
+            ..AdapterConfig::new(
+                reqwest::Url::parse("https://test.com").unwrap(),
+                "test-model",
+            )
         };
 
         let adapter = CodexAdapter::with_config(config.clone());
@@ -397,15 +576,12 @@ mod tests {
     #[test]
     fn test_build_openai_body_default() {
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Hello, world!".to_string(),
             template_id: Some("test".to_string()),
-            output_schema: None,
-            tracing: false,
-            async_mode: false,
+            ..Default::default()
         };
 
-        let body = build_openai_body("gpt-4", &request);
+        let body = build_openai_body("gpt-4", &request, &[]);
 
         assert_eq!(body.model, "gpt-4");
         assert_eq!(body.messages.len(), 1);
@@ -425,15 +601,15 @@ mod tests {
         );
 
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Generate JSON".to_string(),
             template_id: Some("test".to_string()),
             output_schema: Some(serde_json::Value::Object(schema)),
             tracing: true,
             async_mode: true,
+            ..Default::default()
         };
 
-        let body = build_openai_body("gpt-4", &request);
+        let body = build_openai_body("gpt-4", &request, &[]);
 
         assert_eq!(body.stream, Some(true));
         assert!(body.response_format.is_some());
@@ -449,6 +625,66 @@ mod tests {
         assert_eq!(metadata["trace"], true);
     }
 
+    #[test]
+    fn test_build_openai_body_applies_template() {
+        let request = RunRequest {
+            prompt: "Summarize".to_string(),
+            template_id: Some("summarizer".to_string()),
+            template: Some(SubagentTemplate {
+                id: "summarizer".into(),
+                name: "summarizer".into(),
+                description: None,
+                backend: BackendKind::Codex,
+                capabilities: Vec::new(),
+                model: Some("gpt-5-mini".into()),
+                system_prompt: Some("Be terse.".into()),
+                max_tokens: Some(512),
+                tools: Vec::new(),
+                output_schema: None,
+                source: None,
+            }),
+            ..Default::default()
+        };
+
+        let body = build_openai_body("gpt-4", &request, &[]);
+
+        assert_eq!(body.model, "gpt-5-mini");
+        assert_eq!(body.max_tokens, Some(512));
+        assert_eq!(body.messages.len(), 2);
+        assert_eq!(body.messages[0].role, "system");
+        assert_eq!(body.messages[0].content, "Be terse.");
+        assert_eq!(body.messages[1].role, "user");
+    }
+
+    #[test]
+    fn test_build_openai_body_sends_history() {
+        let request = RunRequest {
+            prompt: "Draft a plan".to_string(),
+            ..Default::default()
+        };
+        let history = vec![
+            ChatMessage::user("Draft a plan"),
+            ChatMessage::assistant("1. Read the code"),
+            ChatMessage::user("Add a testing step"),
+        ];
+
+        let body = build_openai_body("gpt-4", &request, &history);
+
+        let turns: Vec<_> = body
+            .messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            turns,
+            vec![
+                ("user", "Draft a plan"),
+                ("assistant", "1. Read the code"),
+                ("user", "Add a testing step"),
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_openai_text_from_choices() {
         let value = json!({
@@ -545,12 +781,52 @@ mod tests {
     }
 
     #[test]
-    fn test_openai_message_serialization() {
-        let message = OpenAiMessage {
-            role: "user".to_string(),
-            content: "Hello, Codex!".to_string(),
+    fn test_extract_openai_usage_splits_cached_tokens() {
+        let value = json!({
+            "model": "gpt-4o-mini-2024-07-18",
+            "usage": {
+                "prompt_tokens": 120,
+                "completion_tokens": 30,
+                "prompt_tokens_details": {"cached_tokens": 100}
+            }
+        });
+
+        assert_eq!(
+            extract_openai_usage(&value),
+            Some(RunUsage {
+                model: Some("gpt-4o-mini-2024-07-18".into()),
+                input_tokens: 20,
+                output_tokens: 30,
+                cached_tokens: 100,
+                ..RunUsage::default()
+            })
+        );
+        assert!(extract_openai_usage(&json!({"usage": null})).is_none());
+    }
+
+    #[test]
+    fn test_decode_openai_event_reads_final_usage_chunk() {
+        let event = SseEvent {
+            event: None,
+            data: json!({
+                "choices": [],
+                "usage": {"prompt_tokens": 5, "completion_tokens": 7}
+            })
+            .to_string(),
         };
 
+        match decode_openai_event(&event).unwrap() {
+            SseStep::Usage(usage) => {
+                assert_eq!((usage.input_tokens, usage.output_tokens), (5, 7))
+            }
+            other => panic!("expected usage, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_openai_message_serialization() {
+        let message = OpenAiMessage::text("user", "Hello, Codex!");
+
         let json = serde_json::to_string(&message).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
 
@@ -592,16 +868,16 @@ mod tests {
     fn test_openai_body_serialization() {
         let body = OpenAiBody {
             model: "gpt-4".to_string(),
-            messages: vec![OpenAiMessage {
-                role: "user".to_string(),
-                content: "Test".to_string(),
-            }],
+            messages: vec![OpenAiMessage::text("user", "Test")],
+            max_tokens: None,
             response_format: Some(JsonSchemaFormat {
                 fmt_type: "json_schema".to_string(),
                 json_schema: json!({"name": "test"}),
                 strict: Some(false),
             }),
             stream: Some(true),
+            stream_options: None,
+            tools: None,
             metadata: Some(json!({"trace": true})),
         };
 
@@ -619,13 +895,13 @@ mod tests {
     fn test_openai_body_serialization_skips_none() {
         let body = OpenAiBody {
             model: "gpt-4".to_string(),
-            messages: vec![OpenAiMessage {
-                role: "user".to_string(),
-                content: "Test".to_string(),
-            }],
+            messages: vec![OpenAiMessage::text("user", "Test")],
+            max_tokens: None,
             response_format: None,
             stream: None,
+            stream_options: None,
             metadata: None,
+            tools: None,
         };
 
         let json = serde_json::to_string(&body).unwrap();
@@ -645,12 +921,9 @@ mod tests {
         // Note: This test shows the intended usage pattern
         // In practice, you'd need an implementation of RunStore
         let _request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Test prompt".to_string(),
             template_id: Some("test".to_string()),
-            output_schema: None,
-            tracing: false,
-            async_mode: false,
+            ..Default::default()
         };
 
         // The run method would:
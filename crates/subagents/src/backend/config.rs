@@ -1,17 +1,84 @@
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use reqwest::Url;
+use reqwest::{RequestBuilder, Url};
+
+use crate::schema::DEFAULT_SCHEMA_REPAIRS;
+use crate::store::BackendKind;
+use crate::templates::BackendConfig;
+
+/// Provider calls made per request, counting the first, unless
+/// `SKRILLS_<PREFIX>_MAX_ATTEMPTS` says otherwise.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+
+/// First retry delay before jitter; doubled on each further attempt.
+pub const DEFAULT_BACKOFF_MS: u64 = 500;
+
+/// Timeout of the built-in backends unless `SKRILLS_<PREFIX>_TIMEOUT_MS`
+/// says otherwise.
+pub const DEFAULT_TIMEOUT_MS: u64 = 120_000;
 
 #[derive(Debug, Clone)]
 pub struct AdapterConfig {
     pub api_key: String,
     pub base_url: Url,
     pub model: String,
+    /// Longest wait to connect, for the response head and between two body
+    /// chunks; a stream that keeps arriving may take longer in total.
     pub timeout: Duration,
+    /// Attempts per provider call, including the first; see
+    /// [`crate::backend::retry`].
+    pub max_attempts: u32,
+    pub backoff: Duration,
+    /// Repair turns a run gets when its output misses its `output_schema`.
+    pub schema_repairs: usize,
 }
 
 impl AdapterConfig {
+    /// A config for `model` at `base_url` with no API key and the default
+    /// timeout, retries and schema repairs; override fields with struct
+    /// update syntax.
+    pub fn new(base_url: Url, model: impl Into<String>) -> Self {
+        Self {
+            api_key: String::new(),
+            base_url,
+            model: model.into(),
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: Duration::from_millis(DEFAULT_BACKOFF_MS),
+            schema_repairs: DEFAULT_SCHEMA_REPAIRS,
+        }
+    }
+
+    /// Builds the config of a `[backends.<name>]` server. The API key is
+    /// read from `api_key_env` and left empty when that is unset.
+    pub fn from_backend_config(config: &BackendConfig, default_timeout_ms: u64) -> Result<Self> {
+        let api_key = config
+            .api_key_env
+            .as_ref()
+            .and_then(|var| std::env::var(var).ok())
+            .unwrap_or_default();
+        // Paths are joined onto the base, so keep its last segment.
+        let mut base_url = config.base_url.clone();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        let base_url = Url::parse(&base_url)
+            .with_context(|| format!("invalid base_url: {}", config.base_url))?;
+        Ok(Self {
+            api_key,
+            base_url,
+            model: config.model.clone(),
+            timeout: config
+                .timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_millis(default_timeout_ms)),
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            backoff: Duration::from_millis(DEFAULT_BACKOFF_MS),
+            schema_repairs: config.schema_repairs.unwrap_or(DEFAULT_SCHEMA_REPAIRS),
+        })
+    }
+
     pub fn from_env(
         prefix: &str,
         default_model: &str,
@@ -38,11 +105,97 @@ impl AdapterConfig {
             .and_then(|v| v.parse::<u64>().ok())
             .unwrap_or(default_timeout_ms);
 
+        let attempts_var = format!("SKRILLS_{}_MAX_ATTEMPTS", prefix);
+        let max_attempts = std::env::var(&attempts_var)
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+
+        let backoff_var = format!("SKRILLS_{}_BACKOFF_MS", prefix);
+        let backoff_ms = std::env::var(&backoff_var)
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BACKOFF_MS);
+
         Ok(Self {
             api_key,
             base_url,
             model,
             timeout: Duration::from_millis(timeout_ms),
+            max_attempts,
+            backoff: Duration::from_millis(backoff_ms),
+            schema_repairs: schema_repairs_from_env(prefix),
         })
     }
 }
+
+/// Repair turns from `SKRILLS_<PREFIX>_SCHEMA_REPAIRS`, or
+/// [`DEFAULT_SCHEMA_REPAIRS`].
+pub fn schema_repairs_from_env(prefix: &str) -> usize {
+    std::env::var(format!("SKRILLS_{prefix}_SCHEMA_REPAIRS"))
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SCHEMA_REPAIRS)
+}
+
+/// Header a request's API key is sent in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthHeader {
+    /// `Authorization: Bearer <key>`.
+    Bearer,
+    /// The bare key in the named header, like Anthropic's `x-api-key`.
+    Named(String),
+}
+
+impl AuthHeader {
+    /// `Authorization` means a bearer token; any other name carries the key
+    /// as is.
+    pub fn from_name(name: &str) -> Self {
+        if name.eq_ignore_ascii_case("authorization") {
+            AuthHeader::Bearer
+        } else {
+            AuthHeader::Named(name.to_string())
+        }
+    }
+
+    /// Adds `key` to `request`; an empty key sends no credentials.
+    pub(crate) fn apply(&self, request: RequestBuilder, key: &str) -> RequestBuilder {
+        if key.is_empty() {
+            return request;
+        }
+        match self {
+            AuthHeader::Bearer => request.bearer_auth(key),
+            AuthHeader::Named(name) => request.header(name.as_str(), key),
+        }
+    }
+}
+
+/// The backend an HTTP adapter serves and how it authenticates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub kind: BackendKind,
+    pub auth: AuthHeader,
+    /// Variable the API key comes from; runs fail while the key is empty.
+    /// `None` for servers that need no key.
+    pub key_var: Option<String>,
+}
+
+impl Endpoint {
+    /// Endpoint of a `[backends.<name>]` server, authenticated with
+    /// `default_header` unless the table names another.
+    pub fn declared(name: &str, config: &BackendConfig, default_header: &str) -> Self {
+        Self {
+            kind: BackendKind::Other(name.to_string()),
+            auth: AuthHeader::from_name(config.auth_header.as_deref().unwrap_or(default_header)),
+            key_var: config.api_key_env.clone(),
+        }
+    }
+
+    /// Error for a run that cannot start because the key is missing.
+    pub(crate) fn missing_key(&self, api_key: &str) -> Option<String> {
+        match &self.key_var {
+            Some(var) if api_key.is_empty() => Some(format!("missing {var}")),
+            _ => None,
+        }
+    }
+}
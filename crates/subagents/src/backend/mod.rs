@@ -1,13 +1,34 @@
 pub mod claude;
 pub mod codex;
 pub mod config;
+pub mod ollama;
+pub mod process;
+pub mod replay;
+pub mod retry;
+pub mod sse;
 
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde_json::json;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
-use crate::store::{BackendKind, RunId, RunRequest, RunStatus, RunStore, SubagentTemplate};
+use crate::schema::{repair_prompt, validate_output, SchemaMismatch};
+use crate::store::{
+    BackendKind, ChatMessage, RunEvent, RunId, RunRequest, RunState, RunStatus, RunStore,
+    SubagentError, SubagentTemplate,
+};
+use crate::templates::{BackendConfig, BackendProtocol};
+use crate::tools::{ToolLoop, ToolOutput, ToolProvider, ToolSpec};
+use claude::ClaudeAdapter;
+use codex::CodexAdapter;
+use config::{AdapterConfig, Endpoint};
+use ollama::OllamaAdapter;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AdapterCapabilities {
@@ -17,12 +38,35 @@ pub struct AdapterCapabilities {
     pub supports_secure_transcript: bool,
 }
 
+/// An in-flight run spawned by an adapter.
+#[derive(Debug)]
+pub struct RunHandle {
+    pub run_id: RunId,
+    /// Cancelling this token aborts the run, including any in-flight request.
+    pub cancel: CancellationToken,
+    /// Resolves to `true` when the run ended because it was canceled.
+    pub task: JoinHandle<bool>,
+}
+
 #[async_trait]
 pub trait BackendAdapter: Send + Sync {
     fn backend(&self) -> BackendKind;
     fn capabilities(&self) -> AdapterCapabilities;
+    /// A copy of this adapter that offers `tools` to the model, or `None`
+    /// when the backend cannot call tools.
+    fn with_tool_loop(&self, _tools: ToolLoop) -> Option<Arc<dyn BackendAdapter>> {
+        None
+    }
     async fn list_templates(&self) -> Result<Vec<SubagentTemplate>>;
-    async fn run(&self, request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunId>;
+    /// Creates a run and spawns its execution, returning a handle to it.
+    async fn start(&self, request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle>;
+    /// Starts a run without keeping its handle.
+    async fn run(&self, request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunId> {
+        Ok(self.start(request, store).await?.run_id)
+    }
+    /// Re-dispatches an existing run with its full conversation, after
+    /// [`RunStore::begin_turn`] appended a follow-up.
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle>;
     async fn get_status(
         &self,
         run_id: RunId,
@@ -32,6 +76,419 @@ pub trait BackendAdapter: Send + Sync {
     async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>>;
 }
 
+/// The execution behind an adapter's runs. [`start_execution`] and
+/// [`resume_execution`] implement [`BackendAdapter::start`] and
+/// [`BackendAdapter::resume`] on top of it.
+#[async_trait]
+pub(crate) trait Execute: Clone + Send + Sync + 'static {
+    /// Repair turns a run gets when its output misses its `output_schema`.
+    fn schema_repairs(&self) -> usize;
+    /// Runs one attempt at answering the run's conversation.
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()>;
+}
+
+/// Creates a run for `request`, marks it dispatched and spawns `adapter`'s
+/// execution of it.
+pub(crate) async fn start_execution<A: Execute>(
+    adapter: &A,
+    request: RunRequest,
+    store: Arc<dyn RunStore>,
+) -> Result<RunHandle> {
+    let run_id = store.create_run(request.clone()).await?;
+    store
+        .update_status(
+            run_id,
+            RunStatus {
+                state: RunState::Running,
+                message: Some("dispatched".into()),
+                updated_at: OffsetDateTime::now_utc(),
+            },
+        )
+        .await?;
+    Ok(spawn_execution(adapter, run_id, request, store))
+}
+
+/// Spawns `adapter`'s execution of an existing run.
+pub(crate) async fn resume_execution<A: Execute>(
+    adapter: &A,
+    run_id: RunId,
+    store: Arc<dyn RunStore>,
+) -> Result<RunHandle> {
+    let record = store
+        .get_run(run_id)
+        .await?
+        .ok_or(SubagentError::NotFound(run_id))?;
+    Ok(spawn_execution(adapter, run_id, record.request, store))
+}
+
+/// Spawns `adapter`'s execution, re-running it while its output needs repair.
+fn spawn_execution<A: Execute>(
+    adapter: &A,
+    run_id: RunId,
+    request: RunRequest,
+    store: Arc<dyn RunStore>,
+) -> RunHandle {
+    let adapter = adapter.clone();
+    let repairs = adapter.schema_repairs();
+    let exec_store = store.clone();
+    let execution = with_schema_repairs(run_id, store.clone(), repairs, move || {
+        let (adapter, request, store) = (adapter.clone(), request.clone(), exec_store.clone());
+        async move { adapter.execute(run_id, request, store).await }
+    });
+    spawn_run(run_id, store, execution)
+}
+
+/// Timeout for declared backends without `timeout_secs`; local models can be
+/// slow to load.
+const DECLARED_TIMEOUT_MS: u64 = 300_000;
+
+/// Builds the adapter for a `[backends.<name>]` server. `tools` are offered
+/// to servers whose protocol supports tool calls.
+pub fn declared_adapter(
+    name: &str,
+    config: &BackendConfig,
+    tools: Option<ToolLoop>,
+) -> Result<Arc<dyn BackendAdapter>> {
+    let adapter_config = AdapterConfig::from_backend_config(config, DECLARED_TIMEOUT_MS)?;
+    Ok(match config.protocol {
+        BackendProtocol::OpenaiChat => {
+            let adapter = CodexAdapter::with_config(adapter_config)
+                .with_endpoint(Endpoint::declared(name, config, "Authorization"));
+            match tools {
+                Some(tools) => Arc::new(adapter.with_tools(tools)),
+                None => Arc::new(adapter),
+            }
+        }
+        BackendProtocol::AnthropicMessages => {
+            let adapter = ClaudeAdapter::with_config(adapter_config)
+                .with_endpoint(Endpoint::declared(name, config, "x-api-key"));
+            match tools {
+                Some(tools) => Arc::new(adapter.with_tools(tools)),
+                None => Arc::new(adapter),
+            }
+        }
+        BackendProtocol::Ollama => Arc::new(OllamaAdapter::new(
+            adapter_config,
+            Endpoint::declared(name, config, "Authorization"),
+        )),
+    })
+}
+
+/// Data for a run's `start` event: the executing process, which owns the run
+/// while it lives (see [`crate::queue::reconcile`]), and the applied
+/// template and autoloaded skills, if any.
+pub(crate) fn start_event_data(request: &RunRequest) -> serde_json::Value {
+    let mut data = json!({ "pid": std::process::id() });
+    if let Some(t) = &request.template {
+        data["template"] = json!(t.id);
+        data["model"] = json!(t.model);
+        data["tools"] = json!(t.tools);
+    }
+    if let Some(skills) = &request.skills {
+        data["skills"] = json!(skills.skills);
+        data["skills_truncated"] = json!(skills.truncated);
+    }
+    data
+}
+
+/// Records the tools offered to a run in its start event `data`. Runs with
+/// tools are not streamed, since tool calls are read from whole responses,
+/// so `data` also gets `"stream": false`.
+pub(crate) fn record_offered_tools(data: &mut serde_json::Value, tools: &[ToolSpec]) {
+    if tools.is_empty() {
+        return;
+    }
+    data["offered_tools"] = json!(tools.iter().map(|t| &t.name).collect::<Vec<_>>());
+    data["stream"] = json!(false);
+}
+
+/// Loads the conversation to send for `run_id`.
+pub(crate) async fn load_history(
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+) -> Result<Vec<ChatMessage>> {
+    Ok(store
+        .get_run(run_id)
+        .await?
+        .map(|record| record.conversation())
+        .unwrap_or_default())
+}
+
+/// Spawns `execution` for `run_id`, racing it against a fresh cancellation
+/// token. Cancelling drops the execution future, which aborts any in-flight
+/// HTTP request, and marks the run canceled; an execution error marks it
+/// failed.
+///
+/// The run is also dropped once the store shows it canceled, which is how
+/// `skrills subagents stop` reaches a run executing in another process.
+pub(crate) fn spawn_run<F>(run_id: RunId, store: Arc<dyn RunStore>, execution: F) -> RunHandle
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    let task = tokio::spawn(async move {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => cancel_run(run_id, &store).await.unwrap_or(false),
+            _ = canceled_in_store(run_id, &store) => true,
+            result = execution => {
+                // A run that already finished, e.g. canceled meanwhile,
+                // keeps the outcome it has.
+                let result = result.or_else(|err| match err.downcast_ref() {
+                    Some(SubagentError::Completed(id)) if *id == run_id => Ok(()),
+                    _ => Err(err),
+                });
+                if let Err(err) = result {
+                    let _ = store
+                        .append_event(
+                            run_id,
+                            RunEvent {
+                                ts: OffsetDateTime::now_utc(),
+                                kind: "error".into(),
+                                data: Some(json!({"message": err.to_string()})),
+                            },
+                        )
+                        .await;
+                    let _ = store
+                        .update_status(
+                            run_id,
+                            RunStatus {
+                                state: RunState::Failed,
+                                message: Some(err.to_string()),
+                                updated_at: OffsetDateTime::now_utc(),
+                            },
+                        )
+                        .await;
+                }
+                false
+            }
+        }
+    });
+    RunHandle {
+        run_id,
+        cancel,
+        task,
+    }
+}
+
+/// How often an executing run checks whether it was stopped through the
+/// store by another process.
+const STOP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Resolves once the store records `run_id` as canceled, checking every
+/// [`STOP_POLL_INTERVAL`].
+async fn canceled_in_store(run_id: RunId, store: &Arc<dyn RunStore>) {
+    loop {
+        tokio::time::sleep(STOP_POLL_INTERVAL).await;
+        if let Ok(Some(status)) = store.get_status(run_id).await {
+            if status.state == RunState::Canceled {
+                return;
+            }
+        }
+    }
+}
+
+/// Marks a run canceled and records a `canceled` event.
+///
+/// Returns `false` without recording anything when the run already reached a
+/// terminal state.
+pub async fn cancel_run(run_id: RunId, store: &Arc<dyn RunStore>) -> Result<bool> {
+    if !store.stop(run_id).await? {
+        return Ok(false);
+    }
+    store
+        .append_event(
+            run_id,
+            RunEvent {
+                ts: OffsetDateTime::now_utc(),
+                kind: "canceled".into(),
+                data: Some(json!({"message": "stopped by user"})),
+            },
+        )
+        .await?;
+    Ok(true)
+}
+
+/// Executes one tool call requested by the model, recording it as a
+/// `tool_call` event and its outcome as a `tool_result` event.
+///
+/// A failing call is reported back to the model as an error result rather
+/// than failing the run, so the model can recover.
+pub(crate) async fn execute_tool_call(
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+    provider: &dyn ToolProvider,
+    id: &str,
+    name: &str,
+    arguments: serde_json::Value,
+) -> Result<ToolOutput> {
+    store
+        .append_event(
+            run_id,
+            RunEvent {
+                ts: OffsetDateTime::now_utc(),
+                kind: "tool_call".into(),
+                data: Some(json!({"id": id, "name": name, "arguments": arguments})),
+            },
+        )
+        .await?;
+    let output = provider
+        .call_tool(name, arguments)
+        .await
+        .unwrap_or_else(|err| ToolOutput {
+            content: err.to_string(),
+            is_error: true,
+        });
+    store
+        .append_event(
+            run_id,
+            RunEvent {
+                ts: OffsetDateTime::now_utc(),
+                kind: "tool_result".into(),
+                data: Some(json!({
+                    "id": id,
+                    "name": name,
+                    "content": output.content,
+                    "is_error": output.is_error,
+                })),
+            },
+        )
+        .await?;
+    Ok(output)
+}
+
+/// Records the final `completion` event, appends it to the conversation as
+/// the assistant turn and marks the run as succeeded.
+///
+/// When the run has an `output_schema`, the completion must validate
+/// against it first; the parsed value is stored as the run's `result`, and a
+/// mismatch is returned as a [`SchemaMismatch`] error for
+/// [`with_schema_repairs`] to handle.
+///
+/// A run that already reached a terminal state, e.g. one canceled while the
+/// provider was answering, is left untouched and reported as
+/// [`SubagentError::Completed`].
+pub(crate) async fn finish_run(
+    run_id: RunId,
+    completion: String,
+    store: &Arc<dyn RunStore>,
+) -> Result<()> {
+    let record = store
+        .get_run(run_id)
+        .await?
+        .ok_or(SubagentError::NotFound(run_id))?;
+    if record.status.state.is_terminal() {
+        return Err(SubagentError::Completed(run_id).into());
+    }
+    let result = match &record.request.output_schema {
+        Some(schema) => match validate_output(schema, &completion)? {
+            Ok(value) => Some(value),
+            Err(errors) => {
+                return Err(SchemaMismatch {
+                    output: completion,
+                    errors,
+                }
+                .into())
+            }
+        },
+        None => None,
+    };
+    let now = OffsetDateTime::now_utc();
+    let completed = store
+        .complete(
+            run_id,
+            RunEvent {
+                ts: now,
+                kind: "completion".into(),
+                data: Some(json!({ "text": completion })),
+            },
+            ChatMessage::assistant(completion),
+            result,
+            RunStatus {
+                state: RunState::Succeeded,
+                message: Some("completed".into()),
+                updated_at: now,
+            },
+        )
+        .await?;
+    if !completed {
+        return Err(SubagentError::Completed(run_id).into());
+    }
+    Ok(())
+}
+
+/// Runs `attempt` until its output satisfies the run's `output_schema`.
+///
+/// Each mismatch is recorded as a `schema_error` event, and the rejected
+/// output plus a repair prompt listing the errors join the conversation
+/// before the next attempt. After `max_repairs` failed repairs the run ends
+/// in [`RunState::SchemaFailed`].
+pub(crate) async fn with_schema_repairs<F, Fut>(
+    run_id: RunId,
+    store: Arc<dyn RunStore>,
+    max_repairs: usize,
+    mut attempt: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut repairs = 0;
+    loop {
+        let err = match attempt().await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let Some(mismatch) = err.downcast_ref::<SchemaMismatch>() else {
+            return Err(err);
+        };
+        let exhausted = repairs == max_repairs;
+        store
+            .append_event(
+                run_id,
+                RunEvent {
+                    ts: OffsetDateTime::now_utc(),
+                    kind: "schema_error".into(),
+                    data: Some(json!({
+                        "errors": mismatch.errors,
+                        "output": mismatch.output,
+                        "repair": !exhausted,
+                    })),
+                },
+            )
+            .await?;
+        store
+            .append_message(run_id, ChatMessage::assistant(mismatch.output.clone()))
+            .await?;
+        if exhausted {
+            store
+                .update_status(
+                    run_id,
+                    RunStatus {
+                        state: RunState::SchemaFailed,
+                        message: Some(format!(
+                            "output failed output_schema after {repairs} repairs: {}",
+                            mismatch.errors.join("; ")
+                        )),
+                        updated_at: OffsetDateTime::now_utc(),
+                    },
+                )
+                .await?;
+            return Ok(());
+        }
+        repairs += 1;
+        store
+            .append_message(run_id, ChatMessage::user(repair_prompt(&mismatch.errors)))
+            .await?;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,12 +509,10 @@ mod tests {
         let run_id = adapter
             .run(
                 RunRequest {
-                    backend: BackendKind::Codex,
                     prompt: "hello".into(),
-                    template_id: None,
                     output_schema: Some(json!({"type": "object"})),
-                    async_mode: false,
                     tracing: true,
+                    ..Default::default()
                 },
                 store.clone(),
             )
@@ -87,9 +542,7 @@ mod tests {
                     backend: BackendKind::Claude,
                     prompt: "hi".into(),
                     template_id: Some("default".into()),
-                    output_schema: None,
-                    async_mode: false,
-                    tracing: false,
+                    ..Default::default()
                 },
                 store.clone(),
             )
@@ -110,4 +563,55 @@ mod tests {
             .unwrap();
         assert_eq!(status.state, RunState::Canceled);
     }
+
+    #[tokio::test]
+    async fn a_run_stopped_through_the_store_is_dropped() {
+        let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+        let run_id = store
+            .create_run(RunRequest {
+                prompt: "hi".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let handle = spawn_run(run_id, store.clone(), std::future::pending());
+
+        // What `skrills subagents stop` does from another process.
+        assert!(store.stop(run_id).await.unwrap());
+        let canceled = tokio::time::timeout(STOP_POLL_INTERVAL * 5, handle.task)
+            .await
+            .expect("run kept executing after it was stopped")
+            .unwrap();
+        assert!(canceled);
+    }
+
+    #[tokio::test]
+    async fn finishing_a_canceled_run_records_nothing() {
+        let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+        let run_id = store
+            .create_run(RunRequest {
+                prompt: "hi".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(cancel_run(run_id, &store).await.unwrap());
+        let err = finish_run(run_id, "late answer".into(), &store)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SubagentError::Completed(id)) if *id == run_id
+        ));
+        let record = store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Canceled);
+        assert!(record.events.iter().all(|e| e.kind != "completion"));
+        assert_eq!(record.conversation().len(), 1);
+        let running = RunStatus {
+            state: RunState::Running,
+            message: None,
+            updated_at: OffsetDateTime::now_utc(),
+        };
+        assert!(!store.update_status(run_id, running).await.unwrap());
+    }
 }
@@ -0,0 +1,360 @@
+//! Backend for servers speaking Ollama's native `api/chat` protocol.
+//!
+//! Declared in `subagents.toml` as a `[backends.<name>]` table with
+//! `protocol = "ollama"`. Streamed runs read the newline-delimited JSON
+//! chunks Ollama sends, and `output_schema` is passed as the request's
+//! `format`. Tool calls are not offered to these models.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+
+use crate::backend::config::{AdapterConfig, Endpoint};
+use crate::backend::retry::send_with_retry;
+use crate::backend::sse::{read_body, stream_ndjson_completion, SseStep};
+use crate::backend::{
+    cancel_run, finish_run, load_history, resume_execution, start_event_data, start_execution,
+    AdapterCapabilities, BackendAdapter, Execute, RunHandle,
+};
+use crate::store::{
+    BackendKind, ChatMessage, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus,
+    RunStore, RunUsage, SubagentTemplate,
+};
+
+#[derive(Debug, Clone)]
+pub struct OllamaAdapter {
+    config: AdapterConfig,
+    endpoint: Endpoint,
+    client: reqwest::Client,
+}
+
+impl OllamaAdapter {
+    pub fn new(config: AdapterConfig, endpoint: Endpoint) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.timeout)
+            .build()
+            .expect("failed to build reqwest client");
+        Self {
+            config,
+            endpoint,
+            client,
+        }
+    }
+
+    async fn execute_run(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        store
+            .append_event(
+                run_id,
+                RunEvent {
+                    ts: OffsetDateTime::now_utc(),
+                    kind: "start".into(),
+                    data: Some(start_event_data(&request)),
+                },
+            )
+            .await?;
+
+        if let Some(msg) = self.endpoint.missing_key(&self.config.api_key) {
+            store
+                .update_status(
+                    run_id,
+                    RunStatus {
+                        state: RunState::Failed,
+                        message: Some(msg.clone()),
+                        updated_at: OffsetDateTime::now_utc(),
+                    },
+                )
+                .await?;
+            return Err(anyhow!(msg));
+        }
+
+        let history = load_history(run_id, &store).await?;
+        let body = build_ollama_body(&self.config.model, &request, &history);
+        let url = self
+            .config
+            .base_url
+            .join("api/chat")
+            .unwrap_or_else(|_| self.config.base_url.clone());
+        let resp = send_with_retry(run_id, &store, &self.config, "Ollama API", || {
+            self.endpoint
+                .auth
+                .apply(self.client.post(url.clone()), &self.config.api_key)
+                .json(&body)
+        })
+        .await?;
+
+        let status = resp.status();
+        if status.is_success() && body.stream {
+            let completion = stream_ndjson_completion(
+                resp,
+                run_id,
+                &store,
+                self.config.timeout,
+                decode_ollama_chunk,
+            )
+            .await?;
+            return finish_run(run_id, completion, &store).await;
+        }
+        let text = read_body(resp, self.config.timeout).await?;
+        let parsed: Value = serde_json::from_str(&text).unwrap_or_else(|_| json!({ "raw": text }));
+        if let Some(usage) = extract_ollama_usage(&parsed) {
+            store.add_usage(run_id, usage).await?;
+        }
+        if !status.is_success() {
+            let msg = parsed
+                .get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("ollama call failed")
+                .to_string();
+            store
+                .append_event(
+                    run_id,
+                    RunEvent {
+                        ts: OffsetDateTime::now_utc(),
+                        kind: "error".into(),
+                        data: Some(parsed.clone()),
+                    },
+                )
+                .await?;
+            store
+                .update_status(
+                    run_id,
+                    RunStatus {
+                        state: RunState::Failed,
+                        message: Some(msg.clone()),
+                        updated_at: OffsetDateTime::now_utc(),
+                    },
+                )
+                .await?;
+            return Err(anyhow!(msg));
+        }
+        let completion = parsed
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_str())
+            .map(String::from)
+            .unwrap_or(text);
+        finish_run(run_id, completion, &store).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaBody {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    /// JSON schema the reply must follow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<Value>,
+}
+
+/// Builds an `api/chat` body; `history` is the run's conversation and falls
+/// back to the bare prompt when empty.
+fn build_ollama_body(model: &str, request: &RunRequest, history: &[ChatMessage]) -> OllamaBody {
+    let template = request.template.as_ref();
+    let mut messages = Vec::new();
+    if let Some(system) = request.system_prompt() {
+        messages.push(OllamaMessage {
+            role: "system".into(),
+            content: system,
+        });
+    }
+    if history.is_empty() {
+        messages.push(OllamaMessage {
+            role: "user".into(),
+            content: request.prompt.clone(),
+        });
+    }
+    messages.extend(history.iter().map(|m| OllamaMessage {
+        role: m.role.as_str().into(),
+        content: m.content.clone(),
+    }));
+    OllamaBody {
+        model: template
+            .and_then(|t| t.model.clone())
+            .unwrap_or_else(|| model.to_string()),
+        messages,
+        stream: request.async_mode,
+        format: request.output_schema.clone(),
+        options: template
+            .and_then(|t| t.max_tokens)
+            .map(|max| json!({ "num_predict": max })),
+    }
+}
+
+/// Reads the token counts of a response or final stream chunk.
+fn extract_ollama_usage(val: &Value) -> Option<RunUsage> {
+    let input = val.get("prompt_eval_count").and_then(|v| v.as_u64());
+    let output = val.get("eval_count").and_then(|v| v.as_u64());
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+    Some(RunUsage {
+        model: val.get("model").and_then(|m| m.as_str()).map(String::from),
+        input_tokens: input.unwrap_or(0),
+        output_tokens: output.unwrap_or(0),
+        ..RunUsage::default()
+    })
+}
+
+/// Decodes one stream chunk into its text delta, or the end of the stream
+/// with the usage carried by the final `done` chunk.
+fn decode_ollama_chunk(chunk: &Value) -> Result<SseStep> {
+    if let Some(err) = chunk.get("error") {
+        let msg = err.as_str().unwrap_or("ollama stream failed");
+        return Err(anyhow!(msg.to_string()));
+    }
+    if chunk.get("done").and_then(|d| d.as_bool()) == Some(true) {
+        return Ok(SseStep::Done(extract_ollama_usage(chunk)));
+    }
+    Ok(chunk
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .map(|s| SseStep::Delta(s.to_string()))
+        .unwrap_or(SseStep::Skip))
+}
+
+#[async_trait]
+impl Execute for OllamaAdapter {
+    fn schema_repairs(&self) -> usize {
+        self.config.schema_repairs
+    }
+
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        self.execute_run(run_id, request, store).await
+    }
+}
+
+#[async_trait]
+impl BackendAdapter for OllamaAdapter {
+    fn backend(&self) -> BackendKind {
+        self.endpoint.kind.clone()
+    }
+
+    fn capabilities(&self) -> AdapterCapabilities {
+        AdapterCapabilities {
+            supports_schema: true,
+            supports_async: true,
+            supports_tracing: false,
+            supports_secure_transcript: false,
+        }
+    }
+
+    async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+        let name = self.endpoint.kind.name();
+        Ok(vec![SubagentTemplate {
+            id: "default".into(),
+            name: format!("Default {name} Agent"),
+            description: Some(format!("{name} model {}", self.config.model)),
+            backend: self.endpoint.kind.clone(),
+            capabilities: vec!["structured_outputs".into()],
+            model: None,
+            system_prompt: None,
+            max_tokens: None,
+            tools: Vec::new(),
+            output_schema: None,
+            source: None,
+        }])
+    }
+
+    async fn start(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        request.backend = self.endpoint.kind.clone();
+        start_execution(self, request, store).await
+    }
+
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        resume_execution(self, run_id, store).await
+    }
+
+    async fn get_status(
+        &self,
+        run_id: RunId,
+        store: Arc<dyn RunStore>,
+    ) -> Result<Option<RunStatus>> {
+        store.get_status(run_id).await
+    }
+
+    async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
+        cancel_run(run_id, &store).await
+    }
+
+    async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
+        let runs: Vec<RunRecord> = store.history(limit).await?;
+        Ok(runs.into_iter().map(|r| r.status).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::ChatRole;
+
+    fn request(prompt: &str) -> RunRequest {
+        RunRequest {
+            backend: BackendKind::Other("local".into()),
+            prompt: prompt.into(),
+            output_schema: Some(json!({"type": "object"})),
+            async_mode: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn body_carries_history_schema_and_stream_flag() {
+        let history = vec![
+            ChatMessage::user("first"),
+            ChatMessage::assistant("reply"),
+            ChatMessage::user("again"),
+        ];
+        let body = build_ollama_body("llama3.2", &request("first"), &history);
+        let value = serde_json::to_value(&body).unwrap();
+        assert_eq!(value["model"], "llama3.2");
+        assert_eq!(value["stream"], true);
+        assert_eq!(value["format"], json!({"type": "object"}));
+        assert_eq!(value["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(value["messages"][1]["role"], ChatRole::Assistant.as_str());
+        assert!(value.get("options").is_none());
+    }
+
+    #[test]
+    fn chunks_decode_to_deltas_and_final_usage() {
+        let delta = json!({"model": "llama3.2", "message": {"role": "assistant", "content": "hi"}, "done": false});
+        assert_eq!(
+            decode_ollama_chunk(&delta).unwrap(),
+            SseStep::Delta("hi".into())
+        );
+        let last =
+            json!({"model": "llama3.2", "done": true, "prompt_eval_count": 12, "eval_count": 3});
+        let SseStep::Done(Some(usage)) = decode_ollama_chunk(&last).unwrap() else {
+            panic!("expected usage");
+        };
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+        assert_eq!(
+            decode_ollama_chunk(&json!({"done": true})).unwrap(),
+            SseStep::Done(None)
+        );
+        assert!(decode_ollama_chunk(&json!({"error": "model not found"})).is_err());
+    }
+}
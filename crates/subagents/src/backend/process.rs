@@ -0,0 +1,628 @@
+//! Backend that runs subagents through locally installed agent CLIs.
+//!
+//! `codex exec --json` and `claude -p --output-format stream-json` both print
+//! one JSON event per line. Each line is decoded as it arrives: assistant text
+//! becomes `stream` events, tool activity becomes `tool` events, and the
+//! CLI's final result becomes the run's `completion`. The CLIs use their own
+//! login, so no API key is needed.
+//!
+//! The child runs in its own process group; dropping a run (for example on
+//! cancellation) kills the whole group, including any commands it spawned.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::backend::{
+    cancel_run, config::schema_repairs_from_env, finish_run, load_history, resume_execution,
+    start_event_data, start_execution, AdapterCapabilities, BackendAdapter, Execute, RunHandle,
+};
+use crate::schema::DEFAULT_SCHEMA_REPAIRS;
+use crate::store::{
+    BackendKind, ChatMessage, ChatRole, RunEvent, RunId, RunRecord, RunRequest, RunStatus,
+    RunStore, SubagentTemplate,
+};
+
+/// Only the tail of stderr is kept for error messages.
+const STDERR_TAIL_BYTES: usize = 2048;
+
+/// Which CLI a [`ProcessAdapter`] drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliKind {
+    Codex,
+    Claude,
+}
+
+impl CliKind {
+    fn backend(self) -> BackendKind {
+        match self {
+            CliKind::Codex => BackendKind::CodexCli,
+            CliKind::Claude => BackendKind::ClaudeCli,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CliKind::Codex => "codex",
+            CliKind::Claude => "claude",
+        }
+    }
+}
+
+/// What one JSONL line from a CLI contributes to the run.
+#[derive(Debug, Clone, PartialEq)]
+enum CliEvent {
+    Text(String),
+    Tool(Value),
+    /// The CLI finished; carries the final answer when the CLI reports one.
+    Done(Option<String>),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessAdapter {
+    kind: CliKind,
+    program: PathBuf,
+    schema_repairs: usize,
+}
+
+impl ProcessAdapter {
+    /// Runs `codex exec`, using `SKRILLS_CODEX_CLI` as the executable when set.
+    pub fn codex() -> Self {
+        let program = std::env::var_os("SKRILLS_CODEX_CLI").unwrap_or_else(|| "codex".into());
+        Self::new(CliKind::Codex, program.into())
+            .with_schema_repairs(schema_repairs_from_env("CODEX_CLI"))
+    }
+
+    /// Runs `claude -p`, using `SKRILLS_CLAUDE_CLI` as the executable when set.
+    pub fn claude() -> Self {
+        let program = std::env::var_os("SKRILLS_CLAUDE_CLI").unwrap_or_else(|| "claude".into());
+        Self::new(CliKind::Claude, program.into())
+            .with_schema_repairs(schema_repairs_from_env("CLAUDE_CLI"))
+    }
+
+    pub fn new(kind: CliKind, program: PathBuf) -> Self {
+        Self {
+            kind,
+            program,
+            schema_repairs: DEFAULT_SCHEMA_REPAIRS,
+        }
+    }
+
+    /// Sets the repair turns a run gets when its output misses its
+    /// `output_schema`.
+    pub fn with_schema_repairs(mut self, schema_repairs: usize) -> Self {
+        self.schema_repairs = schema_repairs;
+        self
+    }
+
+    fn args(&self, request: &RunRequest, history: &[ChatMessage]) -> Vec<String> {
+        let template = request.template.as_ref();
+        let prompt = render_conversation(&request.prompt, history);
+        let model = template.and_then(|t| t.model.clone());
+        let system = request.system_prompt();
+        let mut args = Vec::new();
+        match self.kind {
+            CliKind::Codex => {
+                args.extend(["exec".into(), "--json".into()]);
+                if let Some(model) = model {
+                    args.extend(["--model".into(), model]);
+                }
+                // `codex exec` has no system prompt flag, so template
+                // instructions lead the prompt instead.
+                let prompt = match system {
+                    Some(system) => format!("{system}\n\n{prompt}"),
+                    None => prompt,
+                };
+                args.push(prompt);
+            }
+            CliKind::Claude => {
+                args.extend([
+                    "-p".into(),
+                    "--output-format".into(),
+                    "stream-json".into(),
+                    "--verbose".into(),
+                ]);
+                if let Some(model) = model {
+                    args.extend(["--model".into(), model]);
+                }
+                if let Some(system) = system {
+                    args.extend(["--append-system-prompt".into(), system]);
+                }
+                if let Some(tools) = template.map(|t| &t.tools).filter(|t| !t.is_empty()) {
+                    args.extend(["--allowedTools".into(), tools.join(",")]);
+                }
+                args.push(prompt);
+            }
+        }
+        args
+    }
+
+    async fn execute_run(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        store
+            .append_event(
+                run_id,
+                RunEvent {
+                    ts: OffsetDateTime::now_utc(),
+                    kind: "start".into(),
+                    data: Some(start_event_data(&request)),
+                },
+            )
+            .await?;
+
+        let history = load_history(run_id, &store).await?;
+        let mut cmd = Command::new(&self.program);
+        cmd.args(self.args(&request, &history))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("spawning {}", self.program.display()))?;
+        let mut group = ProcessGroup(child.id());
+
+        let stdout = child.stdout.take().context("child stdout not captured")?;
+        let mut stderr = child.stderr.take().context("child stderr not captured")?;
+        let stderr_task = tokio::spawn(async move {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf).await;
+            let start = buf.len().saturating_sub(STDERR_TAIL_BYTES);
+            String::from_utf8_lossy(&buf[start..]).trim().to_string()
+        });
+
+        let mut texts: Vec<String> = Vec::new();
+        let mut outcome: Option<Result<Option<String>, String>> = None;
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            // CLIs may print banners or warnings that are not JSON.
+            let Ok(value) = serde_json::from_str::<Value>(line.trim()) else {
+                continue;
+            };
+            let events = match self.kind {
+                CliKind::Codex => decode_codex_line(&value),
+                CliKind::Claude => decode_claude_line(&value),
+            };
+            for event in events {
+                match event {
+                    CliEvent::Text(text) => {
+                        store
+                            .append_event(
+                                run_id,
+                                RunEvent {
+                                    ts: OffsetDateTime::now_utc(),
+                                    kind: "stream".into(),
+                                    data: Some(json!({ "token": text })),
+                                },
+                            )
+                            .await?;
+                        texts.push(text);
+                    }
+                    CliEvent::Tool(data) => {
+                        store
+                            .append_event(
+                                run_id,
+                                RunEvent {
+                                    ts: OffsetDateTime::now_utc(),
+                                    kind: "tool".into(),
+                                    data: Some(data),
+                                },
+                            )
+                            .await?;
+                    }
+                    CliEvent::Done(result) => outcome = Some(Ok(result)),
+                    CliEvent::Failed(msg) => outcome = Some(Err(msg)),
+                }
+            }
+        }
+
+        // The server reaps children itself (SA_NOCLDWAIT), in which case the
+        // exit status is unavailable and the event stream decides the outcome.
+        let status = match child.wait().await {
+            Ok(status) => Some(status),
+            Err(err) if is_echild(&err) => None,
+            Err(err) => return Err(err.into()),
+        };
+        group.disarm();
+        let stderr = stderr_task.await.unwrap_or_default();
+
+        match outcome {
+            Some(Ok(result)) => {
+                let completion = result.unwrap_or_else(|| texts.join("\n\n"));
+                finish_run(run_id, completion, &store).await
+            }
+            Some(Err(msg)) => Err(anyhow!(msg)),
+            None if status.is_some_and(|s| s.success()) && !texts.is_empty() => {
+                finish_run(run_id, texts.join("\n\n"), &store).await
+            }
+            None => {
+                let status = status
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "unknown status".into());
+                let mut msg = format!("{} exited ({status}) without a result", self.kind.label());
+                if !stderr.is_empty() {
+                    msg.push_str(": ");
+                    msg.push_str(&stderr);
+                }
+                Err(anyhow!(msg))
+            }
+        }
+    }
+}
+
+/// Renders a conversation as a single prompt, since each CLI invocation is
+/// a fresh session. A single-turn conversation is passed through unchanged.
+fn render_conversation(prompt: &str, history: &[ChatMessage]) -> String {
+    let Some((latest, earlier)) = history.split_last() else {
+        return prompt.to_string();
+    };
+    if earlier.is_empty() {
+        return latest.content.clone();
+    }
+    let mut out = String::from("Conversation so far:\n");
+    for message in earlier {
+        let speaker = match message.role {
+            ChatRole::User => "User",
+            ChatRole::Assistant => "Assistant",
+        };
+        out.push_str(&format!("\n{speaker}: {}\n", message.content));
+    }
+    out.push_str(&format!(
+        "\nReply to the latest message:\n\n{}",
+        latest.content
+    ));
+    out
+}
+
+/// Kills the child's process group when dropped, unless disarmed after the
+/// child exited on its own.
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pgid) = self.0 {
+            // SAFETY: killpg only sends a signal; the group was created for
+            // this child by `process_group(0)`.
+            unsafe {
+                libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+fn is_echild(err: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        err.raw_os_error() == Some(libc::ECHILD)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = err;
+        false
+    }
+}
+
+fn message_of(value: &Value) -> Option<String> {
+    value
+        .get("message")
+        .or_else(|| value.get("error").and_then(|e| e.get("message")))
+        .or_else(|| value.get("error"))
+        .and_then(|m| m.as_str())
+        .map(String::from)
+}
+
+/// Decodes a `codex exec --json` line. Both the `item.*`/`turn.*` event
+/// format and the older `{"msg": {...}}` envelope are understood.
+fn decode_codex_line(value: &Value) -> Vec<CliEvent> {
+    if let Some(msg) = value.get("msg") {
+        return match msg.get("type").and_then(|t| t.as_str()) {
+            Some("agent_message") => msg
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(|m| vec![CliEvent::Text(m.to_string())])
+                .unwrap_or_default(),
+            Some("task_complete") => vec![CliEvent::Done(
+                msg.get("last_agent_message")
+                    .and_then(|m| m.as_str())
+                    .map(String::from),
+            )],
+            Some("error") => vec![CliEvent::Failed(
+                message_of(msg).unwrap_or_else(|| "codex reported an error".into()),
+            )],
+            Some(kind) if kind.starts_with("exec_command") || kind.starts_with("mcp_tool") => {
+                vec![CliEvent::Tool(msg.clone())]
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("item.completed") => {
+            let Some(item) = value.get("item") else {
+                return Vec::new();
+            };
+            match item.get("type").and_then(|t| t.as_str()) {
+                Some("agent_message" | "assistant_message") => item
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .map(|t| vec![CliEvent::Text(t.to_string())])
+                    .unwrap_or_default(),
+                Some("reasoning") | None => Vec::new(),
+                Some("error") => vec![CliEvent::Failed(
+                    message_of(item).unwrap_or_else(|| "codex reported an error".into()),
+                )],
+                Some(_) => vec![CliEvent::Tool(item.clone())],
+            }
+        }
+        Some("turn.completed") => vec![CliEvent::Done(None)],
+        Some("turn.failed" | "error") => vec![CliEvent::Failed(
+            message_of(value).unwrap_or_else(|| "codex turn failed".into()),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Decodes a `claude -p --output-format stream-json` line.
+fn decode_claude_line(value: &Value) -> Vec<CliEvent> {
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("assistant") => value
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|block| match block.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => block
+                            .get("text")
+                            .and_then(|t| t.as_str())
+                            .map(|t| CliEvent::Text(t.to_string())),
+                        Some("tool_use") => Some(CliEvent::Tool(block.clone())),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Some("result") => {
+            let result = value
+                .get("result")
+                .and_then(|r| r.as_str())
+                .map(String::from);
+            let is_error = value
+                .get("is_error")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false);
+            let subtype = value.get("subtype").and_then(|s| s.as_str());
+            if is_error || subtype.is_some_and(|s| s != "success") {
+                let msg = result
+                    .or_else(|| subtype.map(String::from))
+                    .unwrap_or_else(|| "claude run failed".into());
+                vec![CliEvent::Failed(msg)]
+            } else {
+                vec![CliEvent::Done(result)]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[async_trait]
+impl Execute for ProcessAdapter {
+    fn schema_repairs(&self) -> usize {
+        self.schema_repairs
+    }
+
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        self.execute_run(run_id, request, store).await
+    }
+}
+
+#[async_trait]
+impl BackendAdapter for ProcessAdapter {
+    fn backend(&self) -> BackendKind {
+        self.kind.backend()
+    }
+
+    fn capabilities(&self) -> AdapterCapabilities {
+        AdapterCapabilities {
+            supports_schema: false,
+            supports_async: true,
+            supports_tracing: false,
+            supports_secure_transcript: false,
+        }
+    }
+
+    async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+        Ok(vec![SubagentTemplate {
+            id: "default".into(),
+            name: format!("Local {} CLI", self.kind.label()),
+            description: Some(format!("Runs {} locally", self.program.display())),
+            backend: self.kind.backend(),
+            capabilities: vec!["tools".into()],
+            model: None,
+            system_prompt: None,
+            max_tokens: None,
+            tools: Vec::new(),
+            output_schema: None,
+            source: None,
+        }])
+    }
+
+    async fn start(&self, mut request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        if self.kind == CliKind::Codex {
+            // `codex exec` has no tool allowlist flag, so running such a
+            // template would grant it every tool.
+            if let Some(template) = request.template.as_ref().filter(|t| !t.tools.is_empty()) {
+                return Err(anyhow!(
+                    "template {} restricts tools, which codex exec cannot enforce; use the claude-cli backend",
+                    template.id
+                ));
+            }
+        }
+        request.backend = self.kind.backend();
+        start_execution(self, request, store).await
+    }
+
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        resume_execution(self, run_id, store).await
+    }
+
+    async fn get_status(
+        &self,
+        run_id: RunId,
+        store: Arc<dyn RunStore>,
+    ) -> Result<Option<RunStatus>> {
+        store.get_status(run_id).await
+    }
+
+    async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
+        cancel_run(run_id, &store).await
+    }
+
+    async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
+        let runs: Vec<RunRecord> = store.history(limit).await?;
+        Ok(runs.into_iter().map(|r| r.status).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(template: Option<SubagentTemplate>) -> RunRequest {
+        RunRequest {
+            backend: BackendKind::ClaudeCli,
+            prompt: "review the diff".into(),
+            async_mode: true,
+            template,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn claude_args_apply_template() {
+        let adapter = ProcessAdapter::new(CliKind::Claude, "claude".into());
+        let template = SubagentTemplate {
+            id: "reviewer".into(),
+            name: "reviewer".into(),
+            description: None,
+            backend: BackendKind::Claude,
+            capabilities: Vec::new(),
+            model: Some("sonnet".into()),
+            system_prompt: Some("You review code.".into()),
+            max_tokens: None,
+            tools: vec!["Read".into(), "Grep".into()],
+            output_schema: None,
+            source: None,
+        };
+        assert_eq!(
+            adapter.args(&request(Some(template)), &[]),
+            vec![
+                "-p",
+                "--output-format",
+                "stream-json",
+                "--verbose",
+                "--model",
+                "sonnet",
+                "--append-system-prompt",
+                "You review code.",
+                "--allowedTools",
+                "Read,Grep",
+                "review the diff",
+            ]
+        );
+        let codex = ProcessAdapter::new(CliKind::Codex, "codex".into());
+        assert_eq!(
+            codex.args(&request(None), &[]),
+            vec!["exec", "--json", "review the diff"]
+        );
+    }
+
+    #[test]
+    fn follow_ups_render_the_conversation() {
+        let history = [
+            ChatMessage::user("review the diff"),
+            ChatMessage::assistant("Looks fine."),
+            ChatMessage::user("Check the tests too"),
+        ];
+        assert_eq!(
+            render_conversation("review the diff", &history),
+            "Conversation so far:\n\nUser: review the diff\n\nAssistant: Looks fine.\n\n\
+             Reply to the latest message:\n\nCheck the tests too"
+        );
+        assert_eq!(
+            render_conversation("review the diff", &history[..1]),
+            "review the diff"
+        );
+    }
+
+    #[test]
+    fn decodes_codex_events() {
+        let lines = [
+            json!({"type": "thread.started", "thread_id": "t1"}),
+            json!({"type": "item.completed", "item": {"id": "i0", "type": "reasoning", "text": "thinking"}}),
+            json!({"type": "item.completed", "item": {"id": "i1", "type": "command_execution", "command": "ls"}}),
+            json!({"type": "item.completed", "item": {"id": "i2", "type": "agent_message", "text": "Done."}}),
+            json!({"type": "turn.completed", "usage": {"input_tokens": 10}}),
+            json!({"type": "turn.failed", "error": {"message": "quota exceeded"}}),
+            json!({"id": "0", "msg": {"type": "task_complete", "last_agent_message": "Legacy"}}),
+        ];
+        let decoded: Vec<_> = lines.iter().map(decode_codex_line).collect();
+        assert!(decoded[0].is_empty());
+        assert!(decoded[1].is_empty());
+        assert!(matches!(decoded[2][0], CliEvent::Tool(_)));
+        assert_eq!(decoded[3], vec![CliEvent::Text("Done.".into())]);
+        assert_eq!(decoded[4], vec![CliEvent::Done(None)]);
+        assert_eq!(decoded[5], vec![CliEvent::Failed("quota exceeded".into())]);
+        assert_eq!(decoded[6], vec![CliEvent::Done(Some("Legacy".into()))]);
+    }
+
+    #[test]
+    fn decodes_claude_events() {
+        let assistant = json!({"type": "assistant", "message": {"content": [
+            {"type": "text", "text": "Looking."},
+            {"type": "tool_use", "id": "tu1", "name": "Read", "input": {"file_path": "a.rs"}}
+        ]}});
+        let decoded = decode_claude_line(&assistant);
+        assert_eq!(decoded[0], CliEvent::Text("Looking.".into()));
+        assert!(matches!(decoded[1], CliEvent::Tool(_)));
+
+        let ok =
+            json!({"type": "result", "subtype": "success", "is_error": false, "result": "LGTM"});
+        assert_eq!(
+            decode_claude_line(&ok),
+            vec![CliEvent::Done(Some("LGTM".into()))]
+        );
+        let max_turns = json!({"type": "result", "subtype": "error_max_turns", "is_error": true});
+        assert_eq!(
+            decode_claude_line(&max_turns),
+            vec![CliEvent::Failed("error_max_turns".into())]
+        );
+    }
+}
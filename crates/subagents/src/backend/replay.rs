@@ -0,0 +1,116 @@
+//! Backend that answers every turn with a recorded response.
+//!
+//! `skrills subagents eval --replay` runs suites offline with it: each case's
+//! request is built as for a live run, then answered from the fixture
+//! recorded for the case. The run keeps the backend it would have used live,
+//! and its `start` event is marked `"replay": true`.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::backend::{
+    cancel_run, finish_run, resume_execution, start_event_data, start_execution,
+    AdapterCapabilities, BackendAdapter, Execute, RunHandle,
+};
+use crate::schema::DEFAULT_SCHEMA_REPAIRS;
+use crate::store::{
+    BackendKind, RunEvent, RunId, RunRecord, RunRequest, RunStatus, RunStore, SubagentTemplate,
+};
+
+#[derive(Debug, Clone)]
+pub struct ReplayAdapter {
+    response: String,
+}
+
+impl ReplayAdapter {
+    pub fn new(response: impl Into<String>) -> Self {
+        Self {
+            response: response.into(),
+        }
+    }
+
+    async fn execute_run(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        let mut data = start_event_data(&request);
+        data["replay"] = true.into();
+        store
+            .append_event(
+                run_id,
+                RunEvent {
+                    ts: OffsetDateTime::now_utc(),
+                    kind: "start".into(),
+                    data: Some(data),
+                },
+            )
+            .await?;
+        finish_run(run_id, self.response.clone(), &store).await
+    }
+}
+
+#[async_trait]
+impl Execute for ReplayAdapter {
+    fn schema_repairs(&self) -> usize {
+        DEFAULT_SCHEMA_REPAIRS
+    }
+
+    async fn execute(
+        &self,
+        run_id: RunId,
+        request: RunRequest,
+        store: Arc<dyn RunStore>,
+    ) -> Result<()> {
+        self.execute_run(run_id, request, store).await
+    }
+}
+
+#[async_trait]
+impl BackendAdapter for ReplayAdapter {
+    fn backend(&self) -> BackendKind {
+        BackendKind::Other("replay".into())
+    }
+
+    fn capabilities(&self) -> AdapterCapabilities {
+        AdapterCapabilities {
+            supports_schema: true,
+            supports_async: true,
+            supports_tracing: false,
+            supports_secure_transcript: false,
+        }
+    }
+
+    async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+        Ok(Vec::new())
+    }
+
+    async fn start(&self, request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        start_execution(self, request, store).await
+    }
+
+    async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+        resume_execution(self, run_id, store).await
+    }
+
+    async fn get_status(
+        &self,
+        run_id: RunId,
+        store: Arc<dyn RunStore>,
+    ) -> Result<Option<RunStatus>> {
+        store.get_status(run_id).await
+    }
+
+    async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
+        cancel_run(run_id, &store).await
+    }
+
+    async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
+        let runs: Vec<RunRecord> = store.history(limit).await?;
+        Ok(runs.into_iter().map(|r| r.status).collect())
+    }
+}
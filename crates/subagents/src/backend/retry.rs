@@ -0,0 +1,194 @@
+//! Retry policy shared by the HTTP backends.
+//!
+//! Provider calls that fail with a timeout, a connection error, `408`,
+//! `429` or a `5xx` status are retried with exponential backoff and jitter,
+//! or after the delay the provider asked for in `retry-after`. Every attempt
+//! is recorded on the run as an `attempt` event, so a run that survived a
+//! rate limit shows how it got there.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde_json::json;
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+
+use crate::backend::config::AdapterConfig;
+use crate::store::{RunEvent, RunId, RunStore};
+
+/// Upper bound for a single computed backoff delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Upper bound for a delay requested through `retry-after`.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Sends the request built by `request`, retrying transient failures up to
+/// `config.max_attempts` times in total.
+///
+/// The last response is returned even when its status is an error, so the
+/// caller can report the provider's message; only transport errors become
+/// `Err`.
+pub async fn send_with_retry(
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+    config: &AdapterConfig,
+    api: &str,
+    request: impl Fn() -> RequestBuilder,
+) -> Result<Response> {
+    let max_attempts = config.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        // Only the wait for the response head is bounded here; bodies are
+        // read with an idle timeout, see `sse::read_body`.
+        let outcome = tokio::time::timeout(config.timeout, request().send()).await;
+        let delay = match &outcome {
+            Ok(Ok(resp)) if is_retryable(resp.status()) => Some(
+                retry_after(resp.headers()).unwrap_or_else(|| backoff(config.backoff, attempt)),
+            ),
+            Ok(Err(err)) if err.is_timeout() || err.is_connect() => {
+                Some(backoff(config.backoff, attempt))
+            }
+            Err(_) => Some(backoff(config.backoff, attempt)),
+            _ => None,
+        }
+        .filter(|_| attempt < max_attempts);
+
+        let mut data = json!({ "attempt": attempt, "max_attempts": max_attempts });
+        match &outcome {
+            Ok(Ok(resp)) => data["status"] = resp.status().as_u16().into(),
+            Ok(Err(err)) => data["error"] = err.to_string().into(),
+            Err(_) => data["error"] = no_response(config.timeout).into(),
+        }
+        if let Some(delay) = delay {
+            data["retry_in_ms"] = (delay.as_millis() as u64).into();
+        }
+        store
+            .append_event(
+                run_id,
+                RunEvent {
+                    ts: OffsetDateTime::now_utc(),
+                    kind: "attempt".into(),
+                    data: Some(data),
+                },
+            )
+            .await?;
+
+        match delay {
+            Some(delay) => {
+                tracing::debug!(%run_id, attempt, ?delay, "retrying {api} call");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            None => {
+                return match outcome {
+                    Ok(outcome) => outcome.with_context(|| format!("calling {api}")),
+                    Err(_) => Err(anyhow!("calling {api}: {}", no_response(config.timeout))),
+                }
+            }
+        }
+    }
+}
+
+fn no_response(timeout: Duration) -> String {
+    format!("no response within {}ms", timeout.as_millis())
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+/// Exponential backoff from `base` with equal jitter: the delay for
+/// `attempt` lies between half and all of `base * 2^(attempt - 1)`.
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    let ceiling = base
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF);
+    let half = ceiling / 2;
+    let jitter = (uuid::Uuid::new_v4().as_u128() % (half.as_nanos() + 1)) as u64;
+    half + Duration::from_nanos(jitter)
+}
+
+/// Delay requested by the provider, from `retry-after-ms` (OpenAI) or
+/// `retry-after` in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+    let delay = if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        Duration::try_from_secs_f64(ms / 1000.0).ok()?
+    } else {
+        let value = header("retry-after")?;
+        match value.parse::<f64>() {
+            Ok(secs) => Duration::try_from_secs_f64(secs).ok()?,
+            Err(_) => {
+                let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+                (at - OffsetDateTime::now_utc())
+                    .try_into()
+                    .unwrap_or(Duration::ZERO)
+            }
+        }
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter_and_is_capped() {
+        let base = Duration::from_millis(100);
+        for attempt in 1..=4 {
+            let ceiling = base * (1 << (attempt - 1));
+            let delay = backoff(base, attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{delay:?}");
+        }
+        assert!(backoff(base, 40) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_millis_and_dates() {
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "2")])),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "3"), ("retry-after-ms", "250")])),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "86400")])),
+            Some(MAX_RETRY_AFTER)
+        );
+        assert_eq!(
+            retry_after(&headers(&[(
+                "retry-after",
+                "Sun, 06 Nov 1994 08:49:37 GMT"
+            )])),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")])), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable(StatusCode::from_u16(529).unwrap()));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,299 @@
+//! Server-sent events (SSE) handling shared by the HTTP backends.
+//!
+//! Both the OpenAI chat/completions and Anthropic messages APIs stream
+//! completions as `text/event-stream` bodies. [`SseParser`] turns raw body
+//! chunks into events, and [`stream_completion`] appends each text delta to
+//! the run as a `stream` event while the response is still arriving.
+//! Ollama streams newline-delimited JSON instead, read by
+//! [`stream_ndjson_completion`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+
+use crate::store::{RunEvent, RunId, RunStore, RunUsage};
+
+/// A single dispatched server-sent event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Value of the `event:` field, if present.
+    pub event: Option<String>,
+    /// `data:` lines joined with `\n`.
+    pub data: String,
+}
+
+/// What a backend-specific decoder made of one event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseStep {
+    /// A fragment of completion text.
+    Delta(String),
+    /// Token usage reported mid-stream, added to the run.
+    Usage(RunUsage),
+    /// Metadata or keep-alive with nothing to record.
+    Skip,
+    /// The backend signalled the end of the stream, with the usage a final
+    /// chunk reported alongside.
+    Done(Option<RunUsage>),
+}
+
+/// Incremental SSE parser; feed it body chunks as they arrive.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes a body chunk and returns every event completed by it.
+    ///
+    /// Lines are only decoded once terminated, so multi-byte characters
+    /// split across chunks are handled.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line[..line.len() - 1]);
+            if let Some(event) = self.process_line(line.strip_suffix('\r').unwrap_or(&line)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flushes a trailing event whose terminating blank line never arrived.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buf.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buf)).into_owned();
+            if let Some(event) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+/// Returns true when the response body is an SSE stream.
+pub fn is_event_stream(resp: &reqwest::Response) -> bool {
+    resp.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim_start().starts_with("text/event-stream"))
+}
+
+/// Reads an SSE response to completion, recording each text delta as a
+/// `stream` event on the run, and returns the full completion text.
+///
+/// `decode` maps an event to a [`SseStep`]; returning an error (for example
+/// on an in-band `error` event) aborts the stream. So does a body that ends
+/// before `decode` reported [`SseStep::Done`], or that stays silent for
+/// longer than `idle`: a dropped connection must not pass for a complete
+/// answer.
+pub async fn stream_completion<F>(
+    mut resp: reqwest::Response,
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+    idle: Duration,
+    mut decode: F,
+) -> Result<String>
+where
+    F: FnMut(&SseEvent) -> Result<SseStep>,
+{
+    let mut parser = SseParser::new();
+    let mut completion = String::new();
+    loop {
+        let (events, eof) = match next_chunk(&mut resp, idle).await? {
+            Some(chunk) => (parser.push(chunk.as_ref()), false),
+            None => (parser.finish().into_iter().collect(), true),
+        };
+        for event in events {
+            if apply_step(decode(&event)?, &mut completion, run_id, store).await? {
+                return Ok(completion);
+            }
+        }
+        if eof {
+            bail!("stream ended before the provider finished the completion");
+        }
+    }
+}
+
+/// Like [`stream_completion`], for bodies of newline-delimited JSON objects
+/// such as Ollama's `api/chat` stream.
+pub async fn stream_ndjson_completion<F>(
+    mut resp: reqwest::Response,
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+    idle: Duration,
+    mut decode: F,
+) -> Result<String>
+where
+    F: FnMut(&Value) -> Result<SseStep>,
+{
+    let mut buf = Vec::new();
+    let mut completion = String::new();
+    loop {
+        let (lines, eof): (Vec<Vec<u8>>, bool) = match next_chunk(&mut resp, idle).await? {
+            Some(chunk) => {
+                buf.extend_from_slice(chunk.as_ref());
+                let mut lines = Vec::new();
+                while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                    lines.push(buf.drain(..=pos).collect());
+                }
+                (lines, false)
+            }
+            None => (vec![std::mem::take(&mut buf)], true),
+        };
+        for line in lines {
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let value: Value = serde_json::from_str(line)
+                .with_context(|| format!("invalid stream line: {line}"))?;
+            if apply_step(decode(&value)?, &mut completion, run_id, store).await? {
+                return Ok(completion);
+            }
+        }
+        if eof {
+            bail!("stream ended before the provider finished the completion");
+        }
+    }
+}
+
+/// Reads a whole response body, allowing at most `idle` between chunks.
+pub async fn read_body(mut resp: reqwest::Response, idle: Duration) -> Result<String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = next_chunk(&mut resp, idle).await? {
+        body.extend_from_slice(chunk.as_ref());
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Next body chunk, or an error once the provider sent nothing for `idle`.
+///
+/// Bodies are bounded by this instead of a total request timeout, which
+/// would cut off long streamed completions that are still making progress.
+async fn next_chunk(
+    resp: &mut reqwest::Response,
+    idle: Duration,
+) -> Result<Option<impl AsRef<[u8]>>> {
+    match tokio::time::timeout(idle, resp.chunk()).await {
+        Ok(chunk) => Ok(chunk?),
+        Err(_) => bail!("provider sent nothing for {}ms", idle.as_millis()),
+    }
+}
+
+/// Records one decoded step on the run; returns `true` once the stream is
+/// done.
+async fn apply_step(
+    step: SseStep,
+    completion: &mut String,
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+) -> Result<bool> {
+    match step {
+        SseStep::Delta(token) if !token.is_empty() => {
+            completion.push_str(&token);
+            store
+                .append_event(
+                    run_id,
+                    RunEvent {
+                        ts: OffsetDateTime::now_utc(),
+                        kind: "stream".into(),
+                        data: Some(json!({ "token": token })),
+                    },
+                )
+                .await?;
+        }
+        SseStep::Usage(usage) => store.add_usage(run_id, usage).await?,
+        SseStep::Delta(_) | SseStep::Skip => {}
+        SseStep::Done(usage) => {
+            if let Some(usage) = usage {
+                store.add_usage(run_id, usage).await?;
+            }
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parser_handles_split_chunks_and_multiline_data() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"event: content_block_delta\r\nda").is_empty());
+        let events = parser.push(b"ta: {\"a\":1}\r\n\r\n: keep-alive\n\ndata: one\ndata: two\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("content_block_delta".into()),
+                    data: "{\"a\":1}".into(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "one\ntwo".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parser_keeps_multibyte_characters_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let bytes = "data: héllo\n\n".as_bytes();
+        assert!(parser.push(&bytes[..8]).is_empty());
+        let events = parser.push(&bytes[8..]);
+        assert_eq!(events[0].data, "héllo");
+    }
+
+    #[test]
+    fn finish_flushes_unterminated_event() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: [DONE]").is_empty());
+        assert_eq!(parser.finish().unwrap().data, "[DONE]");
+        assert!(parser.finish().is_none());
+    }
+}
@@ -0,0 +1,512 @@
+//! Regression evals for subagent templates and models.
+//!
+//! A suite file lists prompts and the assertions their output must satisfy.
+//! [`run_suite`] builds each case's request as `run_subagent` would and runs
+//! it on that backend's adapter, or answers it from a fixture directory with
+//! a [`ReplayAdapter`] for offline runs. Every case is recorded in the
+//! [`RunStore`] as a run of batch `eval:<suite>:<uuid>`, with an `eval` event
+//! holding its verdict, so each report is compared with the suite's previous
+//! eval.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value};
+use time::OffsetDateTime;
+
+use crate::backend::replay::ReplayAdapter;
+use crate::backend::BackendAdapter;
+use crate::schema::validate_output;
+use crate::service::SubagentService;
+use crate::store::{RunEvent, RunFilter, RunId, RunRecord, RunState, RunStore};
+
+/// A suite file: cases share the suite's backend and template unless they
+/// name their own.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EvalSuite {
+    /// Defaults to the file name without its extension.
+    #[serde(default)]
+    pub name: String,
+    pub backend: Option<String>,
+    pub template: Option<String>,
+    #[serde(default)]
+    pub cases: Vec<EvalCase>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EvalCase {
+    /// Names the case in reports and its fixture file, `<name>.txt`.
+    pub name: String,
+    pub prompt: String,
+    pub backend: Option<String>,
+    pub template: Option<String>,
+    #[serde(default, rename = "assert")]
+    pub assertions: Vec<Assertion>,
+}
+
+/// A check on a case's output, written `{ contains = "..." }`,
+/// `{ regex = "..." }` or `{ schema = { ... } }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Assertion {
+    Contains(String),
+    Regex(String),
+    /// The output is JSON valid against this schema.
+    Schema(Value),
+}
+
+impl Assertion {
+    /// Why `output` fails the assertion, or `None` when it holds.
+    fn failure(&self, output: &str) -> Result<Option<String>> {
+        Ok(match self {
+            Assertion::Contains(text) => (!output.contains(text.as_str()))
+                .then(|| format!("output does not contain {text:?}")),
+            Assertion::Regex(pattern) => {
+                let regex = Regex::new(pattern)?;
+                (!regex.is_match(output)).then(|| format!("output does not match /{pattern}/"))
+            }
+            Assertion::Schema(schema) => validate_output(schema, output)?
+                .err()
+                .map(|errors| format!("output does not match schema: {}", errors.join("; "))),
+        })
+    }
+}
+
+/// Suite and case names end up in batch ids and file names.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl EvalSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let mut suite: EvalSuite =
+            toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        if suite.name.is_empty() {
+            suite.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        suite.validate()?;
+        Ok(suite)
+    }
+
+    /// Rejects unusable names, patterns and schemas before anything runs.
+    pub fn validate(&self) -> Result<()> {
+        if !valid_name(&self.name) {
+            bail!(
+                "suite name {:?} may only contain letters, digits, '-', '_' and '.'",
+                self.name
+            );
+        }
+        if self.cases.is_empty() {
+            bail!("suite {} has no cases", self.name);
+        }
+        let mut names = HashSet::new();
+        for case in &self.cases {
+            if !valid_name(&case.name) {
+                bail!(
+                    "case name {:?} may only contain letters, digits, '-', '_' and '.'",
+                    case.name
+                );
+            }
+            if !names.insert(case.name.as_str()) {
+                bail!("duplicate case name: {}", case.name);
+            }
+            for assertion in &case.assertions {
+                match assertion {
+                    Assertion::Contains(_) => {}
+                    Assertion::Regex(pattern) => {
+                        Regex::new(pattern)
+                            .with_context(|| format!("case {}: invalid regex", case.name))?;
+                    }
+                    Assertion::Schema(schema) => {
+                        jsonschema::validator_for(schema)
+                            .map_err(|e| anyhow!("case {}: invalid schema: {e}", case.name))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where case outputs come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Responses {
+    /// Runs cases on their backends, saving each successful output as a
+    /// fixture under `record` when given.
+    Live { record: Option<PathBuf> },
+    /// Answers cases with the fixtures recorded under the directory.
+    Replay(PathBuf),
+}
+
+/// Fixture file holding the recorded output of `case`.
+pub fn fixture_path(dir: &Path, case: &str) -> PathBuf {
+    dir.join(format!("{case}.txt"))
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CaseResult {
+    pub name: String,
+    /// Missing when the case could not be started.
+    pub run_id: Option<RunId>,
+    pub state: Option<RunState>,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    /// Verdict of the case in the baseline eval.
+    pub previous: Option<bool>,
+}
+
+impl CaseResult {
+    /// `regressed` or `fixed` when the verdict changed since the baseline.
+    pub fn change(&self) -> Option<&'static str> {
+        match (self.previous, self.passed) {
+            (Some(true), false) => Some("regressed"),
+            (Some(false), true) => Some("fixed"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct EvalReport {
+    pub suite: String,
+    /// Batch id of this eval's runs.
+    pub eval_id: String,
+    pub replay: bool,
+    pub passed: usize,
+    pub failed: usize,
+    /// The suite's previous eval, which the `previous` verdicts come from.
+    pub baseline: Option<String>,
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    /// Plain-text report for the CLI.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Eval {}: {} passed, {} failed{}",
+            self.suite,
+            self.passed,
+            self.failed,
+            if self.replay { " (replayed)" } else { "" }
+        );
+        for case in &self.cases {
+            let run = case
+                .run_id
+                .map_or_else(|| "not started".to_string(), |id| format!("run {id}"));
+            let line = format!(
+                "  {}  {:<24} {run}  {}",
+                if case.passed { "pass" } else { "FAIL" },
+                case.name,
+                case.change().unwrap_or("")
+            );
+            let _ = writeln!(out, "{}", line.trim_end());
+            for failure in &case.failures {
+                let _ = writeln!(out, "        {failure}");
+            }
+        }
+        if let Some(baseline) = &self.baseline {
+            let count = |change| {
+                self.cases
+                    .iter()
+                    .filter(|c| c.change() == Some(change))
+                    .count()
+            };
+            let _ = writeln!(
+                out,
+                "\nCompared with {baseline}: {} regressed, {} fixed",
+                count("regressed"),
+                count("fixed")
+            );
+        }
+        let _ = writeln!(
+            out,
+            "Runs: skrills subagents history --batch {}",
+            self.eval_id
+        );
+        out
+    }
+}
+
+/// The case verdicts of a run's `eval` event.
+fn verdict(record: &RunRecord) -> Option<(String, bool)> {
+    let data = record.events.iter().rev().find(|e| e.kind == "eval")?;
+    let data = data.data.as_ref()?;
+    Some((
+        data.get("case")?.as_str()?.to_string(),
+        data.get("passed")?.as_bool()?,
+    ))
+}
+
+/// The most recent eval of `suite` and its verdicts by case.
+async fn baseline(
+    store: &Arc<dyn RunStore>,
+    suite: &str,
+) -> Result<Option<(String, HashMap<String, bool>)>> {
+    let newest = store
+        .query(&RunFilter {
+            batch_prefix: Some(format!("eval:{suite}:")),
+            limit: Some(1),
+            without_events: true,
+            ..RunFilter::default()
+        })
+        .await?;
+    let Some(latest) = newest.first().and_then(|r| r.request.batch_id.clone()) else {
+        return Ok(None);
+    };
+    let verdicts = store
+        .query(&RunFilter {
+            batch_id: Some(latest.clone()),
+            ..RunFilter::default()
+        })
+        .await?
+        .iter()
+        .filter_map(verdict)
+        .collect();
+    Ok(Some((latest, verdicts)))
+}
+
+/// Runs every case of `suite`, one at a time, and reports how each fared.
+pub async fn run_suite(
+    service: &SubagentService,
+    suite: &EvalSuite,
+    responses: &Responses,
+) -> Result<EvalReport> {
+    suite.validate()?;
+    let store = service.store();
+    let baseline = baseline(&store, &suite.name).await?;
+    let eval_id = format!("eval:{}:{}", suite.name, uuid::Uuid::new_v4());
+    let mut cases = Vec::with_capacity(suite.cases.len());
+    for case in &suite.cases {
+        let mut result = run_case(service, &store, suite, case, &eval_id, responses).await?;
+        result.previous = baseline
+            .as_ref()
+            .and_then(|(_, verdicts)| verdicts.get(&case.name).copied());
+        cases.push(result);
+    }
+    let passed = cases.iter().filter(|c| c.passed).count();
+    Ok(EvalReport {
+        suite: suite.name.clone(),
+        eval_id,
+        replay: matches!(responses, Responses::Replay(_)),
+        passed,
+        failed: cases.len() - passed,
+        baseline: baseline.map(|(id, _)| id),
+        cases,
+    })
+}
+
+async fn run_case(
+    service: &SubagentService,
+    store: &Arc<dyn RunStore>,
+    suite: &EvalSuite,
+    case: &EvalCase,
+    eval_id: &str,
+    responses: &Responses,
+) -> Result<CaseResult> {
+    let mut args = JsonMap::new();
+    args.insert("prompt".into(), json!(case.prompt));
+    if let Some(backend) = case.backend.as_ref().or(suite.backend.as_ref()) {
+        args.insert("backend".into(), json!(backend));
+    }
+    if let Some(template) = case.template.as_ref().or(suite.template.as_ref()) {
+        args.insert("template_id".into(), json!(template));
+    }
+    let setup = || -> Result<_> {
+        let (adapter, mut request) = service.build_request(&args, false)?;
+        request.batch_id = Some(eval_id.to_string());
+        let adapter: Arc<dyn BackendAdapter> = match responses {
+            Responses::Live { .. } => adapter,
+            Responses::Replay(dir) => {
+                let path = fixture_path(dir, &case.name);
+                let response = std::fs::read_to_string(&path)
+                    .with_context(|| format!("no recorded response at {}", path.display()))?;
+                Arc::new(ReplayAdapter::new(response))
+            }
+        };
+        Ok((adapter, request))
+    };
+    let (adapter, request) = match setup() {
+        Ok(setup) => setup,
+        Err(err) => {
+            return Ok(CaseResult {
+                name: case.name.clone(),
+                run_id: None,
+                state: None,
+                passed: false,
+                failures: vec![format!("{err:#}")],
+                previous: None,
+            })
+        }
+    };
+
+    let handle = adapter.start(request, store.clone()).await?;
+    let run_id = handle.run_id;
+    handle.task.await?;
+    let record = store
+        .get_run(run_id)
+        .await?
+        .ok_or_else(|| anyhow!("run not found: {run_id}"))?;
+    let state = record.status.state.clone();
+    let output = record.completion_text().unwrap_or("");
+    let mut failures = Vec::new();
+    if state == RunState::Succeeded {
+        for assertion in &case.assertions {
+            failures.extend(assertion.failure(output)?);
+        }
+        if let Responses::Live { record: Some(dir) } = responses {
+            std::fs::create_dir_all(dir)?;
+            let path = fixture_path(dir, &case.name);
+            std::fs::write(&path, output).with_context(|| format!("writing {}", path.display()))?;
+        }
+    } else {
+        failures.push(match &record.status.message {
+            Some(message) => format!("run {}: {message}", state.as_str()),
+            None => format!("run {}", state.as_str()),
+        });
+    }
+    let passed = failures.is_empty();
+    store
+        .append_event(
+            run_id,
+            RunEvent {
+                ts: OffsetDateTime::now_utc(),
+                kind: "eval".into(),
+                data: Some(json!({
+                    "suite": suite.name,
+                    "case": case.name,
+                    "passed": passed,
+                    "failures": failures,
+                })),
+            },
+        )
+        .await?;
+    Ok(CaseResult {
+        name: case.name.clone(),
+        run_id: Some(run_id),
+        state: Some(state),
+        passed,
+        failures,
+        previous: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BackendKind, MemRunStore};
+
+    const SUITE: &str = r#"
+name = "reviewer"
+
+[[cases]]
+name = "mentions-risk"
+prompt = "Review this diff"
+assert = [{ contains = "risk" }, { regex = "(?i)severity: (high|low)" }]
+
+[[cases]]
+name = "verdict"
+prompt = "Answer as JSON"
+assert = [{ schema = { type = "object", required = ["verdict"] } }]
+"#;
+
+    fn suite() -> EvalSuite {
+        let suite: EvalSuite = toml::from_str(SUITE).unwrap();
+        suite.validate().unwrap();
+        suite
+    }
+
+    #[test]
+    fn assertions_report_what_the_output_misses() {
+        let suite = suite();
+        assert_eq!(
+            suite.cases[1].assertions,
+            [Assertion::Schema(
+                json!({"type": "object", "required": ["verdict"]})
+            )]
+        );
+        let checks = &suite.cases[0].assertions;
+        let failures = |output| {
+            checks
+                .iter()
+                .filter_map(|a| a.failure(output).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert!(failures("Some risk. Severity: HIGH").is_empty());
+        assert_eq!(
+            failures("Looks fine"),
+            [
+                "output does not contain \"risk\"",
+                "output does not match /(?i)severity: (high|low)/"
+            ]
+        );
+        let schema = &suite.cases[1].assertions[0];
+        assert!(schema.failure(r#"{"verdict": "ok"}"#).unwrap().is_none());
+        assert!(schema.failure("{}").unwrap().is_some());
+
+        let mut bad = suite.clone();
+        bad.cases[1].name = "mentions-risk".into();
+        assert!(bad.validate().is_err());
+        let mut bad = suite;
+        bad.cases[0].assertions = vec![Assertion::Regex("(".into())];
+        assert!(bad.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn replayed_evals_are_recorded_and_compared_with_the_last_one() {
+        let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+        let service = SubagentService::with_store(store.clone(), BackendKind::Codex).unwrap();
+        let fixtures = tempfile::tempdir().unwrap();
+        let write = |case: &str, text: &str| {
+            std::fs::write(fixture_path(fixtures.path(), case), text).unwrap();
+        };
+        let replay = Responses::Replay(fixtures.path().to_path_buf());
+        write("mentions-risk", "One risk, severity: low");
+        write("verdict", "not json");
+
+        let first = run_suite(&service, &suite(), &replay).await.unwrap();
+        assert_eq!((first.passed, first.failed, first.replay), (1, 1, true));
+        assert_eq!(first.baseline, None);
+        assert_eq!(first.cases[1].state, Some(RunState::Succeeded));
+        assert!(first.cases[1].failures[0].starts_with("output does not match schema"));
+
+        write("mentions-risk", "Nothing to see");
+        write("verdict", r#"{"verdict": "approve"}"#);
+        let second = run_suite(&service, &suite(), &replay).await.unwrap();
+        assert_eq!(second.baseline.as_deref(), Some(first.eval_id.as_str()));
+        let changes: Vec<_> = second.cases.iter().map(CaseResult::change).collect();
+        assert_eq!(changes, [Some("regressed"), Some("fixed")]);
+
+        let filter = RunFilter {
+            batch_id: Some(second.eval_id.clone()),
+            ..RunFilter::default()
+        };
+        let runs = store.query(&filter).await.unwrap();
+        assert_eq!(runs.len(), 2);
+        for run in &runs {
+            assert_eq!(run.request.backend, BackendKind::Codex);
+            assert!(verdict(run).is_some());
+        }
+        let text = second.render_text();
+        assert!(text.contains("1 regressed, 1 fixed"), "{text}");
+
+        std::fs::remove_file(fixture_path(fixtures.path(), "verdict")).unwrap();
+        let third = run_suite(&service, &suite(), &replay).await.unwrap();
+        assert_eq!(third.cases[1].run_id, None);
+        assert!(third.cases[1].failures[0].starts_with("no recorded response"));
+    }
+}
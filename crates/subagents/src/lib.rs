@@ -5,10 +5,31 @@
 //! behind the `subagents` feature flag.
 
 pub mod backend;
+pub mod eval;
+pub mod notify;
+pub mod queue;
+pub mod schema;
 pub mod service;
+pub mod skills;
+pub mod sqlite;
 pub mod store;
+pub mod templates;
+pub mod tools;
+pub mod transcript;
+pub mod usage;
 
+pub use eval::{Assertion, EvalReport, EvalSuite, Responses};
+pub use notify::{ProgressSender, RunProgress};
 pub use service::SubagentService;
+pub use skills::{LoadedSkill, SkillAutoloader, SkillContext};
+pub use sqlite::SqliteRunStore;
 pub use store::{
-    BackendKind, RunEvent, RunId, RunRecord, RunRequest, RunState, RunStatus, RunStore,
+    BackendKind, ChatMessage, ChatRole, RunEvent, RunFilter, RunId, RunRecord, RunRequest,
+    RunState, RunStatus, RunStore, RunTransition, RunUsage, SubagentTemplate,
 };
+pub use templates::{
+    BackendConfig, BackendProtocol, HooksConfig, QueueConfig, RestartPolicy, SubagentsConfig,
+    TemplateConfig,
+};
+pub use tools::{McpToolHub, ToolLoop, ToolOutput, ToolProvider, ToolSpec};
+pub use usage::{ModelPrice, UsageSummary, UsageTotals};
@@ -0,0 +1,249 @@
+//! Run lifecycle notifications.
+//!
+//! Stores publish every status change as a [`RunTransition`]. The service
+//! turns the transitions of the runs a tool call started into
+//! [`RunProgress`] updates, which the MCP server relays to clients that sent
+//! a progress token, and runs the `[hooks] on_complete` command whenever a
+//! run finishes.
+
+use std::process::Stdio;
+
+use tokio::process::Command;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+
+use crate::store::{RunId, RunTransition};
+
+/// One progress update for a tool call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunProgress {
+    /// Increases with every update.
+    pub progress: f64,
+    /// Number of runs a batch waits for; unknown for single runs.
+    pub total: Option<f64>,
+    pub message: String,
+}
+
+/// Where a tool call sends its [`RunProgress`].
+pub type ProgressSender = mpsc::UnboundedSender<RunProgress>;
+
+/// Runs whose transitions a tool call reports.
+#[derive(Debug, Clone)]
+pub(crate) enum Watch {
+    Run(RunId),
+    /// Members of a batch, plus its reducer.
+    Batch {
+        id: String,
+        total: usize,
+    },
+}
+
+fn describe(transition: &RunTransition) -> String {
+    match &transition.status.message {
+        Some(message) => format!(
+            "run {} {}: {message}",
+            transition.run_id,
+            transition.status.state.as_str()
+        ),
+        None => format!(
+            "run {} {}",
+            transition.run_id,
+            transition.status.state.as_str()
+        ),
+    }
+}
+
+/// Sends the transitions matching `watch` to `progress` until the watched
+/// runs finish or the client stops listening.
+///
+/// Single runs report each transition; batches report each finished run
+/// against the batch total.
+pub(crate) async fn forward_progress(
+    mut transitions: broadcast::Receiver<RunTransition>,
+    watch: Watch,
+    progress: ProgressSender,
+) {
+    let mut step = 0u32;
+    loop {
+        let transition = match transitions.recv().await {
+            Ok(transition) => transition,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!(skipped, "progress fell behind run transitions");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let terminal = transition.status.state.is_terminal();
+        let (update, done) = match &watch {
+            Watch::Run(run_id) if transition.run_id == *run_id => {
+                step += 1;
+                let update = RunProgress {
+                    progress: step.into(),
+                    total: None,
+                    message: describe(&transition),
+                };
+                (update, terminal)
+            }
+            Watch::Batch { id, total } if transition.batch_id.as_ref() == Some(id) && terminal => {
+                step += 1;
+                let update = RunProgress {
+                    progress: step.into(),
+                    total: Some(*total as f64),
+                    message: describe(&transition),
+                };
+                (update, step as usize >= *total)
+            }
+            _ => continue,
+        };
+        if progress.send(update).is_err() || done {
+            return;
+        }
+    }
+}
+
+/// Builds the `on_complete` command for a finished run.
+///
+/// `{run_id}`, `{state}`, `{backend}` and `{message}` in the arguments are
+/// replaced, and the same values are passed as `SKRILLS_RUN_*` variables.
+/// Stdout is discarded: the server's stdout carries the MCP protocol.
+fn hook_command(command: &[String], transition: &RunTransition) -> Option<Command> {
+    let (program, args) = command.split_first()?;
+    let run_id = transition.run_id.to_string();
+    let state = transition.status.state.as_str();
+    let backend = transition.backend.name();
+    let message = transition.status.message.as_deref().unwrap_or("");
+    let expand = |arg: &String| {
+        arg.replace("{run_id}", &run_id)
+            .replace("{state}", state)
+            .replace("{backend}", backend)
+            .replace("{message}", message)
+    };
+    let mut cmd = Command::new(expand(program));
+    cmd.args(args.iter().map(expand))
+        .env("SKRILLS_RUN_ID", &run_id)
+        .env("SKRILLS_RUN_STATE", state)
+        .env("SKRILLS_RUN_BACKEND", backend)
+        .env("SKRILLS_RUN_MESSAGE", message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null());
+    Some(cmd)
+}
+
+/// Runs `command` for every run that reaches a terminal state, until the
+/// store is dropped. Failures are logged and never affect the run.
+pub(crate) async fn run_completion_hooks(
+    mut transitions: broadcast::Receiver<RunTransition>,
+    command: Vec<String>,
+) {
+    loop {
+        let transition = match transitions.recv().await {
+            Ok(transition) => transition,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "on_complete hook missed run transitions");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if !transition.status.state.is_terminal() {
+            continue;
+        }
+        let Some(mut cmd) = hook_command(&command, &transition) else {
+            return;
+        };
+        let run_id = transition.run_id;
+        match cmd.spawn() {
+            Ok(mut child) => {
+                tokio::spawn(async move {
+                    match child.wait().await {
+                        Ok(status) if !status.success() => {
+                            tracing::warn!(%run_id, %status, "on_complete hook failed")
+                        }
+                        Ok(_) => {}
+                        Err(err) => tracing::warn!(%run_id, %err, "on_complete hook failed"),
+                    }
+                });
+            }
+            Err(err) => tracing::warn!(%run_id, %err, "could not start on_complete hook"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BackendKind, RunState, RunStatus};
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    fn transition(run_id: RunId, batch_id: Option<&str>, state: RunState) -> RunTransition {
+        RunTransition {
+            run_id,
+            backend: BackendKind::Other("local".into()),
+            batch_id: batch_id.map(String::from),
+            status: RunStatus {
+                state,
+                message: Some("done".into()),
+                updated_at: OffsetDateTime::now_utc(),
+            },
+        }
+    }
+
+    #[test]
+    fn hook_arguments_and_environment_describe_the_run() {
+        let run_id = RunId(Uuid::new_v4());
+        let command = vec![
+            "notify-send".to_string(),
+            "subagent {state}".to_string(),
+            "{backend}: {message}".to_string(),
+        ];
+        let cmd = hook_command(&command, &transition(run_id, None, RunState::Succeeded)).unwrap();
+        let cmd = cmd.as_std();
+        assert_eq!(cmd.get_program(), "notify-send");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["subagent succeeded", "local: done"]);
+        let run_id = run_id.to_string();
+        assert!(cmd
+            .get_envs()
+            .any(|(k, v)| k == "SKRILLS_RUN_ID" && v == Some(run_id.as_ref())));
+        assert!(hook_command(
+            &[],
+            &transition(RunId(Uuid::new_v4()), None, RunState::Failed)
+        )
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn batch_progress_counts_finished_members() {
+        let (tx, rx) = broadcast::channel(16);
+        let (progress, mut updates) = mpsc::unbounded_channel();
+        let task = tokio::spawn(forward_progress(
+            rx,
+            Watch::Batch {
+                id: "b1".into(),
+                total: 2,
+            },
+            progress,
+        ));
+        let (a, b) = (RunId(Uuid::new_v4()), RunId(Uuid::new_v4()));
+        tx.send(transition(a, Some("b1"), RunState::Running))
+            .unwrap();
+        tx.send(transition(a, Some("other"), RunState::Succeeded))
+            .unwrap();
+        tx.send(transition(a, Some("b1"), RunState::Succeeded))
+            .unwrap();
+        tx.send(transition(b, Some("b1"), RunState::Failed))
+            .unwrap();
+        task.await.unwrap();
+
+        let first = updates.recv().await.unwrap();
+        assert_eq!((first.progress, first.total), (1.0, Some(2.0)));
+        let second = updates.recv().await.unwrap();
+        assert_eq!(second.progress, 2.0);
+        assert!(
+            second.message.ends_with("failed: done"),
+            "{}",
+            second.message
+        );
+        assert!(updates.recv().await.is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Persistent queue behind `run_subagent_async`.
+//!
+//! A queued run is an ordinary `Pending` record, so the queue lives in the
+//! [`RunStore`] and survives restarts. When the MCP server starts serving,
+//! [`reconcile`] picks up the runs a previous server left behind: queued runs
+//! are queued again, and runs that were executing are failed as
+//! `interrupted` or requeued per `[queue] on_restart`. Records note the pid
+//! of the process that created, queued or executed them, so runs of another
+//! live process sharing the store are left alone. Liveness is checked with
+//! `kill(pid, 0)`; on other platforms reconciliation is skipped.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::backend::{BackendAdapter, RunHandle};
+use crate::store::{
+    BackendKind, RunEvent, RunFilter, RunId, RunRecord, RunState, RunStatus, RunStore,
+    RunTransition,
+};
+use crate::templates::RestartPolicy;
+
+/// Queued runs executed at once when `[queue] workers` is unset.
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// Receives the handle of every run the queue dispatches.
+pub(crate) type Register = Arc<dyn Fn(RunHandle) + Send + Sync>;
+
+/// An event recording that this process took over `run_id`'s execution.
+pub(crate) fn owner_event(kind: &str) -> RunEvent {
+    RunEvent {
+        ts: OffsetDateTime::now_utc(),
+        kind: kind.into(),
+        data: Some(json!({ "pid": std::process::id() })),
+    }
+}
+
+/// Pid of the process that last queued or executed the run, or else the
+/// one that created it.
+fn owner(record: &RunRecord) -> Option<u32> {
+    record
+        .events
+        .iter()
+        .rev()
+        .find_map(|e| {
+            let pid = e.data.as_ref()?.get("pid")?.as_u64()?;
+            u32::try_from(pid).ok()
+        })
+        .or(record.request.owner)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Zero and negative pids address process groups, not a server.
+    let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|pid| *pid > 0) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks that the process exists.
+    let rc = unsafe { libc::kill(pid, 0) };
+    rc == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+pub(crate) async fn set_state(
+    store: &Arc<dyn RunStore>,
+    run_id: RunId,
+    state: RunState,
+    message: &str,
+) -> Result<bool> {
+    store
+        .update_status(
+            run_id,
+            RunStatus {
+                state,
+                message: Some(message.into()),
+                updated_at: OffsetDateTime::now_utc(),
+            },
+        )
+        .await
+}
+
+/// Without a liveness check every other process would look gone, so runs
+/// are left as they are.
+#[cfg(not(unix))]
+pub(crate) async fn reconcile(
+    _store: &Arc<dyn RunStore>,
+    _policy: RestartPolicy,
+) -> Result<Vec<RunId>> {
+    tracing::debug!("skipping subagent run reconciliation on this platform");
+    Ok(Vec::new())
+}
+
+/// Settles the unfinished runs of processes that are gone, returning the
+/// runs to queue again, oldest first.
+#[cfg(unix)]
+pub(crate) async fn reconcile(
+    store: &Arc<dyn RunStore>,
+    policy: RestartPolicy,
+) -> Result<Vec<RunId>> {
+    let me = std::process::id();
+    let mut orphans = Vec::new();
+    for state in [RunState::Pending, RunState::Running] {
+        let filter = RunFilter {
+            state: Some(state),
+            ..RunFilter::default()
+        };
+        orphans.extend(
+            store
+                .query(&filter)
+                .await?
+                .into_iter()
+                .filter(|r| !owner(r).is_some_and(|pid| pid == me || process_alive(pid))),
+        );
+    }
+    orphans.sort_by_key(|r| r.created_at);
+
+    let mut requeue = Vec::new();
+    for record in orphans {
+        let run_id = record.id;
+        if record.status.state == RunState::Running && policy == RestartPolicy::Fail {
+            store
+                .append_event(
+                    run_id,
+                    RunEvent {
+                        ts: OffsetDateTime::now_utc(),
+                        kind: "interrupted".into(),
+                        data: Some(json!({"message": "server exited during the run"})),
+                    },
+                )
+                .await?;
+            set_state(store, run_id, RunState::Failed, "interrupted").await?;
+            continue;
+        }
+        store.append_event(run_id, owner_event("requeued")).await?;
+        set_state(store, run_id, RunState::Pending, "requeued").await?;
+        requeue.push(run_id);
+    }
+    if !requeue.is_empty() {
+        tracing::info!(runs = requeue.len(), "requeued subagent runs");
+    }
+    Ok(requeue)
+}
+
+/// Executes queued runs, at most `workers` at a time, until every sender
+/// of `queued` is dropped.
+pub(crate) async fn dispatch(
+    mut queued: mpsc::UnboundedReceiver<RunId>,
+    workers: usize,
+    store: Arc<dyn RunStore>,
+    adapters: HashMap<BackendKind, Arc<dyn BackendAdapter>>,
+    register: Register,
+) {
+    let slots = Arc::new(Semaphore::new(workers.max(1)));
+    while let Some(run_id) = queued.recv().await {
+        let Ok(slot) = slots.clone().acquire_owned().await else {
+            return;
+        };
+        let transitions = store.subscribe();
+        match dispatch_one(run_id, &store, &adapters, &register).await {
+            Ok(true) => {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    wait_until_finished(transitions, run_id, &store).await;
+                    drop(slot);
+                });
+            }
+            Ok(false) => {}
+            Err(err) => {
+                tracing::warn!(%run_id, %err, "could not dispatch queued run");
+                let _ = set_state(&store, run_id, RunState::Failed, &err.to_string()).await;
+            }
+        }
+    }
+}
+
+/// Starts `run_id` if it is still queued; `false` when it was stopped or
+/// picked up elsewhere in the meantime.
+async fn dispatch_one(
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+    adapters: &HashMap<BackendKind, Arc<dyn BackendAdapter>>,
+    register: &Register,
+) -> Result<bool> {
+    let Some(record) = store.get_run(run_id).await? else {
+        return Ok(false);
+    };
+    if record.status.state != RunState::Pending {
+        return Ok(false);
+    }
+    let adapter = adapters
+        .get(&record.request.backend)
+        .ok_or_else(|| anyhow!("backend not configured: {:?}", record.request.backend))?;
+    set_state(store, run_id, RunState::Running, "dispatched").await?;
+    // A stop between the check and the update leaves the run canceled.
+    let running = store.get_status(run_id).await?;
+    if running.is_none_or(|s| s.state != RunState::Running) {
+        return Ok(false);
+    }
+    register(adapter.resume(run_id, store.clone()).await?);
+    Ok(true)
+}
+
+async fn wait_until_finished(
+    mut transitions: broadcast::Receiver<RunTransition>,
+    run_id: RunId,
+    store: &Arc<dyn RunStore>,
+) {
+    loop {
+        match transitions.recv().await {
+            Ok(t) if t.run_id == run_id && t.status.state.is_terminal() => return,
+            Ok(_) => {}
+            Err(RecvError::Lagged(_)) => {
+                let status = store.get_status(run_id).await.ok().flatten();
+                if status.is_none_or(|s| s.state.is_terminal()) {
+                    return;
+                }
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{MemRunStore, RunRequest};
+
+    fn request() -> RunRequest {
+        RunRequest {
+            prompt: "hi".into(),
+            async_mode: true,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a run in `state` owned by `pid`, either from creation or
+    /// through a start event.
+    async fn run(store: &Arc<dyn RunStore>, state: RunState, pid: u32, started: bool) -> RunId {
+        let request = RunRequest {
+            owner: (!started).then_some(pid),
+            ..request()
+        };
+        let run_id = store.create_run(request).await.unwrap();
+        if started {
+            let event = RunEvent {
+                ts: OffsetDateTime::now_utc(),
+                kind: "start".into(),
+                data: Some(json!({ "pid": pid })),
+            };
+            store.append_event(run_id, event).await.unwrap();
+        }
+        if state != RunState::Pending {
+            set_state(store, run_id, state, "dispatched").await.unwrap();
+        }
+        run_id
+    }
+
+    async fn state(store: &Arc<dyn RunStore>, run_id: RunId) -> (RunState, Option<String>) {
+        let status = store.get_status(run_id).await.unwrap().unwrap();
+        (status.state, status.message)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reconcile_settles_runs_of_exited_servers_only() {
+        // No process has pid u32::MAX; this test's process is alive.
+        let gone = u32::MAX;
+        let me = std::process::id();
+        for policy in [RestartPolicy::Fail, RestartPolicy::Requeue] {
+            let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+            let queued = run(&store, RunState::Pending, gone, false).await;
+            let interrupted = run(&store, RunState::Running, gone, true).await;
+            let live = run(&store, RunState::Running, me, true).await;
+            // Created here but not started yet: still ours.
+            let creating = run(&store, RunState::Pending, me, false).await;
+            let done = run(&store, RunState::Succeeded, gone, true).await;
+
+            let requeued = reconcile(&store, policy).await.unwrap();
+
+            assert_eq!(state(&store, live).await.0, RunState::Running);
+            assert_eq!(state(&store, creating).await, (RunState::Pending, None));
+            assert_eq!(state(&store, done).await.0, RunState::Succeeded);
+            assert_eq!(
+                state(&store, queued).await,
+                (RunState::Pending, Some("requeued".into()))
+            );
+            if policy == RestartPolicy::Fail {
+                assert_eq!(requeued, vec![queued]);
+                assert_eq!(
+                    state(&store, interrupted).await,
+                    (RunState::Failed, Some("interrupted".into()))
+                );
+            } else {
+                assert_eq!(requeued, vec![queued, interrupted]);
+                assert_eq!(state(&store, interrupted).await.0, RunState::Pending);
+                let record = store.get_run(interrupted).await.unwrap().unwrap();
+                assert_eq!(owner(&record), Some(std::process::id()));
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Local validation of structured subagent outputs.
+//!
+//! Providers are asked for JSON matching a run's `output_schema`, but nothing
+//! guarantees they comply. Completions are parsed and checked here; a
+//! mismatch is fed back to the model as a repair turn (see
+//! [`crate::backend`]) until it complies or the repair budget runs out.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Repair turns allowed before a run ends in `SchemaFailed`, unless the
+/// backend is configured otherwise.
+pub const DEFAULT_SCHEMA_REPAIRS: usize = 2;
+
+/// A completion that did not satisfy the run's `output_schema`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("output does not match output_schema: {}", errors.join("; "))]
+pub struct SchemaMismatch {
+    /// The rejected completion text.
+    pub output: String,
+    pub errors: Vec<String>,
+}
+
+/// Parses `text` as JSON and validates it against `schema`.
+///
+/// The outer error is an unusable schema; the inner one lists everything
+/// wrong with the output.
+pub fn validate_output(
+    schema: &Value,
+    text: &str,
+) -> Result<std::result::Result<Value, Vec<String>>> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| anyhow!("invalid output_schema: {e}"))?;
+    let value = match parse_json(text) {
+        Ok(value) => value,
+        Err(err) => return Ok(Err(vec![format!("output is not valid JSON: {err}")])),
+    };
+    let errors: Vec<String> = validator
+        .iter_errors(&value)
+        .map(|e| {
+            let path = e.instance_path().to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{path}: {e}")
+            }
+        })
+        .collect();
+    Ok(if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    })
+}
+
+/// Parses a completion as JSON, accepting a single fenced code block around
+/// it since models add one even when asked not to.
+fn parse_json(text: &str) -> serde_json::Result<Value> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|body| {
+            // Drop the info string (`json`) on the opening fence line.
+            body.split_once('\n').map_or(body, |(_, code)| code)
+        });
+    serde_json::from_str(unfenced.unwrap_or(trimmed))
+}
+
+/// The follow-up sent to the model after a mismatch.
+pub fn repair_prompt(errors: &[String]) -> String {
+    let mut prompt = String::from("Your previous reply does not match the required JSON schema:\n");
+    for error in errors {
+        prompt.push_str(&format!("- {error}\n"));
+    }
+    prompt.push_str("\nReply again with only the corrected JSON, without commentary.");
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["verdict"],
+            "properties": {
+                "verdict": {"type": "string", "enum": ["approve", "reject"]},
+                "score": {"type": "integer"}
+            }
+        })
+    }
+
+    #[test]
+    fn accepts_valid_and_fenced_json() {
+        assert_eq!(
+            validate_output(&schema(), r#"{"verdict": "approve"}"#)
+                .unwrap()
+                .unwrap(),
+            json!({"verdict": "approve"})
+        );
+        let fenced = "```json\n{\"verdict\": \"reject\", \"score\": 2}\n```";
+        assert_eq!(
+            validate_output(&schema(), fenced).unwrap().unwrap()["score"],
+            2
+        );
+    }
+
+    #[test]
+    fn reports_parse_and_schema_errors() {
+        let errors = validate_output(&schema(), "Looks good to me!")
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("output is not valid JSON"));
+
+        let errors = validate_output(&schema(), r#"{"verdict": "maybe", "score": "high"}"#)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.starts_with("/verdict: ")));
+        assert!(errors.iter().any(|e| e.starts_with("/score: ")));
+    }
+
+    #[test]
+    fn rejects_unusable_schema() {
+        let err = validate_output(&json!({"type": 12}), "{}").unwrap_err();
+        assert!(err.to_string().starts_with("invalid output_schema"));
+    }
+}
@@ -1,63 +1,333 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::io::Write as _;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use rmcp::model::{object, JsonObject};
 use rmcp::model::{CallToolResult, Content, Tool};
 use serde_json::{json, Map as JsonMap, Value};
+use tokio::sync::{broadcast, mpsc};
 
-use crate::backend::BackendAdapter;
-use crate::backend::{claude::ClaudeAdapter, codex::CodexAdapter};
-use crate::store::{default_store_path, BackendKind, RunId, RunRequest, RunStore, StateRunStore};
+use crate::backend::config::DEFAULT_TIMEOUT_MS;
+use crate::backend::{cancel_run, declared_adapter, BackendAdapter, RunHandle};
+use crate::backend::{claude::ClaudeAdapter, codex::CodexAdapter, process::ProcessAdapter};
+use crate::notify::{forward_progress, run_completion_hooks, ProgressSender, Watch};
+use crate::queue::{self, dispatch, owner_event, reconcile, Register};
+use crate::skills::SkillAutoloader;
+use crate::sqlite;
+use crate::store::{
+    BackendKind, ChatMessage, MemRunStore, RunFilter, RunId, RunRecord, RunRequest, RunState,
+    RunStore, RunTransition, SubagentError, SubagentTemplate,
+};
+use crate::templates::{backend_from_str, default_config_path, load_templates, SubagentsConfig};
+use crate::tools::{McpToolHub, ToolLoop, DEFAULT_MAX_TOOL_ITERATIONS};
+use crate::transcript::{self, default_key_path, default_transcript_dir, TranscriptKey};
+use crate::usage::{cost, days_ago, summarize};
+use skrills_discovery::SkillRoot;
 
-fn backend_from_str(raw: &str) -> BackendKind {
-    match raw.to_ascii_lowercase().as_str() {
-        "codex" | "gpt" | "openai" => BackendKind::Codex,
-        "claude" | "anthropic" => BackendKind::Claude,
-        other => BackendKind::Other(other.to_string()),
-    }
-}
+const CODEX_MODEL: &str = "gpt-5-codex";
+const CLAUDE_MODEL: &str = "claude-code";
+
+/// Batch members run at once unless `concurrency` says otherwise.
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+const MAX_BATCH_CONCURRENCY: usize = 16;
 
 fn run_id_from_value(val: &Value) -> Result<RunId> {
     let s = val
         .as_str()
         .ok_or_else(|| anyhow!("run_id must be a string"))?;
-    let uuid = uuid::Uuid::parse_str(s).map_err(|e| anyhow!("invalid run_id: {e}"))?;
-    Ok(RunId(uuid))
+    s.parse()
+}
+
+/// Checks that a client-supplied transcript `path` stays inside the
+/// transcripts directory: relative, with no `..`, root or prefix components.
+fn relative_transcript_path(path: &str) -> Result<&Path> {
+    let path = Path::new(path);
+    let plain = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if !plain || path.as_os_str().is_empty() {
+        return Err(anyhow!(
+            "transcript path must be relative to the transcripts directory and must not contain `..`: {}",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+fn batch_entry(record: &RunRecord) -> Value {
+    json!({
+        "run_id": record.id,
+        "backend": record.request.backend,
+        "template_id": record.request.template_id,
+        "status": record.status,
+        "output": record.completion_text(),
+        "result": record.result,
+    })
+}
+
+/// Appends each batch member's output (or failure) to the reducer prompt.
+fn reducer_prompt(prompt: &str, runs: &[RunRecord]) -> String {
+    let mut out = format!(
+        "{prompt}\n\nOutputs of {} subagent runs follow.",
+        runs.len()
+    );
+    for (index, run) in runs.iter().enumerate() {
+        let body = match run.completion_text() {
+            Some(text) if run.status.state == RunState::Succeeded => text.to_string(),
+            _ => format!(
+                "(run {}: {})",
+                run.status.state.as_str(),
+                run.status.message.as_deref().unwrap_or("no output")
+            ),
+        };
+        out.push_str(&format!(
+            "\n\n## Run {} ({})\n\n{body}",
+            index + 1,
+            run.request.backend.name()
+        ));
+    }
+    out
+}
+
+/// `on_complete` hook, subscribed early so no run is missed and spawned on
+/// first use inside a runtime; the service may be built outside one.
+type DeferredHook = (broadcast::Receiver<RunTransition>, Vec<String>);
+
+/// Wraps `handle` so its run id is sent on `done` once its task ends.
+fn notify_when_done(handle: RunHandle, done: mpsc::UnboundedSender<RunId>) -> RunHandle {
+    let RunHandle {
+        run_id,
+        cancel,
+        task,
+    } = handle;
+    let task = tokio::spawn(async move {
+        let canceled = task.await.unwrap_or(false);
+        let _ = done.send(run_id);
+        canceled
+    });
+    RunHandle {
+        run_id,
+        cancel,
+        task,
+    }
+}
+
+/// Keeps a run's handle so `stop_run` can abort it.
+fn register(running: &Mutex<HashMap<RunId, RunHandle>>, handle: RunHandle) {
+    let mut running = running.lock().unwrap();
+    running.retain(|_, h| !h.task.is_finished());
+    running.insert(handle.run_id, handle);
 }
 
 pub struct SubagentService {
     store: Arc<dyn RunStore>,
     adapters: HashMap<BackendKind, Arc<dyn BackendAdapter>>,
     default_backend: BackendKind,
+    /// Handles for runs started by this service, used to abort them on stop.
+    running: Arc<Mutex<HashMap<RunId, RunHandle>>>,
+    /// Roots scanned for agent definitions to offer as templates.
+    agent_roots: Vec<SkillRoot>,
+    config: SubagentsConfig,
+    /// Tools offered to the API-backed adapters, kept to rebuild declared
+    /// backends when the config changes.
+    tools: Option<ToolLoop>,
+    /// Keyfile for transcript bundles; [`default_key_path`] when unset.
+    transcript_key: Option<PathBuf>,
+    /// Directory transcript bundles are written under;
+    /// [`default_transcript_dir`] when unset.
+    transcript_dir: Option<PathBuf>,
+    /// Source of the skills `autoload: true` runs inject.
+    skills: Option<Arc<dyn SkillAutoloader>>,
+    /// Feeds `run_subagent_async` runs to the queue dispatcher.
+    queue: mpsc::UnboundedSender<RunId>,
+    /// Receiving end of `queue`, until [`Self::start`] hands it to the
+    /// dispatcher.
+    queued: Mutex<Option<mpsc::UnboundedReceiver<RunId>>>,
+    hook: Mutex<Option<DeferredHook>>,
 }
 
 impl SubagentService {
+    /// Builds the service from `subagents.toml` and the default run
+    /// database. Neither is fatal: an unreadable config falls back to the
+    /// defaults and an unusable database to an in-memory store, each with a
+    /// warning, so they cannot take the MCP server down.
     pub fn new() -> Result<Self> {
-        let store = Arc::new(StateRunStore::new(default_store_path()?)?);
+        let config = default_config_path()
+            .and_then(|path| SubagentsConfig::load(&path))
+            .unwrap_or_else(|err| {
+                tracing::warn!(err = %format!("{err:#}"), "ignoring subagents.toml");
+                SubagentsConfig::default()
+            });
+        let store: Arc<dyn RunStore> = match sqlite::open_default(&config) {
+            Ok(store) => Arc::new(store),
+            Err(err) => {
+                tracing::warn!(
+                    err = %format!("{err:#}"),
+                    "cannot open the run database; runs will not be persisted"
+                );
+                Arc::new(MemRunStore::new())
+            }
+        };
         let default_backend = std::env::var("SKRILLS_SUBAGENTS_DEFAULT_BACKEND")
             .ok()
+            .or_else(|| config.default_backend.clone())
             .as_deref()
             .map(backend_from_str)
             .unwrap_or(BackendKind::Codex);
-        Self::with_store(store, default_backend)
+        let hub = McpToolHub::discover(&config);
+        let tools = (!hub.is_empty()).then(|| {
+            ToolLoop::new(Arc::new(hub)).with_max_iterations(
+                config
+                    .max_tool_iterations
+                    .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS),
+            )
+        });
+        let service = Self::with_store(store, default_backend)?.with_config(config);
+        Ok(match tools {
+            Some(tools) => service.with_tools(tools),
+            None => service,
+        })
     }
 
     pub fn with_store(store: Arc<dyn RunStore>, default_backend: BackendKind) -> Result<Self> {
         let mut adapters: HashMap<BackendKind, Arc<dyn BackendAdapter>> = HashMap::new();
-        adapters.insert(
-            BackendKind::Codex,
-            Arc::new(CodexAdapter::new("gpt-5-codex".into())),
-        );
-        adapters.insert(
-            BackendKind::Claude,
-            Arc::new(ClaudeAdapter::new("claude-code".into())),
-        );
-        Ok(Self {
+        adapters.insert(BackendKind::CodexCli, Arc::new(ProcessAdapter::codex()));
+        adapters.insert(BackendKind::ClaudeCli, Arc::new(ProcessAdapter::claude()));
+        let (queue, queued) = mpsc::unbounded_channel();
+        let mut service = Self {
             store,
             adapters,
             default_backend,
-        })
+            running: Arc::new(Mutex::new(HashMap::new())),
+            agent_roots: Vec::new(),
+            config: SubagentsConfig::default(),
+            tools: None,
+            transcript_key: None,
+            transcript_dir: None,
+            skills: None,
+            queue,
+            queued: Mutex::new(Some(queued)),
+            hook: Mutex::new(None),
+        };
+        service.register_api_backends();
+        Ok(service)
+    }
+
+    /// Offers the agents discovered under `roots` as run templates.
+    pub fn with_agent_roots(mut self, roots: Vec<SkillRoot>) -> Self {
+        self.agent_roots = roots;
+        self
+    }
+
+    /// Uses `config` for `subagents.toml` templates and declared backends.
+    pub fn with_config(mut self, config: SubagentsConfig) -> Self {
+        let hook = &config.hooks.on_complete;
+        *self.hook.get_mut().unwrap() =
+            (!hook.is_empty()).then(|| (self.store.subscribe(), hook.clone()));
+        self.config = config;
+        self.register_api_backends();
+        self.register_declared_backends();
+        self
+    }
+
+    /// Signs and encrypts transcript bundles with the key in `path`.
+    pub fn with_transcript_key(mut self, path: PathBuf) -> Self {
+        self.transcript_key = Some(path);
+        self
+    }
+
+    /// Writes transcript bundles under `dir`.
+    pub fn with_transcript_dir(mut self, dir: PathBuf) -> Self {
+        self.transcript_dir = Some(dir);
+        self
+    }
+
+    /// Lets runs started with `autoload: true` inject the skills `skills`
+    /// selects for their prompt.
+    pub fn with_skills(mut self, skills: Arc<dyn SkillAutoloader>) -> Self {
+        self.skills = Some(skills);
+        self
+    }
+
+    /// Lets the configured backends that support tool calls use `tools`
+    /// during runs.
+    pub fn with_tools(mut self, tools: ToolLoop) -> Self {
+        for adapter in self.adapters.values_mut() {
+            if let Some(with_tools) = adapter.with_tool_loop(tools.clone()) {
+                *adapter = with_tools;
+            }
+        }
+        self.tools = Some(tools);
+        self.register_declared_backends();
+        self
+    }
+
+    /// Registers the Codex and Claude API adapters with the models and
+    /// timeout from `subagents.toml`, keeping any tool loop already set.
+    fn register_api_backends(&mut self) {
+        let timeout_ms = self
+            .config
+            .timeout_secs
+            .map_or(DEFAULT_TIMEOUT_MS, |secs| secs.saturating_mul(1000));
+        let codex_model = self.config.codex_model.as_deref().unwrap_or(CODEX_MODEL);
+        let claude_model = self.config.claude_model.as_deref().unwrap_or(CLAUDE_MODEL);
+        let api: [(BackendKind, Arc<dyn BackendAdapter>); 2] = [
+            (
+                BackendKind::Codex,
+                Arc::new(CodexAdapter::with_timeout(codex_model.into(), timeout_ms)),
+            ),
+            (
+                BackendKind::Claude,
+                Arc::new(ClaudeAdapter::with_timeout(claude_model.into(), timeout_ms)),
+            ),
+        ];
+        for (kind, adapter) in api {
+            let adapter = self
+                .tools
+                .clone()
+                .and_then(|tools| adapter.with_tool_loop(tools))
+                .unwrap_or(adapter);
+            self.adapters.insert(kind, adapter);
+        }
+    }
+
+    /// Registers an adapter for each `[backends.<name>]` table. Tables that
+    /// fail to build, or whose name belongs to a built-in backend, are
+    /// skipped with a warning.
+    fn register_declared_backends(&mut self) {
+        self.adapters
+            .retain(|kind, _| !matches!(kind, BackendKind::Other(_)));
+        for (name, backend) in &self.config.backends {
+            let kind = backend_from_str(name);
+            let BackendKind::Other(name) = kind else {
+                tracing::warn!(%name, "backend name is reserved for a built-in backend");
+                continue;
+            };
+            match declared_adapter(&name, backend, self.tools.clone()) {
+                Ok(adapter) => {
+                    self.adapters.insert(BackendKind::Other(name), adapter);
+                }
+                Err(err) => tracing::warn!(%name, %err, "skipping backend"),
+            }
+        }
+    }
+
+    /// Templates from agent definitions and config, rescanned on each call
+    /// so edits are picked up without a restart.
+    fn templates(&self) -> Result<Vec<SubagentTemplate>> {
+        load_templates(&self.agent_roots, &self.config, &self.default_backend)
+    }
+
+    fn resolve_template(&self, id: &str) -> Result<Option<SubagentTemplate>> {
+        // "default" names each adapter's built-in template.
+        if id == "default" {
+            return Ok(None);
+        }
+        self.templates()?
+            .into_iter()
+            .find(|t| t.id == id)
+            .map(Some)
+            .ok_or_else(|| anyhow!("unknown template: {id}"))
     }
 
     fn adapter_for(&self, backend: Option<BackendKind>) -> Result<Arc<dyn BackendAdapter>> {
@@ -74,11 +344,13 @@ impl SubagentService {
             "required": ["prompt"],
             "properties": {
                 "prompt": {"type": "string", "description": "User instruction"},
-                "backend": {"type": "string", "description": "codex|claude|other"},
-                "template_id": {"type": "string"},
+                "backend": {"type": "string", "description": "codex|claude|codex-cli|claude-cli, or the name of a [backends.<name>] table in subagents.toml"},
+                "template_id": {"type": "string", "description": "Template id from list_subagents"},
                 "output_schema": {"type": "object"},
                 "tracing": {"type": "boolean"},
                 "stream": {"type": "boolean"},
+                "autoload": {"type": "boolean", "description": "Add the skills relevant to the prompt to the system prompt"},
+                "max_bytes": {"type": "integer", "minimum": 1, "description": "Byte limit for autoloaded skills"},
                 "timeout_ms": {"type": "integer", "minimum": 1, "maximum": 300000}
             }
         })));
@@ -87,9 +359,58 @@ impl SubagentService {
             "required": ["run_id"],
             "properties": {"run_id": {"type": "string"}}
         })));
+        let continue_schema: Arc<JsonObject> = Arc::new(object(json!({
+            "type": "object",
+            "required": ["run_id", "message"],
+            "properties": {
+                "run_id": {"type": "string"},
+                "message": {"type": "string", "description": "Follow-up user turn"}
+            }
+        })));
+        let batch_schema: Arc<JsonObject> = Arc::new(object(json!({
+            "type": "object",
+            "required": ["runs"],
+            "properties": {
+                "runs": {
+                    "type": "array",
+                    "minItems": 1,
+                    "description": "run_subagent arguments for each member run",
+                    "items": {"type": "object", "required": ["prompt"]}
+                },
+                "concurrency": {"type": "integer", "minimum": 1, "maximum": MAX_BATCH_CONCURRENCY},
+                "reducer": {
+                    "type": "object",
+                    "required": ["prompt"],
+                    "description": "Optional final run; the member outputs are appended to its prompt"
+                }
+            }
+        })));
         let history_schema: Arc<JsonObject> = Arc::new(object(json!({
             "type": "object",
-            "properties": {"limit": {"type": "integer", "minimum": 1, "maximum": 50}},
+            "properties": {
+                "limit": {"type": "integer", "minimum": 1, "maximum": 50},
+                "batch_id": {"type": "string", "description": "Only runs from this batch"},
+                "state": {"type": "string", "enum": ["pending", "running", "succeeded", "failed", "canceled", "schema_failed"]},
+                "backend": {"type": "string", "description": "Only runs on this backend"},
+                "days": {"type": "integer", "minimum": 1, "description": "Only runs from the last N days"}
+            },
+        })));
+
+        let usage_schema: Arc<JsonObject> = Arc::new(object(json!({
+            "type": "object",
+            "properties": {
+                "days": {"type": "integer", "minimum": 1, "description": "Only runs from the last N days; all runs when omitted"}
+            },
+        })));
+
+        let transcript_schema: Arc<JsonObject> = Arc::new(object(json!({
+            "type": "object",
+            "required": ["run_id"],
+            "properties": {
+                "run_id": {"type": "string"},
+                "encrypt": {"type": "boolean", "default": true, "description": "Encrypt the transcript files with the local transcript key"},
+                "path": {"type": "string", "description": "File to write, relative to ~/.codex/subagents/transcripts; defaults to <run_id>.json. Existing files are not overwritten"}
+            }
         })));
 
         let run_output_schema: Arc<JsonObject> = Arc::new(object(json!({
@@ -98,7 +419,10 @@ impl SubagentService {
             "properties": {
                 "run_id": {"type": "string"},
                 "status": {"type": "object"},
-                "events": {"type": "array", "items": {"type": "object"}}
+                "events": {"type": "array", "items": {"type": "object"}},
+                "messages": {"type": "array", "items": {"type": "object"}},
+                "result": {"description": "Output parsed and validated against output_schema"},
+                "usage": {"type": "object", "description": "Token counts and cost_usd when the model is priced"}
             }
         })));
         let list_output_schema: Arc<JsonObject> = Arc::new(object(json!({
@@ -127,6 +451,32 @@ impl SubagentService {
                 icons: None,
                 meta: None,
             },
+            Tool {
+                name: "continue_run".into(),
+                title: Some("Continue a subagent run".into()),
+                description: Some(
+                    "Send a follow-up message to a finished run and re-dispatch it with the full conversation"
+                        .into(),
+                ),
+                input_schema: continue_schema,
+                output_schema: Some(run_output_schema.clone()),
+                annotations: None,
+                icons: None,
+                meta: None,
+            },
+            Tool {
+                name: "run_subagent_batch".into(),
+                title: Some("Run a batch of subagents".into()),
+                description: Some(
+                    "Run several subagents with a concurrency limit, wait for all of them and return their outputs, optionally merged by a reducer run"
+                        .into(),
+                ),
+                input_schema: batch_schema,
+                output_schema: None,
+                annotations: None,
+                icons: None,
+                meta: None,
+            },
             Tool {
                 name: "get_run_status".into(),
                 title: Some("Get subagent run status".into()),
@@ -157,6 +507,19 @@ impl SubagentService {
                 icons: None,
                 meta: None,
             },
+            Tool {
+                name: "get_usage_summary".into(),
+                title: Some("Subagent usage summary".into()),
+                description: Some(
+                    "Aggregate token usage and cost of subagent runs by backend, template and day"
+                        .into(),
+                ),
+                input_schema: usage_schema,
+                output_schema: None,
+                annotations: None,
+                icons: None,
+                meta: None,
+            },
         ];
 
         // Codex-only extended tools
@@ -183,8 +546,11 @@ impl SubagentService {
         tools.push(Tool {
             name: "download_transcript_secure".into(),
             title: Some("Download secure transcript".into()),
-            description: Some("Fetch encrypted reasoning transcript (Codex only)".into()),
-            input_schema: Arc::new(JsonObject::default()),
+            description: Some(
+                "Export a finished run as a signed, optionally encrypted transcript bundle (Codex only)"
+                    .into(),
+            ),
+            input_schema: transcript_schema,
             output_schema: None,
             annotations: None,
             icons: None,
@@ -193,19 +559,70 @@ impl SubagentService {
         tools
     }
 
+    /// Settles runs left unfinished by previous servers, then starts the
+    /// run queue and the `on_complete` hook. Only the MCP server calls this,
+    /// when it starts serving; other processes sharing the store leave their
+    /// queued runs to it. Only the first call does anything.
+    pub async fn start(&self) {
+        self.start_hook();
+        let Some(queued) = self.queued.lock().unwrap().take() else {
+            return;
+        };
+        match reconcile(&self.store, self.config.queue.on_restart).await {
+            Ok(requeued) => {
+                for run_id in requeued {
+                    let _ = self.queue.send(run_id);
+                }
+            }
+            Err(err) => tracing::warn!(%err, "could not reconcile unfinished runs"),
+        }
+        let running = self.running.clone();
+        let register: Register = Arc::new(move |handle| register(&running, handle));
+        let workers = self.config.queue.workers.unwrap_or(queue::DEFAULT_WORKERS);
+        tokio::spawn(dispatch(
+            queued,
+            workers,
+            self.store.clone(),
+            self.adapters.clone(),
+            register,
+        ));
+    }
+
+    /// Spawns the `on_complete` hook; tool calls do this on first use.
+    fn start_hook(&self) {
+        if let Some((transitions, command)) = self.hook.lock().unwrap().take() {
+            tokio::spawn(run_completion_hooks(transitions, command));
+        }
+    }
+
     pub async fn handle_call(
         &self,
         name: &str,
         args: Option<&JsonMap<String, Value>>,
     ) -> Result<CallToolResult> {
+        self.handle_call_with_progress(name, args, None).await
+    }
+
+    /// Like [`Self::handle_call`], additionally reporting the state changes
+    /// of the runs the call starts to `progress` until they finish.
+    pub async fn handle_call_with_progress(
+        &self,
+        name: &str,
+        args: Option<&JsonMap<String, Value>>,
+        progress: Option<ProgressSender>,
+    ) -> Result<CallToolResult> {
+        self.start_hook();
         match name {
             "list_subagents" => self.handle_list_subagents().await,
-            "run_subagent" => self.handle_run(false, args).await,
-            "run_subagent_async" => self.handle_run(true, args).await,
+            "run_subagent" => self.handle_run(false, args, progress).await,
+            "run_subagent_async" => self.handle_run(true, args, progress).await,
+            "continue_run" => self.handle_continue(args, progress).await,
+            "run_subagent_batch" => self.handle_batch(args, progress).await,
             "get_run_status" | "get_async_status" => self.handle_status(args).await,
             "stop_run" => self.handle_stop(args).await,
             "get_run_history" => self.handle_history(args).await,
-            "download_transcript_secure" => self.handle_transcript().await,
+            "get_usage_summary" => self.handle_usage(args).await,
+            "download_transcript_secure" => self.handle_transcript(args).await,
             other => Err(anyhow!("unknown tool: {other}")),
         }
     }
@@ -216,6 +633,7 @@ impl SubagentService {
             let mut t = adapter.list_templates().await?;
             templates.append(&mut t);
         }
+        templates.extend(self.templates()?);
         Ok(CallToolResult {
             content: vec![Content::text("listed subagents")],
             structured_content: Some(json!({"templates": templates})),
@@ -228,8 +646,32 @@ impl SubagentService {
         &self,
         async_mode: bool,
         args: Option<&JsonMap<String, Value>>,
+        progress: Option<ProgressSender>,
     ) -> Result<CallToolResult> {
         let args = args.ok_or_else(|| anyhow!("arguments required"))?;
+        let (adapter, request) = self.build_request(args, async_mode)?;
+        let transitions = progress.as_ref().map(|_| self.store.subscribe());
+        let run_id = if async_mode {
+            self.enqueue(request).await?
+        } else {
+            let handle = adapter.start(request, self.store.clone()).await?;
+            let run_id = handle.run_id;
+            self.register(handle);
+            run_id
+        };
+        if let (Some(transitions), Some(progress)) = (transitions, progress) {
+            tokio::spawn(forward_progress(transitions, Watch::Run(run_id), progress));
+        }
+        self.run_result(run_id).await
+    }
+
+    /// Resolves `run_subagent` arguments into a request and the adapter that
+    /// should run it.
+    pub(crate) fn build_request(
+        &self,
+        args: &JsonMap<String, Value>,
+        async_mode: bool,
+    ) -> Result<(Arc<dyn BackendAdapter>, RunRequest)> {
         let prompt = args
             .get("prompt")
             .and_then(|v| v.as_str())
@@ -243,7 +685,15 @@ impl SubagentService {
             .get("template_id")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
-        let output_schema = args.get("output_schema").cloned();
+        let template = template_id
+            .as_deref()
+            .map(|id| self.resolve_template(id))
+            .transpose()?
+            .flatten();
+        let output_schema = args
+            .get("output_schema")
+            .cloned()
+            .or_else(|| template.as_ref().and_then(|t| t.output_schema.clone()));
         let tracing = args
             .get("tracing")
             .and_then(|v| v.as_bool())
@@ -252,8 +702,30 @@ impl SubagentService {
             .get("stream")
             .and_then(|v| v.as_bool())
             .unwrap_or(async_mode);
+        let skills = if args.get("autoload").and_then(|v| v.as_bool()) == Some(true) {
+            let autoloader = self
+                .skills
+                .as_ref()
+                .ok_or_else(|| anyhow!("autoload is not available without skill discovery"))?;
+            let max_bytes = args
+                .get("max_bytes")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize);
+            Some(autoloader.autoload(&prompt, max_bytes)?)
+        } else {
+            None
+        };
 
-        let adapter = self.adapter_for(backend)?;
+        let adapter =
+            self.adapter_for(backend.or_else(|| template.as_ref().map(|t| t.backend.clone())))?;
+        // A template's model names a model of its own provider; drop it when
+        // the run is routed to another one.
+        let template = template.map(|mut t| {
+            if t.backend.provider() != adapter.backend().provider() {
+                t.model = None;
+            }
+            t
+        });
         let request = RunRequest {
             backend: adapter.backend(),
             prompt,
@@ -261,47 +733,274 @@ impl SubagentService {
             output_schema,
             async_mode: stream,
             tracing,
+            template,
+            batch_id: None,
+            skills,
+            owner: None,
+        };
+        Ok((adapter, request))
+    }
+
+    async fn handle_batch(
+        &self,
+        args: Option<&JsonMap<String, Value>>,
+        progress: Option<ProgressSender>,
+    ) -> Result<CallToolResult> {
+        let args = args.ok_or_else(|| anyhow!("arguments required"))?;
+        let items = args
+            .get("runs")
+            .and_then(|v| v.as_array())
+            .filter(|runs| !runs.is_empty())
+            .ok_or_else(|| anyhow!("runs must be a non-empty array"))?;
+        let concurrency = args
+            .get("concurrency")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_BATCH_CONCURRENCY, |v| {
+                v.clamp(1, MAX_BATCH_CONCURRENCY as u64) as usize
+            });
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let mut requests = Vec::with_capacity(items.len());
+        for (index, item) in items.iter().enumerate() {
+            let item = item
+                .as_object()
+                .ok_or_else(|| anyhow!("runs[{index}] must be an object"))?;
+            let (adapter, mut request) = self
+                .build_request(item, false)
+                .map_err(|e| anyhow!("runs[{index}]: {e}"))?;
+            request.batch_id = Some(batch_id.clone());
+            requests.push((adapter, request));
+        }
+        // Validate the reducer before launching anything.
+        let reducer = args
+            .get("reducer")
+            .map(|r| {
+                let r = r
+                    .as_object()
+                    .ok_or_else(|| anyhow!("reducer must be an object"))?;
+                self.build_request(r, false)
+                    .map_err(|e| anyhow!("reducer: {e}"))
+            })
+            .transpose()?;
+
+        let forward = progress.map(|progress| {
+            let watch = Watch::Batch {
+                id: batch_id.clone(),
+                total: requests.len() + usize::from(reducer.is_some()),
+            };
+            tokio::spawn(forward_progress(self.store.subscribe(), watch, progress))
+        });
+        let result = self
+            .finish_batch(batch_id, requests, reducer, concurrency)
+            .await;
+        // On success the forwarder stops by itself after the last run.
+        if let (Err(_), Some(forward)) = (&result, forward) {
+            forward.abort();
+        }
+        result
+    }
+
+    /// Runs the members of a validated batch, then its reducer.
+    async fn finish_batch(
+        &self,
+        batch_id: String,
+        requests: Vec<(Arc<dyn BackendAdapter>, RunRequest)>,
+        reducer: Option<(Arc<dyn BackendAdapter>, RunRequest)>,
+        concurrency: usize,
+    ) -> Result<CallToolResult> {
+        let run_ids = self.run_to_completion(requests, concurrency).await?;
+        let mut runs = Vec::with_capacity(run_ids.len());
+        for run_id in run_ids {
+            if let Some(record) = self.store.get_run(run_id).await? {
+                runs.push(record);
+            }
+        }
+        let succeeded = runs
+            .iter()
+            .filter(|r| r.status.state == RunState::Succeeded)
+            .count();
+
+        let reducer = match reducer {
+            Some((adapter, mut request)) => {
+                request.prompt = reducer_prompt(&request.prompt, &runs);
+                request.batch_id = Some(batch_id.clone());
+                let run_ids = self.run_to_completion(vec![(adapter, request)], 1).await?;
+                self.store.get_run(run_ids[0]).await?
+            }
+            None => None,
         };
-        let run_id = adapter.run(request, self.store.clone()).await?;
-        let status = adapter.get_status(run_id, self.store.clone()).await?;
+
         Ok(CallToolResult {
-            content: vec![Content::text(format!("run_id={run_id}"))],
+            content: vec![Content::text(format!(
+                "batch {batch_id}: {succeeded}/{} runs succeeded",
+                runs.len()
+            ))],
             structured_content: Some(json!({
-                "run_id": run_id,
-                "status": status,
-                "events": self.store.get_run(run_id).await?.map(|r| r.events).unwrap_or_default()
+                "batch_id": batch_id,
+                "succeeded": succeeded,
+                "failed": runs.len() - succeeded,
+                "runs": runs.iter().map(batch_entry).collect::<Vec<_>>(),
+                "reducer": reducer.as_ref().map(batch_entry),
             })),
             is_error: Some(false),
             meta: None,
         })
     }
 
-    async fn handle_status(&self, args: Option<&JsonMap<String, Value>>) -> Result<CallToolResult> {
+    /// Starts `requests` with at most `concurrency` in flight and waits for
+    /// all of them to finish. Runs are registered like any other, so
+    /// `stop_run` can abort individual batch members. When a request fails
+    /// to start, the runs already started are stopped before the error is
+    /// returned.
+    async fn run_to_completion(
+        &self,
+        requests: Vec<(Arc<dyn BackendAdapter>, RunRequest)>,
+        concurrency: usize,
+    ) -> Result<Vec<RunId>> {
+        let (done, mut finished) = mpsc::unbounded_channel();
+        let mut pending = requests.into_iter();
+        let mut run_ids = Vec::new();
+        let mut active = 0;
+        loop {
+            while active < concurrency {
+                let Some((adapter, request)) = pending.next() else {
+                    break;
+                };
+                let handle = match adapter.start(request, self.store.clone()).await {
+                    Ok(handle) => handle,
+                    Err(err) => {
+                        self.stop_all(&run_ids).await;
+                        return Err(err);
+                    }
+                };
+                run_ids.push(handle.run_id);
+                active += 1;
+                self.register(notify_when_done(handle, done.clone()));
+            }
+            if active == 0 {
+                return Ok(run_ids);
+            }
+            // `done` is held here, so the channel never closes.
+            finished.recv().await;
+            active -= 1;
+        }
+    }
+
+    /// Aborts the registered runs among `run_ids` and waits for them.
+    async fn stop_all(&self, run_ids: &[RunId]) {
+        let handles: Vec<RunHandle> = {
+            let mut running = self.running.lock().unwrap();
+            run_ids.iter().filter_map(|id| running.remove(id)).collect()
+        };
+        for handle in handles {
+            handle.cancel.cancel();
+            let _ = handle.task.await;
+        }
+    }
+
+    async fn handle_continue(
+        &self,
+        args: Option<&JsonMap<String, Value>>,
+        progress: Option<ProgressSender>,
+    ) -> Result<CallToolResult> {
         let args = args.ok_or_else(|| anyhow!("arguments required"))?;
-        let run_id_val = args
-            .get("run_id")
-            .ok_or_else(|| anyhow!("run_id is required"))?;
-        let run_id = run_id_from_value(run_id_val)?;
-        let status = self.store.get_status(run_id).await?;
+        let run_id = run_id_from_value(
+            args.get("run_id")
+                .ok_or_else(|| anyhow!("run_id is required"))?,
+        )?;
+        let message = args
+            .get("message")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("message is required"))?;
+        let transitions = progress.as_ref().map(|_| self.store.subscribe());
+        let record = self
+            .store
+            .begin_turn(run_id, ChatMessage::user(message))
+            .await?;
+        let adapter = self.adapter_for(Some(record.request.backend))?;
+        let handle = adapter.resume(run_id, self.store.clone()).await?;
+        self.register(handle);
+        if let (Some(transitions), Some(progress)) = (transitions, progress) {
+            tokio::spawn(forward_progress(transitions, Watch::Run(run_id), progress));
+        }
+        self.run_result(run_id).await
+    }
+
+    /// The store runs are recorded in.
+    pub fn store(&self) -> Arc<dyn RunStore> {
+        self.store.clone()
+    }
+
+    /// Receives the status changes of every run in the store.
+    pub fn subscribe(&self) -> broadcast::Receiver<RunTransition> {
+        self.store.subscribe()
+    }
+
+    fn register(&self, handle: RunHandle) {
+        register(&self.running, handle);
+    }
+
+    /// Records `request` as a queued run and hands it to the dispatcher. In a
+    /// process that never calls [`Self::start`] the run stays queued for the
+    /// next server to pick up.
+    async fn enqueue(&self, request: RunRequest) -> Result<RunId> {
+        let run_id = self.store.create_run(request).await?;
+        self.store
+            .append_event(run_id, owner_event("queued"))
+            .await?;
+        queue::set_state(&self.store, run_id, RunState::Pending, "queued").await?;
+        self.queue
+            .send(run_id)
+            .map_err(|_| anyhow!("run queue is closed"))?;
+        Ok(run_id)
+    }
+
+    async fn run_result(&self, run_id: RunId) -> Result<CallToolResult> {
+        let record = self.store.get_run(run_id).await?;
         Ok(CallToolResult {
-            content: vec![Content::text("status")],
+            content: vec![Content::text(format!("run_id={run_id}"))],
             structured_content: Some(json!({
                 "run_id": run_id,
-                "status": status,
-                "events": self.store.get_run(run_id).await?.map(|r| r.events).unwrap_or_default()
+                "status": record.as_ref().map(|r| &r.status),
+                "events": record.as_ref().map(|r| r.events.as_slice()).unwrap_or_default(),
+                "messages": record.as_ref().map(|r| r.messages.as_slice()).unwrap_or_default(),
+                "result": record.as_ref().and_then(|r| r.result.as_ref()),
+                "usage": record.as_ref().and_then(|r| r.usage.as_ref()).map(|usage| {
+                    let mut value = json!(usage);
+                    value["cost_usd"] = json!(cost(usage, &self.config.prices));
+                    value
+                })
             })),
             is_error: Some(false),
             meta: None,
         })
     }
 
+    async fn handle_status(&self, args: Option<&JsonMap<String, Value>>) -> Result<CallToolResult> {
+        let args = args.ok_or_else(|| anyhow!("arguments required"))?;
+        let run_id_val = args
+            .get("run_id")
+            .ok_or_else(|| anyhow!("run_id is required"))?;
+        let run_id = run_id_from_value(run_id_val)?;
+        let mut result = self.run_result(run_id).await?;
+        result.content = vec![Content::text("status")];
+        Ok(result)
+    }
+
     async fn handle_stop(&self, args: Option<&JsonMap<String, Value>>) -> Result<CallToolResult> {
         let args = args.ok_or_else(|| anyhow!("arguments required"))?;
         let run_id = run_id_from_value(
             args.get("run_id")
                 .ok_or_else(|| anyhow!("run_id is required"))?,
         )?;
-        let stopped = self.store.stop(run_id).await?;
+        let handle = self.running.lock().unwrap().remove(&run_id);
+        let stopped = match handle {
+            Some(handle) => {
+                handle.cancel.cancel();
+                handle.task.await.unwrap_or(false)
+            }
+            // Runs started elsewhere (or before a restart) have no task to abort.
+            None => cancel_run(run_id, &self.store).await?,
+        };
         Ok(CallToolResult {
             content: vec![Content::text("stopped")],
             structured_content: Some(json!({"run_id": run_id, "stopped": stopped})),
@@ -319,7 +1018,23 @@ impl SubagentService {
             .and_then(|v| v.as_u64())
             .map(|v| v as usize)
             .unwrap_or(20);
-        let runs = self.store.history(limit).await?;
+        let arg = |key: &str| args.and_then(|m| m.get(key));
+        let filter = RunFilter {
+            state: arg("state")
+                .and_then(|v| v.as_str())
+                .map(str::parse)
+                .transpose()?,
+            backend: arg("backend")
+                .and_then(|v| v.as_str())
+                .map(backend_from_str),
+            batch_id: arg("batch_id").and_then(|v| v.as_str()).map(String::from),
+            since: arg("days")
+                .and_then(|v| v.as_u64())
+                .map(|days| days_ago(days.clamp(1, u32::MAX as u64) as u32)),
+            limit: Some(limit),
+            ..RunFilter::default()
+        };
+        let runs = self.store.query(&filter).await?;
         Ok(CallToolResult {
             content: vec![Content::text("history")],
             structured_content: Some(json!({"runs": runs})),
@@ -328,10 +1043,96 @@ impl SubagentService {
         })
     }
 
-    async fn handle_transcript(&self) -> Result<CallToolResult> {
+    async fn handle_usage(&self, args: Option<&JsonMap<String, Value>>) -> Result<CallToolResult> {
+        let since = args
+            .and_then(|m| m.get("days"))
+            .and_then(|v| v.as_u64())
+            .map(|days| days_ago(days.clamp(1, u32::MAX as u64) as u32));
+        let runs = self
+            .store
+            .query(&RunFilter {
+                since,
+                without_events: true,
+                ..RunFilter::default()
+            })
+            .await?;
+        let summary = summarize(&runs, &self.config.prices, since);
         Ok(CallToolResult {
-            content: vec![Content::text("secure transcripts are not yet implemented")],
-            structured_content: Some(json!({"status": "unimplemented"})),
+            content: vec![Content::text(summary.render_text())],
+            structured_content: Some(serde_json::to_value(&summary)?),
+            is_error: Some(false),
+            meta: None,
+        })
+    }
+
+    async fn handle_transcript(
+        &self,
+        args: Option<&JsonMap<String, Value>>,
+    ) -> Result<CallToolResult> {
+        let args = args.ok_or_else(|| anyhow!("arguments required"))?;
+        let run_id = run_id_from_value(
+            args.get("run_id")
+                .ok_or_else(|| anyhow!("run_id is required"))?,
+        )?;
+        let encrypt = args
+            .get("encrypt")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let record = self
+            .store
+            .get_run(run_id)
+            .await?
+            .ok_or(SubagentError::NotFound(run_id))?;
+        if !record.status.state.is_terminal() {
+            return Err(SubagentError::InProgress(run_id).into());
+        }
+        let backend = &record.request.backend;
+        if !self
+            .adapter_for(Some(backend.clone()))?
+            .capabilities()
+            .supports_secure_transcript
+        {
+            return Err(anyhow!(
+                "backend {} does not support secure transcripts",
+                backend.name()
+            ));
+        }
+
+        let key_path = match &self.transcript_key {
+            Some(path) => path.clone(),
+            None => default_key_path()?,
+        };
+        let key = TranscriptKey::load_or_create(&key_path)?;
+        let bundle = transcript::export(&record, &key, encrypt)?;
+        let dir = match &self.transcript_dir {
+            Some(dir) => dir.clone(),
+            None => default_transcript_dir()?,
+        };
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(path) => dir.join(relative_transcript_path(path)?),
+            None => dir.join(format!("{run_id}.json")),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("creating {}", path.display()))?;
+        file.write_all(serde_json::to_string_pretty(&bundle)?.as_bytes())?;
+        Ok(CallToolResult {
+            content: vec![Content::text(format!(
+                "wrote transcript of run {run_id} to {}",
+                path.display()
+            ))],
+            structured_content: Some(json!({
+                "run_id": run_id,
+                "path": path,
+                "key_id": bundle.signature.key_id,
+                "encrypted": encrypt,
+                "manifest": bundle.body.manifest,
+            })),
             is_error: Some(false),
             meta: None,
         })
@@ -341,7 +1142,91 @@ impl SubagentService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::store::{MemRunStore, RunState};
+    use crate::backend::{finish_run, spawn_run, AdapterCapabilities};
+    use crate::skills::{LoadedSkill, SkillContext};
+    use crate::store::{RunState, RunStatus};
+    use async_trait::async_trait;
+    use skrills_discovery::SkillSource;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Replies with its prompt after a short delay, tracking how many runs
+    /// are in flight at once.
+    #[derive(Default)]
+    struct EchoAdapter {
+        active: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl BackendAdapter for EchoAdapter {
+        fn backend(&self) -> BackendKind {
+            BackendKind::Other("echo".into())
+        }
+
+        fn capabilities(&self) -> AdapterCapabilities {
+            AdapterCapabilities {
+                supports_schema: false,
+                supports_async: true,
+                supports_tracing: false,
+                supports_secure_transcript: false,
+            }
+        }
+
+        async fn list_templates(&self) -> Result<Vec<SubagentTemplate>> {
+            Ok(Vec::new())
+        }
+
+        async fn start(&self, request: RunRequest, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+            if request.prompt == "unstartable" {
+                return Err(anyhow!("echo cannot start"));
+            }
+            let run_id = store.create_run(request).await?;
+            self.resume(run_id, store).await
+        }
+
+        async fn resume(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<RunHandle> {
+            let (active, peak) = (self.active.clone(), self.peak.clone());
+            let exec_store = store.clone();
+            Ok(spawn_run(run_id, store, async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                let record = exec_store.get_run(run_id).await?.unwrap();
+                if record.request.prompt == "fail" {
+                    return Err(anyhow!("echo refused"));
+                }
+                finish_run(
+                    run_id,
+                    format!("echo: {}", record.request.prompt),
+                    &exec_store,
+                )
+                .await
+            }))
+        }
+
+        async fn get_status(
+            &self,
+            run_id: RunId,
+            store: Arc<dyn RunStore>,
+        ) -> Result<Option<RunStatus>> {
+            store.get_status(run_id).await
+        }
+
+        async fn stop(&self, run_id: RunId, store: Arc<dyn RunStore>) -> Result<bool> {
+            cancel_run(run_id, &store).await
+        }
+
+        async fn history(&self, limit: usize, store: Arc<dyn RunStore>) -> Result<Vec<RunStatus>> {
+            Ok(store
+                .history(limit)
+                .await?
+                .into_iter()
+                .map(|r| r.status)
+                .collect())
+        }
+    }
 
     #[tokio::test]
     async fn tools_include_core_and_extended() {
@@ -354,6 +1239,56 @@ mod tests {
         assert!(names.contains(&"download_transcript_secure"));
     }
 
+    #[tokio::test]
+    async fn tools_attach_to_the_configured_adapters() {
+        struct NoTools;
+
+        #[async_trait]
+        impl crate::tools::ToolProvider for NoTools {
+            async fn list_tools(&self) -> Result<Vec<crate::tools::ToolSpec>> {
+                Ok(Vec::new())
+            }
+
+            async fn call_tool(&self, _: &str, _: Value) -> Result<crate::tools::ToolOutput> {
+                unreachable!()
+            }
+        }
+
+        let mut service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        service.adapters.insert(
+            BackendKind::Codex,
+            Arc::new(CodexAdapter::new("custom-model".into())),
+        );
+        let service = service.with_tools(ToolLoop::new(Arc::new(NoTools)));
+        let templates = service.adapters[&BackendKind::Codex]
+            .list_templates()
+            .await
+            .unwrap();
+        assert_eq!(
+            templates[0].description.as_deref(),
+            Some("Codex model custom-model")
+        );
+    }
+
+    #[tokio::test]
+    async fn config_models_replace_the_built_in_defaults() {
+        let config: SubagentsConfig = toml::from_str(
+            "codex_model = \"gpt-test\"\nclaude_model = \"claude-test\"\ntimeout_secs = 5",
+        )
+        .unwrap();
+        let service = SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex)
+            .unwrap()
+            .with_config(config);
+        for (kind, description) in [
+            (BackendKind::Codex, "Codex model gpt-test"),
+            (BackendKind::Claude, "Claude model claude-test"),
+        ] {
+            let templates = service.adapters[&kind].list_templates().await.unwrap();
+            assert_eq!(templates[0].description.as_deref(), Some(description));
+        }
+    }
+
     #[tokio::test]
     async fn run_and_status_round_trip() {
         let service =
@@ -361,7 +1296,10 @@ mod tests {
         let args = json!({"prompt": "hi", "backend": "codex"})
             .as_object()
             .cloned();
-        let result = service.handle_run(false, args.as_ref()).await.unwrap();
+        let result = service
+            .handle_run(false, args.as_ref(), None)
+            .await
+            .unwrap();
         let run_id = result
             .structured_content
             .as_ref()
@@ -372,4 +1310,503 @@ mod tests {
         let status = service.store.get_status(run_id).await.unwrap().unwrap();
         assert_eq!(status.state, RunState::Running);
     }
+
+    #[tokio::test]
+    async fn stop_aborts_registered_run() {
+        let service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        let args = json!({"prompt": "hi", "backend": "claude"})
+            .as_object()
+            .cloned();
+        let result = service.handle_run(true, args.as_ref(), None).await.unwrap();
+        let run_id = result.structured_content.unwrap()["run_id"].clone();
+
+        let stop_args = json!({ "run_id": run_id }).as_object().cloned();
+        let stopped = service.handle_stop(stop_args.as_ref()).await.unwrap();
+        assert_eq!(stopped.structured_content.unwrap()["stopped"], true);
+        assert!(service.running.lock().unwrap().is_empty());
+
+        let run_id = run_id_from_value(&run_id).unwrap();
+        let record = service.store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Canceled);
+        assert_eq!(record.events.last().unwrap().kind, "canceled");
+
+        let again = service.handle_stop(stop_args.as_ref()).await.unwrap();
+        assert_eq!(again.structured_content.unwrap()["stopped"], false);
+    }
+
+    #[tokio::test]
+    async fn continue_run_appends_a_turn_to_finished_runs() {
+        let service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        let args = json!({"prompt": "draft a plan", "backend": "claude"})
+            .as_object()
+            .cloned();
+        let result = service.handle_run(true, args.as_ref(), None).await.unwrap();
+        let run_id_val = result.structured_content.unwrap()["run_id"].clone();
+        let run_id = run_id_from_value(&run_id_val).unwrap();
+
+        let pending = service
+            .store
+            .create_run(RunRequest {
+                backend: BackendKind::Claude,
+                prompt: "queued".into(),
+                async_mode: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let args = json!({"run_id": pending.to_string(), "message": "add tests"})
+            .as_object()
+            .cloned();
+        let err = service
+            .handle_continue(args.as_ref(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("still in progress"), "{err}");
+
+        let follow_up = json!({"run_id": run_id_val, "message": "add tests"})
+            .as_object()
+            .cloned();
+        let stop_args = json!({ "run_id": run_id_val }).as_object().cloned();
+        service.handle_stop(stop_args.as_ref()).await.unwrap();
+        let result = service
+            .handle_continue(follow_up.as_ref(), None)
+            .await
+            .unwrap();
+        let messages = result.structured_content.unwrap()["messages"].clone();
+        assert_eq!(
+            messages,
+            json!([
+                {"role": "user", "content": "draft a plan"},
+                {"role": "user", "content": "add tests"}
+            ])
+        );
+        assert!(service.running.lock().unwrap().contains_key(&run_id));
+    }
+
+    #[tokio::test]
+    async fn run_applies_agent_template() {
+        let tmp = tempfile::tempdir().unwrap();
+        let agents = tmp.path().join("agents");
+        std::fs::create_dir_all(&agents).unwrap();
+        std::fs::write(
+            agents.join("reviewer.md"),
+            "---\nname: reviewer\ndescription: Reviews diffs\ntools: Read, Grep\n---\nYou review code.",
+        )
+        .unwrap();
+        let service = SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex)
+            .unwrap()
+            .with_agent_roots(vec![SkillRoot {
+                root: agents,
+                source: SkillSource::Claude,
+            }]);
+
+        let listed = service.handle_list_subagents().await.unwrap();
+        let templates = listed.structured_content.unwrap()["templates"].clone();
+        assert!(templates
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["id"] == "reviewer" && t["description"] == "Reviews diffs"));
+
+        let args = json!({"prompt": "check this", "template_id": "reviewer"})
+            .as_object()
+            .cloned();
+        let result = service
+            .handle_run(false, args.as_ref(), None)
+            .await
+            .unwrap();
+        let run_id = run_id_from_value(&result.structured_content.unwrap()["run_id"]).unwrap();
+        let record = service.store.get_run(run_id).await.unwrap().unwrap();
+        // The agent came from a Claude root, so the run routes to Claude.
+        assert_eq!(record.request.backend, BackendKind::Claude);
+        let template = record.request.template.unwrap();
+        assert_eq!(template.system_prompt.as_deref(), Some("You review code."));
+        assert_eq!(template.tools, vec!["Read", "Grep"]);
+
+        let args = json!({"prompt": "x", "template_id": "missing"})
+            .as_object()
+            .cloned();
+        let err = service
+            .handle_run(false, args.as_ref(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown template: missing"));
+    }
+
+    #[tokio::test]
+    async fn batch_runs_with_limited_concurrency_and_reduces() {
+        let echo = EchoAdapter::default();
+        let peak = echo.peak.clone();
+        let mut service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        service
+            .adapters
+            .insert(BackendKind::Other("echo".into()), Arc::new(echo));
+
+        let args = json!({
+            "runs": [
+                {"prompt": "a", "backend": "echo"},
+                {"prompt": "b", "backend": "echo"},
+                {"prompt": "fail", "backend": "echo"},
+                {"prompt": "c", "backend": "echo"},
+                {"prompt": "d", "backend": "echo"}
+            ],
+            "concurrency": 2,
+            "reducer": {"prompt": "merge", "backend": "echo"}
+        })
+        .as_object()
+        .cloned();
+        let result = service.handle_batch(args.as_ref(), None).await.unwrap();
+        let out = result.structured_content.unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(out["succeeded"], 4);
+        assert_eq!(out["failed"], 1);
+        let outputs: Vec<Option<&str>> = out["runs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["output"].as_str())
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                Some("echo: a"),
+                Some("echo: b"),
+                None,
+                Some("echo: c"),
+                Some("echo: d")
+            ]
+        );
+        let reduced = out["reducer"]["output"].as_str().unwrap();
+        assert!(reduced.starts_with("echo: merge\n\nOutputs of 5 subagent runs follow."));
+        assert!(reduced.contains("## Run 2 (echo)\n\necho: b"));
+        assert!(reduced.contains("(run failed: echo refused)"));
+
+        let batch_id = out["batch_id"].clone();
+        let history = json!({"batch_id": batch_id, "limit": 50})
+            .as_object()
+            .cloned();
+        let history = service.handle_history(history.as_ref()).await.unwrap();
+        let runs = history.structured_content.unwrap()["runs"].clone();
+        assert_eq!(runs.as_array().unwrap().len(), 6);
+
+        let bad = json!({"runs": [{"backend": "echo"}]}).as_object().cloned();
+        let err = service.handle_batch(bad.as_ref(), None).await.unwrap_err();
+        assert_eq!(err.to_string(), "runs[0]: prompt is required");
+    }
+
+    #[tokio::test]
+    async fn batch_stops_started_runs_when_a_start_fails() {
+        let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+        let mut service = SubagentService::with_store(store.clone(), BackendKind::Codex).unwrap();
+        service.adapters.insert(
+            BackendKind::Other("echo".into()),
+            Arc::new(EchoAdapter::default()),
+        );
+        let args = json!({
+            "runs": [
+                {"prompt": "a", "backend": "echo"},
+                {"prompt": "unstartable", "backend": "echo"}
+            ],
+            "concurrency": 2
+        })
+        .as_object()
+        .cloned();
+        let err = service.handle_batch(args.as_ref(), None).await.unwrap_err();
+        assert_eq!(err.to_string(), "echo cannot start");
+
+        let runs = store.history(10).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status.state, RunState::Canceled);
+        assert!(service.running.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queue_requeues_interrupted_runs_and_limits_workers() {
+        let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+        let echo = BackendKind::Other("echo".into());
+        let request = |prompt: &str| RunRequest {
+            backend: echo.clone(),
+            prompt: prompt.into(),
+            async_mode: true,
+            ..Default::default()
+        };
+        // Left running by a server that no longer exists.
+        let interrupted = store
+            .create_run(RunRequest {
+                owner: Some(u32::MAX),
+                ..request("before restart")
+            })
+            .await
+            .unwrap();
+        queue::set_state(&store, interrupted, RunState::Running, "dispatched")
+            .await
+            .unwrap();
+
+        let config: SubagentsConfig =
+            toml::from_str("[queue]\nworkers = 1\non_restart = \"requeue\"").unwrap();
+        let adapter = EchoAdapter::default();
+        let peak = adapter.peak.clone();
+        let mut service = SubagentService::with_store(store.clone(), BackendKind::Codex)
+            .unwrap()
+            .with_config(config);
+        service.adapters.insert(echo, Arc::new(adapter));
+        service.start().await;
+
+        let mut run_ids = vec![interrupted];
+        for prompt in ["a", "b"] {
+            let args = json!({"prompt": prompt, "backend": "echo"})
+                .as_object()
+                .cloned();
+            let result = service
+                .handle_call("run_subagent_async", args.as_ref())
+                .await
+                .unwrap();
+            let out = result.structured_content.unwrap();
+            assert_eq!(out["status"]["message"], "queued");
+            run_ids.push(run_id_from_value(&out["run_id"]).unwrap());
+        }
+        let mut states = Vec::new();
+        let finished = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let mut current = Vec::new();
+                for &run_id in &run_ids {
+                    current.push(store.get_status(run_id).await.unwrap().unwrap().state);
+                }
+                states = current;
+                if states.iter().all(RunState::is_terminal) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+        assert!(
+            finished.is_ok(),
+            "runs never finished, last seen {states:?}"
+        );
+        assert_eq!(states, vec![RunState::Succeeded; 3]);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    struct StubSkills;
+
+    impl SkillAutoloader for StubSkills {
+        fn autoload(&self, prompt: &str, max_bytes: Option<usize>) -> Result<SkillContext> {
+            Ok(SkillContext {
+                content: format!("[skills] rust ({prompt}, {max_bytes:?})"),
+                skills: vec![LoadedSkill {
+                    name: "rust/SKILL.md".into(),
+                    hash: "abc123".into(),
+                }],
+                truncated: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn autoload_injects_skills_and_records_their_hashes() {
+        let mut service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        service.adapters.insert(
+            BackendKind::Other("echo".into()),
+            Arc::new(EchoAdapter::default()),
+        );
+        let args =
+            json!({"prompt": "fix it", "backend": "echo", "autoload": true, "max_bytes": 4096})
+                .as_object()
+                .cloned();
+        let err = service
+            .handle_run(false, args.as_ref(), None)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().starts_with("autoload is not available"),
+            "{err}"
+        );
+
+        let service = service.with_skills(Arc::new(StubSkills));
+        let result = service
+            .handle_run(false, args.as_ref(), None)
+            .await
+            .unwrap();
+        let run_id = run_id_from_value(&result.structured_content.unwrap()["run_id"]).unwrap();
+        let record = service.store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(
+            record.request.system_prompt().as_deref(),
+            Some("[skills] rust (fix it, Some(4096))")
+        );
+        let start = crate::backend::start_event_data(&record.request);
+        assert_eq!(
+            start["skills"],
+            json!([{"name": "rust/SKILL.md", "hash": "abc123"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_follows_a_run_until_it_finishes() {
+        let mut service =
+            SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex).unwrap();
+        service.adapters.insert(
+            BackendKind::Other("echo".into()),
+            Arc::new(EchoAdapter::default()),
+        );
+        service.start().await;
+        let (tx, mut updates) = tokio::sync::mpsc::unbounded_channel();
+        let args = json!({"prompt": "hi", "backend": "echo"})
+            .as_object()
+            .cloned();
+        service
+            .handle_call_with_progress("run_subagent_async", args.as_ref(), Some(tx))
+            .await
+            .unwrap();
+
+        let mut messages = Vec::new();
+        while let Some(update) = updates.recv().await {
+            assert_eq!(update.progress, (messages.len() + 1) as f64);
+            messages.push(update.message);
+        }
+        let last = messages.last().unwrap();
+        assert!(last.ends_with("succeeded: completed"), "{messages:?}");
+    }
+
+    #[tokio::test]
+    async fn usage_summary_prices_recorded_tokens() {
+        let config = SubagentsConfig {
+            prices: [(
+                "gpt-4o-mini".to_string(),
+                crate::usage::ModelPrice {
+                    input: 0.15,
+                    output: 0.6,
+                    cached_input: None,
+                    cache_write: None,
+                },
+            )]
+            .into(),
+            ..SubagentsConfig::default()
+        };
+        let service = SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex)
+            .unwrap()
+            .with_config(config);
+        let mut runs = Vec::new();
+        for template_id in [Some("reviewer"), None] {
+            let run_id = service
+                .store
+                .create_run(RunRequest {
+                    prompt: "review".into(),
+                    template_id: template_id.map(String::from),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            service
+                .store
+                .add_usage(
+                    run_id,
+                    crate::store::RunUsage {
+                        model: Some("gpt-4o-mini-2024-07-18".into()),
+                        input_tokens: 1_000_000,
+                        output_tokens: 500_000,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            runs.push(run_id);
+        }
+
+        let args = json!({"run_id": runs[0].to_string()}).as_object().cloned();
+        let status = service
+            .handle_call("get_run_status", args.as_ref())
+            .await
+            .unwrap()
+            .structured_content
+            .unwrap();
+        assert_eq!(status["usage"]["input_tokens"], 1_000_000);
+        assert_eq!(status["usage"]["cost_usd"], 0.45);
+
+        let args = json!({"days": 1}).as_object().cloned();
+        let summary = service
+            .handle_call("get_usage_summary", args.as_ref())
+            .await
+            .unwrap()
+            .structured_content
+            .unwrap();
+        assert_eq!(summary["total"]["runs"], 2);
+        assert_eq!(summary["total"]["cost_usd"], 0.9);
+        assert_eq!(summary["by_backend"]["codex"]["output_tokens"], 1_000_000);
+        assert_eq!(summary["by_template"]["reviewer"]["runs"], 1);
+        assert_eq!(summary["by_template"]["(none)"]["runs"], 1);
+    }
+
+    #[tokio::test]
+    async fn transcript_export_writes_a_verifiable_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("transcript.key");
+        let service = SubagentService::with_store(Arc::new(MemRunStore::new()), BackendKind::Codex)
+            .unwrap()
+            .with_transcript_key(key_path.clone())
+            .with_transcript_dir(dir.path().join("transcripts"));
+        let run_id = service
+            .store
+            .create_run(RunRequest {
+                prompt: "summarize".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let path = dir.path().join("transcripts/out/bundle.json");
+        let args = json!({"run_id": run_id.to_string(), "path": "out/bundle.json"})
+            .as_object()
+            .cloned();
+        let err = service
+            .handle_call("download_transcript_secure", args.as_ref())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("in progress"), "{err}");
+
+        finish_run(run_id, "summary".into(), &service.store)
+            .await
+            .unwrap();
+        let result = service
+            .handle_call("download_transcript_secure", args.as_ref())
+            .await
+            .unwrap()
+            .structured_content
+            .unwrap();
+        assert_eq!(result["encrypted"], true);
+
+        let key = TranscriptKey::load(&key_path).unwrap();
+        let verified = transcript::verify(&std::fs::read_to_string(&path).unwrap(), &key).unwrap();
+        assert_eq!(verified.run_id, run_id);
+        assert_eq!(verified.files["completion.txt"], "summary");
+
+        let err = service
+            .handle_call("download_transcript_secure", args.as_ref())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("creating"), "{err}");
+        for escape in [
+            "../bundle.json",
+            "/tmp/bundle.json",
+            "out/../../bundle.json",
+        ] {
+            let args = json!({"run_id": run_id.to_string(), "path": escape})
+                .as_object()
+                .cloned();
+            let err = service
+                .handle_call("download_transcript_secure", args.as_ref())
+                .await
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("must be relative"),
+                "{escape}: {err}"
+            );
+        }
+        assert!(!dir.path().join("bundle.json").exists());
+    }
 }
@@ -0,0 +1,35 @@
+//! Skills autoloaded into subagent prompts.
+//!
+//! Runs started with `autoload: true` ask the [`SkillAutoloader`] (the
+//! skrills server's autoload pipeline) for the skills relevant to their
+//! prompt. The rendered skills travel with the run's request as system
+//! context, and their names and hashes are recorded in the run's `start`
+//! event so the run can be reproduced.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// A skill injected into a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LoadedSkill {
+    pub name: String,
+    /// Hash of the `SKILL.md` contents that were injected.
+    pub hash: String,
+}
+
+/// Skills rendered for one prompt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkillContext {
+    /// Text appended to the run's system prompt.
+    pub content: String,
+    pub skills: Vec<LoadedSkill>,
+    /// Whether `max_bytes` forced the content down to a manifest.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+pub trait SkillAutoloader: Send + Sync {
+    /// Renders the skills relevant to `prompt`, within `max_bytes` when
+    /// given.
+    fn autoload(&self, prompt: &str, max_bytes: Option<usize>) -> Result<SkillContext>;
+}
@@ -0,0 +1,938 @@
+//! SQLite-backed run store.
+//!
+//! Runs live in a `runs` table with their state, backend, batch and
+//! timestamps as indexed columns; events are rows in an `events` table, so
+//! streaming a completion appends rows instead of rewriting every run. The
+//! database is opened in WAL mode with a busy timeout, which lets the MCP
+//! server and the `skrills subagents` commands share it. Queries run on
+//! tokio's blocking pool, so waiting out another process's lock does not
+//! stall the async runtime.
+//!
+//! On first use the runs of the older `runs.json` store are imported with
+//! [`SqliteRunStore::migrate_json`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use skrills_state::home_dir;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::store::{
+    default_store_path, ChatMessage, RunEvent, RunFilter, RunId, RunRecord, RunRequest, RunState,
+    RunStatus, RunStore, RunTransition, RunUsage, SubagentError, Transitions,
+};
+use crate::templates::SubagentsConfig;
+use crate::usage::days_ago;
+
+/// Bumped whenever [`SCHEMA`] changes; stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id TEXT PRIMARY KEY,
+    backend TEXT NOT NULL,
+    template_id TEXT,
+    batch_id TEXT,
+    state TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    request TEXT NOT NULL,
+    status TEXT NOT NULL,
+    messages TEXT NOT NULL,
+    result TEXT,
+    usage TEXT
+);
+CREATE INDEX IF NOT EXISTS runs_created_at ON runs (created_at);
+CREATE INDEX IF NOT EXISTS runs_state ON runs (state, created_at);
+CREATE INDEX IF NOT EXISTS runs_backend ON runs (backend, created_at);
+CREATE INDEX IF NOT EXISTS runs_batch ON runs (batch_id);
+CREATE TABLE IF NOT EXISTS events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+    ts INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    data TEXT
+);
+CREATE INDEX IF NOT EXISTS events_run ON events (run_id, seq);
+";
+
+const RUN_COLUMNS: &str = "id, created_at, updated_at, request, status, messages, result, usage";
+
+/// How long a writer waits for another process holding the database lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TERMINAL_STATES: [RunState; 4] = [
+    RunState::Succeeded,
+    RunState::Failed,
+    RunState::Canceled,
+    RunState::SchemaFailed,
+];
+
+/// Default location of the run database.
+pub fn default_db_path() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".codex/subagents/runs.db"))
+}
+
+/// Opens the default database, importing the legacy `runs.json` and
+/// applying `retention_days` from `config`. A failed import or prune is
+/// logged and skipped, so a corrupt `runs.json` does not block the store.
+pub fn open_default(config: &SubagentsConfig) -> Result<SqliteRunStore> {
+    open_with_legacy(&default_db_path()?, default_store_path(), config)
+}
+
+/// [`open_default`] for the database at `path` and the legacy store at
+/// `legacy`.
+fn open_with_legacy(
+    path: &Path,
+    legacy: Result<PathBuf>,
+    config: &SubagentsConfig,
+) -> Result<SqliteRunStore> {
+    let store = SqliteRunStore::open(path)?;
+    match legacy.and_then(|legacy| store.migrate_json(&legacy)) {
+        Ok(0) => {}
+        Ok(imported) => tracing::info!(imported, "migrated subagent runs from runs.json"),
+        Err(err) => tracing::warn!(%err, "skipping migration of runs.json"),
+    }
+    if let Some(days) = config.retention_days {
+        match store.prune(days_ago(days)) {
+            Ok(0) => {}
+            Ok(pruned) => tracing::debug!(pruned, days, "pruned old subagent runs"),
+            Err(err) => tracing::warn!(%err, "skipping retention of subagent runs"),
+        }
+    }
+    Ok(store)
+}
+
+pub struct SqliteRunStore {
+    conn: Arc<Mutex<Connection>>,
+    transitions: Transitions,
+}
+
+impl SqliteRunStore {
+    /// Opens (creating if needed) the database at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).with_context(|| format!("opening {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    /// A private in-memory database, for tests.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version < SCHEMA_VERSION {
+            conn.execute_batch(SCHEMA)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            transitions: Transitions::default(),
+        })
+    }
+
+    /// Imports the runs of a `runs.json` file and renames it to
+    /// `runs.json.migrated`, so the import happens once. Runs already in the
+    /// database are kept as they are. Returns the number of runs imported.
+    pub fn migrate_json(&self, path: &Path) -> Result<usize> {
+        if !path.exists() {
+            return Ok(0);
+        }
+        let text = fs::read_to_string(path)?;
+        let records: Vec<RunRecord> =
+            serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        let mut imported = 0;
+        {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for record in &records {
+                let exists = tx
+                    .query_row(
+                        "SELECT 1 FROM runs WHERE id = ?1",
+                        [record.id.to_string()],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                if exists {
+                    continue;
+                }
+                save_run(&tx, record)?;
+                for event in &record.events {
+                    insert_event(&tx, record.id, event)?;
+                }
+                imported += 1;
+            }
+            tx.commit()?;
+        }
+        let mut migrated = path.as_os_str().to_owned();
+        migrated.push(".migrated");
+        fs::rename(path, &migrated)?;
+        Ok(imported)
+    }
+
+    /// Deletes finished runs last updated before `before`, with their
+    /// events. Returns the number of runs removed.
+    pub fn prune(&self, before: OffsetDateTime) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let states = TERMINAL_STATES
+            .iter()
+            .map(|s| to_json(s).map(SqlValue::Text))
+            .collect::<Result<Vec<_>>>()?;
+        let placeholders = vec!["?"; states.len()].join(", ");
+        let mut params = vec![SqlValue::Integer(nanos(before))];
+        params.extend(states);
+        let removed = conn.execute(
+            &format!("DELETE FROM runs WHERE updated_at < ? AND state IN ({placeholders})"),
+            params_from_iter(params),
+        )?;
+        Ok(removed)
+    }
+
+    /// Runs `f` with the connection on the blocking pool.
+    async fn with_conn<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap())).await?
+    }
+
+    /// Loads a run, applies `change` and writes it back in one transaction.
+    async fn update<T: Send + 'static>(
+        &self,
+        run_id: RunId,
+        with_events: bool,
+        change: impl FnOnce(&mut RunRecord) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let mut record =
+                load_run(&tx, run_id, with_events)?.ok_or(SubagentError::NotFound(run_id))?;
+            let out = change(&mut record)?;
+            save_run(&tx, &record)?;
+            tx.commit()?;
+            Ok(out)
+        })
+        .await
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(match serde_json::to_value(value)? {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    })
+}
+
+fn from_json<T: DeserializeOwned>(text: &str) -> Result<T> {
+    Ok(serde_json::from_str(text)?)
+}
+
+fn nanos(ts: OffsetDateTime) -> i64 {
+    i64::try_from(ts.unix_timestamp_nanos()).unwrap_or(i64::MAX)
+}
+
+fn from_nanos(nanos: i64) -> Result<OffsetDateTime> {
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(nanos.into())?)
+}
+
+/// Reads one `RUN_COLUMNS` row; events are loaded separately.
+fn read_run(row: &rusqlite::Row<'_>) -> rusqlite::Result<Result<RunRecord>> {
+    let id: String = row.get(0)?;
+    let created_at: i64 = row.get(1)?;
+    let updated_at: i64 = row.get(2)?;
+    let request: String = row.get(3)?;
+    let status: String = row.get(4)?;
+    let messages: String = row.get(5)?;
+    let result: Option<String> = row.get(6)?;
+    let usage: Option<String> = row.get(7)?;
+    Ok((|| {
+        Ok(RunRecord {
+            id: RunId(Uuid::parse_str(&id)?),
+            request: from_json(&request)?,
+            status: from_json(&status)?,
+            events: Vec::new(),
+            messages: from_json(&messages)?,
+            result: result.as_deref().map(from_json).transpose()?,
+            usage: usage.as_deref().map(from_json).transpose()?,
+            created_at: from_nanos(created_at)?,
+            updated_at: from_nanos(updated_at)?,
+        })
+    })())
+}
+
+/// Runs matching `filter`, newest first; ties on `created_at` keep insertion
+/// order, newest first.
+fn select(conn: &Connection, filter: &RunFilter) -> Result<Vec<RunRecord>> {
+    let mut clauses = Vec::new();
+    let mut params = Vec::new();
+    if let Some(state) = &filter.state {
+        clauses.push("state = ?");
+        params.push(SqlValue::Text(to_json(state)?));
+    }
+    if let Some(backend) = &filter.backend {
+        clauses.push("backend = ?");
+        params.push(SqlValue::Text(backend.name().to_string()));
+    }
+    if let Some(batch_id) = &filter.batch_id {
+        clauses.push("batch_id = ?");
+        params.push(SqlValue::Text(batch_id.clone()));
+    }
+    if let Some(prefix) = &filter.batch_prefix {
+        clauses.push("substr(batch_id, 1, length(?)) = ?");
+        params.push(SqlValue::Text(prefix.clone()));
+        params.push(SqlValue::Text(prefix.clone()));
+    }
+    if let Some(since) = filter.since {
+        clauses.push("created_at >= ?");
+        params.push(SqlValue::Integer(nanos(since)));
+    }
+    if let Some(until) = filter.until {
+        clauses.push("created_at < ?");
+        params.push(SqlValue::Integer(nanos(until)));
+    }
+    let mut sql = format!("SELECT {RUN_COLUMNS} FROM runs");
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(" ORDER BY created_at DESC, rowid DESC");
+    if let Some(limit) = filter.limit {
+        sql.push_str(" LIMIT ?");
+        params.push(SqlValue::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+    }
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(params), read_run)?;
+    let mut runs = Vec::new();
+    for row in rows {
+        let mut record = row??;
+        if !filter.without_events {
+            record.events = load_events(conn, record.id)?;
+        }
+        runs.push(record);
+    }
+    Ok(runs)
+}
+
+fn load_run(conn: &Connection, run_id: RunId, with_events: bool) -> Result<Option<RunRecord>> {
+    let record = conn
+        .query_row(
+            &format!("SELECT {RUN_COLUMNS} FROM runs WHERE id = ?1"),
+            [run_id.to_string()],
+            read_run,
+        )
+        .optional()?
+        .transpose()?;
+    let Some(mut record) = record else {
+        return Ok(None);
+    };
+    if with_events {
+        record.events = load_events(conn, run_id)?;
+    }
+    Ok(Some(record))
+}
+
+fn load_events(conn: &Connection, run_id: RunId) -> Result<Vec<RunEvent>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT ts, kind, data FROM events WHERE run_id = ?1 ORDER BY seq")?;
+    let rows = stmt.query_map([run_id.to_string()], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+        ))
+    })?;
+    let mut events = Vec::new();
+    for row in rows {
+        let (ts, kind, data) = row?;
+        events.push(RunEvent {
+            ts: from_nanos(ts)?,
+            kind,
+            data: data.as_deref().map(from_json).transpose()?,
+        });
+    }
+    Ok(events)
+}
+
+/// Inserts or updates the run row; events are stored separately.
+fn save_run(conn: &Connection, record: &RunRecord) -> Result<()> {
+    conn.execute(
+        "INSERT INTO runs (id, backend, template_id, batch_id, state, created_at, updated_at,
+                           request, status, messages, result, usage)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+         ON CONFLICT (id) DO UPDATE SET
+             state = excluded.state,
+             updated_at = excluded.updated_at,
+             status = excluded.status,
+             messages = excluded.messages,
+             result = excluded.result,
+             usage = excluded.usage",
+        params![
+            record.id.to_string(),
+            record.request.backend.name(),
+            record.request.template_id,
+            record.request.batch_id,
+            to_json(&record.status.state)?,
+            nanos(record.created_at),
+            nanos(record.updated_at),
+            serde_json::to_string(&record.request)?,
+            serde_json::to_string(&record.status)?,
+            serde_json::to_string(&record.messages)?,
+            record
+                .result
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            record
+                .usage
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_event(conn: &Connection, run_id: RunId, event: &RunEvent) -> Result<()> {
+    conn.prepare_cached("INSERT INTO events (run_id, ts, kind, data) VALUES (?1, ?2, ?3, ?4)")?
+        .execute(params![
+            run_id.to_string(),
+            nanos(event.ts),
+            event.kind,
+            event.data.as_ref().map(serde_json::to_string).transpose()?,
+        ])?;
+    Ok(())
+}
+
+#[async_trait]
+impl RunStore for SqliteRunStore {
+    async fn create_run(&self, mut request: RunRequest) -> Result<RunId> {
+        request.owner.get_or_insert_with(std::process::id);
+        let now = OffsetDateTime::now_utc();
+        let id = RunId(Uuid::new_v4());
+        let messages = vec![ChatMessage::user(&request.prompt)];
+        let record = RunRecord {
+            id,
+            request,
+            status: RunStatus {
+                state: RunState::Pending,
+                message: None,
+                updated_at: now,
+            },
+            events: Vec::new(),
+            messages,
+            result: None,
+            usage: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let transition = RunTransition::of(&record);
+        self.with_conn(move |conn| save_run(conn, &record)).await?;
+        self.transitions.publish(transition);
+        Ok(id)
+    }
+
+    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<bool> {
+        let transition = self
+            .update(run_id, false, |record| {
+                if record.status.state.is_terminal() {
+                    return Ok(None);
+                }
+                record.updated_at = status.updated_at;
+                record.status = status;
+                Ok(Some(RunTransition::of(record)))
+            })
+            .await?;
+        let updated = transition.is_some();
+        if let Some(transition) = transition {
+            self.transitions.publish(transition);
+        }
+        Ok(updated)
+    }
+
+    async fn append_event(&self, run_id: RunId, event: RunEvent) -> Result<()> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let updated = tx.execute(
+                "UPDATE runs SET updated_at = ?1 WHERE id = ?2",
+                params![nanos(event.ts), run_id.to_string()],
+            )?;
+            if updated == 0 {
+                return Err(SubagentError::NotFound(run_id).into());
+            }
+            insert_event(&tx, run_id, &event)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn append_message(&self, run_id: RunId, message: ChatMessage) -> Result<()> {
+        self.update(run_id, false, |record| {
+            record.messages.push(message);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_result(&self, run_id: RunId, result: serde_json::Value) -> Result<()> {
+        self.update(run_id, false, |record| {
+            record.result = Some(result);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn complete(
+        &self,
+        run_id: RunId,
+        event: RunEvent,
+        message: ChatMessage,
+        result: Option<serde_json::Value>,
+        status: RunStatus,
+    ) -> Result<bool> {
+        let transition = self
+            .with_conn(move |conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let mut record =
+                    load_run(&tx, run_id, false)?.ok_or(SubagentError::NotFound(run_id))?;
+                if !record.complete(message, result, status) {
+                    return Ok(None);
+                }
+                save_run(&tx, &record)?;
+                insert_event(&tx, run_id, &event)?;
+                tx.commit()?;
+                Ok(Some(RunTransition::of(&record)))
+            })
+            .await?;
+        let completed = transition.is_some();
+        if let Some(transition) = transition {
+            self.transitions.publish(transition);
+        }
+        Ok(completed)
+    }
+
+    async fn add_usage(&self, run_id: RunId, usage: RunUsage) -> Result<()> {
+        self.update(run_id, false, |record| {
+            record
+                .usage
+                .get_or_insert_with(RunUsage::default)
+                .add(usage);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn begin_turn(&self, run_id: RunId, message: ChatMessage) -> Result<RunRecord> {
+        // Events are needed to rebuild the conversation of runs recorded
+        // before messages were.
+        let record = self
+            .update(run_id, true, |record| {
+                record.begin_turn(message)?;
+                Ok(record.clone())
+            })
+            .await?;
+        self.transitions.publish(RunTransition::of(&record));
+        Ok(record)
+    }
+
+    async fn get_run(&self, run_id: RunId) -> Result<Option<RunRecord>> {
+        self.with_conn(move |conn| load_run(conn, run_id, true))
+            .await
+    }
+
+    async fn get_status(&self, run_id: RunId) -> Result<Option<RunStatus>> {
+        self.with_conn(move |conn| {
+            let status: Option<String> = conn
+                .query_row(
+                    "SELECT status FROM runs WHERE id = ?1",
+                    [run_id.to_string()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            status.as_deref().map(from_json).transpose()
+        })
+        .await
+    }
+
+    async fn history(&self, limit: usize) -> Result<Vec<RunRecord>> {
+        self.query(&RunFilter {
+            limit: Some(limit),
+            ..RunFilter::default()
+        })
+        .await
+    }
+
+    async fn query(&self, filter: &RunFilter) -> Result<Vec<RunRecord>> {
+        let filter = filter.clone();
+        self.with_conn(move |conn| select(conn, &filter)).await
+    }
+
+    async fn stop(&self, run_id: RunId) -> Result<bool> {
+        let transition = self
+            .update(run_id, false, |record| {
+                if record.status.state.is_terminal() {
+                    return Ok(None);
+                }
+                let now = OffsetDateTime::now_utc();
+                record.status = RunStatus {
+                    state: RunState::Canceled,
+                    message: Some("stopped by user".into()),
+                    updated_at: now,
+                };
+                record.updated_at = now;
+                Ok(Some(RunTransition::of(record)))
+            })
+            .await?;
+        let stopped = transition.is_some();
+        if let Some(transition) = transition {
+            self.transitions.publish(transition);
+        }
+        Ok(stopped)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<RunTransition> {
+        self.transitions.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BackendKind, StateRunStore};
+    use serde_json::json;
+
+    fn request(backend: BackendKind, batch_id: Option<&str>) -> RunRequest {
+        RunRequest {
+            backend,
+            prompt: "summarize".into(),
+            batch_id: batch_id.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn status(state: RunState) -> RunStatus {
+        RunStatus {
+            state,
+            message: None,
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn event(kind: &str) -> RunEvent {
+        RunEvent {
+            ts: OffsetDateTime::now_utc(),
+            kind: kind.into(),
+            data: Some(json!({"kind": kind})),
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_round_trip_with_events_and_terminal_states_stick() {
+        let store = SqliteRunStore::in_memory().unwrap();
+        let run_id = store
+            .create_run(request(BackendKind::Codex, None))
+            .await
+            .unwrap();
+        store.append_event(run_id, event("start")).await.unwrap();
+        store.append_event(run_id, event("stream")).await.unwrap();
+        store
+            .append_message(run_id, ChatMessage::assistant("done"))
+            .await
+            .unwrap();
+        store
+            .add_usage(
+                run_id,
+                RunUsage {
+                    input_tokens: 3,
+                    ..RunUsage::default()
+                },
+            )
+            .await
+            .unwrap();
+        store
+            .update_status(run_id, status(RunState::Succeeded))
+            .await
+            .unwrap();
+        store
+            .update_status(run_id, status(RunState::Failed))
+            .await
+            .unwrap();
+        assert!(!store.stop(run_id).await.unwrap());
+
+        let record = store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Succeeded);
+        let kinds: Vec<_> = record.events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["start", "stream"]);
+        assert_eq!(record.events[1].data, Some(json!({"kind": "stream"})));
+        assert_eq!(record.messages.len(), 2);
+        assert_eq!(record.usage.unwrap().input_tokens, 3);
+
+        let reopened = store
+            .begin_turn(run_id, ChatMessage::user("again"))
+            .await
+            .unwrap();
+        assert_eq!(reopened.status.state, RunState::Running);
+        assert_eq!(reopened.events.len(), 2);
+        assert!(store
+            .append_event(RunId(Uuid::new_v4()), event("x"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn queries_filter_by_state_backend_batch_and_date() {
+        let store = SqliteRunStore::in_memory().unwrap();
+        let codex = store
+            .create_run(request(BackendKind::Codex, Some("b1")))
+            .await
+            .unwrap();
+        let claude = store
+            .create_run(request(BackendKind::Claude, Some("b1")))
+            .await
+            .unwrap();
+        let cli = store
+            .create_run(request(BackendKind::CodexCli, None))
+            .await
+            .unwrap();
+        store
+            .update_status(claude, status(RunState::Succeeded))
+            .await
+            .unwrap();
+
+        let ids = |runs: Vec<RunRecord>| runs.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        let all = store.history(10).await.unwrap();
+        assert_eq!(ids(all.clone()), vec![cli, claude, codex]);
+        let query = |filter: RunFilter| {
+            let store = &store;
+            async move { ids(store.query(&filter).await.unwrap()) }
+        };
+        assert_eq!(
+            query(RunFilter {
+                batch_id: Some("b1".into()),
+                ..RunFilter::default()
+            })
+            .await,
+            vec![claude, codex]
+        );
+        assert_eq!(
+            query(RunFilter {
+                batch_prefix: Some("b".into()),
+                ..RunFilter::default()
+            })
+            .await,
+            vec![claude, codex]
+        );
+        assert!(query(RunFilter {
+            batch_prefix: Some("b1x".into()),
+            ..RunFilter::default()
+        })
+        .await
+        .is_empty());
+        assert_eq!(
+            query(RunFilter {
+                state: Some(RunState::Pending),
+                backend: Some(BackendKind::Codex),
+                ..RunFilter::default()
+            })
+            .await,
+            vec![codex]
+        );
+        assert_eq!(
+            query(RunFilter {
+                since: Some(all[1].created_at),
+                limit: Some(1),
+                ..RunFilter::default()
+            })
+            .await,
+            vec![cli]
+        );
+        assert!(query(RunFilter {
+            until: Some(all[2].created_at),
+            ..RunFilter::default()
+        })
+        .await
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn runs_created_at_the_same_instant_list_newest_first() {
+        let store = SqliteRunStore::in_memory().unwrap();
+        let now = OffsetDateTime::now_utc();
+        let records: Vec<RunRecord> = (0..3)
+            .map(|_| RunRecord {
+                id: RunId(Uuid::new_v4()),
+                request: request(BackendKind::Codex, None),
+                status: status(RunState::Pending),
+                events: Vec::new(),
+                messages: Vec::new(),
+                result: None,
+                usage: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+        for record in &records {
+            save_run(&store.conn.lock().unwrap(), record).unwrap();
+        }
+        store
+            .append_event(records[0].id, event("start"))
+            .await
+            .unwrap();
+
+        let listed = store.history(10).await.unwrap();
+        let ids: Vec<_> = listed.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![records[2].id, records[1].id, records[0].id]);
+        assert_eq!(listed[2].events.len(), 1);
+
+        let rows = store
+            .query(&RunFilter {
+                without_events: true,
+                ..RunFilter::default()
+            })
+            .await
+            .unwrap();
+        assert!(rows.iter().all(|r| r.events.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn completing_a_stopped_run_writes_nothing() {
+        let store = SqliteRunStore::in_memory().unwrap();
+        let run_id = store
+            .create_run(request(BackendKind::Codex, None))
+            .await
+            .unwrap();
+
+        assert!(store.stop(run_id).await.unwrap());
+        assert!(!store
+            .complete(
+                run_id,
+                event("completion"),
+                ChatMessage::assistant("done"),
+                Some(json!({ "ok": true })),
+                status(RunState::Succeeded),
+            )
+            .await
+            .unwrap());
+        let record = store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Canceled);
+        assert!(record.events.is_empty());
+        assert_eq!(record.messages.len(), 1);
+        assert!(record.result.is_none());
+
+        let other = store
+            .create_run(request(BackendKind::Codex, None))
+            .await
+            .unwrap();
+        assert!(store
+            .complete(
+                other,
+                event("completion"),
+                ChatMessage::assistant("done"),
+                None,
+                status(RunState::Succeeded),
+            )
+            .await
+            .unwrap());
+        let record = store.get_run(other).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Succeeded);
+        assert_eq!(record.events.len(), 1);
+        assert_eq!(record.completion_text(), Some("done"));
+        assert!(!store.stop(other).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn prune_removes_old_finished_runs_only() {
+        let store = SqliteRunStore::in_memory().unwrap();
+        let done = store
+            .create_run(request(BackendKind::Codex, None))
+            .await
+            .unwrap();
+        store.append_event(done, event("start")).await.unwrap();
+        store
+            .update_status(done, status(RunState::Failed))
+            .await
+            .unwrap();
+        let running = store
+            .create_run(request(BackendKind::Codex, None))
+            .await
+            .unwrap();
+
+        let later = OffsetDateTime::now_utc() + time::Duration::minutes(1);
+        assert_eq!(store.prune(later).unwrap(), 1);
+        assert!(store.get_run(done).await.unwrap().is_none());
+        assert!(store.get_run(running).await.unwrap().is_some());
+        let orphans: i64 = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+
+    #[tokio::test]
+    async fn json_store_is_migrated_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("runs.json");
+        let legacy = StateRunStore::new(json_path.clone()).unwrap();
+        let run_id = legacy
+            .create_run(request(BackendKind::Claude, None))
+            .await
+            .unwrap();
+        legacy.append_event(run_id, event("start")).await.unwrap();
+        legacy
+            .update_status(run_id, status(RunState::Succeeded))
+            .await
+            .unwrap();
+
+        let db_path = dir.path().join("runs.db");
+        let store = SqliteRunStore::open(&db_path).unwrap();
+        assert_eq!(store.migrate_json(&json_path).unwrap(), 1);
+        assert!(!json_path.exists());
+        assert!(dir.path().join("runs.json.migrated").exists());
+        assert_eq!(store.migrate_json(&json_path).unwrap(), 0);
+
+        // A second handle, as another skrills process would open it.
+        let other = SqliteRunStore::open(&db_path).unwrap();
+        let record = other.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(record.status.state, RunState::Succeeded);
+        assert_eq!(record.events.len(), 1);
+        assert_eq!(record.request.backend, BackendKind::Claude);
+    }
+
+    #[tokio::test]
+    async fn a_corrupt_runs_json_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("runs.json");
+        fs::write(&legacy, "[{ not json").unwrap();
+
+        let store = open_with_legacy(
+            &dir.path().join("runs.db"),
+            Ok(legacy.clone()),
+            &Default::default(),
+        )
+        .unwrap();
+
+        assert!(store.history(10).await.unwrap().is_empty());
+        assert!(legacy.exists());
+    }
+}
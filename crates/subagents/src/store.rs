@@ -9,17 +9,82 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use skrills_state::home_dir;
 use std::fmt;
+use std::str::FromStr;
 use std::sync::Mutex;
 use time::OffsetDateTime;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+use crate::skills::SkillContext;
+
+/// Serialized as its [`BackendKind::name`], which
+/// [`crate::templates::backend_from_str`] accepts; the older `"Codex"` and
+/// `{"Other": "name"}` forms still deserialize, for stores written before that.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum BackendKind {
+    #[default]
     Codex,
     Claude,
+    /// Local `codex exec` process.
+    CodexCli,
+    /// Local `claude -p` process.
+    ClaudeCli,
     Other(String),
 }
 
+impl Serialize for BackendKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for BackendKind {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Name(String),
+            Other {
+                #[serde(alias = "Other")]
+                other: String,
+            },
+        }
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Name(name) => match name.as_str() {
+                "codex" | "Codex" => BackendKind::Codex,
+                "claude" | "Claude" => BackendKind::Claude,
+                "codex-cli" | "CodexCli" => BackendKind::CodexCli,
+                "claude-cli" | "ClaudeCli" => BackendKind::ClaudeCli,
+                _ => BackendKind::Other(name),
+            },
+            Repr::Other { other } => BackendKind::Other(other),
+        })
+    }
+}
+
+impl BackendKind {
+    /// Model provider behind the backend; a template's model only carries
+    /// over between backends of the same provider.
+    pub fn provider(&self) -> &str {
+        match self {
+            BackendKind::Codex | BackendKind::CodexCli => "openai",
+            BackendKind::Claude | BackendKind::ClaudeCli => "anthropic",
+            BackendKind::Other(name) => name,
+        }
+    }
+
+    /// Name as accepted by [`crate::templates::backend_from_str`].
+    pub fn name(&self) -> &str {
+        match self {
+            BackendKind::Codex => "codex",
+            BackendKind::Claude => "claude",
+            BackendKind::CodexCli => "codex-cli",
+            BackendKind::ClaudeCli => "claude-cli",
+            BackendKind::Other(name) => name,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SubagentTemplate {
     pub id: String,
@@ -27,9 +92,25 @@ pub struct SubagentTemplate {
     pub description: Option<String>,
     pub backend: BackendKind,
     pub capabilities: Vec<String>,
+    /// Model override; the adapter's configured model is used when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Tool allowlist; empty means no restriction was declared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Default output schema for runs that do not supply one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Agent definition file the template was built from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct RunRequest {
     pub backend: BackendKind,
     pub prompt: String,
@@ -37,6 +118,42 @@ pub struct RunRequest {
     pub output_schema: Option<Value>,
     pub async_mode: bool,
     pub tracing: bool,
+    /// Template resolved from `template_id`, applied by the backend adapters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<SubagentTemplate>,
+    /// Batch this run was launched in by `run_subagent_batch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    /// Skills autoloaded for the prompt when the run asked for them. Stored
+    /// with the run, rendered content included, so a re-dispatch sends the
+    /// same system prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skills: Option<SkillContext>,
+    /// Pid of the process that created the run, filled in by
+    /// [`RunStore::create_run`] when unset. Queue reconciliation leaves the
+    /// runs of live processes alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<u32>,
+}
+
+impl RunRequest {
+    /// System prompt for the backend: the template's, followed by the
+    /// autoloaded skills.
+    pub fn system_prompt(&self) -> Option<String> {
+        let template = self
+            .template
+            .as_ref()
+            .and_then(|t| t.system_prompt.as_deref());
+        let skills = self
+            .skills
+            .as_ref()
+            .map(|s| s.content.as_str())
+            .filter(|content| !content.is_empty());
+        match (template, skills) {
+            (Some(template), Some(skills)) => Some(format!("{template}\n\n{skills}")),
+            (template, skills) => template.or(skills).map(String::from),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -46,6 +163,50 @@ pub enum RunState {
     Succeeded,
     Failed,
     Canceled,
+    /// The output never satisfied the run's `output_schema`, even after
+    /// repair turns.
+    SchemaFailed,
+}
+
+impl FromStr for RunState {
+    type Err = anyhow::Error;
+
+    /// Parses a state name case-insensitively, with or without underscores
+    /// (`schema_failed`, `SchemaFailed`).
+    fn from_str(raw: &str) -> Result<Self> {
+        let name = raw.replace(['_', '-'], "").to_ascii_lowercase();
+        Ok(match name.as_str() {
+            "pending" => RunState::Pending,
+            "running" => RunState::Running,
+            "succeeded" => RunState::Succeeded,
+            "failed" => RunState::Failed,
+            "canceled" | "cancelled" => RunState::Canceled,
+            "schemafailed" => RunState::SchemaFailed,
+            _ => anyhow::bail!("unknown run state: {raw}"),
+        })
+    }
+}
+
+impl RunState {
+    /// Lowercase name, as accepted by [`RunState::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunState::Pending => "pending",
+            RunState::Running => "running",
+            RunState::Succeeded => "succeeded",
+            RunState::Failed => "failed",
+            RunState::Canceled => "canceled",
+            RunState::SchemaFailed => "schema_failed",
+        }
+    }
+
+    /// Terminal states are final: once reached, a run's status never changes.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            RunState::Succeeded | RunState::Failed | RunState::Canceled | RunState::SchemaFailed
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -62,16 +223,162 @@ pub struct RunEvent {
     pub data: Option<Value>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+}
+
+/// One turn of a run's conversation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Tokens a run consumed, summed over every provider call it made.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RunUsage {
+    /// Model the provider reported; prices are looked up by it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Input tokens billed at the full rate.
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens read from the provider's prompt cache.
+    #[serde(default)]
+    pub cached_tokens: u64,
+    /// Input tokens written to the provider's prompt cache (Anthropic bills
+    /// these above the input rate).
+    #[serde(default)]
+    pub cache_write_tokens: u64,
+}
+
+impl RunUsage {
+    pub fn add(&mut self, other: RunUsage) {
+        if other.model.is_some() {
+            self.model = other.model;
+        }
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RunRecord {
     pub id: RunId,
     pub request: RunRequest,
     pub status: RunStatus,
     pub events: Vec<RunEvent>,
+    /// Conversation so far, starting with the request prompt.
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    /// Parsed output of a run with an `output_schema`, once it validated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Token usage reported by the provider, across all turns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RunUsage>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
 
+impl RunRecord {
+    /// Conversation to send to the backend. Runs persisted before messages
+    /// were recorded are rebuilt from the prompt and completion events.
+    pub fn conversation(&self) -> Vec<ChatMessage> {
+        if !self.messages.is_empty() {
+            return self.messages.clone();
+        }
+        let mut messages = vec![ChatMessage::user(&self.request.prompt)];
+        let completion = self
+            .events
+            .iter()
+            .rev()
+            .find(|e| e.kind == "completion")
+            .and_then(|e| e.data.as_ref()?.get("text")?.as_str());
+        if let Some(text) = completion {
+            messages.push(ChatMessage::assistant(text));
+        }
+        messages
+    }
+
+    /// Final text of the run: its last assistant turn.
+    pub fn completion_text(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == ChatRole::Assistant)
+            .map(|m| m.content.as_str())
+    }
+
+    /// Appends a user turn and reopens a finished run for dispatch.
+    pub(crate) fn begin_turn(&mut self, message: ChatMessage) -> Result<()> {
+        if !self.status.state.is_terminal() {
+            return Err(SubagentError::InProgress(self.id).into());
+        }
+        self.messages = self.conversation();
+        self.messages.push(message);
+        self.result = None;
+        let now = OffsetDateTime::now_utc();
+        self.status = RunStatus {
+            state: RunState::Running,
+            message: Some("continued".into()),
+            updated_at: now,
+        };
+        self.updated_at = now;
+        Ok(())
+    }
+
+    /// Records the final assistant turn, `result` and `status`; returns
+    /// `false` without changing anything once the run is terminal.
+    pub(crate) fn complete(
+        &mut self,
+        message: ChatMessage,
+        result: Option<Value>,
+        status: RunStatus,
+    ) -> bool {
+        if self.status.state.is_terminal() {
+            return false;
+        }
+        self.messages.push(message);
+        if result.is_some() {
+            self.result = result;
+        }
+        self.updated_at = status.updated_at;
+        self.status = status;
+        true
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct RunId(pub Uuid);
 
@@ -81,30 +388,178 @@ impl fmt::Display for RunId {
     }
 }
 
+impl FromStr for RunId {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let uuid = Uuid::parse_str(raw).map_err(|e| anyhow::anyhow!("invalid run_id: {e}"))?;
+        Ok(RunId(uuid))
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum SubagentError {
     #[error("run not found: {0}")]
     NotFound(RunId),
     #[error("run already completed: {0}")]
     Completed(RunId),
+    #[error("run still in progress: {0}")]
+    InProgress(RunId),
     #[error("storage error: {0}")]
     Storage(String),
 }
 
+/// Narrows [`RunStore::query`]; unset fields match every run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunFilter {
+    pub state: Option<RunState>,
+    pub backend: Option<BackendKind>,
+    pub batch_id: Option<String>,
+    /// Runs whose batch id starts with this prefix.
+    pub batch_prefix: Option<String>,
+    /// Runs created at or after this instant.
+    pub since: Option<OffsetDateTime>,
+    /// Runs created before this instant.
+    pub until: Option<OffsetDateTime>,
+    pub limit: Option<usize>,
+    /// Leave [`RunRecord::events`] empty, for callers that only need the
+    /// run rows (usage summaries, listings).
+    pub without_events: bool,
+}
+
+impl RunFilter {
+    pub fn matches(&self, record: &RunRecord) -> bool {
+        self.state
+            .as_ref()
+            .is_none_or(|state| &record.status.state == state)
+            && self
+                .backend
+                .as_ref()
+                .is_none_or(|backend| &record.request.backend == backend)
+            && self
+                .batch_id
+                .as_ref()
+                .is_none_or(|id| record.request.batch_id.as_ref() == Some(id))
+            && self.batch_prefix.as_ref().is_none_or(|prefix| {
+                record
+                    .request
+                    .batch_id
+                    .as_ref()
+                    .is_some_and(|id| id.starts_with(prefix.as_str()))
+            })
+            && self.since.is_none_or(|since| record.created_at >= since)
+            && self.until.is_none_or(|until| record.created_at < until)
+    }
+}
+
+/// A run's status changed; published to subscribers of [`RunStore::subscribe`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTransition {
+    pub run_id: RunId,
+    pub backend: BackendKind,
+    pub batch_id: Option<String>,
+    pub status: RunStatus,
+}
+
+impl RunTransition {
+    pub fn of(record: &RunRecord) -> Self {
+        Self {
+            run_id: record.id,
+            backend: record.request.backend.clone(),
+            batch_id: record.request.batch_id.clone(),
+            status: record.status.clone(),
+        }
+    }
+}
+
+/// Transitions a subscriber may fall behind by before it skips ahead.
+const TRANSITION_BUFFER: usize = 256;
+
+/// Broadcast channel behind [`RunStore::subscribe`].
+#[derive(Debug, Clone)]
+pub struct Transitions(broadcast::Sender<RunTransition>);
+
+impl Default for Transitions {
+    fn default() -> Self {
+        Self(broadcast::channel(TRANSITION_BUFFER).0)
+    }
+}
+
+impl Transitions {
+    pub fn subscribe(&self) -> broadcast::Receiver<RunTransition> {
+        self.0.subscribe()
+    }
+
+    /// Publishes `transition`; nothing happens without subscribers.
+    pub fn publish(&self, transition: RunTransition) {
+        let _ = self.0.send(transition);
+    }
+}
+
 #[async_trait]
 pub trait RunStore: Send + Sync {
+    /// Records a new `Pending` run, stamping `request.owner` with this
+    /// process's pid when it is unset.
     async fn create_run(&self, request: RunRequest) -> Result<RunId>;
-    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<()>;
+    /// Moves the run to `status`.
+    ///
+    /// Returns `false` without changing anything when the run already reached
+    /// a terminal state; only [`RunStore::begin_turn`] leaves one.
+    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<bool>;
     async fn append_event(&self, run_id: RunId, event: RunEvent) -> Result<()>;
+    async fn append_message(&self, run_id: RunId, message: ChatMessage) -> Result<()>;
+    async fn set_result(&self, run_id: RunId, result: Value) -> Result<()>;
+    /// Finishes a run in one step: appends the completion `event` and the
+    /// assistant `message`, stores `result` and moves the run to `status`.
+    ///
+    /// Like [`RunStore::update_status`], returns `false` without writing
+    /// anything when the run already reached a terminal state, so a run
+    /// stopped meanwhile keeps no trace of the late completion.
+    async fn complete(
+        &self,
+        run_id: RunId,
+        event: RunEvent,
+        message: ChatMessage,
+        result: Option<Value>,
+        status: RunStatus,
+    ) -> Result<bool>;
+    /// Adds the tokens of one provider call to the run's usage.
+    async fn add_usage(&self, run_id: RunId, usage: RunUsage) -> Result<()>;
+    /// Appends a user turn to a finished run and marks it running again.
+    ///
+    /// This is the only transition out of a terminal state; it fails while
+    /// the run is still in progress.
+    async fn begin_turn(&self, run_id: RunId, message: ChatMessage) -> Result<RunRecord>;
     async fn get_run(&self, run_id: RunId) -> Result<Option<RunRecord>>;
     async fn get_status(&self, run_id: RunId) -> Result<Option<RunStatus>>;
     async fn history(&self, limit: usize) -> Result<Vec<RunRecord>>;
+    /// Runs matching `filter`, most recent first. Stores with an index
+    /// override this; the default scans [`RunStore::history`].
+    async fn query(&self, filter: &RunFilter) -> Result<Vec<RunRecord>> {
+        Ok(self
+            .history(usize::MAX)
+            .await?
+            .into_iter()
+            .filter(|r| filter.matches(r))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .map(|mut r| {
+                if filter.without_events {
+                    r.events.clear();
+                }
+                r
+            })
+            .collect())
+    }
     async fn stop(&self, run_id: RunId) -> Result<bool>;
+    /// Receives every status change made through this store from now on:
+    /// creation, status updates, follow-up turns and stops.
+    fn subscribe(&self) -> broadcast::Receiver<RunTransition>;
 }
 
 /// In-memory store for tests and ephemeral runs.
 pub struct MemRunStore {
     inner: Arc<Mutex<HashMap<RunId, RunRecord>>>,
+    transitions: Transitions,
 }
 
 impl Default for MemRunStore {
@@ -117,15 +572,18 @@ impl MemRunStore {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(Mutex::new(HashMap::new())),
+            transitions: Transitions::default(),
         }
     }
 }
 
 #[async_trait]
 impl RunStore for MemRunStore {
-    async fn create_run(&self, request: RunRequest) -> Result<RunId> {
+    async fn create_run(&self, mut request: RunRequest) -> Result<RunId> {
+        request.owner.get_or_insert_with(std::process::id);
         let now = OffsetDateTime::now_utc();
         let id = RunId(Uuid::new_v4());
+        let messages = vec![ChatMessage::user(&request.prompt)];
         let record = RunRecord {
             id,
             request,
@@ -135,34 +593,111 @@ impl RunStore for MemRunStore {
                 updated_at: now,
             },
             events: Vec::new(),
+            messages,
+            result: None,
+            usage: None,
             created_at: now,
             updated_at: now,
         };
-        let mut guard = self.inner.lock().unwrap();
-        guard.insert(id, record);
+        let transition = RunTransition::of(&record);
+        self.inner.lock().unwrap().insert(id, record);
+        self.transitions.publish(transition);
         Ok(id)
     }
 
-    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<()> {
+    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<bool> {
+        let transition = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            if record.status.state.is_terminal() {
+                return Ok(false);
+            }
+            record.status = status.clone();
+            record.updated_at = status.updated_at;
+            RunTransition::of(record)
+        };
+        self.transitions.publish(transition);
+        Ok(true)
+    }
+
+    async fn append_event(&self, run_id: RunId, event: RunEvent) -> Result<()> {
         let mut guard = self.inner.lock().unwrap();
         let record = guard
             .get_mut(&run_id)
             .ok_or(SubagentError::NotFound(run_id))?;
-        record.status = status.clone();
-        record.updated_at = status.updated_at;
+        record.updated_at = event.ts;
+        record.events.push(event);
         Ok(())
     }
 
-    async fn append_event(&self, run_id: RunId, event: RunEvent) -> Result<()> {
+    async fn append_message(&self, run_id: RunId, message: ChatMessage) -> Result<()> {
         let mut guard = self.inner.lock().unwrap();
         let record = guard
             .get_mut(&run_id)
             .ok_or(SubagentError::NotFound(run_id))?;
-        record.updated_at = event.ts;
-        record.events.push(event);
+        record.messages.push(message);
         Ok(())
     }
 
+    async fn set_result(&self, run_id: RunId, result: Value) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let record = guard
+            .get_mut(&run_id)
+            .ok_or(SubagentError::NotFound(run_id))?;
+        record.result = Some(result);
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        run_id: RunId,
+        event: RunEvent,
+        message: ChatMessage,
+        result: Option<Value>,
+        status: RunStatus,
+    ) -> Result<bool> {
+        let transition = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            if !record.complete(message, result, status) {
+                return Ok(false);
+            }
+            record.events.push(event);
+            RunTransition::of(record)
+        };
+        self.transitions.publish(transition);
+        Ok(true)
+    }
+
+    async fn add_usage(&self, run_id: RunId, usage: RunUsage) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let record = guard
+            .get_mut(&run_id)
+            .ok_or(SubagentError::NotFound(run_id))?;
+        record
+            .usage
+            .get_or_insert_with(RunUsage::default)
+            .add(usage);
+        Ok(())
+    }
+
+    async fn begin_turn(&self, run_id: RunId, message: ChatMessage) -> Result<RunRecord> {
+        let record = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            record.begin_turn(message)?;
+            record.clone()
+        };
+        self.transitions.publish(RunTransition::of(&record));
+        Ok(record)
+    }
+
     async fn get_run(&self, run_id: RunId) -> Result<Option<RunRecord>> {
         let guard = self.inner.lock().unwrap();
         Ok(guard.get(&run_id).cloned())
@@ -183,30 +718,38 @@ impl RunStore for MemRunStore {
     }
 
     async fn stop(&self, run_id: RunId) -> Result<bool> {
-        let mut guard = self.inner.lock().unwrap();
-        let record = guard
-            .get_mut(&run_id)
-            .ok_or(SubagentError::NotFound(run_id))?;
-        match record.status.state {
-            RunState::Succeeded | RunState::Failed | RunState::Canceled => Ok(false),
-            _ => {
-                let now = OffsetDateTime::now_utc();
-                record.status = RunStatus {
-                    state: RunState::Canceled,
-                    message: Some("stopped by user".into()),
-                    updated_at: now,
-                };
-                record.updated_at = now;
-                Ok(true)
+        let transition = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            if record.status.state.is_terminal() {
+                return Ok(false);
             }
-        }
+            let now = OffsetDateTime::now_utc();
+            record.status = RunStatus {
+                state: RunState::Canceled,
+                message: Some("stopped by user".into()),
+                updated_at: now,
+            };
+            record.updated_at = now;
+            RunTransition::of(record)
+        };
+        self.transitions.publish(transition);
+        Ok(true)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<RunTransition> {
+        self.transitions.subscribe()
     }
 }
 
-/// Disk-backed store using the shared state directory.
+/// Disk-backed store that rewrites a single JSON file on every change.
+/// Superseded by [`crate::sqlite::SqliteRunStore`].
 pub struct StateRunStore {
     path: PathBuf,
     inner: Arc<Mutex<HashMap<RunId, RunRecord>>>,
+    transitions: Transitions,
 }
 
 impl StateRunStore {
@@ -214,6 +757,7 @@ impl StateRunStore {
         let mut store = Self {
             path,
             inner: Arc::new(Mutex::new(HashMap::new())),
+            transitions: Transitions::default(),
         };
         store.load_from_disk()?;
         Ok(store)
@@ -245,16 +789,19 @@ impl StateRunStore {
     }
 }
 
-/// Default on-disk path for persisted runs.
+/// Path of the JSON run store used before [`crate::sqlite::SqliteRunStore`];
+/// its runs are migrated into the database on first start.
 pub fn default_store_path() -> Result<PathBuf> {
     Ok(home_dir()?.join(".codex/subagents/runs.json"))
 }
 
 #[async_trait]
 impl RunStore for StateRunStore {
-    async fn create_run(&self, request: RunRequest) -> Result<RunId> {
+    async fn create_run(&self, mut request: RunRequest) -> Result<RunId> {
+        request.owner.get_or_insert_with(std::process::id);
         let now = OffsetDateTime::now_utc();
         let id = RunId(Uuid::new_v4());
+        let messages = vec![ChatMessage::user(&request.prompt)];
         let record = RunRecord {
             id,
             request,
@@ -264,28 +811,38 @@ impl RunStore for StateRunStore {
                 updated_at: now,
             },
             events: Vec::new(),
+            messages,
+            result: None,
+            usage: None,
             created_at: now,
             updated_at: now,
         };
+        let transition = RunTransition::of(&record);
         {
             let mut guard = self.inner.lock().unwrap();
             guard.insert(id, record);
         }
         self.persist()?;
+        self.transitions.publish(transition);
         Ok(id)
     }
 
-    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<()> {
-        {
+    async fn update_status(&self, run_id: RunId, status: RunStatus) -> Result<bool> {
+        let transition = {
             let mut guard = self.inner.lock().unwrap();
             let record = guard
                 .get_mut(&run_id)
                 .ok_or(SubagentError::NotFound(run_id))?;
+            if record.status.state.is_terminal() {
+                return Ok(false);
+            }
             record.status = status.clone();
             record.updated_at = status.updated_at;
-        }
+            RunTransition::of(record)
+        };
         self.persist()?;
-        Ok(())
+        self.transitions.publish(transition);
+        Ok(true)
     }
 
     async fn append_event(&self, run_id: RunId, event: RunEvent) -> Result<()> {
@@ -301,6 +858,83 @@ impl RunStore for StateRunStore {
         Ok(())
     }
 
+    async fn append_message(&self, run_id: RunId, message: ChatMessage) -> Result<()> {
+        {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            record.messages.push(message);
+        }
+        self.persist()?;
+        Ok(())
+    }
+
+    async fn set_result(&self, run_id: RunId, result: Value) -> Result<()> {
+        {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            record.result = Some(result);
+        }
+        self.persist()?;
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        run_id: RunId,
+        event: RunEvent,
+        message: ChatMessage,
+        result: Option<Value>,
+        status: RunStatus,
+    ) -> Result<bool> {
+        let transition = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            if !record.complete(message, result, status) {
+                return Ok(false);
+            }
+            record.events.push(event);
+            RunTransition::of(record)
+        };
+        self.persist()?;
+        self.transitions.publish(transition);
+        Ok(true)
+    }
+
+    async fn add_usage(&self, run_id: RunId, usage: RunUsage) -> Result<()> {
+        {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            record
+                .usage
+                .get_or_insert_with(RunUsage::default)
+                .add(usage);
+        }
+        self.persist()?;
+        Ok(())
+    }
+
+    async fn begin_turn(&self, run_id: RunId, message: ChatMessage) -> Result<RunRecord> {
+        let record = {
+            let mut guard = self.inner.lock().unwrap();
+            let record = guard
+                .get_mut(&run_id)
+                .ok_or(SubagentError::NotFound(run_id))?;
+            record.begin_turn(message)?;
+            record.clone()
+        };
+        self.persist()?;
+        self.transitions.publish(RunTransition::of(&record));
+        Ok(record)
+    }
+
     async fn get_run(&self, run_id: RunId) -> Result<Option<RunRecord>> {
         let guard = self.inner.lock().unwrap();
         Ok(guard.get(&run_id).cloned())
@@ -321,27 +955,31 @@ impl RunStore for StateRunStore {
     }
 
     async fn stop(&self, run_id: RunId) -> Result<bool> {
-        {
+        let transition = {
             let mut guard = self.inner.lock().unwrap();
             let record = guard
                 .get_mut(&run_id)
                 .ok_or(SubagentError::NotFound(run_id))?;
-            match record.status.state {
-                RunState::Succeeded | RunState::Failed | RunState::Canceled => return Ok(false),
-                _ => {
-                    let now = OffsetDateTime::now_utc();
-                    record.status = RunStatus {
-                        state: RunState::Canceled,
-                        message: Some("stopped by user".into()),
-                        updated_at: now,
-                    };
-                    record.updated_at = now;
-                }
+            if record.status.state.is_terminal() {
+                return Ok(false);
             }
-        }
+            let now = OffsetDateTime::now_utc();
+            record.status = RunStatus {
+                state: RunState::Canceled,
+                message: Some("stopped by user".into()),
+                updated_at: now,
+            };
+            record.updated_at = now;
+            RunTransition::of(record)
+        };
         self.persist()?;
+        self.transitions.publish(transition);
         Ok(true)
     }
+
+    fn subscribe(&self) -> broadcast::Receiver<RunTransition> {
+        self.transitions.subscribe()
+    }
 }
 
 #[cfg(test)]
@@ -353,12 +991,8 @@ mod tests {
         let store = MemRunStore::new();
         let first = store
             .create_run(RunRequest {
-                backend: BackendKind::Codex,
                 prompt: "first".into(),
-                template_id: None,
-                output_schema: None,
-                async_mode: false,
-                tracing: false,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -368,10 +1002,7 @@ mod tests {
             .create_run(RunRequest {
                 backend: BackendKind::Claude,
                 prompt: "second".into(),
-                template_id: None,
-                output_schema: None,
-                async_mode: false,
-                tracing: false,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -387,12 +1018,8 @@ mod tests {
         let store = MemRunStore::new();
         let run_id = store
             .create_run(RunRequest {
-                backend: BackendKind::Codex,
                 prompt: "stop me".into(),
-                template_id: None,
-                output_schema: None,
-                async_mode: false,
-                tracing: false,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -412,12 +1039,9 @@ mod store_tests {
 
     fn sample_request() -> RunRequest {
         RunRequest {
-            backend: BackendKind::Codex,
             prompt: "hello".to_string(),
             template_id: Some("default".to_string()),
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         }
     }
 
@@ -467,6 +1091,168 @@ mod store_tests {
         assert_eq!(status.state, RunState::Canceled);
     }
 
+    #[tokio::test]
+    async fn terminal_status_is_not_overwritten() {
+        let store = MemRunStore::new();
+        let run_id = store.create_run(sample_request()).await.unwrap();
+        assert!(store.stop(run_id).await.unwrap());
+        store
+            .update_status(
+                run_id,
+                RunStatus {
+                    state: RunState::Succeeded,
+                    message: Some("completed".into()),
+                    updated_at: OffsetDateTime::now_utc(),
+                },
+            )
+            .await
+            .unwrap();
+        let status = store.get_status(run_id).await.unwrap().unwrap();
+        assert_eq!(status.state, RunState::Canceled);
+        assert!(!store.stop(run_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn begin_turn_reopens_finished_runs_and_persists_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs.json");
+        let store = StateRunStore::new(path.clone()).unwrap();
+        let run_id = store.create_run(sample_request()).await.unwrap();
+        let err = store
+            .begin_turn(run_id, ChatMessage::user("too early"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("still in progress"));
+
+        store
+            .append_message(run_id, ChatMessage::assistant("first answer"))
+            .await
+            .unwrap();
+        store
+            .update_status(
+                run_id,
+                RunStatus {
+                    state: RunState::Succeeded,
+                    message: Some("completed".into()),
+                    updated_at: OffsetDateTime::now_utc(),
+                },
+            )
+            .await
+            .unwrap();
+        let record = store
+            .begin_turn(run_id, ChatMessage::user("follow up"))
+            .await
+            .unwrap();
+        assert_eq!(record.status.state, RunState::Running);
+
+        let reopened = StateRunStore::new(path).unwrap();
+        let record = reopened.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(
+            record.messages,
+            vec![
+                ChatMessage::user(sample_request().prompt),
+                ChatMessage::assistant("first answer"),
+                ChatMessage::user("follow up"),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_states_parse_from_snake_or_pascal_case() {
+        assert_eq!(
+            "schema_failed".parse::<RunState>().unwrap(),
+            RunState::SchemaFailed
+        );
+        assert_eq!(
+            "Succeeded".parse::<RunState>().unwrap(),
+            RunState::Succeeded
+        );
+        assert_eq!("cancelled".parse::<RunState>().unwrap(), RunState::Canceled);
+        assert!("done".parse::<RunState>().is_err());
+    }
+
+    #[test]
+    fn backend_kinds_serialize_under_their_names() {
+        for kind in [
+            BackendKind::Codex,
+            BackendKind::Claude,
+            BackendKind::CodexCli,
+            BackendKind::ClaudeCli,
+            BackendKind::Other("vllm".into()),
+        ] {
+            let value = serde_json::to_value(&kind).unwrap();
+            assert_eq!(value, kind.name());
+            assert_eq!(crate::templates::backend_from_str(kind.name()), kind);
+            assert_eq!(serde_json::from_value::<BackendKind>(value).unwrap(), kind);
+        }
+        let legacy: BackendKind = serde_json::from_str("\"CodexCli\"").unwrap();
+        assert_eq!(legacy, BackendKind::CodexCli);
+        for legacy in [r#"{"Other":"vllm"}"#, r#"{"other":"vllm"}"#] {
+            let legacy: BackendKind = serde_json::from_str(legacy).unwrap();
+            assert_eq!(legacy, BackendKind::Other("vllm".into()));
+        }
+    }
+
+    #[test]
+    fn system_prompt_appends_autoloaded_skills_to_the_template() {
+        let mut request = sample_request();
+        assert_eq!(request.system_prompt(), None);
+        request.skills = Some(SkillContext {
+            content: "[skills] rust".into(),
+            skills: Vec::new(),
+            truncated: false,
+        });
+        assert_eq!(request.system_prompt().as_deref(), Some("[skills] rust"));
+        request.template = Some(SubagentTemplate {
+            id: "review".into(),
+            name: "Review".into(),
+            description: None,
+            backend: BackendKind::Codex,
+            capabilities: Vec::new(),
+            model: None,
+            system_prompt: Some("You review code.".into()),
+            max_tokens: None,
+            tools: Vec::new(),
+            output_schema: None,
+            source: None,
+        });
+        assert_eq!(
+            request.system_prompt().as_deref(),
+            Some("You review code.\n\n[skills] rust")
+        );
+    }
+
+    #[test]
+    fn conversation_is_rebuilt_for_runs_without_messages() {
+        let now = OffsetDateTime::now_utc();
+        let record = RunRecord {
+            id: RunId(Uuid::new_v4()),
+            request: sample_request(),
+            status: RunStatus {
+                state: RunState::Succeeded,
+                message: None,
+                updated_at: now,
+            },
+            events: vec![RunEvent {
+                ts: now,
+                kind: "completion".into(),
+                data: Some(serde_json::json!({"text": "done"})),
+            }],
+            messages: Vec::new(),
+            result: None,
+            usage: None,
+            created_at: now,
+            updated_at: now,
+        };
+        assert_eq!(
+            record.conversation(),
+            vec![
+                ChatMessage::user(sample_request().prompt),
+                ChatMessage::assistant("done"),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn state_store_persists_runs() {
         let dir = tempfile::tempdir().unwrap();
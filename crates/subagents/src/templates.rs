@@ -0,0 +1,397 @@
+//! Subagent templates built from agent definitions and `subagents.toml`.
+//!
+//! Discovered agent markdown files become templates: the frontmatter supplies
+//! the name, description, model, max tokens and tool allowlist, and the body
+//! becomes the system prompt. `[templates.<id>]` tables in `subagents.toml`
+//! add templates of their own, or override fields of an agent template with
+//! the same id.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use skrills_discovery::{
+    discover_agents, parse_frontmatter, AgentMeta, FrontValue, SkillRoot, SkillSource,
+};
+use skrills_state::home_dir;
+
+use crate::store::{BackendKind, SubagentTemplate};
+use crate::usage::ModelPrice;
+
+/// Claude agent model aliases and the API models they resolve to.
+const CLAUDE_MODEL_ALIASES: &[(&str, &str)] = &[
+    ("sonnet", "claude-sonnet-4-5"),
+    ("opus", "claude-opus-4-1"),
+    ("haiku", "claude-haiku-4-5"),
+];
+
+/// Parses a backend name as accepted by the MCP tools and `subagents.toml`.
+pub fn backend_from_str(raw: &str) -> BackendKind {
+    match raw.to_ascii_lowercase().as_str() {
+        "codex" | "gpt" | "openai" => BackendKind::Codex,
+        "claude" | "anthropic" => BackendKind::Claude,
+        "codex-cli" | "codex-exec" => BackendKind::CodexCli,
+        "claude-cli" => BackendKind::ClaudeCli,
+        other => BackendKind::Other(other.to_string()),
+    }
+}
+
+/// Contents of `~/.codex/subagents.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SubagentsConfig {
+    pub default_backend: Option<String>,
+    pub codex_model: Option<String>,
+    pub claude_model: Option<String>,
+    pub timeout_secs: Option<u64>,
+    /// Rounds of tool calls a run may make before it fails.
+    pub max_tool_iterations: Option<usize>,
+    /// MCP servers whose tools subagents may use. Empty by default, so no
+    /// server is started for a run unless it is listed here.
+    #[serde(default)]
+    pub tool_servers: Vec<String>,
+    /// Finished runs older than this many days are deleted when the service
+    /// starts; runs are kept forever when unset.
+    pub retention_days: Option<u32>,
+    /// `[prices.<model>]` tables used to cost run usage.
+    #[serde(default)]
+    pub prices: BTreeMap<String, ModelPrice>,
+    #[serde(default)]
+    pub templates: BTreeMap<String, TemplateConfig>,
+    /// `[backends.<name>]` model servers, selected by name like the
+    /// built-in backends.
+    #[serde(default)]
+    pub backends: BTreeMap<String, BackendConfig>,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+}
+
+/// The `[queue]` table: the persistent queue behind `run_subagent_async`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QueueConfig {
+    /// Queued runs executed at once; [`crate::queue::DEFAULT_WORKERS`] when
+    /// unset.
+    pub workers: Option<usize>,
+    /// What happens to runs a previous server left running.
+    #[serde(default)]
+    pub on_restart: RestartPolicy,
+}
+
+/// Handling of runs interrupted by a server exit, applied on the next start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Mark them failed with the message `interrupted`.
+    #[default]
+    Fail,
+    /// Queue them again; they rerun from their last user turn.
+    Requeue,
+}
+
+/// The `[hooks]` table: local commands run on run lifecycle events.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HooksConfig {
+    /// Command and arguments run when a run finishes, whatever its state.
+    /// `{run_id}`, `{state}`, `{backend}` and `{message}` are substituted.
+    #[serde(default)]
+    pub on_complete: Vec<String>,
+}
+
+/// Wire protocol spoken by a declared backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendProtocol {
+    /// OpenAI `chat/completions`, as served by vLLM, llama.cpp and most
+    /// local servers.
+    OpenaiChat,
+    /// Anthropic `messages`.
+    AnthropicMessages,
+    /// Ollama's native `api/chat`.
+    Ollama,
+}
+
+/// A `[backends.<name>]` table declaring an HTTP model server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackendConfig {
+    pub protocol: BackendProtocol,
+    pub base_url: String,
+    pub model: String,
+    /// Environment variable holding the API key; requests carry no
+    /// credentials when unset.
+    pub api_key_env: Option<String>,
+    /// Header the key is sent in; `Authorization` sends it as a bearer
+    /// token. Defaults to the protocol's usual header.
+    pub auth_header: Option<String>,
+    pub timeout_secs: Option<u64>,
+    /// Attempts per provider call, including the first.
+    pub max_attempts: Option<u32>,
+    /// Repair turns a run gets when its output misses its `output_schema`.
+    pub schema_repairs: Option<usize>,
+}
+
+/// A `[templates.<id>]` table. Every field is optional so a table can
+/// override just part of an agent template.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TemplateConfig {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub backend: Option<String>,
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub tools: Option<Vec<String>>,
+    pub output_schema: Option<Value>,
+}
+
+/// Default location of the subagents config file.
+pub fn default_config_path() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".codex/subagents.toml"))
+}
+
+impl SubagentsConfig {
+    /// Loads the config at `path`; a missing file yields the defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+/// Resolves a Claude agent `model` value; `inherit` means no override.
+fn agent_model(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.eq_ignore_ascii_case("inherit") {
+        return None;
+    }
+    let resolved = CLAUDE_MODEL_ALIASES
+        .iter()
+        .find(|(alias, _)| raw.eq_ignore_ascii_case(alias))
+        .map(|(_, model)| *model)
+        .unwrap_or(raw);
+    Some(resolved.to_string())
+}
+
+fn template_capabilities(template: &SubagentTemplate) -> Vec<String> {
+    let mut caps = Vec::new();
+    if !template.tools.is_empty() {
+        caps.push("tools".to_string());
+    }
+    if template.output_schema.is_some() {
+        caps.push("structured_outputs".to_string());
+    }
+    caps
+}
+
+/// Builds a template from an agent definition's markdown.
+pub fn template_from_agent(agent: &AgentMeta, text: &str) -> SubagentTemplate {
+    let (fields, body) = parse_frontmatter(text);
+    let scalar = |key: &str| fields.get(key).and_then(FrontValue::as_scalar);
+    let id = scalar("name")
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .unwrap_or_else(|| {
+            Path::new(&agent.name)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| agent.name.clone())
+        });
+    let backend = match (scalar("backend"), &agent.source) {
+        (Some(raw), _) => backend_from_str(raw),
+        (None, SkillSource::Codex) => BackendKind::Codex,
+        (None, _) => BackendKind::Claude,
+    };
+    let body = body.trim();
+    let mut template = SubagentTemplate {
+        name: id.clone(),
+        id,
+        description: scalar("description").map(String::from),
+        backend,
+        capabilities: Vec::new(),
+        model: scalar("model").and_then(agent_model),
+        system_prompt: (!body.is_empty()).then(|| body.to_string()),
+        max_tokens: scalar("max_tokens").and_then(|s| s.parse().ok()),
+        tools: fields
+            .get("tools")
+            .map(FrontValue::to_list)
+            .unwrap_or_default(),
+        output_schema: None,
+        source: Some(agent.path.clone()),
+    };
+    template.capabilities = template_capabilities(&template);
+    template
+}
+
+/// Applies a `[templates.<id>]` table on top of `base`, or builds a new
+/// template when there is no agent with that id.
+pub fn apply_template_config(
+    id: &str,
+    config: &TemplateConfig,
+    base: Option<SubagentTemplate>,
+    default_backend: &BackendKind,
+) -> SubagentTemplate {
+    let mut template = base.unwrap_or_else(|| SubagentTemplate {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: None,
+        backend: default_backend.clone(),
+        capabilities: Vec::new(),
+        model: None,
+        system_prompt: None,
+        max_tokens: None,
+        tools: Vec::new(),
+        output_schema: None,
+        source: None,
+    });
+    if let Some(name) = &config.name {
+        template.name = name.clone();
+    }
+    if let Some(description) = &config.description {
+        template.description = Some(description.clone());
+    }
+    if let Some(backend) = &config.backend {
+        template.backend = backend_from_str(backend);
+    }
+    if let Some(model) = &config.model {
+        template.model = Some(model.clone());
+    }
+    if let Some(prompt) = &config.system_prompt {
+        template.system_prompt = Some(prompt.clone());
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        template.max_tokens = Some(max_tokens);
+    }
+    if let Some(tools) = &config.tools {
+        template.tools = tools.clone();
+    }
+    if let Some(schema) = &config.output_schema {
+        template.output_schema = Some(schema.clone());
+    }
+    template.capabilities = template_capabilities(&template);
+    template
+}
+
+/// Loads templates from the agents under `roots` and the tables in `config`.
+///
+/// Agents are taken in discovery (priority) order, so the first agent with a
+/// given id wins; config tables are applied last.
+pub fn load_templates(
+    roots: &[SkillRoot],
+    config: &SubagentsConfig,
+    default_backend: &BackendKind,
+) -> Result<Vec<SubagentTemplate>> {
+    let mut templates: BTreeMap<String, SubagentTemplate> = BTreeMap::new();
+    for agent in discover_agents(roots)? {
+        let text = match fs::read_to_string(&agent.path) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::warn!(path = %agent.path.display(), %err, "skipping unreadable agent");
+                continue;
+            }
+        };
+        let template = template_from_agent(&agent, &text);
+        templates.entry(template.id.clone()).or_insert(template);
+    }
+    for (id, table) in &config.templates {
+        let base = templates.remove(id);
+        let template = apply_template_config(id, table, base, default_backend);
+        templates.insert(id.clone(), template);
+    }
+    Ok(templates.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn agent(name: &str, source: SkillSource) -> AgentMeta {
+        AgentMeta {
+            name: name.into(),
+            path: PathBuf::from(name),
+            source,
+            root: PathBuf::new(),
+            hash: String::new(),
+            plugin: None,
+        }
+    }
+
+    #[test]
+    fn agent_markdown_becomes_template() {
+        let text = "---\nname: code-reviewer\ndescription: \"Reviews diffs\"\ntools: Read, Grep, Glob\nmodel: sonnet\n---\n\nYou review code.\n";
+        let template = template_from_agent(&agent("reviewer.md", SkillSource::Claude), text);
+        assert_eq!(template.id, "code-reviewer");
+        assert_eq!(template.description.as_deref(), Some("Reviews diffs"));
+        assert_eq!(template.backend, BackendKind::Claude);
+        assert_eq!(template.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(template.system_prompt.as_deref(), Some("You review code."));
+        assert_eq!(template.tools, vec!["Read", "Grep", "Glob"]);
+        assert_eq!(template.capabilities, vec!["tools"]);
+    }
+
+    #[test]
+    fn frontmatter_block_lists_and_missing_header() {
+        let text = "---\nmodel: inherit\ntools:\n  - search\n  - 'fetch'\n---\nBody";
+        let template = template_from_agent(&agent("plugin/helper.md", SkillSource::Codex), text);
+        assert_eq!(template.id, "helper");
+        assert_eq!(template.backend, BackendKind::Codex);
+        assert!(template.model.is_none());
+        assert_eq!(template.tools, vec!["search", "fetch"]);
+
+        let plain = template_from_agent(&agent("plain.md", SkillSource::Claude), "Just a prompt");
+        assert_eq!(plain.id, "plain");
+        assert_eq!(plain.system_prompt.as_deref(), Some("Just a prompt"));
+    }
+
+    #[test]
+    fn config_tables_override_and_add_templates() {
+        let tmp = tempdir().unwrap();
+        let agents = tmp.path().join("agents");
+        fs::create_dir_all(&agents).unwrap();
+        fs::write(
+            agents.join("reviewer.md"),
+            "---\nname: reviewer\nmodel: haiku\n---\nReview carefully.",
+        )
+        .unwrap();
+        let config: SubagentsConfig = toml::from_str(
+            r#"
+default_backend = "claude"
+
+[templates.reviewer]
+max_tokens = 2048
+output_schema = { type = "object", required = ["verdict"] }
+
+[templates.summarizer]
+backend = "codex"
+system_prompt = "Summarize."
+"#,
+        )
+        .unwrap();
+        let roots = vec![SkillRoot {
+            root: agents,
+            source: SkillSource::Claude,
+        }];
+
+        let templates = load_templates(&roots, &config, &BackendKind::Claude).unwrap();
+        assert_eq!(templates.len(), 2);
+        let reviewer = &templates[0];
+        assert_eq!(reviewer.id, "reviewer");
+        assert_eq!(reviewer.model.as_deref(), Some("claude-haiku-4-5"));
+        assert_eq!(reviewer.max_tokens, Some(2048));
+        assert_eq!(
+            reviewer.output_schema,
+            Some(json!({"type": "object", "required": ["verdict"]}))
+        );
+        assert_eq!(reviewer.capabilities, vec!["structured_outputs"]);
+        let summarizer = &templates[1];
+        assert_eq!(summarizer.backend, BackendKind::Codex);
+        assert_eq!(summarizer.system_prompt.as_deref(), Some("Summarize."));
+        assert!(summarizer.source.is_none());
+    }
+}
@@ -0,0 +1,510 @@
+//! MCP tools offered to subagents during a run.
+//!
+//! [`McpToolHub`] connects as an MCP client to the servers Claude Code and
+//! Codex are configured with (the same `McpServer` entries `skrills sync`
+//! reads) that `tool_servers` allows, and exposes their tools to the model
+//! as `server__tool`. The HTTP
+//! backends drive the model/tool loop through [`ToolLoop`], executing each
+//! requested call and feeding the result back until the model answers or
+//! the iteration limit is reached.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rmcp::model::{CallToolRequestParam, RawContent};
+use rmcp::service::{serve_client, RunningService};
+use rmcp::transport::TokioChildProcess;
+use rmcp::RoleClient;
+use serde_json::Value;
+use skrills_sync::{AgentAdapter, ClaudeAdapter, CodexAdapter, McpServer};
+use tokio::process::Command;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::store::RunRequest;
+use crate::templates::SubagentsConfig;
+
+/// Tool rounds allowed per run unless `max_tool_iterations` says otherwise.
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 8;
+
+/// How long to wait for a server to start and list its tools.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a server that failed to connect is left alone before the next
+/// run tries it again.
+const RECONNECT_AFTER: Duration = Duration::from_secs(30);
+
+/// A tool as offered to the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSpec {
+    /// Name the model calls the tool by.
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON schema of the tool arguments.
+    pub input_schema: Value,
+}
+
+/// Result of a tool call, passed back to the model verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+#[async_trait]
+pub trait ToolProvider: Send + Sync {
+    async fn list_tools(&self) -> Result<Vec<ToolSpec>>;
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolOutput>;
+}
+
+/// Tools available to the HTTP backends, and how many rounds of tool calls
+/// a run may make.
+#[derive(Clone)]
+pub struct ToolLoop {
+    pub provider: Arc<dyn ToolProvider>,
+    pub max_iterations: usize,
+}
+
+impl std::fmt::Debug for ToolLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolLoop")
+            .field("max_iterations", &self.max_iterations)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ToolLoop {
+    pub fn new(provider: Arc<dyn ToolProvider>) -> Self {
+        Self {
+            provider,
+            max_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+        }
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Tools offered for `request`. Templates must declare the `tools`
+    /// capability to get any; runs without a template use the adapter's
+    /// built-in one, which does. A template that lists tools narrows the set
+    /// to those names, matched against either `server__tool` or the bare
+    /// tool name.
+    pub async fn tools_for(&self, request: &RunRequest) -> Result<Vec<ToolSpec>> {
+        if let Some(template) = &request.template {
+            if !template.capabilities.iter().any(|c| c == "tools") {
+                return Ok(Vec::new());
+            }
+        }
+        let tools = self.provider.list_tools().await?;
+        let allow = match request.template.as_ref().map(|t| &t.tools) {
+            Some(allow) if !allow.is_empty() => allow,
+            _ => return Ok(tools),
+        };
+        Ok(tools
+            .into_iter()
+            .filter(|tool| {
+                let bare = tool
+                    .name
+                    .split_once("__")
+                    .map_or(tool.name.as_str(), |(_, t)| t);
+                allow.iter().any(|a| a == &tool.name || a == bare)
+            })
+            .collect())
+    }
+}
+
+/// Builds the model-facing name for `tool` on `server`. Both providers
+/// restrict tool names to `[A-Za-z0-9_-]{1,64}`.
+pub fn qualified_tool_name(server: &str, tool: &str) -> String {
+    let name: String = format!("{server}__{tool}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.chars().take(64).collect()
+}
+
+/// Makes `name` unique among `routes` by suffixing `_2`, `_3`, … within the
+/// length limit, since distinct tools can sanitize or truncate to the same
+/// qualified name.
+fn unique_tool_name(name: String, routes: &HashMap<String, (usize, String)>) -> String {
+    if !routes.contains_key(&name) {
+        return name;
+    }
+    let mut n = 2;
+    loop {
+        let suffix = format!("_{n}");
+        let base: String = name.chars().take(64 - suffix.len()).collect();
+        let candidate = format!("{base}{suffix}");
+        if !routes.contains_key(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+struct Connection {
+    server: String,
+    client: RunningService<RoleClient, ()>,
+}
+
+struct Connected {
+    connections: Vec<Arc<Connection>>,
+    /// Model-facing name to (connection index, server-side tool name).
+    routes: HashMap<String, (usize, String)>,
+    tools: Vec<ToolSpec>,
+    /// Servers not connected yet, by index into `McpToolHub::servers`, with
+    /// when they last failed to connect.
+    unconnected: Vec<(usize, Option<Instant>)>,
+}
+
+/// MCP client over the configured stdio servers. Servers are started on the
+/// first run that needs tools and kept for the life of the hub; a server that
+/// fails to start is tried again by later runs, at most every
+/// [`RECONNECT_AFTER`].
+pub struct McpToolHub {
+    servers: Vec<McpServer>,
+    connected: Mutex<Connected>,
+}
+
+impl McpToolHub {
+    pub fn new(servers: Vec<McpServer>) -> Self {
+        Self {
+            connected: Mutex::new(Connected {
+                connections: Vec::new(),
+                routes: HashMap::new(),
+                tools: Vec::new(),
+                unconnected: (0..servers.len()).map(|i| (i, None)).collect(),
+            }),
+            servers,
+        }
+    }
+
+    /// Collects the enabled servers named in `tool_servers` from the Claude
+    /// Code and Codex configs. Skrills itself is skipped so a subagent cannot
+    /// recurse into this server.
+    pub fn discover(config: &SubagentsConfig) -> Self {
+        if config.tool_servers.is_empty() {
+            return Self::new(Vec::new());
+        }
+        let mut servers = BTreeMap::new();
+        let claude = ClaudeAdapter::new().and_then(|a| a.read_mcp_servers());
+        let codex = CodexAdapter::new().and_then(|a| a.read_mcp_servers());
+        for found in [codex, claude] {
+            match found {
+                Ok(found) => servers.extend(found),
+                Err(err) => tracing::warn!(error = %err, "failed to read MCP servers"),
+            }
+        }
+        let servers = servers
+            .into_values()
+            .filter(|s| s.enabled && !is_skrills(&s.command))
+            .filter(|s| config.tool_servers.contains(&s.name))
+            .collect();
+        Self::new(servers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Connects the servers that are not connected yet and are due for an
+    /// attempt, then returns the connection state.
+    async fn connected(&self) -> MutexGuard<'_, Connected> {
+        let mut connected = self.connected.lock().await;
+        let due = std::mem::take(&mut connected.unconnected);
+        for (index, failed_at) in due {
+            if failed_at.is_some_and(|at| at.elapsed() < RECONNECT_AFTER) {
+                connected.unconnected.push((index, failed_at));
+                continue;
+            }
+            let server = &self.servers[index];
+            let listed = tokio::time::timeout(CONNECT_TIMEOUT, connect(server))
+                .await
+                .map_err(|_| anyhow!("timed out"))
+                .and_then(|r| r);
+            let (client, tools) = match listed {
+                Ok(listed) => listed,
+                Err(err) => {
+                    let name = &server.name;
+                    tracing::warn!(server = %name, error = %err, "MCP server unavailable");
+                    connected.unconnected.push((index, Some(Instant::now())));
+                    continue;
+                }
+            };
+            let connection = connected.connections.len();
+            for tool in tools {
+                let qualified = qualified_tool_name(&server.name, &tool.name);
+                let name = unique_tool_name(qualified.clone(), &connected.routes);
+                if name != qualified {
+                    tracing::warn!(
+                        server = %server.name,
+                        tool = %tool.name,
+                        %name,
+                        "tool name collides with another tool; renamed"
+                    );
+                }
+                connected
+                    .routes
+                    .insert(name.clone(), (connection, tool.name.to_string()));
+                connected.tools.push(ToolSpec {
+                    name,
+                    description: tool.description.map(|d| d.to_string()),
+                    input_schema: Value::Object((*tool.input_schema).clone()),
+                });
+            }
+            connected.connections.push(Arc::new(Connection {
+                server: server.name.clone(),
+                client,
+            }));
+        }
+        connected
+    }
+}
+
+fn is_skrills(command: &str) -> bool {
+    Path::new(command)
+        .file_stem()
+        .is_some_and(|stem| stem == "skrills")
+}
+
+async fn connect(
+    server: &McpServer,
+) -> Result<(RunningService<RoleClient, ()>, Vec<rmcp::model::Tool>)> {
+    let mut command = Command::new(&server.command);
+    command.args(&server.args).envs(&server.env);
+    let (transport, _) = TokioChildProcess::builder(command)
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("spawning {}", server.command))?;
+    let client = serve_client((), transport).await?;
+    let tools = client.peer().list_all_tools().await?;
+    Ok((client, tools))
+}
+
+#[async_trait]
+impl ToolProvider for McpToolHub {
+    async fn list_tools(&self) -> Result<Vec<ToolSpec>> {
+        Ok(self.connected().await.tools.clone())
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolOutput> {
+        // The lock is released before the call so runs can use tools
+        // concurrently.
+        let (connection, tool) = {
+            let connected = self.connected().await;
+            let (index, tool) = connected
+                .routes
+                .get(name)
+                .ok_or_else(|| anyhow!("unknown tool: {name}"))?;
+            (connected.connections[*index].clone(), tool.clone())
+        };
+        let arguments = match arguments {
+            Value::Object(map) => Some(map),
+            Value::Null => None,
+            _ => return Err(anyhow!("tool arguments must be a JSON object")),
+        };
+        let result = connection
+            .client
+            .peer()
+            .call_tool(CallToolRequestParam {
+                name: tool.clone().into(),
+                arguments,
+            })
+            .await
+            .with_context(|| format!("calling {tool} on {}", connection.server))?;
+        let mut content: Vec<String> = result
+            .content
+            .iter()
+            .filter_map(|c| match &c.raw {
+                RawContent::Text(text) => Some(text.text.clone()),
+                _ => None,
+            })
+            .collect();
+        if content.is_empty() {
+            if let Some(structured) = &result.structured_content {
+                content.push(structured.to_string());
+            }
+        }
+        Ok(ToolOutput {
+            content: content.join("\n"),
+            is_error: result.is_error.unwrap_or(false),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BackendKind, SubagentTemplate};
+    use serde_json::json;
+
+    struct FixedTools(Vec<&'static str>);
+
+    #[async_trait]
+    impl ToolProvider for FixedTools {
+        async fn list_tools(&self) -> Result<Vec<ToolSpec>> {
+            Ok(self
+                .0
+                .iter()
+                .map(|name| ToolSpec {
+                    name: name.to_string(),
+                    description: None,
+                    input_schema: json!({"type": "object"}),
+                })
+                .collect())
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<ToolOutput> {
+            unreachable!()
+        }
+    }
+
+    fn request(tools: Option<Vec<String>>) -> RunRequest {
+        RunRequest {
+            prompt: "p".into(),
+            template: tools.map(|tools| SubagentTemplate {
+                id: "t".into(),
+                name: "t".into(),
+                description: None,
+                backend: BackendKind::Codex,
+                capabilities: vec!["tools".into()],
+                model: None,
+                system_prompt: None,
+                max_tokens: None,
+                tools,
+                output_schema: None,
+                source: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn qualified_names_are_sanitized_and_bounded() {
+        assert_eq!(
+            qualified_tool_name("git hub", "search.code"),
+            "git_hub__search_code"
+        );
+        assert_eq!(qualified_tool_name(&"s".repeat(70), "t").len(), 64);
+    }
+
+    #[test]
+    fn colliding_names_get_a_suffix() {
+        let mut routes = HashMap::new();
+        let first = qualified_tool_name("git hub", "search");
+        let second = qualified_tool_name("git.hub", "search");
+        assert_eq!(first, second);
+        routes.insert(unique_tool_name(first, &routes), (0, "search".into()));
+        let renamed = unique_tool_name(second, &routes);
+        assert_eq!(renamed, "git_hub__search_2");
+        routes.insert(renamed, (1, "search".into()));
+
+        let long = qualified_tool_name(&"s".repeat(70), "t");
+        routes.insert(long.clone(), (2, "t".into()));
+        let renamed = unique_tool_name(long, &routes);
+        assert_eq!(renamed.len(), 64);
+        assert!(renamed.ends_with("_2"));
+    }
+
+    #[tokio::test]
+    async fn template_tools_narrow_the_offered_set() {
+        let tools = ToolLoop::new(Arc::new(FixedTools(vec![
+            "github__search",
+            "github__create_issue",
+            "fs__read_file",
+        ])));
+        let names = |specs: Vec<ToolSpec>| specs.into_iter().map(|s| s.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(tools.tools_for(&request(None)).await.unwrap()).len(),
+            3
+        );
+        let allowed = request(Some(vec!["read_file".into(), "github__search".into()]));
+        assert_eq!(
+            names(tools.tools_for(&allowed).await.unwrap()),
+            vec!["github__search", "fs__read_file"]
+        );
+        let builtins = request(Some(vec!["Read".into(), "Grep".into()]));
+        assert!(tools.tools_for(&builtins).await.unwrap().is_empty());
+        let mut undeclared = request(Some(Vec::new()));
+        undeclared.template.as_mut().unwrap().capabilities.clear();
+        assert!(tools.tools_for(&undeclared).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unreachable_servers_offer_no_tools() {
+        let hub = McpToolHub::new(vec![McpServer {
+            name: "missing".into(),
+            command: "/nonexistent/mcp-server".into(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            enabled: true,
+            plugin: None,
+        }]);
+        assert!(hub.list_tools().await.unwrap().is_empty());
+        let err = hub.call_tool("missing__x", json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("unknown tool"));
+    }
+
+    /// Answers `initialize` and `tools/list` with a single `echo` tool.
+    #[cfg(unix)]
+    const FAKE_MCP_SERVER: &str = r#"#!/bin/sh
+reply() {
+    id=$(printf '%s' "$1" | sed 's/.*"id":\([0-9]*\).*/\1/')
+    printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$2"
+}
+read -r line
+reply "$line" '{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"fake","version":"1"}}'
+read -r line
+read -r line
+reply "$line" '{"tools":[{"name":"echo","inputSchema":{"type":"object"}}]}'
+cat > /dev/null
+"#;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_servers_are_retried() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let command = dir.path().join("fake-mcp");
+        let hub = McpToolHub::new(vec![McpServer {
+            name: "fake".into(),
+            command: command.to_string_lossy().into_owned(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            enabled: true,
+            plugin: None,
+        }]);
+        assert!(hub.list_tools().await.unwrap().is_empty());
+
+        std::fs::write(&command, FAKE_MCP_SERVER).unwrap();
+        std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // Not due yet: the failure is too recent.
+        assert!(hub.list_tools().await.unwrap().is_empty());
+
+        hub.connected.lock().await.unconnected[0].1 = Instant::now().checked_sub(RECONNECT_AFTER);
+        let tools = hub.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "fake__echo");
+        assert!(hub.connected.lock().await.unconnected.is_empty());
+    }
+
+    #[test]
+    fn servers_are_opt_in() {
+        let hub = McpToolHub::discover(&SubagentsConfig::default());
+        assert!(hub.is_empty());
+    }
+}
@@ -0,0 +1,467 @@
+//! Signed, optionally encrypted transcript bundles.
+//!
+//! `download_transcript_secure` exports a finished run as a single JSON
+//! bundle. The run's request, status, events, conversation and completion
+//! are stored as named files; a manifest lists the SHA-256 digest and size
+//! of each. The files are optionally encrypted with AES-256-GCM, and the
+//! header, manifest and payload are signed with HMAC-SHA256.
+//!
+//! Both keys are derived from one secret in a local keyfile
+//! (`~/.codex/subagents/transcript.key`, or `SKRILLS_TRANSCRIPT_KEY_FILE`),
+//! so [`verify`] needs nothing but the bundle and that file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use skrills_state::home_dir;
+use time::OffsetDateTime;
+
+use crate::store::{ChatRole, RunId, RunRecord, RunState};
+
+/// Format tag of bundles written by this version.
+pub const FORMAT: &str = "skrills-transcript/1";
+
+const SIGNATURE_ALG: &str = "HMAC-SHA256";
+const ENCRYPTION_ALG: &str = "AES-256-GCM";
+const KEY_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Keyfile used when `SKRILLS_TRANSCRIPT_KEY_FILE` is unset.
+pub fn default_key_path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("SKRILLS_TRANSCRIPT_KEY_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(home_dir()?.join(".codex/subagents/transcript.key"))
+}
+
+/// Directory bundles are written to when no path is given.
+pub fn default_transcript_dir() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".codex/subagents/transcripts"))
+}
+
+/// Secret from which the signing and encryption keys are derived.
+pub struct TranscriptKey {
+    secret: [u8; KEY_LEN],
+}
+
+impl TranscriptKey {
+    pub fn generate() -> Self {
+        let secret = Aes256Gcm::generate_key(OsRng);
+        Self {
+            secret: secret.into(),
+        }
+    }
+
+    /// Reads a keyfile holding the base64-encoded secret.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading transcript key {}", path.display()))?;
+        let bytes = BASE64
+            .decode(text.trim())
+            .with_context(|| format!("decoding transcript key {}", path.display()))?;
+        let secret = bytes
+            .try_into()
+            .map_err(|_| anyhow!("transcript key must be {KEY_LEN} bytes"))?;
+        Ok(Self { secret })
+    }
+
+    /// Loads the keyfile, generating it (readable by the owner only) on
+    /// first use.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            return Self::load(path);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let key = Self::generate();
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("creating transcript key {}", path.display()))?;
+        writeln!(file, "{}", BASE64.encode(key.secret))?;
+        Ok(key)
+    }
+
+    /// Short fingerprint recorded in bundles, so a verifier holding the
+    /// wrong keyfile gets a clear error.
+    pub fn id(&self) -> String {
+        hex(&Sha256::digest(self.secret)[..8])
+    }
+
+    fn derive(&self, label: &str) -> [u8; KEY_LEN] {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.secret).expect("HMAC accepts any key");
+        mac.update(label.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        <HmacSha256 as Mac>::new_from_slice(&self.derive("skrills transcript signing"))
+            .expect("HMAC accepts any key")
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(
+            &self.derive("skrills transcript encryption"),
+        ))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// Hex SHA-256 of the file contents.
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Encryption {
+    pub alg: String,
+    /// Base64 AES-GCM nonce.
+    pub nonce: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Signature {
+    pub alg: String,
+    pub key_id: String,
+    /// Base64 MAC over the rest of the bundle.
+    pub value: String,
+}
+
+/// Everything the signature covers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleBody {
+    pub format: String,
+    pub run_id: RunId,
+    pub backend: String,
+    pub state: RunState,
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+    pub manifest: Vec<ManifestEntry>,
+    pub encryption: Option<Encryption>,
+    /// Base64 JSON object of file name to contents, encrypted when
+    /// `encryption` is set.
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptBundle {
+    #[serde(flatten)]
+    pub body: BundleBody,
+    pub signature: Signature,
+}
+
+/// Files of a run's transcript, in manifest order.
+fn transcript_files(record: &RunRecord) -> Result<Vec<(&'static str, String)>> {
+    let conversation = record.conversation();
+    let mut files = vec![
+        (
+            "request.json",
+            serde_json::to_string_pretty(&record.request)?,
+        ),
+        (
+            "status.json",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "status": record.status,
+                "result": record.result,
+                "usage": record.usage,
+            }))?,
+        ),
+        ("events.json", serde_json::to_string_pretty(&record.events)?),
+        (
+            "messages.json",
+            serde_json::to_string_pretty(&conversation)?,
+        ),
+    ];
+    if let Some(completion) = conversation
+        .iter()
+        .rev()
+        .find(|m| m.role == ChatRole::Assistant)
+    {
+        files.push(("completion.txt", completion.content.clone()));
+    }
+    Ok(files)
+}
+
+fn sign(key: &TranscriptKey, body: &BundleBody) -> Result<Vec<u8>> {
+    let mut mac = key.mac();
+    mac.update(&serde_json::to_vec(body)?);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Builds a signed bundle of `record`, encrypting the files when `encrypt`
+/// is set.
+pub fn export(record: &RunRecord, key: &TranscriptKey, encrypt: bool) -> Result<TranscriptBundle> {
+    let files = transcript_files(record)?;
+    let manifest = files
+        .iter()
+        .map(|(name, contents)| ManifestEntry {
+            name: name.to_string(),
+            sha256: hex(&Sha256::digest(contents.as_bytes())),
+            bytes: contents.len() as u64,
+        })
+        .collect();
+    let plaintext = serde_json::to_vec(&files.into_iter().collect::<BTreeMap<_, _>>())?;
+    let (encryption, payload) = if encrypt {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = key
+            .cipher()
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow!("encrypting transcript"))?;
+        (
+            Some(Encryption {
+                alg: ENCRYPTION_ALG.into(),
+                nonce: BASE64.encode(nonce),
+            }),
+            ciphertext,
+        )
+    } else {
+        (None, plaintext)
+    };
+    let body = BundleBody {
+        format: FORMAT.into(),
+        run_id: record.id,
+        backend: record.request.backend.name().to_string(),
+        state: record.status.state.clone(),
+        exported_at: OffsetDateTime::now_utc(),
+        manifest,
+        encryption,
+        payload: BASE64.encode(payload),
+    };
+    let signature = Signature {
+        alg: SIGNATURE_ALG.into(),
+        key_id: key.id(),
+        value: BASE64.encode(sign(key, &body)?),
+    };
+    Ok(TranscriptBundle { body, signature })
+}
+
+/// A bundle whose signature and manifest checked out.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedTranscript {
+    pub run_id: RunId,
+    pub backend: String,
+    pub state: RunState,
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+    pub key_id: String,
+    pub encrypted: bool,
+    pub manifest: Vec<ManifestEntry>,
+    /// Decrypted file contents by name.
+    #[serde(skip)]
+    pub files: BTreeMap<String, String>,
+}
+
+/// Checks the signature of a bundle, decrypts it and checks every file
+/// against the manifest.
+pub fn verify(bundle: &str, key: &TranscriptKey) -> Result<VerifiedTranscript> {
+    let bundle: TranscriptBundle =
+        serde_json::from_str(bundle).context("not a transcript bundle")?;
+    let TranscriptBundle { body, signature } = bundle;
+    if body.format != FORMAT {
+        bail!("unsupported transcript format: {}", body.format);
+    }
+    if signature.alg != SIGNATURE_ALG {
+        bail!("unsupported signature algorithm: {}", signature.alg);
+    }
+    if signature.key_id != key.id() {
+        bail!(
+            "bundle was signed with key {}, but the keyfile is key {}",
+            signature.key_id,
+            key.id()
+        );
+    }
+    let mut mac = key.mac();
+    mac.update(&serde_json::to_vec(&body)?);
+    let expected = BASE64
+        .decode(&signature.value)
+        .context("decoding signature")?;
+    mac.verify_slice(&expected)
+        .map_err(|_| anyhow!("signature mismatch: the bundle was modified"))?;
+
+    let payload = BASE64.decode(&body.payload).context("decoding payload")?;
+    let plaintext = match &body.encryption {
+        None => payload,
+        Some(encryption) => {
+            if encryption.alg != ENCRYPTION_ALG {
+                bail!("unsupported encryption algorithm: {}", encryption.alg);
+            }
+            let nonce = BASE64.decode(&encryption.nonce).context("decoding nonce")?;
+            if nonce.len() != 12 {
+                bail!("invalid nonce length: {}", nonce.len());
+            }
+            key.cipher()
+                .decrypt(Nonce::from_slice(&nonce), payload.as_slice())
+                .map_err(|_| anyhow!("decrypting transcript failed"))?
+        }
+    };
+    let files: BTreeMap<String, String> =
+        serde_json::from_slice(&plaintext).context("parsing transcript files")?;
+
+    for entry in &body.manifest {
+        let contents = files
+            .get(&entry.name)
+            .ok_or_else(|| anyhow!("{} is listed in the manifest but missing", entry.name))?;
+        if contents.len() as u64 != entry.bytes
+            || hex(&Sha256::digest(contents.as_bytes())) != entry.sha256
+        {
+            bail!("{} does not match its manifest digest", entry.name);
+        }
+    }
+    if let Some(extra) = files
+        .keys()
+        .find(|name| !body.manifest.iter().any(|e| &e.name == *name))
+    {
+        bail!("{extra} is not listed in the manifest");
+    }
+
+    Ok(VerifiedTranscript {
+        run_id: body.run_id,
+        backend: body.backend,
+        state: body.state,
+        exported_at: body.exported_at,
+        key_id: signature.key_id,
+        encrypted: body.encryption.is_some(),
+        manifest: body.manifest,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{ChatMessage, RunEvent, RunRequest, RunStatus, RunUsage};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn record() -> RunRecord {
+        let now = OffsetDateTime::now_utc();
+        RunRecord {
+            id: RunId(Uuid::new_v4()),
+            request: RunRequest {
+                prompt: "review the secret patch".into(),
+                ..Default::default()
+            },
+            status: RunStatus {
+                state: RunState::Succeeded,
+                message: None,
+                updated_at: now,
+            },
+            events: vec![RunEvent {
+                ts: now,
+                kind: "completion".into(),
+                data: Some(json!({"text": "looks good"})),
+            }],
+            messages: vec![
+                ChatMessage::user("review the secret patch"),
+                ChatMessage::assistant("looks good"),
+            ],
+            result: None,
+            usage: Some(RunUsage {
+                input_tokens: 12,
+                ..RunUsage::default()
+            }),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn resign(bundle: &mut TranscriptBundle, key: &TranscriptKey) {
+        bundle.signature.value = BASE64.encode(sign(key, &bundle.body).unwrap());
+    }
+
+    #[test]
+    fn bundles_round_trip_with_and_without_encryption() {
+        let key = TranscriptKey::generate();
+        let record = record();
+        for encrypt in [false, true] {
+            let bundle = export(&record, &key, encrypt).unwrap();
+            let payload = BASE64.decode(&bundle.body.payload).unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&payload).contains("looks good"),
+                !encrypt
+            );
+            let text = serde_json::to_string_pretty(&bundle).unwrap();
+            let verified = verify(&text, &key).unwrap();
+            assert_eq!(verified.run_id, record.id);
+            assert_eq!(verified.encrypted, encrypt);
+            assert_eq!(verified.files["completion.txt"], "looks good");
+            let names: Vec<_> = verified.manifest.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(
+                names,
+                [
+                    "request.json",
+                    "status.json",
+                    "events.json",
+                    "messages.json",
+                    "completion.txt"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn tampering_and_wrong_keys_are_rejected() {
+        let key = TranscriptKey::generate();
+        let mut bundle = export(&record(), &key, false).unwrap();
+
+        let mut tampered = bundle.clone();
+        tampered.body.state = RunState::Failed;
+        let err = verify(&serde_json::to_string(&tampered).unwrap(), &key).unwrap_err();
+        assert!(err.to_string().contains("signature mismatch"), "{err}");
+
+        let other = TranscriptKey::generate();
+        let err = verify(&serde_json::to_string(&bundle).unwrap(), &other).unwrap_err();
+        assert!(err.to_string().contains("signed with key"), "{err}");
+
+        // A consistently signed bundle whose manifest lies about a file.
+        bundle.body.manifest[0].sha256 = hex(&Sha256::digest(b"other"));
+        resign(&mut bundle, &key);
+        let err = verify(&serde_json::to_string(&bundle).unwrap(), &key).unwrap_err();
+        assert!(
+            err.to_string().contains("request.json does not match"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn keyfile_is_created_once_and_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys/transcript.key");
+        let created = TranscriptKey::load_or_create(&path).unwrap();
+        let loaded = TranscriptKey::load_or_create(&path).unwrap();
+        assert_eq!(created.id(), loaded.id());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::write(&path, "c2hvcnQ=\n").unwrap();
+        assert!(TranscriptKey::load(&path).is_err());
+    }
+}
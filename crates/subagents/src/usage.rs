@@ -0,0 +1,366 @@
+//! Token usage and cost reporting.
+//!
+//! The HTTP backends record the `usage` block of every provider response on
+//! the run (see [`RunUsage`]). Costs are not stored; they are computed when a
+//! summary is built, from the `[prices]` table in `subagents.toml`, so a
+//! corrected price applies to past runs too.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::store::{RunRecord, RunUsage};
+
+/// Key used for runs launched without a template.
+const NO_TEMPLATE: &str = "(none)";
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    /// Price of cached input tokens; the full input price when unset.
+    #[serde(default)]
+    pub cached_input: Option<f64>,
+    /// Price of input tokens written to the prompt cache; the full input
+    /// price when unset.
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+}
+
+/// Looks up the price for `model`. Providers report dated snapshots
+/// (`gpt-4o-mini-2024-07-18`), so the longest configured prefix wins when
+/// there is no exact entry.
+pub fn price_for<'a>(
+    prices: &'a BTreeMap<String, ModelPrice>,
+    model: &str,
+) -> Option<&'a ModelPrice> {
+    prices.get(model).or_else(|| {
+        prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| price)
+    })
+}
+
+/// Cost of `usage` in USD, or `None` when the model has no price.
+pub fn cost(usage: &RunUsage, prices: &BTreeMap<String, ModelPrice>) -> Option<f64> {
+    let price = price_for(prices, usage.model.as_deref()?)?;
+    let cached = price.cached_input.unwrap_or(price.input);
+    let cache_write = price.cache_write.unwrap_or(price.input);
+    Some(
+        (usage.input_tokens as f64 * price.input
+            + usage.cached_tokens as f64 * cached
+            + usage.cache_write_tokens as f64 * cache_write
+            + usage.output_tokens as f64 * price.output)
+            / 1_000_000.0,
+    )
+}
+
+/// Usage summed over a group of runs.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct UsageTotals {
+    pub runs: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub cost_usd: f64,
+    /// Runs that used tokens of a model missing from the price table; their
+    /// cost is not included in `cost_usd`.
+    pub unpriced_runs: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: Option<&RunUsage>, cost: Option<f64>) {
+        self.runs += 1;
+        let Some(usage) = usage else {
+            return;
+        };
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cached_tokens += usage.cached_tokens;
+        self.cache_write_tokens += usage.cache_write_tokens;
+        match cost {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_runs += 1,
+        }
+    }
+}
+
+/// Usage of the runs created since `since`, grouped by backend, template and
+/// UTC day (`YYYY-MM-DD`).
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct UsageSummary {
+    pub since: Option<String>,
+    pub total: UsageTotals,
+    pub by_backend: BTreeMap<String, UsageTotals>,
+    pub by_template: BTreeMap<String, UsageTotals>,
+    pub by_day: BTreeMap<String, UsageTotals>,
+}
+
+/// Start of a window covering the last `days` days.
+pub fn days_ago(days: u32) -> OffsetDateTime {
+    OffsetDateTime::now_utc() - time::Duration::days(days.into())
+}
+
+/// Builds a [`UsageSummary`] from `runs`, skipping those created before
+/// `since`.
+pub fn summarize(
+    runs: &[RunRecord],
+    prices: &BTreeMap<String, ModelPrice>,
+    since: Option<OffsetDateTime>,
+) -> UsageSummary {
+    let mut summary = UsageSummary {
+        since: since.map(|s| s.date().to_string()),
+        ..UsageSummary::default()
+    };
+    for run in runs {
+        if since.is_some_and(|since| run.created_at < since) {
+            continue;
+        }
+        let usage = run.usage.as_ref();
+        let cost = usage.and_then(|u| cost(u, prices));
+        let template = run.request.template_id.as_deref().unwrap_or(NO_TEMPLATE);
+        summary.total.add(usage, cost);
+        for totals in [
+            summary
+                .by_backend
+                .entry(run.request.backend.name().to_string())
+                .or_default(),
+            summary.by_template.entry(template.to_string()).or_default(),
+            summary
+                .by_day
+                .entry(run.created_at.date().to_string())
+                .or_default(),
+        ] {
+            totals.add(usage, cost);
+        }
+    }
+    summary
+}
+
+impl UsageSummary {
+    /// Plain-text report for the CLI.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        match &self.since {
+            Some(since) => {
+                let _ = writeln!(out, "Subagent usage since {since}");
+            }
+            None => out.push_str("Subagent usage (all runs)\n"),
+        }
+        let _ = writeln!(out, "\n{}", row("total", &self.total));
+        for (title, groups) in [
+            ("By backend", &self.by_backend),
+            ("By template", &self.by_template),
+            ("By day", &self.by_day),
+        ] {
+            if groups.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n{title}:");
+            for (key, totals) in groups {
+                let _ = writeln!(out, "  {}", row(key, totals));
+            }
+        }
+        if self.total.unpriced_runs > 0 {
+            let _ = writeln!(
+                out,
+                "\n{} run(s) used models without a price in subagents.toml [prices].",
+                self.total.unpriced_runs
+            );
+        }
+        out
+    }
+}
+
+fn row(key: &str, totals: &UsageTotals) -> String {
+    format!(
+        "{key:<24} {:>5} runs  {:>10} in  {:>10} cached  {:>10} cache-write  {:>10} out  ${:.4}",
+        totals.runs,
+        totals.input_tokens,
+        totals.cached_tokens,
+        totals.cache_write_tokens,
+        totals.output_tokens,
+        totals.cost_usd
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{BackendKind, RunId, RunRequest, RunState, RunStatus};
+    use time::format_description::well_known::Rfc3339;
+    use uuid::Uuid;
+
+    fn at(ts: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(ts, &Rfc3339).unwrap()
+    }
+
+    fn prices() -> BTreeMap<String, ModelPrice> {
+        BTreeMap::from([
+            (
+                "gpt-4o".to_string(),
+                ModelPrice {
+                    input: 2.5,
+                    output: 10.0,
+                    cached_input: Some(1.25),
+                    cache_write: None,
+                },
+            ),
+            (
+                "gpt-4o-mini".to_string(),
+                ModelPrice {
+                    input: 0.15,
+                    output: 0.6,
+                    cached_input: None,
+                    cache_write: None,
+                },
+            ),
+        ])
+    }
+
+    fn run(
+        backend: BackendKind,
+        template: Option<&str>,
+        created_at: OffsetDateTime,
+        usage: Option<RunUsage>,
+    ) -> RunRecord {
+        RunRecord {
+            id: RunId(Uuid::new_v4()),
+            request: RunRequest {
+                backend,
+                prompt: "p".into(),
+                template_id: template.map(String::from),
+                ..Default::default()
+            },
+            status: RunStatus {
+                state: RunState::Succeeded,
+                message: None,
+                updated_at: created_at,
+            },
+            events: Vec::new(),
+            messages: Vec::new(),
+            result: None,
+            usage,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    fn usage(model: &str, input: u64, cached: u64, output: u64) -> Option<RunUsage> {
+        Some(RunUsage {
+            model: Some(model.into()),
+            input_tokens: input,
+            output_tokens: output,
+            cached_tokens: cached,
+            ..RunUsage::default()
+        })
+    }
+
+    #[test]
+    fn prices_match_the_longest_model_prefix() {
+        let prices = prices();
+        assert_eq!(
+            price_for(&prices, "gpt-4o-mini-2024-07-18").unwrap().input,
+            0.15
+        );
+        assert_eq!(price_for(&prices, "gpt-4o-2024-08-06").unwrap().input, 2.5);
+        assert!(price_for(&prices, "claude-sonnet-4-5").is_none());
+
+        let cost = cost(
+            &usage("gpt-4o", 1_000_000, 1_000_000, 100_000).unwrap(),
+            &prices,
+        );
+        assert_eq!(cost, Some(2.5 + 1.25 + 1.0));
+    }
+
+    #[test]
+    fn cache_writes_use_their_own_price() {
+        let prices = BTreeMap::from([(
+            "claude-sonnet-4-5".to_string(),
+            ModelPrice {
+                input: 3.0,
+                output: 15.0,
+                cached_input: Some(0.3),
+                cache_write: Some(3.75),
+            },
+        )]);
+        let usage = RunUsage {
+            model: Some("claude-sonnet-4-5".into()),
+            cache_write_tokens: 1_000_000,
+            ..RunUsage::default()
+        };
+        assert_eq!(cost(&usage, &prices), Some(3.75));
+
+        let unset = BTreeMap::from([(
+            "claude-sonnet-4-5".to_string(),
+            ModelPrice {
+                cache_write: None,
+                ..prices["claude-sonnet-4-5"]
+            },
+        )]);
+        assert_eq!(cost(&usage, &unset), Some(3.0));
+    }
+
+    #[test]
+    fn prices_without_cache_entries_charge_the_input_price() {
+        let price: ModelPrice = toml::from_str("input = 2.0\noutput = 8.0").unwrap();
+        assert_eq!(price.cached_input, None);
+        assert_eq!(price.cache_write, None);
+
+        let prices = BTreeMap::from([("gpt-4.1".to_string(), price)]);
+        let usage = usage("gpt-4.1", 1_000_000, 1_000_000, 0).unwrap();
+        assert_eq!(cost(&usage, &prices), Some(4.0));
+    }
+
+    #[test]
+    fn summary_groups_by_backend_template_and_day() {
+        let day1 = at("2026-03-01T10:00:00Z");
+        let day2 = at("2026-03-02T09:00:00Z");
+        let runs = vec![
+            run(
+                BackendKind::Codex,
+                Some("reviewer"),
+                day1,
+                usage("gpt-4o-mini", 2_000_000, 0, 0),
+            ),
+            run(
+                BackendKind::Codex,
+                None,
+                day2,
+                usage("gpt-4o-mini", 0, 0, 1_000_000),
+            ),
+            run(
+                BackendKind::Claude,
+                Some("reviewer"),
+                day2,
+                usage("claude-haiku-4-5", 10, 0, 5),
+            ),
+            run(BackendKind::CodexCli, None, day2, None),
+        ];
+
+        let summary = summarize(&runs, &prices(), None);
+        assert_eq!(summary.total.runs, 4);
+        assert_eq!(summary.total.input_tokens, 2_000_010);
+        assert_eq!(summary.total.unpriced_runs, 1);
+        assert!((summary.total.cost_usd - 0.9).abs() < 1e-9);
+        assert_eq!(summary.by_backend["codex"].runs, 2);
+        assert_eq!(summary.by_backend["claude"].output_tokens, 5);
+        assert_eq!(summary.by_backend["codex-cli"].input_tokens, 0);
+        assert_eq!(summary.by_template["reviewer"].runs, 2);
+        assert_eq!(summary.by_template[NO_TEMPLATE].runs, 2);
+        assert_eq!(summary.by_day["2026-03-01"].runs, 1);
+        assert_eq!(summary.by_day["2026-03-02"].runs, 3);
+
+        let recent = summarize(&runs, &prices(), Some(day2 - time::Duration::hours(1)));
+        assert_eq!(recent.total.runs, 3);
+        assert!(recent
+            .render_text()
+            .contains("Subagent usage since 2026-03-02"));
+    }
+}
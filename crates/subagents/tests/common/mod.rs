@@ -0,0 +1,57 @@
+//! Helpers shared by the subagent integration tests.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use httpmock::MockServer;
+use skrills_subagents::backend::config::AdapterConfig;
+use skrills_subagents::{BackendKind, RunId, RunRecord, RunRequest, RunStore};
+
+/// Adapter config pointed at `server`, with fast retries.
+pub fn config(server: &MockServer) -> AdapterConfig {
+    AdapterConfig {
+        api_key: "test-key".into(),
+        timeout: Duration::from_secs(5),
+        max_attempts: 3,
+        backoff: Duration::from_millis(1),
+        ..AdapterConfig::new(
+            reqwest::Url::parse(&server.url("/v1/")).unwrap(),
+            "test-model",
+        )
+    }
+}
+
+/// An async "say hello" run on `backend`.
+pub fn request(backend: BackendKind) -> RunRequest {
+    RunRequest {
+        backend,
+        prompt: "say hello".into(),
+        async_mode: true,
+        ..Default::default()
+    }
+}
+
+/// Polls `run_id` until it reaches a terminal state.
+pub async fn wait_for_terminal(store: &Arc<dyn RunStore>, run_id: RunId) -> RunRecord {
+    for _ in 0..500 {
+        let record = store.get_run(run_id).await.unwrap().unwrap();
+        if record.status.state.is_terminal() {
+            return record;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {run_id} did not finish");
+}
+
+pub fn event_kinds(record: &RunRecord) -> Vec<&str> {
+    record.events.iter().map(|e| e.kind.as_str()).collect()
+}
+
+pub fn completion_text(record: &RunRecord) -> Option<String> {
+    record
+        .events
+        .iter()
+        .find(|e| e.kind == "completion")
+        .and_then(|e| e.data.as_ref()?.get("text")?.as_str().map(String::from))
+}
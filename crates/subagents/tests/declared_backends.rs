@@ -0,0 +1,178 @@
+//! Backends declared in `subagents.toml`, served by an httpmock stand-in.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{completion_text, wait_for_terminal};
+use httpmock::prelude::*;
+use serde_json::json;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{BackendKind, RunId, RunState, RunStore, SubagentService, SubagentsConfig};
+
+fn declared_service(config: &str) -> (SubagentService, Arc<dyn RunStore>) {
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let config: SubagentsConfig = toml::from_str(config).unwrap();
+    let service = SubagentService::with_store(store.clone(), BackendKind::Codex)
+        .unwrap()
+        .with_config(config);
+    (service, store)
+}
+
+async fn run(service: &SubagentService, args: serde_json::Value) -> RunId {
+    // Async runs wait for the queue the server starts.
+    service.start().await;
+    let result = service
+        .handle_call("run_subagent_async", args.as_object())
+        .await
+        .unwrap();
+    let run_id = result.structured_content.unwrap()["run_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    RunId(uuid::Uuid::parse_str(&run_id).unwrap())
+}
+
+#[tokio::test]
+async fn openai_chat_backend_runs_without_credentials() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .header_missing("authorization")
+                .json_body_includes(r#"{"model": "qwen2.5-coder"}"#);
+            then.status(200).json_body(json!({
+                "model": "qwen2.5-coder",
+                "choices": [{"message": {"role": "assistant", "content": "from vllm"}}],
+                "usage": {"prompt_tokens": 5, "completion_tokens": 2}
+            }));
+        })
+        .await;
+    let (service, store) = declared_service(&format!(
+        r#"
+[backends.vllm]
+protocol = "openai-chat"
+base_url = "{}"
+model = "qwen2.5-coder"
+"#,
+        server.url("/v1")
+    ));
+
+    let listed = service
+        .handle_call("list_subagents", None)
+        .await
+        .unwrap()
+        .structured_content
+        .unwrap();
+    assert!(listed["templates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|t| t["backend"] == "vllm"));
+
+    let run_id = run(&service, json!({"prompt": "hi", "backend": "VLLM"})).await;
+    let record = wait_for_terminal(&store, run_id).await;
+    mock.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(record.request.backend, BackendKind::Other("vllm".into()));
+    assert_eq!(completion_text(&record).as_deref(), Some("from vllm"));
+    assert_eq!(record.usage.unwrap().input_tokens, 5);
+}
+
+#[tokio::test]
+async fn declared_key_is_sent_in_the_configured_header() {
+    // Cargo sets CARGO_PKG_NAME for the test process, so the key variable is
+    // present without mutating the environment other tests read.
+    let key = std::env::var("CARGO_PKG_NAME").unwrap();
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/chat/completions")
+                .header("x-llama-key", &key);
+            then.status(200).json_body(json!({
+                "choices": [{"message": {"role": "assistant", "content": "ok"}}]
+            }));
+        })
+        .await;
+    let (service, store) = declared_service(&format!(
+        r#"
+[backends.llama]
+protocol = "openai-chat"
+base_url = "{}"
+model = "local"
+api_key_env = "CARGO_PKG_NAME"
+auth_header = "x-llama-key"
+"#,
+        server.base_url()
+    ));
+
+    let run_id = run(&service, json!({"prompt": "hi", "backend": "llama"})).await;
+    let record = wait_for_terminal(&store, run_id).await;
+    mock.assert_async().await;
+    assert_eq!(completion_text(&record).as_deref(), Some("ok"));
+
+    // A declared key variable that is unset fails the run up front.
+    let (service, store) = declared_service(
+        r#"
+[backends.remote]
+protocol = "anthropic-messages"
+base_url = "http://127.0.0.1:9/v1"
+model = "claude-sonnet-4-5"
+api_key_env = "SKRILLS_TEST_UNSET_KEY"
+"#,
+    );
+    let run_id = run(&service, json!({"prompt": "hi", "backend": "remote"})).await;
+    let record = wait_for_terminal(&store, run_id).await;
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(
+        record.status.message.as_deref(),
+        Some("missing SKRILLS_TEST_UNSET_KEY")
+    );
+}
+
+#[tokio::test]
+async fn ollama_backend_streams_ndjson_chunks() {
+    let server = MockServer::start_async().await;
+    let lines = [
+        json!({"model": "llama3.2", "message": {"role": "assistant", "content": "Hel"}, "done": false}),
+        json!({"model": "llama3.2", "message": {"role": "assistant", "content": "lo"}, "done": false}),
+        json!({"model": "llama3.2", "message": {"role": "assistant", "content": ""}, "done": true, "prompt_eval_count": 7, "eval_count": 2}),
+    ];
+    let body: String = lines.iter().map(|l| format!("{l}\n")).collect();
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/api/chat")
+                .json_body_includes(r#"{"model": "llama3.2", "stream": true}"#);
+            then.status(200)
+                .header("content-type", "application/x-ndjson")
+                .body(body);
+        })
+        .await;
+    let (service, store) = declared_service(&format!(
+        r#"
+[backends.ollama]
+protocol = "ollama"
+base_url = "{}"
+model = "llama3.2"
+"#,
+        server.base_url()
+    ));
+
+    let run_id = run(&service, json!({"prompt": "hi", "backend": "ollama"})).await;
+    let record = wait_for_terminal(&store, run_id).await;
+    mock.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    let tokens: Vec<_> = record
+        .events
+        .iter()
+        .filter(|e| e.kind == "stream")
+        .map(|e| e.data.as_ref().unwrap()["token"].clone())
+        .collect();
+    assert_eq!(tokens, [json!("Hel"), json!("lo")]);
+    assert_eq!(completion_text(&record).as_deref(), Some("Hello"));
+    let usage = record.usage.unwrap();
+    assert_eq!((usage.input_tokens, usage.output_tokens), (7, 2));
+}
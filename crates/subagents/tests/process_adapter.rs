@@ -0,0 +1,223 @@
+//! Process backend tests driven by fake `codex` / `claude` executables.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{completion_text, event_kinds, request, wait_for_terminal};
+use skrills_subagents::backend::process::{CliKind, ProcessAdapter};
+use skrills_subagents::backend::BackendAdapter;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{BackendKind, RunState, RunStore, SubagentTemplate};
+use tempfile::TempDir;
+
+fn fake_cli(dir: &Path, body: &str) -> PathBuf {
+    let path = dir.join("fake-cli");
+    fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn claude_stream_json_becomes_run_events() {
+    let tmp = TempDir::new().unwrap();
+    let args_file = tmp.path().join("args");
+    let script = fake_cli(
+        tmp.path(),
+        &format!(
+            r#"printf '%s\n' "$@" > '{}'
+echo 'not json: starting up'
+echo '{{"type":"system","subtype":"init","session_id":"s1"}}'
+echo '{{"type":"assistant","message":{{"content":[{{"type":"text","text":"Checking."}},{{"type":"tool_use","id":"t1","name":"Read","input":{{}}}}]}}}}'
+echo '{{"type":"assistant","message":{{"content":[{{"type":"text","text":"Hello!"}}]}}}}'
+echo '{{"type":"result","subtype":"success","is_error":false,"result":"Hello!"}}'"#,
+            args_file.display()
+        ),
+    );
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ProcessAdapter::new(CliKind::Claude, script);
+    let run_id = adapter
+        .run(request(BackendKind::ClaudeCli), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(record.request.backend, BackendKind::ClaudeCli);
+    assert_eq!(
+        event_kinds(&record),
+        vec!["start", "stream", "tool", "stream", "completion"]
+    );
+    assert_eq!(completion_text(&record).as_deref(), Some("Hello!"));
+    let args = fs::read_to_string(args_file).unwrap();
+    assert_eq!(
+        args.lines().collect::<Vec<_>>(),
+        vec![
+            "-p",
+            "--output-format",
+            "stream-json",
+            "--verbose",
+            "say hello"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn codex_exec_json_becomes_run_events() {
+    let tmp = TempDir::new().unwrap();
+    let script = fake_cli(
+        tmp.path(),
+        r#"echo '{"type":"thread.started","thread_id":"th1"}'
+echo '{"type":"turn.started"}'
+echo '{"type":"item.completed","item":{"id":"item_0","type":"command_execution","command":"ls","status":"completed"}}'
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"All set."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":5,"output_tokens":2}}'"#,
+    );
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ProcessAdapter::new(CliKind::Codex, script);
+    let run_id = adapter
+        .run(request(BackendKind::CodexCli), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(
+        event_kinds(&record),
+        vec!["start", "tool", "stream", "completion"]
+    );
+    assert_eq!(completion_text(&record).as_deref(), Some("All set."));
+}
+
+#[tokio::test]
+async fn failed_result_and_silent_exit_fail_the_run() {
+    let tmp = TempDir::new().unwrap();
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+
+    let script = fake_cli(
+        tmp.path(),
+        r#"echo '{"type":"result","subtype":"error_during_execution","is_error":true,"result":"Credit balance is too low"}'"#,
+    );
+    let adapter = ProcessAdapter::new(CliKind::Claude, script);
+    let run_id = adapter
+        .run(request(BackendKind::ClaudeCli), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(
+        record.status.message.as_deref(),
+        Some("Credit balance is too low")
+    );
+
+    let script = fake_cli(tmp.path(), "echo 'not logged in' >&2\nexit 1");
+    let adapter = ProcessAdapter::new(CliKind::Codex, script);
+    let run_id = adapter
+        .run(request(BackendKind::CodexCli), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+    assert_eq!(record.status.state, RunState::Failed);
+    let message = record.status.message.unwrap();
+    assert!(message.contains("without a result"), "{message}");
+    assert!(message.contains("not logged in"), "{message}");
+}
+
+/// True when `pid` no longer names a live (non-zombie) process.
+#[cfg(target_os = "linux")]
+fn is_dead(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{pid}/stat")) {
+        Ok(stat) => stat
+            .rsplit_once(") ")
+            .is_some_and(|(_, rest)| rest.starts_with('Z')),
+        Err(_) => true,
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn cancel_kills_the_process_group() {
+    let tmp = TempDir::new().unwrap();
+    let pids = tmp.path().join("pids");
+    let script = fake_cli(
+        tmp.path(),
+        &format!(
+            r#"sleep 30 &
+echo "$$ $!" > '{}'
+echo '{{"type":"assistant","message":{{"content":[{{"type":"text","text":"working"}}]}}}}'
+wait"#,
+            pids.display()
+        ),
+    );
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ProcessAdapter::new(CliKind::Claude, script);
+    let handle = adapter
+        .start(request(BackendKind::ClaudeCli), store.clone())
+        .await
+        .unwrap();
+    // Wait until the child has spawned its grandchild and started streaming.
+    for _ in 0..500 {
+        let record = store.get_run(handle.run_id).await.unwrap().unwrap();
+        if record.events.iter().any(|e| e.kind == "stream") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let pids: Vec<u32> = fs::read_to_string(&pids)
+        .unwrap()
+        .split_whitespace()
+        .map(|p| p.parse().unwrap())
+        .collect();
+    assert!(pids.iter().all(|pid| !is_dead(*pid)));
+
+    handle.cancel.cancel();
+    let canceled = tokio::time::timeout(Duration::from_secs(5), handle.task)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(canceled);
+
+    let record = store.get_run(handle.run_id).await.unwrap().unwrap();
+    assert_eq!(record.status.state, RunState::Canceled);
+    assert_eq!(record.events.last().unwrap().kind, "canceled");
+    for _ in 0..100 {
+        if pids.iter().all(|pid| is_dead(*pid)) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("process group {pids:?} survived cancellation");
+}
+
+#[tokio::test]
+async fn codex_rejects_templates_with_a_tools_allowlist() {
+    let tmp = TempDir::new().unwrap();
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ProcessAdapter::new(CliKind::Codex, fake_cli(tmp.path(), "exit 0"));
+    let mut request = request(BackendKind::CodexCli);
+    request.template = Some(SubagentTemplate {
+        id: "reviewer".into(),
+        name: "reviewer".into(),
+        description: None,
+        backend: BackendKind::CodexCli,
+        capabilities: Vec::new(),
+        model: None,
+        system_prompt: None,
+        max_tokens: None,
+        tools: vec!["Read".into()],
+        output_schema: None,
+        source: None,
+    });
+
+    let err = adapter.run(request, store.clone()).await.unwrap_err();
+    assert!(err.to_string().contains("reviewer"), "{err}");
+    assert!(store.history(10).await.unwrap().is_empty());
+}
@@ -0,0 +1,124 @@
+//! Retry and rate-limit handling for the HTTP backends, against an httpmock
+//! provider.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{config, request, wait_for_terminal};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use skrills_subagents::backend::codex::CodexAdapter;
+use skrills_subagents::backend::BackendAdapter;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{BackendKind, RunRecord, RunState, RunStore};
+
+fn attempts(record: &RunRecord) -> Vec<&Value> {
+    record
+        .events
+        .iter()
+        .filter(|e| e.kind == "attempt")
+        .filter_map(|e| e.data.as_ref())
+        .collect()
+}
+
+#[tokio::test]
+async fn rate_limited_call_is_retried_after_the_requested_delay() {
+    let server = MockServer::start_async().await;
+    let limited = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(429)
+                .header("retry-after-ms", "250")
+                .json_body(json!({"error": {"message": "rate limited"}}));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+
+    // Swap in a healthy provider while the adapter waits out `retry-after-ms`.
+    while limited.calls_async().await == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    limited.delete_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200).json_body(json!({
+                "choices": [{"message": {"role": "assistant", "content": "hello"}}]
+            }));
+        })
+        .await;
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Succeeded);
+    let attempts = attempts(&record);
+    assert_eq!(attempts.len(), 2);
+    assert_eq!(
+        *attempts[0],
+        json!({"attempt": 1, "max_attempts": 3, "status": 429, "retry_in_ms": 250})
+    );
+    assert_eq!(
+        *attempts[1],
+        json!({"attempt": 2, "max_attempts": 3, "status": 200})
+    );
+}
+
+#[tokio::test]
+async fn server_errors_fail_the_run_once_attempts_run_out() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(503)
+                .json_body(json!({"error": {"message": "overloaded"}}));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(mock.calls_async().await, 3);
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(record.status.message.as_deref(), Some("overloaded"));
+    let attempts = attempts(&record);
+    assert_eq!(attempts.len(), 3);
+    assert!(attempts[..2].iter().all(|a| a.get("retry_in_ms").is_some()));
+    assert!(attempts[2].get("retry_in_ms").is_none());
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(401)
+                .json_body(json!({"error": {"message": "bad key"}}));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(mock.calls_async().await, 1);
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(record.status.message.as_deref(), Some("bad key"));
+    assert_eq!(attempts(&record).len(), 1);
+}
@@ -0,0 +1,129 @@
+//! `output_schema` validation and repair turns, against an httpmock model.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{config, wait_for_terminal};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use skrills_subagents::backend::codex::CodexAdapter;
+use skrills_subagents::backend::config::AdapterConfig;
+use skrills_subagents::backend::BackendAdapter;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{ChatRole, RunRecord, RunRequest, RunState, RunStore};
+
+fn request() -> RunRequest {
+    RunRequest {
+        prompt: "review the patch".into(),
+        output_schema: Some(json!({
+            "type": "object",
+            "required": ["verdict"],
+            "properties": {"verdict": {"type": "string", "enum": ["approve", "reject"]}}
+        })),
+        async_mode: true,
+        ..Default::default()
+    }
+}
+
+fn reply(content: &str) -> Value {
+    json!({"choices": [{"message": {"role": "assistant", "content": content}}]})
+}
+
+fn schema_errors(record: &RunRecord) -> Vec<&Value> {
+    record
+        .events
+        .iter()
+        .filter(|e| e.kind == "schema_error")
+        .filter_map(|e| e.data.as_ref())
+        .collect()
+}
+
+#[tokio::test]
+async fn invalid_output_is_repaired_and_parsed_into_result() {
+    let server = MockServer::start_async().await;
+    // Registered first: the opening request is a prefix of the repair turn.
+    let repaired = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .json_body_includes(
+                    json!({"messages": [{}, {"role": "assistant"}, {"role": "user"}]}).to_string(),
+                );
+            then.status(200)
+                .json_body(reply("```json\n{\"verdict\": \"approve\"}\n```"));
+        })
+        .await;
+    let first = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200).json_body(reply("Looks fine to me."));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter.run(request(), store.clone()).await.unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    first.assert_async().await;
+    repaired.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(record.result, Some(json!({"verdict": "approve"})));
+    let errors = schema_errors(&record);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["output"], "Looks fine to me.");
+    assert_eq!(errors[0]["repair"], true);
+    let repair_turn = &record.messages[2];
+    assert_eq!(repair_turn.role, ChatRole::User);
+    assert!(repair_turn.content.contains("output is not valid JSON"));
+    assert_eq!(record.messages.len(), 4);
+}
+
+#[tokio::test]
+async fn repairs_are_bounded_and_end_in_schema_failed() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200).json_body(reply(r#"{"verdict": "maybe"}"#));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter.run(request(), store.clone()).await.unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(mock.calls_async().await, 3);
+    assert_eq!(record.status.state, RunState::SchemaFailed);
+    let message = record.status.message.as_deref().unwrap();
+    assert!(message.starts_with("output failed output_schema after 2 repairs"));
+    assert!(message.contains("/verdict"));
+    assert_eq!(record.result, None);
+    assert_eq!(schema_errors(&record).len(), 3);
+    assert!(!record.events.iter().any(|e| e.kind == "completion"));
+}
+
+#[tokio::test]
+async fn repair_count_follows_the_adapter_config() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200).json_body(reply(r#"{"verdict": "maybe"}"#));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(AdapterConfig {
+        schema_repairs: 0,
+        ..config(&server)
+    });
+    let run_id = adapter.run(request(), store.clone()).await.unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(mock.calls_async().await, 1);
+    assert_eq!(record.status.state, RunState::SchemaFailed);
+    assert_eq!(schema_errors(&record)[0]["repair"], false);
+}
@@ -0,0 +1,316 @@
+//! Streaming and cancellation tests for the HTTP backends, served by an
+//! httpmock stand-in.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{completion_text, config, request, wait_for_terminal};
+use httpmock::prelude::*;
+use serde_json::json;
+use skrills_subagents::backend::claude::ClaudeAdapter;
+use skrills_subagents::backend::codex::CodexAdapter;
+use skrills_subagents::backend::BackendAdapter;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{BackendKind, ChatMessage, RunRecord, RunState, RunStore};
+
+fn stream_tokens(record: &RunRecord) -> Vec<String> {
+    record
+        .events
+        .iter()
+        .filter(|e| e.kind == "stream")
+        .filter_map(|e| e.data.as_ref()?.get("token")?.as_str().map(String::from))
+        .collect()
+}
+
+#[tokio::test]
+async fn codex_streams_chat_completion_deltas() {
+    let server = MockServer::start_async().await;
+    let chunk = |content: &str| {
+        format!(
+            "data: {}\n\n",
+            json!({"choices": [{"index": 0, "delta": {"content": content}}]})
+        )
+    };
+    let body = format!(
+        "data: {}\n\n{}{}: keep-alive\n\n{}data: {}\n\ndata: [DONE]\n\n",
+        json!({"choices": [{"index": 0, "delta": {"role": "assistant"}}]}),
+        chunk("Hel"),
+        chunk("lo, "),
+        chunk("world"),
+        json!({
+            "model": "test-model-0613",
+            "choices": [],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 3}
+        }),
+    );
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .header("authorization", "Bearer test-key")
+                .json_body_includes(
+                    r#"{"stream": true, "stream_options": {"include_usage": true}}"#,
+                );
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(body);
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    mock.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(stream_tokens(&record), vec!["Hel", "lo, ", "world"]);
+    assert_eq!(completion_text(&record).as_deref(), Some("Hello, world"));
+    let usage = record.usage.unwrap();
+    assert_eq!(usage.model.as_deref(), Some("test-model-0613"));
+    assert_eq!((usage.input_tokens, usage.output_tokens), (9, 3));
+}
+
+#[tokio::test]
+async fn codex_falls_back_to_json_body() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200)
+                .json_body(json!({"choices": [{"message": {"content": "whole reply"}}]}));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert!(stream_tokens(&record).is_empty());
+    assert_eq!(completion_text(&record).as_deref(), Some("whole reply"));
+}
+
+#[tokio::test]
+async fn claude_streams_message_deltas() {
+    let server = MockServer::start_async().await;
+    let event = |name: &str, data: serde_json::Value| format!("event: {name}\ndata: {data}\n\n");
+    let body = [
+        event(
+            "message_start",
+            json!({"type": "message_start", "message": {"id": "msg_1"}}),
+        ),
+        event(
+            "content_block_start",
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        ),
+        event("ping", json!({"type": "ping"})),
+        event(
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+        ),
+        event(
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": " there"}}),
+        ),
+        event(
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": 0}),
+        ),
+        event(
+            "message_delta",
+            json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}),
+        ),
+        event("message_stop", json!({"type": "message_stop"})),
+    ]
+    .concat();
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/messages")
+                .header("x-api-key", "test-key")
+                .header("anthropic-version", "2023-06-01");
+            then.status(200)
+                .header("content-type", "text/event-stream; charset=utf-8")
+                .body(body);
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ClaudeAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Claude), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    mock.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(stream_tokens(&record), vec!["Hi", " there"]);
+    assert_eq!(completion_text(&record).as_deref(), Some("Hi there"));
+}
+
+#[tokio::test]
+async fn claude_stream_error_event_fails_run() {
+    let server = MockServer::start_async().await;
+    let body = format!(
+        "event: content_block_delta\ndata: {}\n\nevent: error\ndata: {}\n\n",
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "partial"}}),
+        json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+    );
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/messages");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(body);
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ClaudeAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Claude), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(record.status.message.as_deref(), Some("Overloaded"));
+    assert_eq!(stream_tokens(&record), vec!["partial"]);
+    assert!(completion_text(&record).is_none());
+}
+
+#[tokio::test]
+async fn codex_stream_cut_off_before_done_fails_run() {
+    let server = MockServer::start_async().await;
+    let body = format!(
+        "data: {}\n\n",
+        json!({"choices": [{"index": 0, "delta": {"content": "partial"}}]}),
+    );
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(body);
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(stream_tokens(&record), vec!["partial"]);
+    assert!(completion_text(&record).is_none());
+}
+
+#[tokio::test]
+async fn follow_up_turn_sends_the_full_conversation() {
+    let server = MockServer::start_async().await;
+    // Register the follow-up first: the opening turn is a prefix of it.
+    let second = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/messages").json_body_includes(
+                json!({"messages": [
+                    {"role": "user", "content": "say hello"},
+                    {"role": "assistant", "content": "Hello!"},
+                    {"role": "user", "content": "now in French"}
+                ]})
+                .to_string(),
+            );
+            then.status(200)
+                .json_body(json!({"content": [{"type": "text", "text": "Bonjour !"}]}));
+        })
+        .await;
+
+    let first = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/messages")
+                .json_body_includes(r#"{"messages": [{"role": "user", "content": "say hello"}]}"#);
+            then.status(200)
+                .json_body(json!({"content": [{"type": "text", "text": "Hello!"}]}));
+        })
+        .await;
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ClaudeAdapter::with_config(config(&server));
+    let mut req = request(BackendKind::Claude);
+    req.async_mode = false;
+    let run_id = adapter.run(req, store.clone()).await.unwrap();
+    wait_for_terminal(&store, run_id).await;
+    first.assert_async().await;
+
+    store
+        .begin_turn(run_id, ChatMessage::user("now in French"))
+        .await
+        .unwrap();
+    adapter.resume(run_id, store.clone()).await.unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    second.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(
+        record.messages,
+        vec![
+            ChatMessage::user("say hello"),
+            ChatMessage::assistant("Hello!"),
+            ChatMessage::user("now in French"),
+            ChatMessage::assistant("Bonjour !"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn cancel_aborts_in_flight_request() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/chat/completions");
+            then.status(200)
+                .delay(Duration::from_secs(30))
+                .json_body(json!({"choices": [{"message": {"content": "too late"}}]}));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = CodexAdapter::with_config(config(&server));
+    let handle = adapter
+        .start(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    for _ in 0..200 {
+        if mock.calls_async().await > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(mock.calls_async().await, 1, "request should be in flight");
+
+    handle.cancel.cancel();
+    let canceled = tokio::time::timeout(Duration::from_secs(2), handle.task)
+        .await
+        .expect("cancellation should not wait for the response")
+        .unwrap();
+    assert!(canceled);
+
+    let record = store.get_run(handle.run_id).await.unwrap().unwrap();
+    assert_eq!(record.status.state, RunState::Canceled);
+    assert_eq!(record.events.last().unwrap().kind, "canceled");
+    assert!(completion_text(&record).is_none());
+    assert!(!adapter.stop(handle.run_id, store.clone()).await.unwrap());
+}
@@ -69,12 +69,8 @@ mod subagent_service_tests {
 
         // Create a run request
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Test prompt for subagent execution".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         // Create the run via the store
@@ -102,12 +98,8 @@ mod subagent_service_tests {
 
         // Create a run request
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Simple test".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         // Create the run
@@ -156,12 +148,8 @@ mod subagent_service_tests {
 
         // Create a run request
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "This will fail".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         // Create the run
@@ -198,12 +186,9 @@ mod subagent_service_tests {
 
         // Create a run request (note: async_mode handles timeouts in real implementation)
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Long running task".to_string(),
-            template_id: None,
-            output_schema: None,
             async_mode: true, // Use async for potentially long tasks
-            tracing: false,
+            ..Default::default()
         };
 
         // Create the run
@@ -236,12 +221,8 @@ mod subagent_service_tests {
 
         for i in 0..3 {
             let request = RunRequest {
-                backend: BackendKind::Codex,
                 prompt: format!("Test run {}", i),
-                template_id: None,
-                output_schema: None,
-                async_mode: false,
-                tracing: false,
+                ..Default::default()
             };
 
             let run_id: RunId = ctx.store.create_run(request).await.unwrap();
@@ -302,12 +283,8 @@ mod subagent_service_tests {
 
         // Test Codex backend
         let codex_request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Codex test".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         let codex_run_id: RunId = ctx.store.create_run(codex_request).await.unwrap();
@@ -318,10 +295,7 @@ mod subagent_service_tests {
         let claude_request = RunRequest {
             backend: BackendKind::Claude,
             prompt: "Claude test".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         let claude_run_id: RunId = ctx.store.create_run(claude_request).await.unwrap();
@@ -339,12 +313,9 @@ mod subagent_service_tests {
         let ctx = TestContext::new().unwrap();
 
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Test with template".to_string(),
             template_id: Some("test-template".to_string()),
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         let run_id: RunId = ctx.store.create_run(request).await.unwrap();
@@ -371,12 +342,9 @@ mod subagent_service_tests {
         });
 
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Test with output schema".to_string(),
-            template_id: None,
             output_schema: Some(output_schema.clone()),
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         let run_id: RunId = ctx.store.create_run(request).await.unwrap();
@@ -395,12 +363,8 @@ mod subagent_service_tests {
         let ctx = TestContext::new().unwrap();
 
         let request = RunRequest {
-            backend: BackendKind::Codex,
             prompt: "Event tracking test".to_string(),
-            template_id: None,
-            output_schema: None,
-            async_mode: false,
-            tracing: false,
+            ..Default::default()
         };
 
         let run_id = ctx.store.create_run(request).await.unwrap();
@@ -0,0 +1,228 @@
+//! Tool-use loop tests for the HTTP backends, with an httpmock model and an
+//! in-process tool provider.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::{config, event_kinds, request, wait_for_terminal};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use skrills_subagents::backend::claude::ClaudeAdapter;
+use skrills_subagents::backend::codex::CodexAdapter;
+use skrills_subagents::backend::BackendAdapter;
+use skrills_subagents::store::MemRunStore;
+use skrills_subagents::{
+    BackendKind, RunState, RunStore, ToolLoop, ToolOutput, ToolProvider, ToolSpec,
+};
+
+/// Offers a single `files__read` tool and records every call made to it.
+#[derive(Default)]
+struct FakeFiles {
+    calls: Mutex<Vec<Value>>,
+}
+
+#[async_trait]
+impl ToolProvider for FakeFiles {
+    async fn list_tools(&self) -> Result<Vec<ToolSpec>> {
+        Ok(vec![ToolSpec {
+            name: "files__read".into(),
+            description: Some("Read a file".into()),
+            input_schema: json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+        }])
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolOutput> {
+        assert_eq!(name, "files__read");
+        self.calls.lock().unwrap().push(arguments);
+        Ok(ToolOutput {
+            content: "fn main() {}".into(),
+            is_error: false,
+        })
+    }
+}
+
+#[tokio::test]
+async fn codex_runs_tool_calls_until_the_model_answers() {
+    let server = MockServer::start_async().await;
+    // Registered first: the opening request is a prefix of the follow-up.
+    let answer = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .json_body_includes(
+                    json!({"messages": [
+                        {},
+                        {"role": "assistant"},
+                        {"role": "tool", "tool_call_id": "call_1", "content": "fn main() {}"}
+                    ]})
+                    .to_string(),
+                );
+            then.status(200).json_body(json!({
+                "choices": [{"message": {"role": "assistant", "content": "An empty main."}}]
+            }));
+        })
+        .await;
+    let tool_call = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .json_body_includes(r#"{"stream": false, "tools": [{"type": "function", "function": {"name": "files__read"}}]}"#);
+            then.status(200).json_body(json!({
+                "choices": [{"message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "files__read", "arguments": "{\"path\":\"main.rs\"}"}
+                    }]
+                }}]
+            }));
+        })
+        .await;
+
+    let files = Arc::new(FakeFiles::default());
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter =
+        CodexAdapter::with_config(config(&server)).with_tools(ToolLoop::new(files.clone()));
+    let run_id = adapter
+        .run(request(BackendKind::Codex), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    tool_call.assert_async().await;
+    answer.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(
+        event_kinds(&record),
+        vec![
+            "start",
+            "attempt",
+            "tool_call",
+            "tool_result",
+            "attempt",
+            "completion"
+        ]
+    );
+    let start = record.events[0].data.as_ref().unwrap();
+    assert_eq!(start["offered_tools"], json!(["files__read"]));
+    assert_eq!(start["stream"], false);
+    assert_eq!(
+        record.events[2].data,
+        Some(json!({"id": "call_1", "name": "files__read", "arguments": {"path": "main.rs"}}))
+    );
+    assert_eq!(
+        *files.calls.lock().unwrap(),
+        vec![json!({"path": "main.rs"})]
+    );
+    assert_eq!(record.messages.last().unwrap().content, "An empty main.");
+}
+
+#[tokio::test]
+async fn claude_runs_tool_use_blocks_until_the_model_answers() {
+    let server = MockServer::start_async().await;
+    let answer = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/messages").json_body_includes(
+                json!({"messages": [
+                    {},
+                    {"role": "assistant"},
+                    {"role": "user", "content": [{
+                        "type": "tool_result",
+                        "tool_use_id": "toolu_1",
+                        "content": "fn main() {}",
+                        "is_error": false
+                    }]}
+                ]})
+                .to_string(),
+            );
+            then.status(200).json_body(json!({
+                "content": [{"type": "text", "text": "An empty main."}],
+                "stop_reason": "end_turn"
+            }));
+        })
+        .await;
+    let tool_use = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/messages")
+                .json_body_includes(r#"{"stream": false, "tools": [{"name": "files__read"}]}"#);
+            then.status(200).json_body(json!({
+                "content": [
+                    {"type": "text", "text": "Let me look."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "files__read", "input": {"path": "main.rs"}}
+                ],
+                "stop_reason": "tool_use"
+            }));
+        })
+        .await;
+
+    let files = Arc::new(FakeFiles::default());
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter =
+        ClaudeAdapter::with_config(config(&server)).with_tools(ToolLoop::new(files.clone()));
+    let run_id = adapter
+        .run(request(BackendKind::Claude), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    tool_use.assert_async().await;
+    answer.assert_async().await;
+    assert_eq!(record.status.state, RunState::Succeeded);
+    assert_eq!(
+        event_kinds(&record),
+        vec![
+            "start",
+            "attempt",
+            "tool_call",
+            "tool_result",
+            "attempt",
+            "completion"
+        ]
+    );
+    assert_eq!(
+        *files.calls.lock().unwrap(),
+        vec![json!({"path": "main.rs"})]
+    );
+}
+
+#[tokio::test]
+async fn tool_loop_stops_at_the_iteration_limit() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/v1/messages");
+            then.status(200).json_body(json!({
+                "content": [{"type": "tool_use", "id": "toolu_1", "name": "files__read", "input": {}}],
+                "stop_reason": "tool_use"
+            }));
+        })
+        .await;
+
+    let store: Arc<dyn RunStore> = Arc::new(MemRunStore::new());
+    let adapter = ClaudeAdapter::with_config(config(&server))
+        .with_tools(ToolLoop::new(Arc::new(FakeFiles::default())).with_max_iterations(2));
+    let run_id = adapter
+        .run(request(BackendKind::Claude), store.clone())
+        .await
+        .unwrap();
+    let record = wait_for_terminal(&store, run_id).await;
+
+    assert_eq!(mock.calls_async().await, 3);
+    assert_eq!(record.status.state, RunState::Failed);
+    assert_eq!(
+        record.status.message.as_deref(),
+        Some("tool loop exceeded 2 iterations")
+    );
+    let calls = record
+        .events
+        .iter()
+        .filter(|e| e.kind == "tool_call")
+        .count();
+    assert_eq!(calls, 2);
+}
@@ -1,33 +1,52 @@
 //! Claude Code adapter for reading/writing ~/.claude configuration.
 
 use super::traits::{AgentAdapter, FieldSupport};
-use crate::common::{Command, McpServer, Preferences};
+use crate::common::{Command, McpServer, MemoryFile, MemoryScope, Preferences};
+use crate::memory::{self, CLAUDE_MEMORY_FILE, CODEX_MEMORY_SECTION};
 use crate::report::{SkipReason, WriteReport};
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
+use skrills_discovery::{InstalledPlugin, PluginRegistry};
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use walkdir::WalkDir;
 
+/// Placeholder Claude expands to a plugin's install directory in `.mcp.json`.
+const CLAUDE_PLUGIN_ROOT_VAR: &str = "${CLAUDE_PLUGIN_ROOT}";
+
 /// Adapter for Claude Code configuration.
 pub struct ClaudeAdapter {
     root: PathBuf,
+    /// Repository root when syncing project config; `None` for `~/.claude`.
+    project_root: Option<PathBuf>,
 }
 
 impl ClaudeAdapter {
     /// Creates a new ClaudeAdapter with the default root (~/.claude).
     pub fn new() -> Result<Self> {
         let home = dirs::home_dir().context("Could not determine home directory")?;
-        Ok(Self {
-            root: home.join(".claude"),
-        })
+        Ok(Self::with_root(home.join(".claude")))
     }
 
     /// Creates a ClaudeAdapter with a custom root (for testing).
     pub fn with_root(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            project_root: None,
+        }
+    }
+
+    /// Creates a ClaudeAdapter for a repository's `.claude/` directory.
+    ///
+    /// Project MCP servers live in `<project>/.mcp.json` rather than settings,
+    /// and project memory in `<project>/CLAUDE.md`.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self {
+            root: project_root.join(".claude"),
+            project_root: Some(project_root.to_path_buf()),
+        }
     }
 
     fn commands_dir(&self) -> PathBuf {
@@ -42,6 +61,91 @@ impl ClaudeAdapter {
         self.root.join("settings.json")
     }
 
+    fn mcp_config_path(&self) -> PathBuf {
+        match &self.project_root {
+            Some(project) => project.join(".mcp.json"),
+            None => self.settings_path(),
+        }
+    }
+
+    /// Memory files Claude loads for this scope, in load order.
+    fn memory_paths(&self) -> Vec<(PathBuf, MemoryScope)> {
+        match &self.project_root {
+            Some(project) => vec![
+                (project.join(CLAUDE_MEMORY_FILE), MemoryScope::Project),
+                (self.root.join(CLAUDE_MEMORY_FILE), MemoryScope::Project),
+            ],
+            None => vec![(self.root.join(CLAUDE_MEMORY_FILE), MemoryScope::User)],
+        }
+    }
+
+    /// Memory file that receives synced memory, preferring `<project>/CLAUDE.md`
+    /// unless only `.claude/CLAUDE.md` exists.
+    fn memory_target(&self) -> PathBuf {
+        let paths = self.memory_paths();
+        paths
+            .iter()
+            .map(|(path, _)| path)
+            .find(|path| path.exists())
+            .unwrap_or(&paths[0].0)
+            .clone()
+    }
+
+    /// Reads Claude's installed plugin registry, if present.
+    ///
+    /// An unreadable registry (e.g. a malformed `settings.json`) is logged and
+    /// treated as absent, matching discovery.
+    fn plugin_registry(&self) -> Option<PluginRegistry> {
+        PluginRegistry::load(&self.root).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "ignoring unreadable Claude plugin registry");
+            None
+        })
+    }
+
+    /// Lists plugins recorded in Claude's installed plugin registry.
+    ///
+    /// Returns an empty list when no registry exists or it cannot be read.
+    pub fn installed_plugins(&self) -> Vec<InstalledPlugin> {
+        self.plugin_registry()
+            .map(|r| r.plugins().to_vec())
+            .unwrap_or_default()
+    }
+
+    fn parse_mcp_server(name: &str, config: &serde_json::Value) -> McpServer {
+        McpServer {
+            name: name.to_string(),
+            command: config
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            args: config
+                .get("args")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            env: config
+                .get("env")
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            enabled: config
+                .get("disabled")
+                .and_then(|v| v.as_bool())
+                .map(|d| !d)
+                .unwrap_or(true),
+            plugin: None,
+        }
+    }
+
     fn hash_content(content: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(content);
@@ -91,6 +195,7 @@ impl ClaudeAdapter {
                 source_path: path.to_path_buf(),
                 modified,
                 hash,
+                plugin: None,
             });
         }
 
@@ -119,6 +224,7 @@ impl AgentAdapter for ClaudeAdapter {
             mcp_servers: true,
             preferences: true,
             skills: true,
+            memory: true,
         }
     }
 
@@ -129,7 +235,9 @@ impl AgentAdapter for ClaudeAdapter {
         // 1) Core ~/.claude/commands
         self.collect_commands_from_dir(&self.commands_dir(), &mut seen, &mut commands)?;
 
-        // 2) Marketplaces & Cache
+        // 2) Marketplaces & Cache. With a plugin registry, installed (cache) commands
+        // are limited to enabled plugins and everything is tagged with its plugin.
+        let registry = self.plugin_registry();
         let mut bases = vec!["plugins/cache"];
         if include_marketplace {
             bases.push("plugins/marketplaces");
@@ -160,6 +268,17 @@ impl AgentAdapter for ClaudeAdapter {
                     continue;
                 }
 
+                let plugin = match &registry {
+                    Some(registry) if base == "plugins/cache" => {
+                        match registry.enabled_plugin_for_path(path) {
+                            Some(p) => Some(p.name.clone()),
+                            None => continue,
+                        }
+                    }
+                    Some(registry) => registry.plugin_for_path(path).map(|p| p.name.clone()),
+                    None => None,
+                };
+
                 let name = path
                     .file_stem()
                     .and_then(|s| s.to_str())
@@ -181,6 +300,7 @@ impl AgentAdapter for ClaudeAdapter {
                     source_path: path.to_path_buf(),
                     modified,
                     hash,
+                    plugin,
                 });
             }
         }
@@ -189,49 +309,51 @@ impl AgentAdapter for ClaudeAdapter {
     }
 
     fn read_mcp_servers(&self) -> Result<HashMap<String, McpServer>> {
-        let path = self.settings_path();
-        if !path.exists() {
-            return Ok(HashMap::new());
-        }
+        let mut servers = HashMap::new();
 
-        let content = fs::read_to_string(&path)?;
-        let settings: serde_json::Value = serde_json::from_str(&content)?;
+        let path = self.mcp_config_path();
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let settings: serde_json::Value = serde_json::from_str(&content)?;
+            if let Some(mcp) = settings.get("mcpServers").and_then(|v| v.as_object()) {
+                for (name, config) in mcp {
+                    servers.insert(name.clone(), Self::parse_mcp_server(name, config));
+                }
+            }
+        }
 
-        let mut servers = HashMap::new();
-        if let Some(mcp) = settings.get("mcpServers").and_then(|v| v.as_object()) {
-            for (name, config) in mcp {
-                let server = McpServer {
-                    name: name.clone(),
-                    command: config
-                        .get("command")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string(),
-                    args: config
-                        .get("args")
-                        .and_then(|v| v.as_array())
-                        .map(|arr| {
-                            arr.iter()
-                                .filter_map(|v| v.as_str().map(String::from))
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                    env: config
-                        .get("env")
-                        .and_then(|v| v.as_object())
-                        .map(|obj| {
-                            obj.iter()
-                                .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                    enabled: config
-                        .get("disabled")
-                        .and_then(|v| v.as_bool())
-                        .map(|d| !d)
-                        .unwrap_or(true),
-                };
-                servers.insert(name.clone(), server);
+        // Servers bundled with enabled plugins; user-level entries win on name clashes.
+        if let Some(registry) = self.plugin_registry() {
+            for plugin in registry.enabled() {
+                let mcp_path = plugin.install_path.join(".mcp.json");
+                if !mcp_path.exists() {
+                    continue;
+                }
+                let content = fs::read_to_string(&mcp_path)?;
+                let config: serde_json::Value = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", mcp_path.display()))?;
+                let entries = config
+                    .get("mcpServers")
+                    .unwrap_or(&config)
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default();
+                let plugin_root = plugin.install_path.to_string_lossy();
+                for (name, config) in &entries {
+                    if servers.contains_key(name) {
+                        continue;
+                    }
+                    let mut server = Self::parse_mcp_server(name, config);
+                    server.command = server.command.replace(CLAUDE_PLUGIN_ROOT_VAR, &plugin_root);
+                    for arg in &mut server.args {
+                        *arg = arg.replace(CLAUDE_PLUGIN_ROOT_VAR, &plugin_root);
+                    }
+                    for value in server.env.values_mut() {
+                        *value = value.replace(CLAUDE_PLUGIN_ROOT_VAR, &plugin_root);
+                    }
+                    server.plugin = Some(plugin.name.clone());
+                    servers.insert(name.clone(), server);
+                }
             }
         }
 
@@ -263,6 +385,17 @@ impl AgentAdapter for ClaudeAdapter {
         Ok(skills)
     }
 
+    fn read_memory(&self) -> Result<Vec<MemoryFile>> {
+        let mut files = Vec::new();
+        for (path, scope) in self.memory_paths() {
+            if path.exists() {
+                let content = memory::read_claude_memory(&path)?;
+                files.extend(memory::memory_file(&path, scope, &content));
+            }
+        }
+        Ok(files)
+    }
+
     fn write_commands(&self, commands: &[Command]) -> Result<WriteReport> {
         let dir = self.commands_dir();
         fs::create_dir_all(&dir)?;
@@ -285,13 +418,14 @@ impl AgentAdapter for ClaudeAdapter {
 
             fs::write(&path, &cmd.content)?;
             report.written += 1;
+            report.paths.push(path);
         }
 
         Ok(report)
     }
 
     fn write_mcp_servers(&self, servers: &HashMap<String, McpServer>) -> Result<WriteReport> {
-        let path = self.settings_path();
+        let path = self.mcp_config_path();
 
         // Read existing settings or create new
         let mut settings: serde_json::Value = if path.exists() {
@@ -326,6 +460,7 @@ impl AgentAdapter for ClaudeAdapter {
             fs::create_dir_all(parent)?;
         }
         fs::write(&path, serde_json::to_string_pretty(&settings)?)?;
+        report.paths.push(path);
 
         Ok(report)
     }
@@ -351,6 +486,7 @@ impl AgentAdapter for ClaudeAdapter {
             fs::create_dir_all(parent)?;
         }
         fs::write(&path, serde_json::to_string_pretty(&settings)?)?;
+        report.paths.push(path);
 
         Ok(report)
     }
@@ -377,10 +513,21 @@ impl AgentAdapter for ClaudeAdapter {
 
             fs::write(&path, &skill.content)?;
             report.written += 1;
+            report.paths.push(path);
         }
 
         Ok(report)
     }
+
+    fn write_memory(&self, memory: &[MemoryFile], dry_run: bool) -> Result<WriteReport> {
+        memory::write_memory_section(
+            &self.memory_target(),
+            CODEX_MEMORY_SECTION,
+            "AGENTS.md",
+            memory,
+            dry_run,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -470,6 +617,96 @@ mod tests {
         assert_eq!(shared[0].content, b"core".to_vec());
     }
 
+    fn seed_plugin_registry(root: &std::path::Path) -> (PathBuf, PathBuf) {
+        let alpha = root.join("plugins/cache/mkt/alpha/1.0.0");
+        let beta = root.join("plugins/cache/mkt/beta/0.2.0");
+        for dir in [&alpha, &beta] {
+            fs::create_dir_all(dir.join("commands")).unwrap();
+        }
+        fs::write(alpha.join("commands/alpha-cmd.md"), "# Alpha").unwrap();
+        fs::write(beta.join("commands/beta-cmd.md"), "# Beta").unwrap();
+        fs::write(
+            root.join("plugins/installed_plugins.json"),
+            serde_json::json!({
+                "version": 1,
+                "plugins": {
+                    "alpha@mkt": { "version": "1.0.0", "installPath": alpha },
+                    "beta@mkt": { "version": "0.2.0", "installPath": beta }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            root.join("settings.json"),
+            r#"{"enabledPlugins": {"alpha@mkt": true, "beta@mkt": false}}"#,
+        )
+        .unwrap();
+        (alpha, beta)
+    }
+
+    #[test]
+    fn read_commands_limits_cache_to_enabled_plugins() {
+        let tmp = tempdir().unwrap();
+        seed_plugin_registry(tmp.path());
+
+        let adapter = ClaudeAdapter::with_root(tmp.path().to_path_buf());
+        let cmds = adapter.read_commands(false).unwrap();
+
+        assert_eq!(cmds.len(), 1);
+        assert_eq!(cmds[0].name, "alpha-cmd");
+        assert_eq!(cmds[0].plugin.as_deref(), Some("alpha"));
+
+        let plugins = adapter.installed_plugins();
+        assert_eq!(plugins.len(), 2);
+        assert!(plugins.iter().any(|p| p.name == "beta" && !p.enabled));
+    }
+
+    #[test]
+    fn read_commands_tolerates_malformed_plugin_settings() {
+        let tmp = tempdir().unwrap();
+        seed_plugin_registry(tmp.path());
+        fs::write(tmp.path().join("settings.json"), "{ not json").unwrap();
+
+        let adapter = ClaudeAdapter::with_root(tmp.path().to_path_buf());
+        let cmds = adapter.read_commands(false).unwrap();
+
+        assert_eq!(cmds.len(), 2);
+        assert!(cmds.iter().all(|c| c.plugin.is_none()));
+        assert!(adapter.installed_plugins().is_empty());
+    }
+
+    #[test]
+    fn read_mcp_servers_includes_enabled_plugin_servers() {
+        let tmp = tempdir().unwrap();
+        let (alpha, beta) = seed_plugin_registry(tmp.path());
+        fs::write(
+            alpha.join(".mcp.json"),
+            r#"{"mcpServers": {"alpha-srv": {
+                "command": "${CLAUDE_PLUGIN_ROOT}/bin/srv",
+                "args": ["--config", "${CLAUDE_PLUGIN_ROOT}/srv.toml"],
+                "env": {"SRV_HOME": "${CLAUDE_PLUGIN_ROOT}/data"}
+            }}}"#,
+        )
+        .unwrap();
+        fs::write(
+            beta.join(".mcp.json"),
+            r#"{"beta-srv": {"command": "beta"}}"#,
+        )
+        .unwrap();
+
+        let adapter = ClaudeAdapter::with_root(tmp.path().to_path_buf());
+        let servers = adapter.read_mcp_servers().unwrap();
+
+        assert_eq!(servers.len(), 1);
+        let server = servers.get("alpha-srv").unwrap();
+        assert_eq!(server.plugin.as_deref(), Some("alpha"));
+        let root = alpha.to_string_lossy();
+        assert_eq!(server.command, format!("{root}/bin/srv"));
+        assert_eq!(server.args[1], format!("{root}/srv.toml"));
+        assert_eq!(server.env["SRV_HOME"], format!("{root}/data"));
+    }
+
     #[test]
     fn write_commands_creates_files() {
         let tmp = tempdir().unwrap();
@@ -481,10 +718,12 @@ mod tests {
             source_path: PathBuf::from("/tmp/hello.md"),
             modified: SystemTime::now(),
             hash: "abc123".to_string(),
+            plugin: None,
         }];
 
         let report = adapter.write_commands(&commands).unwrap();
         assert_eq!(report.written, 1);
+        assert_eq!(report.paths, vec![tmp.path().join("commands/hello.md")]);
 
         let written = fs::read(tmp.path().join("commands/hello.md")).unwrap();
         assert_eq!(written, b"# Hello World");
@@ -505,6 +744,7 @@ mod tests {
             source_path: PathBuf::from("/tmp/unchanged.md"),
             modified: SystemTime::now(),
             hash: hash.clone(),
+            plugin: None,
         }];
         adapter.write_commands(&commands).unwrap();
 
@@ -515,6 +755,7 @@ mod tests {
             source_path: PathBuf::from("/tmp/unchanged.md"),
             modified: SystemTime::now(),
             hash,
+            plugin: None,
         }];
         let report = adapter.write_commands(&commands2).unwrap();
 
@@ -553,6 +794,24 @@ mod tests {
         assert!(server.enabled);
     }
 
+    #[test]
+    fn project_adapter_uses_repo_mcp_json() {
+        let tmp = tempdir().unwrap();
+        fs::write(
+            tmp.path().join(".mcp.json"),
+            r#"{"mcpServers": {"repo-server": {"command": "npx", "args": ["srv"]}}}"#,
+        )
+        .unwrap();
+
+        let adapter = ClaudeAdapter::for_project(tmp.path());
+        assert_eq!(adapter.config_root(), tmp.path().join(".claude"));
+        let servers = adapter.read_mcp_servers().unwrap();
+        assert_eq!(servers["repo-server"].args, vec!["srv"]);
+
+        adapter.write_mcp_servers(&servers).unwrap();
+        assert!(!tmp.path().join(".claude/settings.json").exists());
+    }
+
     #[test]
     fn write_mcp_servers_creates_settings() {
         let tmp = tempdir().unwrap();
@@ -567,6 +826,7 @@ mod tests {
                 args: vec!["arg1".to_string()],
                 env: HashMap::new(),
                 enabled: true,
+                plugin: None,
             },
         );
 
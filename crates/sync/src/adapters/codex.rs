@@ -1,33 +1,47 @@
 //! Codex adapter for reading/writing ~/.codex configuration.
 
 use super::traits::{AgentAdapter, FieldSupport};
-use crate::common::{Command, McpServer, Preferences};
+use crate::common::{Command, McpServer, MemoryFile, MemoryScope, Preferences};
+use crate::memory::{self, AGENTS_MEMORY_FILE, CLAUDE_MEMORY_SECTION};
 use crate::report::{SkipReason, WriteReport};
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use walkdir::WalkDir;
 
 /// Adapter for Codex CLI configuration.
 pub struct CodexAdapter {
     root: PathBuf,
+    /// Repository root when syncing project config; `None` for `~/.codex`.
+    project_root: Option<PathBuf>,
 }
 
 impl CodexAdapter {
     /// Creates a new CodexAdapter with the default root (~/.codex).
     pub fn new() -> Result<Self> {
         let home = dirs::home_dir().context("Could not determine home directory")?;
-        Ok(Self {
-            root: home.join(".codex"),
-        })
+        Ok(Self::with_root(home.join(".codex")))
     }
 
     /// Creates a CodexAdapter with a custom root (for testing).
     pub fn with_root(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            project_root: None,
+        }
+    }
+
+    /// Creates a CodexAdapter for a repository's `.codex/` directory.
+    ///
+    /// Project memory lives in `<project>/AGENTS.md`.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self {
+            root: project_root.join(".codex"),
+            project_root: Some(project_root.to_path_buf()),
+        }
     }
 
     fn prompts_dir(&self) -> PathBuf {
@@ -43,6 +57,13 @@ impl CodexAdapter {
         self.root.join("config.json")
     }
 
+    fn memory_path(&self) -> (PathBuf, MemoryScope) {
+        match &self.project_root {
+            Some(project) => (project.join(AGENTS_MEMORY_FILE), MemoryScope::Project),
+            None => (self.root.join(AGENTS_MEMORY_FILE), MemoryScope::User),
+        }
+    }
+
     fn hash_content(content: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(content);
@@ -71,6 +92,7 @@ impl AgentAdapter for CodexAdapter {
             mcp_servers: true,
             preferences: true,
             skills: true,
+            memory: true,
         }
     }
 
@@ -103,6 +125,7 @@ impl AgentAdapter for CodexAdapter {
                     source_path: path.to_path_buf(),
                     modified,
                     hash,
+                    plugin: None,
                 });
             }
         }
@@ -153,6 +176,7 @@ impl AgentAdapter for CodexAdapter {
                         .and_then(|v| v.as_bool())
                         .map(|d| !d)
                         .unwrap_or(true),
+                    plugin: None,
                 };
                 servers.insert(name.clone(), server);
             }
@@ -208,12 +232,24 @@ impl AgentAdapter for CodexAdapter {
                     source_path: path.to_path_buf(),
                     modified,
                     hash,
+                    plugin: None,
                 });
             }
         }
         Ok(skills)
     }
 
+    fn read_memory(&self) -> Result<Vec<MemoryFile>> {
+        let (path, scope) = self.memory_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = memory::strip_generated_sections(&fs::read_to_string(&path)?);
+        Ok(memory::memory_file(&path, scope, &content)
+            .into_iter()
+            .collect())
+    }
+
     fn write_commands(&self, commands: &[Command]) -> Result<WriteReport> {
         let dir = self.prompts_dir();
         fs::create_dir_all(&dir)?;
@@ -235,6 +271,7 @@ impl AgentAdapter for CodexAdapter {
 
             fs::write(&path, &cmd.content)?;
             report.written += 1;
+            report.paths.push(path);
         }
 
         Ok(report)
@@ -275,6 +312,7 @@ impl AgentAdapter for CodexAdapter {
             fs::create_dir_all(parent)?;
         }
         fs::write(&path, serde_json::to_string_pretty(&settings)?)?;
+        report.paths.push(path);
 
         Ok(report)
     }
@@ -300,6 +338,7 @@ impl AgentAdapter for CodexAdapter {
             fs::create_dir_all(parent)?;
         }
         fs::write(&path, serde_json::to_string_pretty(&settings)?)?;
+        report.paths.push(path);
 
         Ok(report)
     }
@@ -325,10 +364,16 @@ impl AgentAdapter for CodexAdapter {
 
             fs::write(&path, &skill.content)?;
             report.written += 1;
+            report.paths.push(path);
         }
 
         Ok(report)
     }
+
+    fn write_memory(&self, memory: &[MemoryFile], dry_run: bool) -> Result<WriteReport> {
+        let (path, _) = self.memory_path();
+        memory::write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", memory, dry_run)
+    }
 }
 
 #[cfg(test)]
@@ -377,6 +422,7 @@ mod tests {
             source_path: PathBuf::from("/tmp/hello.md"),
             modified: SystemTime::now(),
             hash: "abc123".to_string(),
+            plugin: None,
         }];
 
         let report = adapter.write_commands(&commands).unwrap();
@@ -397,6 +443,7 @@ mod tests {
             source_path: PathBuf::from("/tmp/test.md"),
             modified: SystemTime::now(),
             hash: "hash123".to_string(),
+            plugin: None,
         }];
 
         adapter.write_commands(&commands).unwrap();
@@ -448,6 +495,7 @@ mod tests {
                 args: vec!["arg1".to_string()],
                 env: HashMap::new(),
                 enabled: true,
+                plugin: None,
             },
         );
 
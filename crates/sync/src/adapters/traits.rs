@@ -1,6 +1,6 @@
 //! Trait definition for agent adapters.
 
-use crate::common::{Command, CommonConfig, McpServer, Preferences};
+use crate::common::{Command, CommonConfig, McpServer, MemoryFile, Preferences};
 use crate::report::WriteReport;
 use anyhow::Result;
 use std::collections::HashMap;
@@ -13,6 +13,7 @@ pub struct FieldSupport {
     pub mcp_servers: bool,
     pub preferences: bool,
     pub skills: bool,
+    pub memory: bool,
 }
 
 #[cfg(test)]
@@ -44,6 +45,9 @@ pub trait AgentAdapter: Send + Sync {
     /// Read skills from native format
     fn read_skills(&self) -> Result<Vec<Command>>;
 
+    /// Read memory files (e.g. CLAUDE.md, AGENTS.md), excluding synced sections
+    fn read_memory(&self) -> Result<Vec<MemoryFile>>;
+
     /// Read complete configuration
     fn read_all(&self) -> Result<CommonConfig> {
         Ok(CommonConfig {
@@ -51,6 +55,7 @@ pub trait AgentAdapter: Send + Sync {
             mcp_servers: self.read_mcp_servers()?,
             preferences: self.read_preferences()?,
             skills: self.read_skills()?,
+            memory: self.read_memory()?,
         })
     }
 
@@ -67,4 +72,8 @@ pub trait AgentAdapter: Send + Sync {
 
     /// Write skills to native format
     fn write_skills(&self, skills: &[Command]) -> Result<WriteReport>;
+
+    /// Write memory into this agent's memory file, inside a skrills-owned section.
+    /// With `dry_run`, reports what would change without writing.
+    fn write_memory(&self, memory: &[MemoryFile], dry_run: bool) -> Result<WriteReport>;
 }
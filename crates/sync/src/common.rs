@@ -21,6 +21,9 @@ pub struct Command {
     pub modified: SystemTime,
     /// SHA256 hash of content for change detection
     pub hash: String,
+    /// Claude plugin providing this item, when known from the plugin registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
 }
 
 /// An MCP server configuration.
@@ -38,6 +41,9 @@ pub struct McpServer {
     /// Whether the server is enabled
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Claude plugin providing this server, when known from the plugin registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -54,6 +60,29 @@ pub struct Preferences {
     pub custom: HashMap<String, serde_json::Value>,
 }
 
+/// Where a memory file applies.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// Home-level memory (e.g. `~/.claude/CLAUDE.md`)
+    User,
+    /// Repository memory (e.g. `<repo>/CLAUDE.md`)
+    Project,
+}
+
+/// An agent memory file (`CLAUDE.md`, `AGENTS.md`) that can be synced.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MemoryFile {
+    /// Whether this is user or project memory
+    pub scope: MemoryScope,
+    /// Content with imports inlined and generated sections removed
+    pub content: String,
+    /// Original file path (for reference)
+    pub source_path: PathBuf,
+    /// SHA256 hash of content for change detection
+    pub hash: String,
+}
+
 /// Complete syncable configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CommonConfig {
@@ -61,6 +90,8 @@ pub struct CommonConfig {
     pub mcp_servers: HashMap<String, McpServer>,
     pub preferences: Preferences,
     pub skills: Vec<Command>,
+    #[serde(default)]
+    pub memory: Vec<MemoryFile>,
 }
 
 /// Metadata about a sync operation.
@@ -1,14 +1,25 @@
 //! Cross-agent configuration sync for skrills.
 //!
-//! Syncs commands, MCP servers, preferences, and skills between
-//! Claude Code and Codex using a pluggable adapter architecture.
+//! Syncs commands, MCP servers, preferences, skills, and memory files between
+//! Claude Code and Codex using a pluggable adapter architecture, either for
+//! the user's home configuration or for a single repository.
 
 pub mod adapters;
 pub mod common;
+pub mod memory;
 pub mod orchestrator;
+pub mod project;
 pub mod report;
 
 pub use adapters::{AgentAdapter, ClaudeAdapter, CodexAdapter, FieldSupport};
-pub use common::{Command, CommonConfig, McpServer, Preferences, SyncMeta};
-pub use orchestrator::{parse_direction, SyncDirection, SyncOrchestrator, SyncParams};
-pub use report::{SkipReason, SyncReport, WriteReport};
+pub use common::{
+    Command, CommonConfig, McpServer, MemoryFile, MemoryScope, Preferences, SyncMeta,
+};
+pub use orchestrator::{
+    parse_direction, ChangeKind, PendingChange, SyncDirection, SyncOrchestrator, SyncParams,
+};
+pub use project::{find_git_root, ProjectScope};
+pub use report::{
+    ArtifactOutcome, ArtifactOutcomes, ReportTotals, SkipEntry, SkipReason, SyncReport,
+    SyncReportDocument, WriteReport, REPORT_SCHEMA_VERSION,
+};
@@ -0,0 +1,365 @@
+//! Memory-file sync between `CLAUDE.md` and `AGENTS.md`.
+//!
+//! Each agent's memory is written into a skrills-owned section of the other
+//! agent's file, delimited by `<!-- <name>:start -->` / `<!-- <name>:end -->`
+//! markers. Generated sections (ours, and others such as `available_skills`)
+//! are stripped when a memory file is read, so syncing in both directions
+//! never feeds synced content back into its source.
+
+use crate::common::{MemoryFile, MemoryScope};
+use crate::report::{SkipReason, WriteReport};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Claude's memory file name.
+pub const CLAUDE_MEMORY_FILE: &str = "CLAUDE.md";
+/// Codex's memory (instructions) file name.
+pub const AGENTS_MEMORY_FILE: &str = "AGENTS.md";
+/// Section in `AGENTS.md` holding memory synced from Claude.
+pub const CLAUDE_MEMORY_SECTION: &str = "skrills:claude-memory";
+/// Section in `CLAUDE.md` holding memory synced from Codex.
+pub const CODEX_MEMORY_SECTION: &str = "skrills:codex-memory";
+
+/// Claude stops following `@path` imports beyond this depth.
+const MAX_IMPORT_DEPTH: usize = 5;
+
+/// Returns the section name if `line` is a `<!-- name:start -->` (or
+/// `name-start`) marker, with `true` for start and `false` for end markers.
+fn section_marker(line: &str) -> Option<(&str, bool)> {
+    let inner = line
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim();
+    for (suffix, is_start) in [
+        (":start", true),
+        ("-start", true),
+        (":end", false),
+        ("-end", false),
+    ] {
+        if let Some(name) = inner.strip_suffix(suffix) {
+            if !name.is_empty() && !name.contains(char::is_whitespace) {
+                return Some((name, is_start));
+            }
+        }
+    }
+    None
+}
+
+/// Removes every generated `<!-- name:start -->` ... `<!-- name:end -->`
+/// block. An unterminated start marker is kept as ordinary text.
+pub fn strip_generated_sections(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((name, true)) = section_marker(lines[i]) {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|l| section_marker(l) == Some((name, false)));
+            if let Some(offset) = end {
+                i += offset + 2;
+                continue;
+            }
+        }
+        out.push(lines[i]);
+        i += 1;
+    }
+    out.join("\n")
+}
+
+/// Extracts `@path` import targets from a line, ignoring inline code spans.
+fn import_targets(line: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    let mut in_code = false;
+    for token in line.split_whitespace() {
+        if !in_code && !token.starts_with('`') {
+            if let Some(target) = token.strip_prefix('@') {
+                let target = target.trim_end_matches([',', ';', ':', ')', '!', '?']);
+                if !target.is_empty() {
+                    targets.push(target);
+                }
+            }
+        }
+        if token.matches('`').count() % 2 == 1 {
+            in_code = !in_code;
+        }
+    }
+    targets
+}
+
+fn resolve_import(base_dir: &Path, target: &str) -> Option<PathBuf> {
+    let path = match target.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest),
+        None => base_dir.join(target),
+    };
+    path.is_file().then_some(path)
+}
+
+fn expand_imports(path: &Path, depth: usize, stack: &mut Vec<PathBuf>) -> Result<String> {
+    let canonical = path.canonicalize()?;
+    stack.push(canonical);
+    let text = strip_generated_sections(&fs::read_to_string(path)?);
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut out: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence || depth >= MAX_IMPORT_DEPTH {
+            out.push(line.to_string());
+            continue;
+        }
+
+        let targets = import_targets(line);
+        let mut imported = Vec::new();
+        let mut resolved = 0;
+        for target in &targets {
+            let Some(import) = resolve_import(base_dir, target) else {
+                continue;
+            };
+            resolved += 1;
+            // Files already being expanded are skipped to break import cycles.
+            if !stack.contains(&import.canonicalize()?) {
+                imported.push(expand_imports(&import, depth + 1, stack)?);
+            }
+        }
+        // A line made up only of imports is replaced by their content.
+        let only_imports = resolved > 0
+            && resolved == targets.len()
+            && resolved == line.split_whitespace().count();
+        if !only_imports {
+            out.push(line.to_string());
+        }
+        out.extend(imported.into_iter().map(|s| s.trim().to_string()));
+    }
+    stack.pop();
+    Ok(out.join("\n"))
+}
+
+/// Reads a Claude memory file, inlining `@path` imports (relative to the
+/// importing file, or `~/`-prefixed) up to Claude's import depth.
+pub fn read_claude_memory(path: &Path) -> Result<String> {
+    expand_imports(path, 0, &mut Vec::new())
+}
+
+fn hash_content(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Builds a `MemoryFile` from already-resolved content, skipping empty files.
+pub(crate) fn memory_file(path: &Path, scope: MemoryScope, content: &str) -> Option<MemoryFile> {
+    let content = content.trim();
+    if content.is_empty() {
+        return None;
+    }
+    Some(MemoryFile {
+        scope,
+        content: content.to_string(),
+        source_path: path.to_path_buf(),
+        hash: hash_content(content),
+    })
+}
+
+/// Replaces the `section` block in `existing`, appending it when absent.
+pub fn upsert_section(existing: &str, section: &str, body: &str) -> String {
+    let start = format!("<!-- {section}:start -->");
+    let end = format!("<!-- {section}:end -->");
+    let block = format!("{start}\n{}\n{end}", body.trim());
+
+    if let (Some(s), Some(e)) = (existing.find(&start), existing.find(&end)) {
+        if s < e {
+            return format!("{}{}{}", &existing[..s], block, &existing[e + end.len()..]);
+        }
+    }
+    if existing.trim().is_empty() {
+        format!("{block}\n")
+    } else {
+        format!("{}\n\n{block}\n", existing.trim_end())
+    }
+}
+
+/// Removes the `section` block from `existing`, with the blank line that
+/// `upsert_section` put before it.
+pub fn remove_section(existing: &str, section: &str) -> String {
+    let start = format!("<!-- {section}:start -->");
+    let end = format!("<!-- {section}:end -->");
+    let (Some(s), Some(e)) = (existing.find(&start), existing.find(&end)) else {
+        return existing.to_string();
+    };
+    if s > e {
+        return existing.to_string();
+    }
+    let before = existing[..s].trim_end();
+    let after = existing[e + end.len()..].trim_start_matches(['\r', '\n']);
+    match (before.is_empty(), after.trim().is_empty()) {
+        (true, _) => after.to_string(),
+        (false, true) => format!("{before}\n"),
+        (false, false) => format!("{before}\n\n{after}"),
+    }
+}
+
+/// Writes `memory` into the `section` block of the file at `path`, or
+/// removes the block when there is no memory to sync.
+///
+/// Text outside the section is preserved and nothing is written when the
+/// file is already up to date. With `dry_run` the report says what would
+/// change without touching the file.
+pub(crate) fn write_memory_section(
+    path: &Path,
+    section: &str,
+    source_label: &str,
+    memory: &[MemoryFile],
+    dry_run: bool,
+) -> Result<WriteReport> {
+    let mut report = WriteReport::default();
+    let existing = if path.exists() {
+        fs::read_to_string(path)?
+    } else if memory.is_empty() {
+        return Ok(report);
+    } else {
+        String::new()
+    };
+
+    let updated = if memory.is_empty() {
+        remove_section(&existing, section)
+    } else {
+        let body = format!(
+            "<!-- Synced from {source_label} by skrills; edit the source file instead. -->\n\n{}",
+            memory
+                .iter()
+                .map(|m| m.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        );
+        upsert_section(&existing, section, &body)
+    };
+    let item = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if updated == existing {
+        if !memory.is_empty() {
+            report.skipped.push(SkipReason::Unchanged { item });
+        }
+        return Ok(report);
+    }
+
+    if !dry_run {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, updated)?;
+        report.paths.push(path.to_path_buf());
+    }
+    report.written += 1;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn strip_generated_sections_removes_marked_blocks() {
+        let text = "# Mine\n<!-- skrills:claude-memory:start -->\nsynced\n<!-- skrills:claude-memory:end -->\n<!-- skrills-integration-start -->\nprotocol\n<!-- skrills-integration-end -->\nkept";
+        assert_eq!(strip_generated_sections(text), "# Mine\nkept");
+        assert_eq!(
+            strip_generated_sections("<!-- open:start -->\ntext"),
+            "<!-- open:start -->\ntext"
+        );
+    }
+
+    #[test]
+    fn read_claude_memory_inlines_imports() {
+        let tmp = tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("docs")).unwrap();
+        fs::write(
+            tmp.path().join("docs/style.md"),
+            "Use 4 spaces.\n@../CLAUDE.md",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("CLAUDE.md"),
+            "# Rules\n@docs/style.md\nSee @docs/style.md, and `@docs/style.md`.\n```\n@docs/style.md\n```",
+        )
+        .unwrap();
+
+        let memory = read_claude_memory(&tmp.path().join("CLAUDE.md")).unwrap();
+        assert_eq!(
+            memory,
+            "# Rules\nUse 4 spaces.\nSee @docs/style.md, and `@docs/style.md`.\nUse 4 spaces.\n```\n@docs/style.md\n```"
+        );
+    }
+
+    #[test]
+    fn write_memory_section_is_idempotent_and_preserves_text() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("AGENTS.md");
+        fs::write(&path, "# Codex notes\n").unwrap();
+        let memory =
+            vec![memory_file(Path::new("CLAUDE.md"), MemoryScope::User, "Be brief.").unwrap()];
+
+        let report =
+            write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &memory, true).unwrap();
+        assert_eq!(report.written, 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Codex notes\n");
+
+        let report =
+            write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &memory, false)
+                .unwrap();
+        assert_eq!(report.written, 1);
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# Codex notes\n\n<!-- skrills:claude-memory:start -->"));
+        assert!(written.contains("Be brief."));
+        assert_eq!(strip_generated_sections(&written).trim(), "# Codex notes");
+
+        for dry_run in [true, false] {
+            let report =
+                write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &memory, dry_run)
+                    .unwrap();
+            assert_eq!(report.written, 0);
+            assert!(matches!(report.skipped[0], SkipReason::Unchanged { .. }));
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+    }
+
+    #[test]
+    fn empty_memory_removes_the_section() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("AGENTS.md");
+        let memory =
+            vec![memory_file(Path::new("CLAUDE.md"), MemoryScope::User, "Be brief.").unwrap()];
+        fs::write(&path, "# Codex notes\n").unwrap();
+        write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &memory, false).unwrap();
+        fs::write(
+            &path,
+            format!("{}\n## Later\n", fs::read_to_string(&path).unwrap()),
+        )
+        .unwrap();
+
+        let report =
+            write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &[], false).unwrap();
+        assert_eq!(report.written, 1);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Codex notes\n\n## Later\n"
+        );
+
+        let report =
+            write_memory_section(&path, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &[], false).unwrap();
+        assert_eq!(report.written, 0);
+        assert!(report.skipped.is_empty());
+        let missing = tmp.path().join("missing/AGENTS.md");
+        write_memory_section(&missing, CLAUDE_MEMORY_SECTION, "CLAUDE.md", &[], false).unwrap();
+        assert!(!missing.exists());
+    }
+}
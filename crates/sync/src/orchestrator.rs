@@ -1,13 +1,14 @@
 //! Sync orchestrator that coordinates adapters and manages sync flow.
 
 use crate::adapters::AgentAdapter;
+use crate::common::{Command, McpServer};
 use crate::report::{SkipReason, SyncReport, WriteReport};
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Direction of sync operation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SyncDirection {
     /// Sync from Claude to Codex
@@ -40,6 +41,9 @@ pub struct SyncParams {
     /// Sync preferences
     #[serde(default = "default_true")]
     pub sync_preferences: bool,
+    /// Sync memory files (CLAUDE.md ⇄ AGENTS.md)
+    #[serde(default = "default_true")]
+    pub sync_memory: bool,
     /// Include marketplace content (e.g. uninstalled plugins)
     #[serde(default)]
     pub include_marketplace: bool,
@@ -56,6 +60,7 @@ impl Default for SyncParams {
             skip_existing_commands: false,
             sync_mcp_servers: true,
             sync_preferences: true,
+            sync_memory: true,
             include_marketplace: false,
         }
     }
@@ -65,6 +70,70 @@ fn default_true() -> bool {
     true
 }
 
+/// Whether a sync would create an item on the target or replace it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+}
+
+/// An item a sync would write, with both versions for diffing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingChange {
+    /// "command", "skill" or "mcp_server"
+    pub artifact: String,
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Text currently on the target; empty for added items.
+    pub current: String,
+    /// Text the sync would write.
+    pub incoming: String,
+}
+
+/// Compares items by name; identical items are not changes.
+fn file_changes(
+    artifact: &str,
+    source: Vec<Command>,
+    target: Vec<Command>,
+    keep_existing: bool,
+) -> Vec<PendingChange> {
+    let target: HashMap<String, Vec<u8>> =
+        target.into_iter().map(|c| (c.name, c.content)).collect();
+    source
+        .into_iter()
+        .filter_map(|item| {
+            let existing = target.get(&item.name);
+            let kind = match existing {
+                None => ChangeKind::Added,
+                Some(content) if *content != item.content && !keep_existing => ChangeKind::Modified,
+                Some(_) => return None,
+            };
+            Some(PendingChange {
+                artifact: artifact.into(),
+                name: item.name,
+                kind,
+                current: existing
+                    .map(|c| String::from_utf8_lossy(c).into_owned())
+                    .unwrap_or_default(),
+                incoming: String::from_utf8_lossy(&item.content).into_owned(),
+            })
+        })
+        .collect()
+}
+
+/// The parts of a server definition both agents store, as sorted JSON.
+fn server_text(server: &McpServer) -> String {
+    let env: std::collections::BTreeMap<_, _> = server.env.iter().collect();
+    serde_json::to_string_pretty(&serde_json::json!({
+        "command": server.command,
+        "args": server.args,
+        "env": env,
+        "enabled": server.enabled,
+    }))
+    .unwrap_or_default()
+}
+
 /// Orchestrates sync operations between agents.
 pub struct SyncOrchestrator<S: AgentAdapter, T: AgentAdapter> {
     source: S,
@@ -87,6 +156,50 @@ impl<S: AgentAdapter, T: AgentAdapter> SyncOrchestrator<S, T> {
         self.target.name()
     }
 
+    /// Lists the commands, skills and MCP servers `sync` would add or change
+    /// with `params`, sorted by artifact and name. Preferences and memory
+    /// are not diffed.
+    pub fn pending_changes(&self, params: &SyncParams) -> Result<Vec<PendingChange>> {
+        let mut changes = Vec::new();
+        if params.sync_commands {
+            changes.extend(file_changes(
+                "command",
+                self.source.read_commands(params.include_marketplace)?,
+                self.target.read_commands(params.include_marketplace)?,
+                params.skip_existing_commands && !params.force,
+            ));
+        }
+        if params.sync_skills {
+            changes.extend(file_changes(
+                "skill",
+                self.source.read_skills()?,
+                self.target.read_skills()?,
+                false,
+            ));
+        }
+        if params.sync_mcp_servers {
+            let target = self.target.read_mcp_servers()?;
+            for (name, server) in self.source.read_mcp_servers()? {
+                let incoming = server_text(&server);
+                let current = target.get(&name).map(server_text);
+                let kind = match &current {
+                    None => ChangeKind::Added,
+                    Some(current) if *current != incoming => ChangeKind::Modified,
+                    Some(_) => continue,
+                };
+                changes.push(PendingChange {
+                    artifact: "mcp_server".into(),
+                    name,
+                    kind,
+                    current: current.unwrap_or_default(),
+                    incoming,
+                });
+            }
+        }
+        changes.sort_by(|a, b| (&a.artifact, &a.name).cmp(&(&b.artifact, &b.name)));
+        Ok(changes)
+    }
+
     /// Performs the sync operation.
     pub fn sync(&self, params: &SyncParams) -> Result<SyncReport> {
         let mut report = SyncReport::new();
@@ -189,6 +302,12 @@ impl<S: AgentAdapter, T: AgentAdapter> SyncOrchestrator<S, T> {
             }
         }
 
+        // Sync memory files
+        if params.sync_memory {
+            let memory = self.source.read_memory()?;
+            report.memory = self.target.write_memory(&memory, params.dry_run)?;
+        }
+
         report.success = true;
         report.summary = report.format_summary(self.source.name(), self.target.name());
 
@@ -318,6 +437,53 @@ mod tests {
         assert_eq!(fs::read_to_string(&greet_path).unwrap(), "# Greet");
     }
 
+    #[test]
+    fn pending_changes_lists_new_and_changed_commands() {
+        let src_dir = tempdir().unwrap();
+        let tgt_dir = tempdir().unwrap();
+
+        let src_cmd_dir = src_dir.path().join("commands");
+        fs::create_dir_all(&src_cmd_dir).unwrap();
+        fs::write(src_cmd_dir.join("hello.md"), "# New Hello").unwrap();
+        fs::write(src_cmd_dir.join("greet.md"), "# Greet").unwrap();
+        fs::write(src_cmd_dir.join("same.md"), "# Same").unwrap();
+
+        let tgt_cmd_dir = tgt_dir.path().join("prompts");
+        fs::create_dir_all(&tgt_cmd_dir).unwrap();
+        fs::write(tgt_cmd_dir.join("hello.md"), "# Existing Hello").unwrap();
+        fs::write(tgt_cmd_dir.join("same.md"), "# Same").unwrap();
+
+        let source = ClaudeAdapter::with_root(src_dir.path().to_path_buf());
+        let target = CodexAdapter::with_root(tgt_dir.path().to_path_buf());
+        let orchestrator = SyncOrchestrator::new(source, target);
+        let mut params = SyncParams {
+            sync_commands: true,
+            sync_mcp_servers: false,
+            sync_preferences: false,
+            sync_skills: false,
+            ..Default::default()
+        };
+
+        let changes = orchestrator.pending_changes(&params).unwrap();
+        let summary: Vec<_> = changes.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("greet", ChangeKind::Added),
+                ("hello", ChangeKind::Modified)
+            ]
+        );
+        assert_eq!(changes[1].current, "# Existing Hello");
+        assert_eq!(changes[1].incoming, "# New Hello");
+
+        // Nothing was written, and kept commands are not changes.
+        assert!(!tgt_cmd_dir.join("greet.md").exists());
+        params.skip_existing_commands = true;
+        let changes = orchestrator.pending_changes(&params).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].name, "greet");
+    }
+
     #[test]
     fn dry_run_does_not_write() {
         let src_dir = tempdir().unwrap();
@@ -0,0 +1,91 @@
+//! Project-scoped sync for repository-local agent configuration.
+//!
+//! A repository can carry its own `.claude/` (commands, skills, settings),
+//! `.mcp.json`, and `CLAUDE.md`, with Codex equivalents under `.codex/` and
+//! `AGENTS.md`. `ProjectScope` builds adapters rooted in the repository so the
+//! regular orchestrator can sync them.
+
+use crate::adapters::{ClaudeAdapter, CodexAdapter};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Returns the closest ancestor of `start` (inclusive) containing `.git`.
+pub fn find_git_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// A repository whose local agent configuration should be synced.
+#[derive(Debug, Clone)]
+pub struct ProjectScope {
+    root: PathBuf,
+}
+
+impl ProjectScope {
+    /// Creates a scope rooted at `root`.
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Resolves a scope from an explicit directory, or the git root of the
+    /// current directory when `dir` is `None`.
+    pub fn resolve(dir: Option<&Path>) -> Result<Self> {
+        let root = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => {
+                let cwd =
+                    std::env::current_dir().context("Could not determine current directory")?;
+                match find_git_root(&cwd) {
+                    Some(root) => root,
+                    None => bail!(
+                        "{} is not inside a git repository; pass --project <dir>",
+                        cwd.display()
+                    ),
+                }
+            }
+        };
+        if !root.is_dir() {
+            bail!("Project directory not found: {}", root.display());
+        }
+        Ok(Self::new(root))
+    }
+
+    /// Repository root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Adapter for the repository's `.claude/` directory and `.mcp.json`.
+    pub fn claude_adapter(&self) -> ClaudeAdapter {
+        ClaudeAdapter::for_project(&self.root)
+    }
+
+    /// Adapter for the repository's `.codex/` directory.
+    pub fn codex_adapter(&self) -> CodexAdapter {
+        CodexAdapter::for_project(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn find_git_root_walks_up() {
+        let tmp = tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join(".git")).unwrap();
+        let nested = tmp.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_git_root(&nested).unwrap(), tmp.path());
+    }
+
+    #[test]
+    fn resolve_rejects_missing_dir() {
+        let tmp = tempdir().unwrap();
+        assert!(ProjectScope::resolve(Some(&tmp.path().join("nope"))).is_err());
+    }
+}
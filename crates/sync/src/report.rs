@@ -3,6 +3,10 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+/// Version of the [`SyncReportDocument`] JSON schema. Bump when fields are
+/// removed or change meaning; adding optional fields does not require a bump.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
 /// Reasons why an item was skipped during sync.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +96,9 @@ pub struct WriteReport {
     pub skipped: Vec<SkipReason>,
     /// Non-fatal warnings
     pub warnings: Vec<String>,
+    /// Files written; empty for dry runs
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
 }
 
 /// Complete sync report across all artifact types.
@@ -102,6 +109,8 @@ pub struct SyncReport {
     pub commands: WriteReport,
     pub mcp_servers: WriteReport,
     pub preferences: WriteReport,
+    #[serde(default)]
+    pub memory: WriteReport,
     /// Overall success status
     pub success: bool,
     /// Summary message
@@ -124,6 +133,20 @@ impl SyncReport {
             + self.commands.written
             + self.mcp_servers.written
             + self.preferences.written
+            + self.memory.written
+    }
+
+    /// Returns the files written across all types.
+    pub fn written_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        [
+            &self.skills,
+            &self.commands,
+            &self.mcp_servers,
+            &self.preferences,
+            &self.memory,
+        ]
+        .into_iter()
+        .flat_map(|report| &report.paths)
     }
 
     /// Returns total items skipped across all types.
@@ -132,6 +155,7 @@ impl SyncReport {
             + self.commands.skipped.len()
             + self.mcp_servers.skipped.len()
             + self.preferences.skipped.len()
+            + self.memory.skipped.len()
     }
 
     /// Generates a formatted summary for display.
@@ -158,6 +182,189 @@ impl SyncReport {
             self.preferences.written,
             self.preferences.skipped.len()
         ));
+        out.push_str(&format!(
+            "  Memory:      {} synced, {} skipped\n",
+            self.memory.written,
+            self.memory.skipped.len()
+        ));
         out
     }
 }
+
+/// A skipped item with its human-readable description and guidance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkipEntry {
+    #[serde(flatten)]
+    pub reason: SkipReason,
+    pub description: String,
+    pub guidance: Option<String>,
+}
+
+impl From<&SkipReason> for SkipEntry {
+    fn from(reason: &SkipReason) -> Self {
+        Self {
+            reason: reason.clone(),
+            description: reason.description(),
+            guidance: reason.guidance().map(String::from),
+        }
+    }
+}
+
+/// Outcome for one artifact type in a [`SyncReportDocument`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArtifactOutcome {
+    pub written: usize,
+    pub skipped: Vec<SkipEntry>,
+    pub warnings: Vec<String>,
+}
+
+impl From<&WriteReport> for ArtifactOutcome {
+    fn from(report: &WriteReport) -> Self {
+        Self {
+            written: report.written,
+            skipped: report.skipped.iter().map(SkipEntry::from).collect(),
+            warnings: report.warnings.clone(),
+        }
+    }
+}
+
+/// Per-artifact outcomes, keyed by fixed field names.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArtifactOutcomes {
+    pub skills: ArtifactOutcome,
+    pub commands: ArtifactOutcome,
+    pub mcp_servers: ArtifactOutcome,
+    pub preferences: ArtifactOutcome,
+    pub memory: ArtifactOutcome,
+}
+
+/// Aggregate counts across all artifacts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportTotals {
+    pub written: usize,
+    pub skipped: usize,
+    pub warnings: usize,
+}
+
+/// Stable, versioned JSON form of a sync run for scripts and CI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReportDocument {
+    /// Always [`REPORT_SCHEMA_VERSION`] for documents produced by this crate.
+    pub schema_version: u32,
+    /// CLI command that produced the report (e.g. `sync-all`).
+    pub command: String,
+    /// Source agent name.
+    pub source: String,
+    /// Target agent name.
+    pub target: String,
+    /// Repository root for project-scoped syncs.
+    pub project: Option<PathBuf>,
+    pub dry_run: bool,
+    pub success: bool,
+    pub artifacts: ArtifactOutcomes,
+    pub totals: ReportTotals,
+    /// Warnings that are not tied to a single artifact.
+    pub warnings: Vec<String>,
+    /// Command-specific details (e.g. `sync-status` plugin listing).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl SyncReportDocument {
+    /// Builds a document for `report`, produced by `command` syncing
+    /// `source` into `target`.
+    pub fn new(command: &str, source: &str, target: &str, report: &SyncReport) -> Self {
+        let reports = [
+            &report.skills,
+            &report.commands,
+            &report.mcp_servers,
+            &report.preferences,
+            &report.memory,
+        ];
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            command: command.to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+            project: None,
+            dry_run: false,
+            success: report.success,
+            artifacts: ArtifactOutcomes {
+                skills: (&report.skills).into(),
+                commands: (&report.commands).into(),
+                mcp_servers: (&report.mcp_servers).into(),
+                preferences: (&report.preferences).into(),
+                memory: (&report.memory).into(),
+            },
+            totals: ReportTotals {
+                written: report.total_synced(),
+                skipped: report.total_skipped(),
+                warnings: reports.iter().map(|r| r.warnings.len()).sum(),
+            },
+            warnings: Vec::new(),
+            details: None,
+        }
+    }
+
+    /// Marks the document as a preview.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Records the repository root of a project-scoped sync.
+    pub fn project(mut self, root: Option<PathBuf>) -> Self {
+        self.project = root;
+        self
+    }
+
+    /// Adds a warning that is not tied to a single artifact.
+    pub fn warning(mut self, warning: impl Into<String>) -> Self {
+        self.warnings.push(warning.into());
+        self.totals.warnings += 1;
+        self
+    }
+
+    /// Attaches command-specific details.
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_carries_skip_guidance_and_totals() {
+        let mut report = SyncReport::new();
+        report.commands.written = 2;
+        report.commands.skipped.push(SkipReason::WouldOverwrite {
+            item: "review".into(),
+        });
+        report.memory.warnings.push("memory warning".into());
+
+        let doc = SyncReportDocument::new("sync-commands", "claude", "codex", &report)
+            .dry_run(true)
+            .warning("overwrites existing prompts");
+        let json = serde_json::to_value(&doc).unwrap();
+
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+        assert_eq!(json["dry_run"], true);
+        assert_eq!(json["artifacts"]["commands"]["written"], 2);
+        let skipped = &json["artifacts"]["commands"]["skipped"][0];
+        assert_eq!(skipped["type"], "WouldOverwrite");
+        assert_eq!(skipped["item"], "review");
+        assert_eq!(
+            skipped["guidance"],
+            "Use --skip-existing-commands to keep target copy"
+        );
+        assert_eq!(json["totals"]["skipped"], 1);
+        assert_eq!(json["totals"]["warnings"], 2);
+        assert!(json.get("details").is_none());
+
+        let parsed: SyncReportDocument = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.artifacts.commands.skipped.len(), 1);
+    }
+}
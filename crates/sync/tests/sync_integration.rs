@@ -131,6 +131,7 @@ mod sync_direction_tests {
             sync_mcp_servers: true,
             sync_preferences: true,
             skip_existing_commands: false,
+            sync_memory: true,
             include_marketplace: false,
         };
 
@@ -181,6 +182,7 @@ mod sync_direction_tests {
             sync_mcp_servers: true,
             sync_preferences: true,
             skip_existing_commands: false,
+            sync_memory: true,
             include_marketplace: false,
         };
 
@@ -232,6 +234,7 @@ mod sync_skip_existing_tests {
             skip_existing_commands: true, // Enable skip
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -298,6 +301,7 @@ mod sync_dry_run_tests {
             sync_mcp_servers: true,
             sync_preferences: true,
             skip_existing_commands: false,
+            sync_memory: true,
             include_marketplace: false,
         };
 
@@ -365,6 +369,7 @@ mod sync_force_overwrite_tests {
             sync_mcp_servers: false,
             sync_preferences: false,
             skip_existing_commands: false, // Should be ignored due to force
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -411,6 +416,7 @@ mod sync_error_handling_tests {
             sync_mcp_servers: true,
             sync_preferences: true,
             skip_existing_commands: false,
+            sync_memory: true,
             include_marketplace: false,
         };
 
@@ -453,6 +459,7 @@ mod sync_error_handling_tests {
             sync_mcp_servers: true,
             sync_preferences: true,
             skip_existing_commands: false,
+            sync_memory: true,
             include_marketplace: false,
         };
 
@@ -479,3 +486,74 @@ mod sync_error_handling_tests {
         }
     }
 }
+
+mod sync_memory_tests {
+    use super::*;
+
+    fn memory_only() -> SyncParams {
+        SyncParams {
+            sync_skills: false,
+            sync_commands: false,
+            sync_mcp_servers: false,
+            sync_preferences: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_memory_round_trip_does_not_duplicate() {
+        let setup = SyncTestSetup::new().unwrap();
+        let claude_root = setup.source_dir.path().to_path_buf();
+        let codex_root = setup.target_dir.path().to_path_buf();
+        fs::write(claude_root.join("rules.md"), "Prefer small commits.").unwrap();
+        fs::write(
+            claude_root.join("CLAUDE.md"),
+            "# Claude memory\n@rules.md\n",
+        )
+        .unwrap();
+        fs::write(codex_root.join("AGENTS.md"), "# Codex notes\n").unwrap();
+
+        let to_codex = SyncOrchestrator::new(
+            ClaudeAdapter::with_root(claude_root.clone()),
+            CodexAdapter::with_root(codex_root.clone()),
+        );
+        let to_claude = SyncOrchestrator::new(
+            CodexAdapter::with_root(codex_root.clone()),
+            ClaudeAdapter::with_root(claude_root.clone()),
+        );
+
+        let report = to_codex.sync(&memory_only()).unwrap();
+        assert_eq!(report.memory.written, 1);
+        let report = to_claude.sync(&memory_only()).unwrap();
+        assert_eq!(report.memory.written, 1);
+
+        let agents = fs::read_to_string(codex_root.join("AGENTS.md")).unwrap();
+        let claude = fs::read_to_string(claude_root.join("CLAUDE.md")).unwrap();
+        assert!(agents.contains("Prefer small commits."));
+        assert_eq!(agents.matches("# Claude memory").count(), 1);
+        assert_eq!(agents.matches("# Codex notes").count(), 1);
+        assert_eq!(claude.matches("# Codex notes").count(), 1);
+        assert_eq!(claude.matches("# Claude memory").count(), 1);
+
+        // A second pass in both directions is a no-op.
+        assert_eq!(to_codex.sync(&memory_only()).unwrap().memory.written, 0);
+        assert_eq!(to_claude.sync(&memory_only()).unwrap().memory.written, 0);
+        assert_eq!(
+            fs::read_to_string(codex_root.join("AGENTS.md")).unwrap(),
+            agents
+        );
+    }
+
+    #[test]
+    fn test_project_memory_reads_repo_claude_md() {
+        let repo = TempDir::new().unwrap();
+        fs::write(repo.path().join("CLAUDE.md"), "# Repo rules").unwrap();
+
+        let memory = ClaudeAdapter::for_project(repo.path())
+            .read_memory()
+            .unwrap();
+        assert_eq!(memory.len(), 1);
+        assert_eq!(memory[0].scope, skrills_sync::MemoryScope::Project);
+        assert_eq!(memory[0].content, "# Repo rules");
+    }
+}
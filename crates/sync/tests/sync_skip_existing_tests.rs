@@ -89,6 +89,7 @@ impl SyncTestContext {
             source_path: PathBuf::from(format!("{}.md", name)),
             modified: SystemTime::now(),
             hash,
+            plugin: None,
         }
     }
 }
@@ -130,6 +131,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: false, // Disabled - should overwrite
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -174,6 +176,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true, // Enabled - should skip existing
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -240,6 +243,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -291,6 +295,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -347,6 +352,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -388,6 +394,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -434,6 +441,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -486,6 +494,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true, // Should be ignored due to force
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 
@@ -534,6 +543,7 @@ mod skip_existing_commands_tests {
             skip_existing_commands: true,
             sync_mcp_servers: false,
             sync_preferences: false,
+            sync_memory: false,
             include_marketplace: false,
         };
 